@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use arrow::{
+    array::RecordBatch,
+    datatypes::{DataType, Field, FieldRef, Schema},
+    ipc::{reader::StreamReader, writer::StreamWriter},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, RustStandardType, StorageFormat, StorageObject, StorageSchema};
+
+
+/// Arrow schema of `O` derived from `StorageObject::schema()`, which must be `StorageSchema::Standard`
+/// - Columns are in schema order and not nullable
+pub fn arrow_schema<O: StorageObject>() -> anyhow::Result<Schema> {
+    Ok(Schema::new(arrow_fields::<O>()?))
+}
+
+fn arrow_fields<O: StorageObject>() -> anyhow::Result<Vec<FieldRef>> {
+    let schema = match O::schema() {
+        StorageSchema::Standard { schema, .. } => schema,
+        _ => return Err(anyhow::anyhow!("Schema of {} is not Standard", O::type_name())),
+    };
+    schema.iter()
+        .map(|(name, typ)| Ok(Arc::new(Field::new(name, arrow_type(typ)?, false))))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to derive Arrow schema of {}", O::type_name()))
+}
+
+fn arrow_type(typ: &RustStandardType) -> anyhow::Result<DataType> {
+    match typ {
+        RustStandardType::String | RustStandardType::Char => Ok(DataType::Utf8),
+        RustStandardType::Int8 => Ok(DataType::Int8),
+        RustStandardType::Int16 => Ok(DataType::Int16),
+        RustStandardType::Int32 => Ok(DataType::Int32),
+        RustStandardType::Int64 | RustStandardType::ISize => Ok(DataType::Int64),
+        RustStandardType::UInt8 => Ok(DataType::UInt8),
+        RustStandardType::UInt16 => Ok(DataType::UInt16),
+        RustStandardType::UInt32 => Ok(DataType::UInt32),
+        RustStandardType::UInt64 | RustStandardType::USize => Ok(DataType::UInt64),
+        RustStandardType::Float32 => Ok(DataType::Float32),
+        RustStandardType::Float64 => Ok(DataType::Float64),
+        RustStandardType::Bool => Ok(DataType::Boolean),
+        // serde formats date times as RFC 3339 strings
+        RustStandardType::DateTime => Ok(DataType::Utf8),
+        RustStandardType::Int128 | RustStandardType::UInt128 => {
+            Err(anyhow::anyhow!("Arrow has no 128-bit integer type: {:?}", typ))
+        }
+    }
+}
+
+/// Converts objects of one type into a record batch with one row per object
+pub fn to_record_batch<O: StorageObject + Serialize>(objects: &[O]) -> anyhow::Result<RecordBatch> {
+    let fields = arrow_fields::<O>()?;
+    serde_arrow::to_record_batch(&fields, &objects).with_context(|| {
+        format!("Failed to convert {} to a record batch", O::type_name())
+    })
+}
+
+/// Converts every row of a record batch into an object
+pub fn from_record_batch<O: StorageObject + DeserializeOwned>(batch: &RecordBatch) -> anyhow::Result<Vec<O>> {
+    serde_arrow::from_record_batch(batch).with_context(|| {
+        format!("Failed to convert a record batch to {}", O::type_name())
+    })
+}
+
+/// Arrow IPC streams, the record batches can be handed to polars or datafusion without conversion.
+/// - `serialize_many` writes many objects of a type as one record batch
+/// - As a `StorageFormat` every object is a stream with one row
+#[derive(Debug, Clone)]
+pub struct ArrowIpcStorageFormat;
+
+impl ArrowIpcStorageFormat {
+
+    /// Writes record batches as an IPC stream, all batches must have the same schema
+    pub fn write_batches(batches: &[RecordBatch]) -> anyhow::Result<Vec<u8>> {
+        let schema = match batches.first() {
+            Some(batch) => batch.schema(),
+            None => return Err(anyhow::anyhow!("No record batches to write")),
+        };
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.into_inner().map_err(|e| e.into())
+    }
+
+    /// Reads every record batch of an IPC stream
+    pub fn read_batches(data: &[u8]) -> anyhow::Result<Vec<RecordBatch>> {
+        let reader = StreamReader::try_new(data, None)?;
+        reader.collect::<Result<Vec<_>, _>>().map_err(|e| e.into())
+    }
+
+    pub fn serialize_many<O: StorageObject + Serialize>(objects: &[O]) -> anyhow::Result<Vec<u8>> {
+        Self::write_batches(&[to_record_batch(objects)?])
+    }
+
+    pub fn deserialize_many<O: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<Vec<O>> {
+        let mut objects = Vec::new();
+        for batch in Self::read_batches(data)? {
+            objects.extend(from_record_batch::<O>(&batch)?);
+        }
+        Ok(objects)
+    }
+}
+
+impl FormatInfo for ArrowIpcStorageFormat {
+    const ID: &'static str = "arrow-ipc";
+    const VERSION: u16 = 1;
+
+    // IPC streams start with the continuation marker of the schema message
+    fn sniff(data: &[u8]) -> bool {
+        data.starts_with(&[0xff, 0xff, 0xff, 0xff])
+    }
+}
+
+impl StorageFormat for ArrowIpcStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        Self::serialize_many(std::slice::from_ref(obj))
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        Self::deserialize_many(data)?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Arrow IPC stream of {} has no rows", T::type_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        value: f64,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("value".to_string(), RustStandardType::Float64);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_arrow_ipc_storage_format_roundtrip() {
+        let objects: Vec<TestObject> = (0..10)
+            .map(|i| TestObject { key: format!("key_{}", i), value: i as f64 / 2.0 })
+            .collect();
+
+        let batch = to_record_batch(&objects).unwrap();
+        assert_eq!(batch.num_rows(), 10);
+        assert_eq!(batch.schema().as_ref(), &arrow_schema::<TestObject>().unwrap());
+
+        let data = ArrowIpcStorageFormat::serialize_many(&objects).unwrap();
+        let batches = ArrowIpcStorageFormat::read_batches(&data).unwrap();
+        assert_eq!(batches, vec![batch]);
+
+        let retrieved: Vec<TestObject> = ArrowIpcStorageFormat::deserialize_many(&data).unwrap();
+        assert_eq!(retrieved, objects);
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    marker::PhantomData,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use ordermap::OrderMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use crate::{ObjectMetadata, Page, PageRequest, RustStandardType, StorageClient, StorageFormat, StorageObject, StorageSchema, TransactionOperation};
+
+/// One mutation made through an `AuditedStorageClient`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub actor: String,
+    pub operation: String,
+    pub type_name: Option<String>,
+    pub key: Option<String>,
+    /// milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub success: bool,
+}
+
+impl StorageObject for AuditRecord {
+    fn type_name() -> &'static str {
+        "AuditRecord"
+    }
+
+    fn schema() -> StorageSchema {
+        let mut schema = OrderMap::new();
+        schema.insert("actor".to_string(), RustStandardType::String);
+        schema.insert("operation".to_string(), RustStandardType::String);
+        schema.insert("type_name".to_string(), RustStandardType::String);
+        schema.insert("key".to_string(), RustStandardType::String);
+        schema.insert("timestamp".to_string(), RustStandardType::UInt64);
+        schema.insert("success".to_string(), RustStandardType::Bool);
+        StorageSchema::Standard {
+            schema,
+            primary_key: "timestamp".to_string(),
+        }
+    }
+}
+
+/// Destination of audit records
+#[async_trait]
+pub trait AuditSink {
+    async fn record(&self, record: AuditRecord) -> anyhow::Result<()>;
+}
+
+/// Appends records as json lines to a file
+pub struct FileAuditSink {
+    path: PathBuf,
+}
+
+impl FileAuditSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn record(&self, record: AuditRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open audit log at path: {}", self.path.display()))?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Stores records as `AuditRecord` objects in another storage client
+/// - Keys are the zero padded timestamp and a sequence number, so they sort chronologically
+pub struct StorageAuditSink<F: StorageFormat, C> {
+    client: C,
+    sequence: AtomicU64,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> StorageAuditSink<F, C> {
+
+    pub fn new(client: C) -> Self {
+        Self { client, sequence: AtomicU64::new(0), _formatter: PhantomData::<F> }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl<F, C> AuditSink for StorageAuditSink<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+    async fn record(&self, record: AuditRecord) -> anyhow::Result<()> {
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let key = format!("{:020}-{:010}", record.timestamp, sequence);
+        self.client.put(&key, record).await
+    }
+}
+
+/// Writes an `AuditRecord` to a sink for every mutation made through the inner client.
+/// - Reads are not audited
+/// - Only the types given to `only_types` are audited, all types if it is not called
+/// - A mutation whose record cannot be written returns an error, even though the mutation itself went through
+pub struct AuditedStorageClient<C, S> {
+    inner: C,
+    sink: S,
+    actor: String,
+    types: Option<HashSet<String>>,
+}
+
+impl<C, S: AuditSink + Send + Sync> AuditedStorageClient<C, S> {
+
+    pub fn new(inner: C, sink: S, actor: impl Into<String>) -> Self {
+        Self { inner, sink, actor: actor.into(), types: None }
+    }
+
+    pub fn only_types<I, T>(mut self, type_names: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.types = Some(type_names.into_iter().map(|type_name| type_name.into()).collect());
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    async fn audit<R>(
+        &self,
+        operation: &str,
+        type_name: Option<&str>,
+        key: Option<&str>,
+        f: impl Future<Output = anyhow::Result<R>>,
+    ) -> anyhow::Result<R> {
+        let audited = match (&self.types, type_name) {
+            (Some(types), Some(type_name)) => types.contains(type_name),
+            _ => true,
+        };
+        let result = f.await;
+        if !audited {
+            return result;
+        }
+
+        let record = AuditRecord {
+            actor: self.actor.clone(),
+            operation: operation.to_string(),
+            type_name: type_name.map(|type_name| type_name.to_string()),
+            key: key.map(|key| key.to_string()),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            success: result.is_ok(),
+        };
+        self.sink.record(record).await.with_context(|| {
+            format!("Failed to write audit record for {} of {:?} key: {:?}", operation, type_name, key)
+        })?;
+        result
+    }
+}
+
+#[async_trait]
+impl<F, C, S> StorageClient<F> for AuditedStorageClient<C, S>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+    S: AuditSink + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("AuditedStorageClient needs an audit sink, use AuditedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.audit("create_object_directory", Some(O::type_name()), None, self.inner.create_object_directory::<O>()).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.audit("put", Some(O::type_name()), Some(key), self.inner.put(key, value)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.audit("delete", Some(O::type_name()), Some(key), self.inner.delete::<O>(key)).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.audit("delete_object_directory", Some(O::type_name()), None, self.inner.delete_object_directory::<O>()).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.audit("delete_all", None, None, self.inner.delete_all()).await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        self.audit("transaction", None, None, self.inner.commit_transaction(operations)).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.inner.get_many(keys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audited_storage_client_records_mutations() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://audited").unwrap()).await.unwrap();
+        let path = std::env::temp_dir().join("audited_storage_client_test.jsonl");
+        let _ = tokio::fs::remove_file(&path).await;
+        let client = AuditedStorageClient::new(inner, FileAuditSink::new(&path), "reporting-job");
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj).await.unwrap();
+        let _: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+
+        let log = tokio::fs::read_to_string(&path).await.unwrap();
+        let records: Vec<AuditRecord> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].operation, "put");
+        assert_eq!(records[0].actor, "reporting-job");
+        assert_eq!(records[1].operation, "delete");
+        assert_eq!(records[1].key.as_deref(), Some("test_key"));
+        assert!(records.iter().all(|record| record.success));
+
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
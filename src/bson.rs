@@ -0,0 +1,84 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject};
+
+
+/// MongoDB's native document encoding, so payloads can be moved to and from Mongo tooling as is.
+/// - Binary and date fields keep their BSON types when they use the `bson` serde helpers
+/// - Objects must serialize as documents, i.e. structs or maps
+#[derive(Debug, Clone)]
+pub struct BsonStorageFormat;
+
+impl FormatInfo for BsonStorageFormat {
+    const ID: &'static str = "bson";
+    const VERSION: u16 = 1;
+
+    // documents start with their total length and end with a null byte
+    fn sniff(data: &[u8]) -> bool {
+        data.len() >= 5
+            && data.last() == Some(&0)
+            && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize == data.len()
+    }
+}
+
+impl StorageFormat for BsonStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        bson::to_vec(obj).map_err(|e| e.into())
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        bson::from_slice(data).map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::{spec::BinarySubtype, Binary, DateTime, Document};
+    use ordermap::OrderMap;
+    use serde::Deserialize;
+
+    use crate::{RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        data: Binary,
+        created: DateTime,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("data".to_string(), RustStandardType::UInt8);
+            schema.insert("created".to_string(), RustStandardType::DateTime);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_bson_storage_format_keeps_types() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            data: Binary { subtype: BinarySubtype::Generic, bytes: vec![0, 1, 2] },
+            created: DateTime::from_millis(1_700_000_000_000),
+        };
+        let data = BsonStorageFormat::serialize(&obj).unwrap();
+
+        let document = Document::from_reader(data.as_slice()).unwrap();
+        assert!(document.get_binary_generic("data").is_ok());
+        assert!(document.get_datetime("created").is_ok());
+
+        let retrieved: TestObject = BsonStorageFormat::deserialize(&data).unwrap();
+        assert_eq!(retrieved, obj);
+    }
+}
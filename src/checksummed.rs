@@ -0,0 +1,85 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{StorageFormat, StorageObject};
+
+const CHECKSUM_LEN: usize = 4;
+
+/// Returned when a stored payload does not match its checksum, e.g. after bit rot on disk
+/// - Recover it from the error with `anyhow::Error::downcast_ref::<CorruptionError>()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptionError {
+    pub type_name: &'static str,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+impl fmt::Display for CorruptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Stored {} is corrupted, checksum {:08x} does not match {:08x}",
+            self.type_name, self.actual, self.expected,
+        )
+    }
+}
+
+impl std::error::Error for CorruptionError {}
+
+/// Prepends a CRC32 of the output of the format `F` and verifies it before deserializing.
+/// - Damaged payloads fail with a `CorruptionError` instead of a confusing error of `F`
+pub struct Checksummed<F> {
+    _format: PhantomData<F>,
+}
+
+impl<F: StorageFormat> StorageFormat for Checksummed<F> {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let payload = F::serialize(obj)?;
+        let mut data = Vec::with_capacity(CHECKSUM_LEN + payload.len());
+        data.extend_from_slice(&crc32fast::hash(&payload).to_be_bytes());
+        data.extend(payload);
+        Ok(data)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        // a payload too short to hold a checksum is treated as corrupted with an expected checksum of 0
+        if data.len() < CHECKSUM_LEN {
+            return Err(CorruptionError { type_name: T::type_name(), expected: 0, actual: crc32fast::hash(data) }.into());
+        }
+        let (checksum, payload) = data.split_at(CHECKSUM_LEN);
+        let expected = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+        let actual = crc32fast::hash(payload);
+        if expected != actual {
+            return Err(CorruptionError { type_name: T::type_name(), expected, actual }.into());
+        }
+        F::deserialize(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_checksummed_format_detects_corruption() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        let mut data = Checksummed::<JsonStorageFormat>::serialize(&obj).unwrap();
+        let retrieved: TestObject = Checksummed::<JsonStorageFormat>::deserialize(&data).unwrap();
+        assert_eq!(retrieved, obj);
+
+        // flip a bit inside the value, the JSON itself stays valid
+        let position = data.len() - 3;
+        data[position] ^= 0x01;
+        let error = Checksummed::<JsonStorageFormat>::deserialize::<TestObject>(&data).unwrap_err();
+        let corruption = error.downcast_ref::<CorruptionError>().expect("Expected a CorruptionError");
+        assert_eq!(corruption.type_name, "TestObject");
+        assert_ne!(corruption.expected, corruption.actual);
+    }
+}
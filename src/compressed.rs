@@ -0,0 +1,82 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{compressed_storage_client::Compression, StorageFormat, StorageObject};
+
+
+/// Compression algorithm of a `Compressed` format
+pub trait CompressionAlgorithm {
+    const COMPRESSION: Compression;
+}
+
+/// gzip at level 6
+#[derive(Debug, Clone)]
+pub struct Gzip;
+
+impl CompressionAlgorithm for Gzip {
+    const COMPRESSION: Compression = Compression::Gzip(6);
+}
+
+/// zstd at level 3
+#[derive(Debug, Clone)]
+pub struct Zstd;
+
+impl CompressionAlgorithm for Zstd {
+    const COMPRESSION: Compression = Compression::Zstd(3);
+}
+
+#[derive(Debug, Clone)]
+pub struct Lz4;
+
+impl CompressionAlgorithm for Lz4 {
+    const COMPRESSION: Compression = Compression::Lz4;
+}
+
+/// Compresses the output of the format `F` with the algorithm `A`, e.g. `Compressed<JsonStorageFormat, Zstd>`.
+/// - Payloads are tagged like those of `CompressedStorageClient`, so any `Compressed<F, _>`
+///   reads objects written with any algorithm
+pub struct Compressed<F, A = Zstd> {
+    _format: PhantomData<(F, A)>,
+}
+
+impl<F, A> StorageFormat for Compressed<F, A>
+where
+    F: StorageFormat,
+    A: CompressionAlgorithm,
+{
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        A::COMPRESSION.compress(&F::serialize(obj)?)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        F::deserialize(&Compression::decompress(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_compressed_format_algorithms() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value ".repeat(100),
+        };
+        let json = JsonStorageFormat::serialize(&obj).unwrap();
+
+        let gzip = Compressed::<JsonStorageFormat, Gzip>::serialize(&obj).unwrap();
+        let zstd = Compressed::<JsonStorageFormat, Zstd>::serialize(&obj).unwrap();
+        let lz4 = Compressed::<JsonStorageFormat, Lz4>::serialize(&obj).unwrap();
+        for data in [&gzip, &zstd, &lz4] {
+            assert!(data.len() < json.len());
+            // the algorithm is read from the payload, not the type parameter
+            let retrieved: TestObject = Compressed::<JsonStorageFormat, Zstd>::deserialize(data).unwrap();
+            assert_eq!(retrieved, obj);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    marker::PhantomData,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use flate2::{read::GzDecoder, write::GzEncoder};
+use futures::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, HealthStatus, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Compression algorithm and level used for new writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// level 0-9
+    Gzip(u32),
+    /// level 1-22
+    Zstd(i32),
+    /// lz4 block format, fastest with the lowest ratio
+    Lz4,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Zstd(3)
+    }
+}
+
+impl Compression {
+
+    /// First byte of every stored payload, lets the reader pick the decoder
+    fn tag(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Gzip(_) => 1,
+            Compression::Zstd(_) => 2,
+            Compression::Lz4 => 3,
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut compressed = vec![self.tag()];
+        match self {
+            Compression::None => compressed.extend_from_slice(data),
+            Compression::Gzip(level) => {
+                let mut encoder = GzEncoder::new(compressed, flate2::Compression::new(*level));
+                encoder.write_all(data)?;
+                compressed = encoder.finish()?;
+            }
+            Compression::Zstd(level) => {
+                compressed.extend(zstd::encode_all(data, *level)?);
+            }
+            Compression::Lz4 => compressed.extend(lz4_flex::compress_prepend_size(data)),
+        }
+        Ok(compressed)
+    }
+
+    /// Decompresses data written with any algorithm
+    pub fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (tag, body) = data.split_first()
+            .ok_or_else(|| anyhow::anyhow!("Compressed payload is empty"))?;
+        match tag {
+            0 => Ok(body.to_vec()),
+            1 => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(body).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+            2 => Ok(zstd::decode_all(body)?),
+            3 => Ok(lz4_flex::decompress_size_prepended(body)?),
+            tag => Err(anyhow::anyhow!("Unknown compression tag: {}", tag)),
+        }
+    }
+}
+
+/// Compresses formatted objects before handing them to the inner client.
+/// - Each stored payload starts with a tag byte for its algorithm, so changing `Compression`
+///   keeps existing objects readable
+/// - The inner client stores the compressed bytes through `RawFormat`
+pub struct CompressedStorageClient<F: StorageFormat, C> {
+    inner: C,
+    compression: Compression,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> CompressedStorageClient<F, C> {
+
+    pub fn new(inner: C, compression: Compression) -> Self {
+        Self { inner, compression, _formatter: PhantomData::<F> }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for CompressedStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<RawFormat> + Send + Sync,
+{
+
+    /// Initializes the inner client with the url and compresses with the default `Compression`
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?, Compression::default()))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<Payload<O>>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let payload = match self.inner.get::<Payload<O>>(key).await? {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+
+        let data = Compression::decompress(payload.data()).with_context(|| {
+            format!("Failed to decompress {} for key: {}", O::type_name(), key)
+        })?;
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let compressed = self.compression.compress(&data).with_context(|| {
+            format!("Failed to compress {} for key: {}", O::type_name(), key)
+        })?;
+        self.inner.put(key, Payload::<O>::new(compressed)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.delete::<Payload<O>>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.inner.delete_object_directory::<Payload<O>>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.inner.restore(id).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.import_all(source).await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let operations = operations.into_iter()
+            .map(|operation| match operation {
+                TransactionOperation::Put { type_name, schema, key, data, fields, indexed_fields, text_fields } => {
+                    let data = self.compression.compress(&data).with_context(|| {
+                        format!("Failed to compress {} for key: {}", type_name, key)
+                    })?;
+                    Ok(TransactionOperation::Put { type_name, schema, key, data, fields, indexed_fields, text_fields })
+                }
+                delete => Ok(delete),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.inner.commit_transaction(operations).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<Payload<O>>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<Payload<O>>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<Payload<O>>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<Payload<O>>(key).await
+    }
+
+    // sizes and etags are those of the stored payloads
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<Payload<O>>(key).await
+    }
+
+    // sizes are those of the compressed payloads
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<Payload<O>>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let payloads = self.inner.get_many::<Payload<O>>(keys).await?;
+        payloads.into_iter().map(|(key, payload)| {
+            let data = Compression::decompress(payload.data()).with_context(|| {
+                format!("Failed to decompress {} for key: {}", O::type_name(), key)
+            })?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let payloads = items.into_iter().map(|(key, value)| {
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            let compressed = self.compression.compress(&data).with_context(|| {
+                format!("Failed to compress {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, Payload::<O>::new(compressed)))
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+        self.inner.put_many(payloads).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.inner.delete_many::<Payload<O>>(keys).await
+    }
+
+    // versions are those of the compressed payloads
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let (payload, version) = match self.inner.get_versioned::<Payload<O>>(key).await? {
+            Some(versioned) => versioned,
+            None => return Ok(None),
+        };
+
+        let data = Compression::decompress(payload.data()).with_context(|| {
+            format!("Failed to decompress {} for key: {}", O::type_name(), key)
+        })?;
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some((obj, version)))
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let compressed = self.compression.compress(&data).with_context(|| {
+            format!("Failed to compress {} for key: {}", O::type_name(), key)
+        })?;
+        self.inner.put_if_version(key, Payload::<O>::new(compressed), expected_version).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let compressed = self.compression.compress(&data).with_context(|| {
+            format!("Failed to compress {} for key: {}", O::type_name(), key)
+        })?;
+        self.inner.put_if_absent(key, Payload::<O>::new(compressed)).await
+    }
+
+    // compressed payloads do not depend on the key, so they are moved as they are
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.inner.copy::<Payload<O>>(from, to).await
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.inner.rename::<Payload<O>>(from, to).await
+    }
+
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.scan::<Payload<O>>().map(|item| {
+            let (key, payload) = item?;
+            let data = Compression::decompress(payload.data()).with_context(|| {
+                format!("Failed to decompress {} for key: {}", O::type_name(), key)
+            })?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compressed_storage_client() {
+        let url = Url::parse("memory://compressed").unwrap();
+        let client = CompressedStorageClient::<JsonStorageFormat, MemoryStorageClient<RawFormat>>::init(url).await.unwrap();
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "a".repeat(10_000),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let stored = client.inner().get::<Payload<TestObject>>("test_key").await.unwrap().unwrap();
+        assert!(stored.data().len() < 1_000);
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj.clone()));
+
+        // objects written with another algorithm stay readable
+        let gzip = Compression::Gzip(6).compress(&JsonStorageFormat::serialize(&obj).unwrap()).unwrap();
+        client.inner().put("gzip_key", Payload::<TestObject>::new(gzip)).await.unwrap();
+        let retrieved: Option<TestObject> = client.get("gzip_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::Context;
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject, StorageSchema};
+
+
+/// A header line with the schema's column names and one data line, opens in Excel.
+/// - Only for `StorageSchema::Standard` objects whose fields are all scalars
+/// - The struct fields must be the schema columns in schema order
+#[derive(Debug, Clone)]
+pub struct CsvStorageFormat;
+
+impl CsvStorageFormat {
+
+    /// Header line of `O` from its schema
+    pub fn header<O: StorageObject>() -> anyhow::Result<Vec<u8>> {
+        let columns = match O::schema() {
+            StorageSchema::Standard { schema, .. } => schema.into_keys().collect::<Vec<_>>(),
+            _ => return Err(anyhow::anyhow!("Schema of {} is not Standard", O::type_name())),
+        };
+        let mut writer = WriterBuilder::new().from_writer(Vec::new());
+        writer.write_record(&columns)?;
+        writer.into_inner().map_err(|e| anyhow::anyhow!("Failed to write header: {}", e))
+    }
+}
+
+impl FormatInfo for CsvStorageFormat {
+    const ID: &'static str = "csv";
+    const VERSION: u16 = 1;
+}
+
+impl StorageFormat for CsvStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let header = Self::header::<T>()?;
+        // the writer derives its header from the struct fields, nested fields fail here
+        let mut writer = WriterBuilder::new().has_headers(true).from_writer(Vec::new());
+        writer.serialize(obj).with_context(|| {
+            format!("Failed to write {} as a flat record", T::type_name())
+        })?;
+        let data = writer.into_inner().map_err(|e| anyhow::anyhow!("Failed to write record: {}", e))?;
+        if !data.starts_with(&header) {
+            return Err(anyhow::anyhow!(
+                "Fields of {} do not match its schema columns: {}",
+                T::type_name(),
+                String::from_utf8_lossy(&header).trim_end(),
+            ));
+        }
+        Ok(data)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        let mut reader = ReaderBuilder::new().has_headers(true).from_reader(data);
+        match reader.deserialize().next() {
+            Some(record) => record.map_err(|e| e.into()),
+            None => Err(anyhow::anyhow!("CSV of {} has no record", T::type_name())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::Deserialize;
+
+    use crate::RustStandardType;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        value: String,
+        amount: f64,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("value".to_string(), RustStandardType::String);
+            schema.insert("amount".to_string(), RustStandardType::Float64);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct NestedObject {
+        key: String,
+        values: Vec<String>,
+    }
+
+    impl StorageObject for NestedObject {
+        fn type_name() -> &'static str {
+            "NestedObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("values".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_csv_storage_format() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "a, \"quoted\" value".to_string(),
+            amount: 1.5,
+        };
+        let data = CsvStorageFormat::serialize(&obj).unwrap();
+        assert_eq!(String::from_utf8(data.clone()).unwrap(), "key,value,amount\ntest_key,\"a, \"\"quoted\"\" value\",1.5\n");
+
+        let retrieved: TestObject = CsvStorageFormat::deserialize(&data).unwrap();
+        assert_eq!(retrieved, obj);
+
+        let nested = NestedObject {
+            key: "test_key".to_string(),
+            values: vec!["a".to_string()],
+        };
+        assert!(CsvStorageFormat::serialize(&nested).is_err());
+    }
+}
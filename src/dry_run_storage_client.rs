@@ -0,0 +1,145 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{memory_storage_client::MemoryStorageClient, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+/// An operation accepted by a `DryRunStorageClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DryRunOperation {
+    CreateObjectDirectory { type_name: &'static str },
+    Get { type_name: &'static str, key: String },
+    Put { type_name: &'static str, key: String, size: usize },
+    Delete { type_name: &'static str, key: String },
+    DeleteObjectDirectory { type_name: &'static str },
+    DeleteAll,
+}
+
+/// Accepts every operation without touching real storage and records what would have happened.
+/// - Objects put during the dry run are kept in memory, so later reads and deletes behave as
+///   they would against an empty store
+/// - `operations` returns the log for assertions or to preview a batch job
+pub struct DryRunStorageClient<F: StorageFormat> {
+    overlay: MemoryStorageClient<F>,
+    operations: Mutex<Vec<DryRunOperation>>,
+}
+
+impl<F: StorageFormat> DryRunStorageClient<F> {
+
+    fn record(&self, operation: DryRunOperation) {
+        self.operations.lock().unwrap_or_else(|e| e.into_inner()).push(operation);
+    }
+
+    pub fn operations(&self) -> Vec<DryRunOperation> {
+        self.operations.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the recorded operations and starts a new log
+    pub fn take_operations(&self) -> Vec<DryRunOperation> {
+        std::mem::take(&mut *self.operations.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for DryRunStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    /// Accepts any url, nothing is connected or created
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let overlay = MemoryStorageClient::init(storage_url).await?;
+        Ok(Self { overlay, operations: Mutex::new(Vec::new()) })
+    }
+
+    fn directory(&self) -> &str {
+        self.overlay.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.record(DryRunOperation::CreateObjectDirectory { type_name: O::type_name() });
+        self.overlay.create_object_directory::<O>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.record(DryRunOperation::Get { type_name: O::type_name(), key: key.to_string() });
+        self.overlay.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        // formatting is still checked, a dry run should fail where the real run would
+        let size = F::serialize(&value)?.len();
+        self.record(DryRunOperation::Put { type_name: O::type_name(), key: key.to_string(), size });
+        self.overlay.put(key, value).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.record(DryRunOperation::Delete { type_name: O::type_name(), key: key.to_string() });
+        self.overlay.delete::<O>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.record(DryRunOperation::DeleteObjectDirectory { type_name: O::type_name() });
+        self.overlay.delete_object_directory::<O>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.record(DryRunOperation::DeleteAll);
+        self.overlay.delete_all().await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.overlay.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.overlay.list_page::<O>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.overlay.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.overlay.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.overlay.metadata::<O>(key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_storage_client_records_operations() {
+        let client = DryRunStorageClient::<JsonStorageFormat>::init(Url::parse("file:///does/not/exist").unwrap()).await.unwrap();
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        let size = JsonStorageFormat::serialize(&obj).unwrap().len();
+        client.put("test_key", obj.clone()).await.unwrap();
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        client.delete_all().await.unwrap();
+
+        assert_eq!(client.take_operations(), vec![
+            DryRunOperation::Put { type_name: "TestObject", key: "test_key".to_string(), size },
+            DryRunOperation::Get { type_name: "TestObject", key: "test_key".to_string() },
+            DryRunOperation::Delete { type_name: "TestObject", key: "test_key".to_string() },
+            DryRunOperation::DeleteAll,
+        ]);
+        assert!(client.operations().is_empty());
+        assert!(!std::path::Path::new("/does/not/exist").exists());
+    }
+}
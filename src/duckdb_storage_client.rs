@@ -0,0 +1,380 @@
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use duckdb::{params_from_iter, types::Value, Connection, OptionalExt};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{RustStandardType, StorageClient, StorageFormat, StorageObject, StorageSchema};
+
+/// Column holding the formatted object, the schema columns are kept alongside it for querying
+const PAYLOAD_COLUMN: &str = "__payload";
+
+/// DuckDB column type for a rust standard type
+pub fn duckdb_type(typ: &RustStandardType) -> &'static str {
+    match typ {
+        RustStandardType::Int8 => "TINYINT",
+        RustStandardType::Int16 => "SMALLINT",
+        RustStandardType::Int32 => "INTEGER",
+        RustStandardType::Int64 | RustStandardType::ISize => "BIGINT",
+        RustStandardType::Int128 => "HUGEINT",
+        RustStandardType::UInt8 => "UTINYINT",
+        RustStandardType::UInt16 => "USMALLINT",
+        RustStandardType::UInt32 => "UINTEGER",
+        RustStandardType::UInt64 | RustStandardType::USize => "UBIGINT",
+        RustStandardType::UInt128 => "UHUGEINT",
+        RustStandardType::Float32 => "FLOAT",
+        RustStandardType::Float64 => "DOUBLE",
+        RustStandardType::String | RustStandardType::Char => "VARCHAR",
+        RustStandardType::Bool => "BOOLEAN",
+        // kept as the serialized string, analysts can cast it to TIMESTAMP
+        RustStandardType::DateTime => "VARCHAR",
+    }
+}
+
+/// Converts a field of the serialized object to a DuckDB value of its schema column type
+fn field_value(typ: &RustStandardType, value: Option<&serde_json::Value>) -> Value {
+    let value = match value {
+        Some(value) if !value.is_null() => value,
+        _ => return Value::Null,
+    };
+    match typ {
+        RustStandardType::Float32 | RustStandardType::Float64 => {
+            value.as_f64().map(Value::Double).unwrap_or(Value::Null)
+        }
+        RustStandardType::Bool => value.as_bool().map(Value::Boolean).unwrap_or(Value::Null),
+        RustStandardType::String | RustStandardType::Char | RustStandardType::DateTime => {
+            match value.as_str() {
+                Some(s) => Value::Text(s.to_string()),
+                None => Value::Text(value.to_string()),
+            }
+        }
+        _ => {
+            if let Some(i) = value.as_i64() {
+                Value::BigInt(i)
+            } else if let Some(u) = value.as_u64() {
+                Value::UBigInt(u)
+            } else {
+                // 128 bit integers are cast from their digits
+                Value::Text(value.to_string())
+            }
+        }
+    }
+}
+
+/// Converts a key to a DuckDB value of the primary key column type
+fn key_value(typ: &RustStandardType, key: &str) -> Value {
+    match typ {
+        RustStandardType::String | RustStandardType::Char | RustStandardType::DateTime => {
+            Value::Text(key.to_string())
+        }
+        _ => match key.parse::<i64>() {
+            Ok(i) => Value::BigInt(i),
+            Err(_) => Value::Text(key.to_string()),
+        },
+    }
+}
+
+/// Stores objects in a DuckDB database file, one table per object type.
+/// - `duckdb:///path/to/store.duckdb`, the file is created if it does not exist
+/// - Each table has one column per field of `StorageSchema::Standard` plus the formatted object
+/// - DuckDB calls block, so every operation runs on the blocking thread pool
+pub struct DuckDbStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    connection: Arc<Mutex<Connection>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> DuckDbStorageClient<F> {
+
+    /// CREATE TABLE IF NOT EXISTS table_name
+    /// - (column_name1 column_type1, column_name2 column_type2, ..., __payload BLOB NOT NULL)
+    /// - PRIMARY KEY (primary_key_name)
+    pub fn create_table_if_not_exists_query<O: StorageObject>() -> anyhow::Result<String> {
+        match O::schema() {
+            StorageSchema::Standard { schema, primary_key } => {
+                let columns: Vec<String> = schema.iter()
+                    .map(|(name, typ)| format!("{} {}", name, duckdb_type(typ)))
+                    .collect();
+                let columns_str = columns.join(", ");
+                Ok(format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({}, {} BLOB NOT NULL, PRIMARY KEY ({}))",
+                    O::type_name(),
+                    columns_str,
+                    PAYLOAD_COLUMN,
+                    primary_key
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Standard"))
+            },
+        }
+    }
+
+    /// INSERT OR REPLACE INTO table_name (column_name1, ..., __payload) VALUES (?, ..., ?)
+    pub fn upsert_query<O: StorageObject>() -> anyhow::Result<String> {
+        match O::schema() {
+            StorageSchema::Standard { schema, .. } => {
+                let mut columns: Vec<&str> = schema.keys().map(|name| name.as_str()).collect();
+                columns.push(PAYLOAD_COLUMN);
+                let placeholders = vec!["?"; columns.len()].join(", ");
+                Ok(format!(
+                    "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                    O::type_name(),
+                    columns.join(", "),
+                    placeholders
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Standard"))
+            },
+        }
+    }
+
+    /// Primary key column name and the value `key` has in that column
+    fn primary_key<O: StorageObject>(key: &str) -> anyhow::Result<(String, Value)> {
+        match O::schema() {
+            StorageSchema::Standard { schema, primary_key } => {
+                let typ = schema.get(&primary_key).ok_or_else(|| {
+                    anyhow::anyhow!("Primary key {} is not in the schema of {}", primary_key, O::type_name())
+                })?;
+                let value = key_value(typ, key);
+                Ok((primary_key, value))
+            }
+            _ => Err(anyhow::anyhow!("Schema is not Standard")),
+        }
+    }
+
+    async fn run<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&Connection) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let connection = connection
+                .lock()
+                .map_err(|_| anyhow::anyhow!("DuckDB connection lock is poisoned"))?;
+            f(&connection)
+        }).await?
+    }
+}
+
+fn table_exists(connection: &Connection, table: &str) -> anyhow::Result<bool> {
+    let count: i64 = connection.query_row(
+        "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = ?",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for DuckDbStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path().to_string();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+
+        let connection = tokio::task::spawn_blocking(move || {
+            Connection::open(&path).with_context(|| {
+                format!("Failed to open duckdb database at path: {}", path)
+            })
+        }).await??;
+
+        Ok(Self { storage_url, connection: Arc::new(Mutex::new(connection)), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let query = Self::create_table_if_not_exists_query::<O>()?;
+        self.run(move |connection| {
+            connection.execute(&query, [])?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to create table for {}", O::type_name())
+        })
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let (primary_key, key_value) = Self::primary_key::<O>(key)?;
+        let query = format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            primary_key
+        );
+        let data = self.run(move |connection| {
+            let data = connection
+                .query_row(&query, [key_value], |row| row.get::<_, Vec<u8>>(0))
+                .optional()?;
+            Ok(data)
+        }).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let (schema, primary_key) = match O::schema() {
+            StorageSchema::Standard { schema, primary_key } => (schema, primary_key),
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let fields = serde_json::to_value(&value).with_context(|| {
+            format!("Failed to extract columns of {} for key: {}", O::type_name(), key)
+        })?;
+
+        let mut values: Vec<Value> = schema.iter()
+            .map(|(name, typ)| {
+                if *name == primary_key {
+                    // the key passed to put always wins over the field value
+                    key_value(typ, key)
+                } else {
+                    field_value(typ, fields.get(name))
+                }
+            })
+            .collect();
+        values.push(Value::Blob(data));
+
+        let query = Self::upsert_query::<O>()?;
+        self.run(move |connection| {
+            connection.execute(&query, params_from_iter(values))?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let (primary_key, key_value) = Self::primary_key::<O>(key)?;
+        let query = format!("DELETE FROM {} WHERE {} = ?", O::type_name(), primary_key);
+        let deleted = self.run(move |connection| {
+            Ok(connection.execute(&query, [key_value])?)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(deleted > 0)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let table = O::type_name().to_string();
+        self.run(move |connection| {
+            if !table_exists(connection, &table)? {
+                return Ok(false);
+            }
+            connection.execute(&format!("DROP TABLE {}", table), [])?;
+            Ok(true)
+        }).await.with_context(|| {
+            format!("Failed to drop table for {}", O::type_name())
+        })
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT table_name FROM information_schema.tables WHERE table_schema = 'main'"
+            )?;
+            let tables = statement
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for table in tables {
+                connection.execute(&format!("DROP TABLE {}", table), [])?;
+            }
+            Ok(())
+        }).await.context("Failed to drop all tables")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{json::JsonStorageFormat, RustStandardType, StorageObject, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: i64,
+        value: String,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::Int64);
+            schema.insert("value".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_table_if_not_exists_query() {
+        let query = DuckDbStorageClient::<JsonStorageFormat>::create_table_if_not_exists_query::<TestObject>();
+        assert_eq!(
+            query.unwrap(),
+            "CREATE TABLE IF NOT EXISTS TestObject (key BIGINT, value VARCHAR, __payload BLOB NOT NULL, PRIMARY KEY (key))"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_storage_client_json() {
+        let path = std::env::temp_dir().join("duckdb_storage_client_test").join("store.duckdb");
+        let _ = tokio::fs::remove_file(&path).await;
+        let url = Url::parse(&format!("duckdb://{}", path.display())).expect("Failed to create duckdb URL");
+        let client = DuckDbStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to open duckdb");
+
+        client.create_object_directory::<TestObject>().await.expect("Failed to create table");
+
+        let obj = TestObject { key: 1, value: "test_value".to_string() };
+        client.put("1", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("1").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("1").await.unwrap());
+        assert!(!client.delete::<TestObject>("1").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        client.delete_all().await.expect("Failed to delete all");
+    }
+}
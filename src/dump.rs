@@ -0,0 +1,89 @@
+use anyhow::Context;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Start of every dump made by `StorageClient::export_all`, followed by one record per object until the end
+/// - A record is the type name, the key and the payload, each a u32 big endian length followed by the bytes
+/// - Payloads are the objects as formatted by the client's `StorageFormat`, so only clients using the same format can import them
+const MAGIC: &[u8; 8] = b"SCDUMP01";
+
+/// One object of a dump, or of `StorageClient::export_stream`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpEntry {
+    pub type_name: String,
+    pub key: String,
+    pub data: Vec<u8>,
+}
+
+pub(crate) async fn write_header<W: AsyncWrite + Unpin>(sink: &mut W) -> anyhow::Result<()> {
+    sink.write_all(MAGIC).await.context("Failed to write dump header")
+}
+
+pub(crate) async fn write_entry<W: AsyncWrite + Unpin>(sink: &mut W, type_name: &str, key: &str, data: &[u8]) -> anyhow::Result<()> {
+    for field in [type_name.as_bytes(), key.as_bytes(), data] {
+        let length = u32::try_from(field.len())
+            .map_err(|_| anyhow::anyhow!("Dump entry {} for key: {} is larger than 4 GiB", type_name, key))?;
+        sink.write_all(&length.to_be_bytes()).await?;
+        sink.write_all(field).await.with_context(|| {
+            format!("Failed to write dump entry {} for key: {}", type_name, key)
+        })?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn read_header<R: AsyncRead + Unpin>(source: &mut R) -> anyhow::Result<()> {
+    let mut magic = [0u8; 8];
+    source.read_exact(&mut magic).await.context("Failed to read dump header")?;
+    if &magic != MAGIC {
+        return Err(anyhow::anyhow!("Not a storage dump, the header is {:?}", magic));
+    }
+    Ok(())
+}
+
+/// The next entry, `None` at the end of the dump
+pub(crate) async fn read_entry<R: AsyncRead + Unpin>(source: &mut R) -> anyhow::Result<Option<DumpEntry>> {
+    let type_name = match read_field(source).await? {
+        Some(type_name) => String::from_utf8(type_name).context("Dump type name is not UTF-8")?,
+        None => return Ok(None),
+    };
+    let key = read_field(source).await?
+        .ok_or_else(|| anyhow::anyhow!("Dump entry of {} is truncated", type_name))?;
+    let key = String::from_utf8(key).context("Dump key is not UTF-8")?;
+    let data = read_field(source).await?
+        .ok_or_else(|| anyhow::anyhow!("Dump entry {} for key: {} is truncated", type_name, key))?;
+    Ok(Some(DumpEntry { type_name, key, data }))
+}
+
+/// A length prefixed field, `None` if the stream ends right before it
+async fn read_field<R: AsyncRead + Unpin>(source: &mut R) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match source.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read dump"),
+    }
+    let mut field = vec![0u8; u32::from_be_bytes(length) as usize];
+    source.read_exact(&mut field).await.context("Dump is truncated")?;
+    Ok(Some(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dump_round_trip() {
+        let mut dump = Vec::new();
+        write_header(&mut dump).await.unwrap();
+        write_entry(&mut dump, "TestObject", "a", b"{}").await.unwrap();
+        write_entry(&mut dump, "Other", "b", b"").await.unwrap();
+
+        let mut source = dump.as_slice();
+        read_header(&mut source).await.unwrap();
+        let first = read_entry(&mut source).await.unwrap().unwrap();
+        assert_eq!(first, DumpEntry { type_name: "TestObject".to_string(), key: "a".to_string(), data: b"{}".to_vec() });
+        assert_eq!(read_entry(&mut source).await.unwrap().unwrap().key, "b");
+        assert!(read_entry(&mut source).await.unwrap().is_none());
+
+        assert!(read_header(&mut b"not a dump".as_slice()).await.is_err());
+    }
+}
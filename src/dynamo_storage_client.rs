@@ -0,0 +1,542 @@
+use std::{collections::{HashMap, HashSet}, marker::PhantomData, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::{
+    primitives::Blob,
+    types::{
+        AttributeDefinition, AttributeValue, BillingMode, DeleteRequest, KeySchemaElement, KeyType,
+        KeysAndAttributes, ReturnValue, ScalarAttributeType, TableStatus, WriteRequest,
+    },
+    Client,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{content_version, StorageClient, StorageFormat, StorageObject, VersionConflictError};
+
+// partition key, holds the object type name
+const PARTITION_KEY: &str = "pk";
+// sort key, holds the object key
+const SORT_KEY: &str = "sk";
+// formatted object, `data` itself is a reserved word in expressions
+const PAYLOAD_ATTRIBUTE: &str = "payload";
+// version of the payload, written with it so conditional puts can compare it on the server
+const VERSION_ATTRIBUTE: &str = "version";
+// BatchWriteItem accepts at most 25 requests
+const BATCH_WRITE_SIZE: usize = 25;
+// BatchGetItem accepts at most 100 keys
+const BATCH_GET_SIZE: usize = 100;
+const TABLE_ACTIVE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const TABLE_ACTIVE_ATTEMPTS: usize = 120;
+
+/// Stores objects in a single DynamoDB table keyed by `type_name` (partition) and key (sort).
+/// - `dynamodb://table_name`, the table is created on demand with on-demand billing
+/// - `endpoint` and `region` can be passed as query parameters, e.g. for DynamoDB local
+/// - Every item carries the `content_version` of its payload, `put_if_version` and `put_if_absent`
+///   are condition expressions on it and on the key
+pub struct DynamoStorageClient<F: StorageFormat> {
+    client: Client,
+    table_name: String,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> DynamoStorageClient<F> {
+
+    fn item_key(type_name: &str, key: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            (PARTITION_KEY.to_string(), AttributeValue::S(type_name.to_string())),
+            (SORT_KEY.to_string(), AttributeValue::S(key.to_string())),
+        ])
+    }
+
+    /// The item of an object with its payload and version, ready for `put_item`
+    fn item(type_name: &str, key: &str, data: Vec<u8>) -> HashMap<String, AttributeValue> {
+        let mut item = Self::item_key(type_name, key);
+        item.insert(VERSION_ATTRIBUTE.to_string(), AttributeValue::S(content_version(&data)));
+        item.insert(PAYLOAD_ATTRIBUTE.to_string(), AttributeValue::B(Blob::new(data)));
+        item
+    }
+
+    /// The formatted object of an item
+    fn payload<'a>(item: &'a HashMap<String, AttributeValue>, key: &str) -> anyhow::Result<&'a [u8]> {
+        match item.get(PAYLOAD_ATTRIBUTE) {
+            Some(AttributeValue::B(data)) => Ok(data.as_ref()),
+            _ => Err(anyhow::anyhow!("Item for key {} has no {} attribute", key, PAYLOAD_ATTRIBUTE)),
+        }
+    }
+
+    /// Version attribute of the stored item, `None` if there is no item under the key
+    async fn stored_version(&self, type_name: &str, key: &str) -> anyhow::Result<Option<String>> {
+        let output = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::item_key(type_name, key)))
+            .projection_expression("#version")
+            .expression_attribute_names("#version", VERSION_ATTRIBUTE)
+            .consistent_read(true)
+            .send()
+            .await
+            .with_context(|| format!("Failed to get version of {} for key: {}", type_name, key))?;
+        Ok(output.item().map(|item| match item.get(VERSION_ATTRIBUTE) {
+            Some(AttributeValue::S(version)) => version.clone(),
+            _ => String::new(),
+        }))
+    }
+
+    /// Items stored under any of the keys, in batches of `BATCH_GET_SIZE` with consistent reads
+    /// - Keys DynamoDB left unprocessed are requested again
+    /// - `keys_only` leaves the payload out, for callers that only need to know what exists
+    async fn batch_get(&self, type_name: &str, keys: &[&str], keys_only: bool) -> anyhow::Result<Vec<HashMap<String, AttributeValue>>> {
+        // a batch with the same key twice is rejected
+        let keys = unique_keys(keys);
+        let mut items = Vec::new();
+        for chunk in keys.chunks(BATCH_GET_SIZE) {
+            let mut request = KeysAndAttributes::builder()
+                .set_keys(Some(chunk.iter().map(|key| Self::item_key(type_name, key)).collect()))
+                .consistent_read(true);
+            if keys_only {
+                request = request
+                    .projection_expression("#pk, #sk")
+                    .expression_attribute_names("#pk", PARTITION_KEY)
+                    .expression_attribute_names("#sk", SORT_KEY);
+            }
+            let mut request = Some(request.build()?);
+
+            while let Some(pending) = request.take() {
+                let output = self.client
+                    .batch_get_item()
+                    .request_items(&self.table_name, pending)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to get items of {} from table: {}", type_name, self.table_name))?;
+                if let Some(found) = output.responses().and_then(|responses| responses.get(&self.table_name)) {
+                    items.extend(found.iter().cloned());
+                }
+                request = output.unprocessed_keys()
+                    .and_then(|unprocessed| unprocessed.get(&self.table_name))
+                    .filter(|unprocessed| !unprocessed.keys().is_empty())
+                    .cloned();
+            }
+        }
+        Ok(items)
+    }
+
+    async fn table_status(&self) -> anyhow::Result<Option<TableStatus>> {
+        match self.client.describe_table().table_name(&self.table_name).send().await {
+            Ok(output) => Ok(output.table().and_then(|t| t.table_status()).cloned()),
+            Err(e) => {
+                if e.as_service_error().map(|e| e.is_resource_not_found_exception()).unwrap_or(false) {
+                    Ok(None)
+                } else {
+                    Err(e.into())
+                }
+            }
+        }
+    }
+
+    async fn create_table_if_not_exists(&self) -> anyhow::Result<()> {
+        if self.table_status().await?.is_none() {
+            self.client
+                .create_table()
+                .table_name(&self.table_name)
+                .attribute_definitions(
+                    AttributeDefinition::builder()
+                        .attribute_name(PARTITION_KEY)
+                        .attribute_type(ScalarAttributeType::S)
+                        .build()?,
+                )
+                .attribute_definitions(
+                    AttributeDefinition::builder()
+                        .attribute_name(SORT_KEY)
+                        .attribute_type(ScalarAttributeType::S)
+                        .build()?,
+                )
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .attribute_name(PARTITION_KEY)
+                        .key_type(KeyType::Hash)
+                        .build()?,
+                )
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .attribute_name(SORT_KEY)
+                        .key_type(KeyType::Range)
+                        .build()?,
+                )
+                .billing_mode(BillingMode::PayPerRequest)
+                .send()
+                .await
+                .with_context(|| format!("Failed to create table: {}", self.table_name))?;
+        }
+
+        for _ in 0..TABLE_ACTIVE_ATTEMPTS {
+            if self.table_status().await? == Some(TableStatus::Active) {
+                return Ok(());
+            }
+            tokio::time::sleep(TABLE_ACTIVE_POLL_INTERVAL).await;
+        }
+        Err(anyhow::anyhow!("Table {} did not become active", self.table_name))
+    }
+
+    /// Deletes the given items in batches, resubmitting anything DynamoDB left unprocessed
+    async fn delete_items(&self, keys: Vec<HashMap<String, AttributeValue>>) -> anyhow::Result<()> {
+        for chunk in keys.chunks(BATCH_WRITE_SIZE) {
+            let mut requests = chunk.iter()
+                .map(|key| {
+                    let delete = DeleteRequest::builder().set_key(Some(key.clone())).build()?;
+                    Ok(WriteRequest::builder().delete_request(delete).build())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            while !requests.is_empty() {
+                let output = self.client
+                    .batch_write_item()
+                    .request_items(&self.table_name, requests)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to delete items from table: {}", self.table_name))?;
+                requests = output.unprocessed_items()
+                    .and_then(|items| items.get(&self.table_name))
+                    .cloned()
+                    .unwrap_or_default();
+            }
+        }
+        Ok(())
+    }
+
+    fn primary_keys(items: &[HashMap<String, AttributeValue>]) -> Vec<HashMap<String, AttributeValue>> {
+        items.iter()
+            .map(|item| {
+                item.iter()
+                    .filter(|(name, _)| *name == PARTITION_KEY || *name == SORT_KEY)
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// `keys` without repetitions, in their first order
+fn unique_keys<'a>(keys: &[&'a str]) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+    keys.iter().copied().filter(|key| seen.insert(*key)).collect()
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for DynamoStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let table_name = match storage_url.host_str() {
+            Some(table_name) if !table_name.is_empty() => table_name.to_string(),
+            _ => return Err(anyhow::anyhow!("Storage URL does not have a table name")),
+        };
+
+        let mut endpoint = None;
+        let mut region = None;
+        for (name, value) in storage_url.query_pairs() {
+            match name.as_ref() {
+                "endpoint" => endpoint = Some(value.to_string()),
+                "region" => region = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(aws_config::Region::new(region));
+        }
+        if let Some(endpoint) = endpoint {
+            loader = loader.endpoint_url(endpoint);
+        }
+        let client = Client::new(&loader.load().await);
+
+        let storage_client = Self { client, table_name, _formatter: PhantomData::<F> };
+        storage_client.create_table_if_not_exists().await?;
+        Ok(storage_client)
+    }
+
+    fn directory(&self) -> &str {
+        &self.table_name
+    }
+
+    // all object types share the table, the partition exists as soon as an item is put
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let output = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::item_key(self.object_directory::<O>(), key)))
+            .consistent_read(true)
+            .send()
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        let item = match output.item() {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let obj = F::deserialize(Self::payload(item, key)?).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(Self::item(self.object_directory::<O>(), key, data)))
+            .send()
+            .await
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+
+        Ok(())
+    }
+
+    // Query on the partition of the type, only the sort keys are read
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut pages = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("#pk = :pk")
+            .expression_attribute_names("#pk", PARTITION_KEY)
+            .expression_attribute_values(":pk", AttributeValue::S(self.object_directory::<O>().to_string()))
+            .projection_expression("#sk")
+            .expression_attribute_names("#sk", SORT_KEY)
+            .consistent_read(true)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.with_context(|| {
+                format!("Failed to query items of {}", O::type_name())
+            })?;
+            keys.extend(page.items().iter().filter_map(|item| match item.get(SORT_KEY) {
+                Some(AttributeValue::S(key)) => Some(key.clone()),
+                _ => None,
+            }));
+        }
+        Ok(keys)
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let output = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::item_key(self.object_directory::<O>(), key)))
+            .projection_expression("#pk")
+            .expression_attribute_names("#pk", PARTITION_KEY)
+            .consistent_read(true)
+            .send()
+            .await
+            .with_context(|| format!("Failed to check {} for key: {}", O::type_name(), key))?;
+        Ok(output.item().is_some())
+    }
+
+    // BatchGetItem, up to 100 keys a request
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let mut objects = HashMap::new();
+        for item in self.batch_get(self.object_directory::<O>(), keys, false).await? {
+            let key = match item.get(SORT_KEY) {
+                Some(AttributeValue::S(key)) => key.clone(),
+                _ => continue,
+            };
+            let obj = F::deserialize(Self::payload(&item, &key)?).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            objects.insert(key, obj);
+        }
+        Ok(objects)
+    }
+
+    // BatchWriteItem does not tell which items existed, they are looked up with BatchGetItem first,
+    // so the count misses objects put between the lookup and the delete
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let existing = Self::primary_keys(&self.batch_get(self.object_directory::<O>(), keys, true).await?);
+        let deleted = existing.len();
+        let keys: Vec<HashMap<String, AttributeValue>> = unique_keys(keys).iter()
+            .map(|key| Self::item_key(self.object_directory::<O>(), key))
+            .collect();
+        self.delete_items(keys).await?;
+        Ok(deleted)
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let output = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::item_key(self.object_directory::<O>(), key)))
+            .consistent_read(true)
+            .send()
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        let item = match output.item() {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let data = Self::payload(item, key)?;
+        let obj = F::deserialize(data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some((obj, content_version(data))))
+    }
+
+    // the version attribute is compared by a condition expression, the compare and the write are one step
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let version = content_version(&data);
+
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(Self::item(self.object_directory::<O>(), key, data)))
+            .condition_expression("#version = :version")
+            .expression_attribute_names("#version", VERSION_ATTRIBUTE)
+            .expression_attribute_values(":version", AttributeValue::S(expected_version.to_string()))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(version),
+            Err(e) if e.as_service_error().map(|e| e.is_conditional_check_failed_exception()).unwrap_or(false) => {
+                Err(VersionConflictError {
+                    type_name: O::type_name(),
+                    key: key.to_string(),
+                    expected: expected_version.to_string(),
+                    actual: self.stored_version(self.object_directory::<O>(), key).await?,
+                }.into())
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key)),
+        }
+    }
+
+    // the item is only written if no item has its key yet
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(Self::item(self.object_directory::<O>(), key, data)))
+            .condition_expression("attribute_not_exists(#pk)")
+            .expression_attribute_names("#pk", PARTITION_KEY)
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().map(|e| e.is_conditional_check_failed_exception()).unwrap_or(false) => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key)),
+        }
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let output = self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .set_key(Some(Self::item_key(self.object_directory::<O>(), key)))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete {} for key: {}", O::type_name(), key))?;
+        Ok(output.attributes().is_some())
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let mut keys = Vec::new();
+        let mut pages = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("#pk = :pk")
+            .expression_attribute_names("#pk", PARTITION_KEY)
+            .expression_attribute_values(":pk", AttributeValue::S(self.object_directory::<O>().to_string()))
+            .projection_expression("#pk, #sk")
+            .expression_attribute_names("#sk", SORT_KEY)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.with_context(|| {
+                format!("Failed to query items of {}", O::type_name())
+            })?;
+            keys.extend(Self::primary_keys(page.items()));
+        }
+
+        if keys.is_empty() {
+            return Ok(false);
+        }
+        self.delete_items(keys).await?;
+        Ok(true)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let mut keys = Vec::new();
+        let mut pages = self.client
+            .scan()
+            .table_name(&self.table_name)
+            .projection_expression("#pk, #sk")
+            .expression_attribute_names("#pk", PARTITION_KEY)
+            .expression_attribute_names("#sk", SORT_KEY)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.with_context(|| {
+                format!("Failed to scan table: {}", self.table_name)
+            })?;
+            keys.extend(Self::primary_keys(page.items()));
+        }
+
+        self.delete_items(keys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::json::JsonStorageFormat;
+
+    use super::*;
+
+    #[test]
+    fn test_dynamo_item_key_and_primary_keys() {
+        let key = DynamoStorageClient::<JsonStorageFormat>::item_key("TestObject", "test_key");
+        assert_eq!(key.get(PARTITION_KEY), Some(&AttributeValue::S("TestObject".to_string())));
+        assert_eq!(key.get(SORT_KEY), Some(&AttributeValue::S("test_key".to_string())));
+
+        // scanned items carry the payload, deletes only take the primary key
+        let mut item = key.clone();
+        item.insert(PAYLOAD_ATTRIBUTE.to_string(), AttributeValue::B(Blob::new(b"{}".to_vec())));
+        assert_eq!(DynamoStorageClient::<JsonStorageFormat>::primary_keys(&[item]), vec![key]);
+    }
+
+    #[test]
+    fn test_dynamo_item_carries_version() {
+        let item = DynamoStorageClient::<JsonStorageFormat>::item("TestObject", "test_key", b"{}".to_vec());
+        assert_eq!(item.get(VERSION_ATTRIBUTE), Some(&AttributeValue::S(content_version(b"{}"))));
+        assert_eq!(DynamoStorageClient::<JsonStorageFormat>::payload(&item, "test_key").unwrap(), b"{}");
+        assert!(DynamoStorageClient::<JsonStorageFormat>::payload(&HashMap::new(), "test_key").is_err());
+        assert_eq!(unique_keys(&["b", "a", "b"]), vec!["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_dynamo_init_requires_table_name() {
+        let result = DynamoStorageClient::<JsonStorageFormat>::init(Url::parse("dynamodb:///").unwrap()).await;
+        assert!(result.is_err());
+    }
+}
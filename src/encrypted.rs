@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload as AeadPayload},
+    Aes256Gcm,
+};
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{StorageFormat, StorageObject};
+
+// cipher id and key id
+const HEADER_LEN: usize = 5;
+
+/// Supplies the keys of an `Encrypted` format
+/// - Formats have no instance, so keys are looked up through the type, e.g. from a static loaded at startup
+/// - Payloads record the id of the key they were sealed with, so old keys keep working after a rotation
+pub trait KeyProvider {
+    /// Id and key for new payloads
+    fn current_key() -> anyhow::Result<(u32, [u8; 32])>;
+
+    /// Key with the given id, for reading
+    fn key(id: u32) -> anyhow::Result<[u8; 32]>;
+}
+
+/// AEAD cipher of an `Encrypted` format
+pub trait Cipher {
+    /// Stored in the payload, a payload is only opened by the cipher that sealed it
+    const ID: u8;
+
+    /// Returns the random nonce followed by the ciphertext and tag
+    fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    fn open(key: &[u8; 32], sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+fn seal<A: Aead + AeadCore>(cipher: A, plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = A::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, AeadPayload { msg: plaintext, aad })
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt payload"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(sealed)
+}
+
+fn open<A: Aead + AeadCore>(cipher: A, nonce_len: usize, sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < nonce_len {
+        return Err(anyhow::anyhow!("Encrypted payload is truncated"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(nonce_len);
+    cipher
+        .decrypt(nonce.into(), AeadPayload { msg: ciphertext, aad })
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt payload, wrong key or tampered data"))
+}
+
+/// AES-256-GCM with a random 12 byte nonce
+#[derive(Debug, Clone)]
+pub struct Aes256GcmCipher;
+
+impl Cipher for Aes256GcmCipher {
+    const ID: u8 = 1;
+
+    fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        seal(Aes256Gcm::new(key.into()), plaintext, aad)
+    }
+
+    fn open(key: &[u8; 32], sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        open(Aes256Gcm::new(key.into()), 12, sealed, aad)
+    }
+}
+
+/// XChaCha20-Poly1305 with a random 24 byte nonce, safe for any number of payloads per key
+#[derive(Debug, Clone)]
+pub struct XChaCha20Poly1305Cipher;
+
+impl Cipher for XChaCha20Poly1305Cipher {
+    const ID: u8 = 2;
+
+    fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        seal(XChaCha20Poly1305::new(key.into()), plaintext, aad)
+    }
+
+    fn open(key: &[u8; 32], sealed: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        open(XChaCha20Poly1305::new(key.into()), 24, sealed, aad)
+    }
+}
+
+/// Seals the output of the format `F` with the cipher `C` and keys from `K`,
+/// e.g. `Encrypted<JsonStorageFormat, AppKeys, XChaCha20Poly1305Cipher>`.
+/// - Payloads are the cipher id, the key id, the nonce and the ciphertext
+/// - The header and the type name are authenticated, so a payload cannot be read as another type.
+///   Unlike `EncryptedStorageClient` the key is not bound, the format does not know it
+pub struct Encrypted<F, K, C = Aes256GcmCipher> {
+    _format: PhantomData<(F, K, C)>,
+}
+
+fn associated_data<T: StorageObject>(header: &[u8]) -> Vec<u8> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(T::type_name().as_bytes());
+    aad
+}
+
+impl<F, K, C> StorageFormat for Encrypted<F, K, C>
+where
+    F: StorageFormat,
+    K: KeyProvider,
+    C: Cipher,
+{
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let (key_id, key) = K::current_key()?;
+        let mut sealed = vec![C::ID];
+        sealed.extend_from_slice(&key_id.to_be_bytes());
+        let aad = associated_data::<T>(&sealed);
+        sealed.extend(C::seal(&key, &F::serialize(obj)?, &aad)?);
+        Ok(sealed)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        if data.len() < HEADER_LEN {
+            return Err(anyhow::anyhow!("Encrypted {} is truncated", T::type_name()));
+        }
+        let (header, sealed) = data.split_at(HEADER_LEN);
+        if header[0] != C::ID {
+            return Err(anyhow::anyhow!("Encrypted {} was sealed with cipher id: {}, expected: {}", T::type_name(), header[0], C::ID));
+        }
+        let key_id = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        let key = K::key(key_id)?;
+        let plaintext = C::open(&key, sealed, &associated_data::<T>(header))?;
+        F::deserialize(&plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    struct TestKeys;
+
+    impl KeyProvider for TestKeys {
+        fn current_key() -> anyhow::Result<(u32, [u8; 32])> {
+            Ok((7, [7; 32]))
+        }
+
+        fn key(id: u32) -> anyhow::Result<[u8; 32]> {
+            match id {
+                7 => Ok([7; 32]),
+                _ => Err(anyhow::anyhow!("Unknown key id: {}", id)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_format_ciphers() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+
+        let aes = Encrypted::<JsonStorageFormat, TestKeys>::serialize(&obj).unwrap();
+        let retrieved: TestObject = Encrypted::<JsonStorageFormat, TestKeys>::deserialize(&aes).unwrap();
+        assert_eq!(retrieved, obj);
+
+        type XChaCha = Encrypted<JsonStorageFormat, TestKeys, XChaCha20Poly1305Cipher>;
+        let mut xchacha = XChaCha::serialize(&obj).unwrap();
+        let retrieved: TestObject = XChaCha::deserialize(&xchacha).unwrap();
+        assert_eq!(retrieved, obj);
+
+        // wrong cipher and tampered payloads are rejected
+        assert!(XChaCha::deserialize::<TestObject>(&aes).is_err());
+        let last = xchacha.len() - 1;
+        xchacha[last] ^= 1;
+        assert!(XChaCha::deserialize::<TestObject>(&xchacha).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload as AeadPayload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts objects with AES-256-GCM before handing them to the inner client.
+/// - Stored bytes are a random 12 byte nonce followed by the ciphertext and tag
+/// - `{type_name}/{key}` is authenticated with the ciphertext, so values moved to another key fail to decrypt
+/// - The inner client stores the encrypted bytes through `RawFormat`
+pub struct EncryptedStorageClient<F: StorageFormat, C> {
+    inner: C,
+    cipher: Aes256Gcm,
+    _formatter: PhantomData<F>,
+}
+
+fn associated_data<O: StorageObject>(key: &str) -> Vec<u8> {
+    format!("{}/{}", O::type_name(), key).into_bytes()
+}
+
+impl<F: StorageFormat, C> EncryptedStorageClient<F, C> {
+
+    pub fn new(inner: C, key: &[u8; 32]) -> Self {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        Self { inner, cipher, _formatter: PhantomData::<F> }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn encrypt<O: StorageObject>(&self, key: &str, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = associated_data::<O>(key);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, AeadPayload { msg: data, aad: &aad })
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt {} for key: {}", O::type_name(), key))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn decrypt<O: StorageObject>(&self, key: &str, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Encrypted {} for key: {} is truncated", O::type_name(), key));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let aad = associated_data::<O>(key);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), AeadPayload { msg: ciphertext, aad: &aad })
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt {} for key: {}, wrong key or tampered data", O::type_name(), key))
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for EncryptedStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<RawFormat> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("EncryptedStorageClient needs key material, use EncryptedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<Payload<O>>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let payload = match self.inner.get::<Payload<O>>(key).await? {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+
+        let data = self.decrypt::<O>(key, payload.data())?;
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let sealed = self.encrypt::<O>(key, &data)?;
+        self.inner.put(key, Payload::<O>::new(sealed)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.delete::<Payload<O>>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.inner.delete_object_directory::<Payload<O>>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<Payload<O>>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<Payload<O>>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<Payload<O>>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<Payload<O>>(key).await
+    }
+
+    // sizes and etags are those of the stored payloads
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<Payload<O>>(key).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let payloads = self.inner.get_many::<Payload<O>>(keys).await?;
+        payloads.into_iter().map(|(key, payload)| {
+            let data = self.decrypt::<O>(&key, payload.data())?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let payloads = items.into_iter().map(|(key, value)| {
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            let sealed = self.encrypt::<O>(&key, &data)?;
+            Ok((key, Payload::<O>::new(sealed)))
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+        self.inner.put_many(payloads).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.inner.delete_many::<Payload<O>>(keys).await
+    }
+
+    // versions are those of the sealed payloads
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let (payload, version) = match self.inner.get_versioned::<Payload<O>>(key).await? {
+            Some(versioned) => versioned,
+            None => return Ok(None),
+        };
+
+        let data = self.decrypt::<O>(key, payload.data())?;
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some((obj, version)))
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let sealed = self.encrypt::<O>(key, &data)?;
+        self.inner.put_if_version(key, Payload::<O>::new(sealed), expected_version).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let sealed = self.encrypt::<O>(key, &data)?;
+        self.inner.put_if_absent(key, Payload::<O>::new(sealed)).await
+    }
+
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.scan::<Payload<O>>().map(move |item| {
+            let (key, payload) = item?;
+            let data = self.decrypt::<O>(&key, payload.data())?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_encrypted_storage_client() {
+        let inner = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://encrypted").unwrap()).await.unwrap();
+        let client = EncryptedStorageClient::<JsonStorageFormat, _>::new(inner, &[7u8; 32]);
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let stored = client.inner().get::<Payload<TestObject>>("test_key").await.unwrap().unwrap();
+        assert!(!String::from_utf8_lossy(stored.data()).contains("test_value"));
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        // a value copied to another key does not decrypt
+        client.inner().put("other_key", stored).await.unwrap();
+        assert!(client.get::<TestObject>("other_key").await.is_err());
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+    }
+}
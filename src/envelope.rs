@@ -0,0 +1,154 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{StorageFormat, StorageObject};
+
+const MAGIC: &[u8; 4] = b"SENV";
+const HEADER_VERSION: u8 = 1;
+
+/// Identifies a format in `Versioned` envelopes
+pub trait FormatInfo {
+    /// Short stable name, formats that read each other's output share it
+    const ID: &'static str;
+    /// Bumped when the format's output changes incompatibly
+    const VERSION: u16;
+
+    /// Whether the data looks like output of this format, e.g. by its magic bytes
+    /// - Formats without a recognizable start return false and are only read from envelopes
+    fn sniff(_data: &[u8]) -> bool {
+        false
+    }
+}
+
+/// Header of a `Versioned` payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    pub format_id: String,
+    pub format_version: u16,
+    pub schema_version: u32,
+}
+
+impl EnvelopeHeader {
+
+    pub fn is_envelope(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// Parses the header and returns it with the payload that follows it
+    pub fn read(data: &[u8]) -> anyhow::Result<(Self, &[u8])> {
+        let truncated = || anyhow::anyhow!("Envelope header is truncated");
+        if !Self::is_envelope(data) {
+            return Err(anyhow::anyhow!("Data is not an envelope, magic bytes are missing"));
+        }
+        let rest = &data[MAGIC.len()..];
+        let (&header_version, rest) = rest.split_first().ok_or_else(truncated)?;
+        if header_version != HEADER_VERSION {
+            return Err(anyhow::anyhow!("Unsupported envelope header version: {}", header_version));
+        }
+        let (&id_len, rest) = rest.split_first().ok_or_else(truncated)?;
+        let id_len = id_len as usize;
+        if rest.len() < id_len + 6 {
+            return Err(truncated());
+        }
+        let (format_id, rest) = rest.split_at(id_len);
+        let format_id = std::str::from_utf8(format_id).context("Envelope format id is not UTF-8")?.to_string();
+        let (format_version, rest) = rest.split_at(2);
+        let (schema_version, payload) = rest.split_at(4);
+
+        let header = Self {
+            format_id,
+            format_version: u16::from_be_bytes([format_version[0], format_version[1]]),
+            schema_version: u32::from_be_bytes([schema_version[0], schema_version[1], schema_version[2], schema_version[3]]),
+        };
+        Ok((header, payload))
+    }
+
+    /// magic, header version, format id length and bytes, format version, schema version
+    pub fn write(&self, data: &mut Vec<u8>) -> anyhow::Result<()> {
+        let id_len = u8::try_from(self.format_id.len())
+            .map_err(|_| anyhow::anyhow!("Format id is longer than 255 bytes: {}", self.format_id))?;
+        data.extend_from_slice(MAGIC);
+        data.push(HEADER_VERSION);
+        data.push(id_len);
+        data.extend_from_slice(self.format_id.as_bytes());
+        data.extend_from_slice(&self.format_version.to_be_bytes());
+        data.extend_from_slice(&self.schema_version.to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Wraps the output of the format `F` in a header with the format id, the format version and
+/// `StorageObject::schema_version()`, and checks them on read.
+/// - Payloads of another format, a newer format version or a newer schema version are rejected
+///   instead of being misread, so a store can change format or layout safely
+pub struct Versioned<F> {
+    _format: PhantomData<F>,
+}
+
+impl<F: StorageFormat + FormatInfo> StorageFormat for Versioned<F> {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let header = EnvelopeHeader {
+            format_id: F::ID.to_string(),
+            format_version: F::VERSION,
+            schema_version: T::schema_version(),
+        };
+        let mut data = Vec::new();
+        header.write(&mut data)?;
+        data.extend(F::serialize(obj)?);
+        Ok(data)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        let (header, payload) = EnvelopeHeader::read(data)?;
+        if header.format_id != F::ID {
+            return Err(anyhow::anyhow!("{} was stored as {}, expected {}", T::type_name(), header.format_id, F::ID));
+        }
+        if header.format_version > F::VERSION {
+            return Err(anyhow::anyhow!(
+                "{} was stored with {} version {}, newer than the supported version {}",
+                T::type_name(), F::ID, header.format_version, F::VERSION,
+            ));
+        }
+        if header.schema_version > T::schema_version() {
+            return Err(anyhow::anyhow!(
+                "{} was stored with schema version {}, newer than the current version {}",
+                T::type_name(), header.schema_version, T::schema_version(),
+            ));
+        }
+        F::deserialize(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_versioned_format_validates_header() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        let data = Versioned::<JsonStorageFormat>::serialize(&obj).unwrap();
+        let (header, payload) = EnvelopeHeader::read(&data).unwrap();
+        assert_eq!(header, EnvelopeHeader { format_id: "json".to_string(), format_version: 1, schema_version: 1 });
+        assert_eq!(payload, JsonStorageFormat::serialize(&obj).unwrap().as_slice());
+
+        let retrieved: TestObject = Versioned::<JsonStorageFormat>::deserialize(&data).unwrap();
+        assert_eq!(retrieved, obj);
+
+        // written by a newer schema
+        let mut newer = Vec::new();
+        EnvelopeHeader { schema_version: 2, ..header.clone() }.write(&mut newer).unwrap();
+        newer.extend_from_slice(payload);
+        assert!(Versioned::<JsonStorageFormat>::deserialize::<TestObject>(&newer).is_err());
+
+        // not an envelope
+        assert!(Versioned::<JsonStorageFormat>::deserialize::<TestObject>(payload).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use etcd_client::{Client, DeleteOptions};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// Stores objects in etcd under `/{directory}/{type_name}/{key}`.
+/// - `etcd://host:port/directory`, the directory becomes the key prefix
+pub struct EtcdStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    client: Client,
+    _formatter: PhantomData<F>,
+}
+
+/// The http endpoint of `etcd://host:port`, etcd listens for clients on 2379 by default
+fn endpoint(storage_url: &Url) -> anyhow::Result<String> {
+    let host = storage_url.host_str()
+        .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a host"))?;
+    Ok(format!("http://{}:{}", host, storage_url.port().unwrap_or(2379)))
+}
+
+impl<F: StorageFormat> EtcdStorageClient<F> {
+
+    /// Deletes every key starting with `prefix`
+    /// - Returns the number of deleted keys
+    async fn delete_prefix(&self, prefix: &str) -> anyhow::Result<i64> {
+        let mut client = self.client.clone();
+        let response = client
+            .delete(prefix, Some(DeleteOptions::new().with_prefix()))
+            .await
+            .with_context(|| format!("Failed to delete keys with prefix: {}", prefix))?;
+        Ok(response.deleted())
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for EtcdStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let endpoint = endpoint(&storage_url)?;
+        let client = Client::connect([endpoint.as_str()], None).await.with_context(|| {
+            format!("Failed to connect to etcd at: {}", endpoint)
+        })?;
+
+        Ok(Self { storage_url, client, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path().trim_end_matches('/')
+    }
+
+    // etcd keys are flat, the prefix exists as soon as a key is put under it
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let mut client = self.client.clone();
+        let etcd_key = self.object_path::<O>(key);
+        let response = client.get(etcd_key.as_str(), None).await.with_context(|| {
+            format!("Failed to get etcd key: {}", etcd_key)
+        })?;
+
+        match response.kvs().first() {
+            Some(kv) => {
+                let obj = F::deserialize(kv.value()).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let mut client = self.client.clone();
+        let etcd_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        client.put(etcd_key.as_str(), data, None).await.with_context(|| {
+            format!("Failed to put etcd key: {}", etcd_key)
+        })?;
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut client = self.client.clone();
+        let etcd_key = self.object_path::<O>(key);
+        let response = client.delete(etcd_key.as_str(), None).await.with_context(|| {
+            format!("Failed to delete etcd key: {}", etcd_key)
+        })?;
+        Ok(response.deleted() > 0)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let prefix = self.object_path::<O>("");
+        let deleted = self.delete_prefix(&prefix).await?;
+        Ok(deleted > 0)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let prefix = format!("{}/", self.directory());
+        self.delete_prefix(&prefix).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_etcd_endpoint() {
+        assert_eq!(endpoint(&Url::parse("etcd://localhost/directory").unwrap()).unwrap(), "http://localhost:2379");
+        assert_eq!(endpoint(&Url::parse("etcd://10.0.0.1:12379/directory").unwrap()).unwrap(), "http://10.0.0.1:12379");
+        assert!(endpoint(&Url::parse("etcd:/directory").unwrap()).is_err());
+    }
+}
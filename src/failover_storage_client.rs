@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    marker::PhantomData,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+/// Sends every operation to a primary client and switches to a secondary client when the
+/// primary fails or does not answer within the timeout.
+/// - After `failback_after` the next operation is tried on the primary again, and the client
+///   switches back once the primary succeeds
+/// - Writes made during a failover only reach the secondary, it should replicate to the primary
+///   (e.g. a standby database) or the writes have to be reconciled after failback
+/// - Both clients store the bytes formatted by `F` through `RawFormat`, so a write can be retried
+pub struct FailoverStorageClient<F: StorageFormat, P, S> {
+    primary: P,
+    secondary: S,
+    timeout: Duration,
+    failback_after: Duration,
+    failed_at: Mutex<Option<Instant>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, P, S> FailoverStorageClient<F, P, S> {
+
+    pub fn new(primary: P, secondary: S) -> Self {
+        Self {
+            primary,
+            secondary,
+            timeout: Duration::from_secs(5),
+            failback_after: Duration::from_secs(30),
+            failed_at: Mutex::new(None),
+            _formatter: PhantomData::<F>,
+        }
+    }
+
+    /// How long the primary gets to answer before the operation goes to the secondary
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long to stay on the secondary before trying the primary again
+    pub fn with_failback_after(mut self, failback_after: Duration) -> Self {
+        self.failback_after = failback_after;
+        self
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    pub fn is_failed_over(&self) -> bool {
+        self.failed_at.lock().map(|failed_at| failed_at.is_some()).unwrap_or(true)
+    }
+
+    fn use_primary(&self) -> bool {
+        match *self.failed_at.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(failed_at) => failed_at.elapsed() >= self.failback_after,
+            None => true,
+        }
+    }
+
+    fn set_failed(&self, failed: bool) {
+        let mut failed_at = self.failed_at.lock().unwrap_or_else(|e| e.into_inner());
+        *failed_at = if failed { Some(Instant::now()) } else { None };
+    }
+
+    /// Runs `primary` unless the client is failed over, and `secondary` if that fails
+    /// - Futures are lazy, the one that is not needed is dropped without running
+    async fn run<R>(
+        &self,
+        operation: &str,
+        primary: impl Future<Output = anyhow::Result<R>>,
+        secondary: impl Future<Output = anyhow::Result<R>>,
+    ) -> anyhow::Result<R> {
+        if self.use_primary() {
+            let primary_error = match tokio::time::timeout(self.timeout, primary).await {
+                Ok(Ok(result)) => {
+                    self.set_failed(false);
+                    return Ok(result);
+                }
+                Ok(Err(e)) => e,
+                Err(_) => anyhow::anyhow!("Primary timed out after {:?}", self.timeout),
+            };
+            self.set_failed(true);
+            return secondary.await.with_context(|| {
+                format!("Failed to {} on secondary after primary failed: {:#}", operation, primary_error)
+            });
+        }
+
+        secondary.await.with_context(|| format!("Failed to {} on secondary", operation))
+    }
+}
+
+#[async_trait]
+impl<F, P, S> StorageClient<F> for FailoverStorageClient<F, P, S>
+where
+    F: StorageFormat + Send + Sync,
+    P: StorageClient<RawFormat> + Send + Sync,
+    S: StorageClient<RawFormat> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("FailoverStorageClient wraps two clients, use FailoverStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.primary.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.run(
+            "create object directory",
+            self.primary.create_object_directory::<Payload<O>>(),
+            self.secondary.create_object_directory::<Payload<O>>(),
+        ).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let payload = self.run(
+            &format!("get {} for key: {}", O::type_name(), key),
+            self.primary.get::<Payload<O>>(key),
+            self.secondary.get::<Payload<O>>(key),
+        ).await?;
+
+        match payload {
+            Some(payload) => {
+                let obj = F::deserialize(payload.data()).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let payload = Payload::<O>::new(data);
+
+        self.run(
+            &format!("put {} for key: {}", O::type_name(), key),
+            self.primary.put(key, payload.clone()),
+            self.secondary.put(key, payload),
+        ).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.run(
+            &format!("delete {} for key: {}", O::type_name(), key),
+            self.primary.delete::<Payload<O>>(key),
+            self.secondary.delete::<Payload<O>>(key),
+        ).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.run(
+            "delete object directory",
+            self.primary.delete_object_directory::<Payload<O>>(),
+            self.secondary.delete_object_directory::<Payload<O>>(),
+        ).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.run("delete all", self.primary.delete_all(), self.secondary.delete_all()).await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        // both are closed even if the first one fails
+        let primary = self.primary.close().await;
+        let secondary = self.secondary.close().await;
+        primary.and(secondary)
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.run(
+            "list keys",
+            self.primary.list_keys::<Payload<O>>(),
+            self.secondary.list_keys::<Payload<O>>(),
+        ).await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.run(
+            "list page",
+            self.primary.list_page::<Payload<O>>(page.clone()),
+            self.secondary.list_page::<Payload<O>>(page),
+        ).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.run(
+            "count",
+            self.primary.count::<Payload<O>>(),
+            self.secondary.count::<Payload<O>>(),
+        ).await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.run(
+            "check existence",
+            self.primary.exists::<Payload<O>>(key),
+            self.secondary.exists::<Payload<O>>(key),
+        ).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.run(
+            "read metadata",
+            self.primary.metadata::<Payload<O>>(key),
+            self.secondary.metadata::<Payload<O>>(key),
+        ).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let payloads = self.run(
+            &format!("get {} for {} keys", O::type_name(), keys.len()),
+            self.primary.get_many::<Payload<O>>(keys),
+            self.secondary.get_many::<Payload<O>>(keys),
+        ).await?;
+
+        payloads.into_iter().map(|(key, payload)| {
+            let obj = F::deserialize(payload.data()).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let payloads = items.into_iter().map(|(key, value)| {
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            Ok((key, Payload::<O>::new(data)))
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.run(
+            &format!("put {} {} objects", payloads.len(), O::type_name()),
+            self.primary.put_many(payloads.clone()),
+            self.secondary.put_many(payloads),
+        ).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.run(
+            &format!("delete {} {} objects", keys.len(), O::type_name()),
+            self.primary.delete_many::<Payload<O>>(keys),
+            self.secondary.delete_many::<Payload<O>>(keys),
+        ).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        json::JsonStorageFormat, memory_storage_client::MemoryStorageClient,
+        read_only_storage_client::ReadOnlyStorageClient, test_object::TestObject,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failover_storage_client_fails_over_and_back() {
+        // a read-only primary fails every write
+        let primary = ReadOnlyStorageClient::<MemoryStorageClient<RawFormat>>::init(Url::parse("memory://primary").unwrap()).await.unwrap();
+        let secondary = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://secondary").unwrap()).await.unwrap();
+        let client = FailoverStorageClient::<JsonStorageFormat, _, _>::new(primary, secondary)
+            .with_failback_after(Duration::from_secs(3600));
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+        assert!(client.is_failed_over());
+
+        // reads stay on the secondary while failed over
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        // once the failback delay passed a successful primary read switches back
+        let client = client.with_failback_after(Duration::ZERO);
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(retrieved.is_none());
+        assert!(!client.is_failed_over());
+    }
+}
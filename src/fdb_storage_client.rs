@@ -0,0 +1,192 @@
+use std::{marker::PhantomData, sync::OnceLock};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use foundationdb::{api::NetworkAutoStop, tuple::Subspace, Database, RangeOption};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// The FDB network thread can only be started once per process and must outlive every database
+static NETWORK: OnceLock<NetworkAutoStop> = OnceLock::new();
+
+/// Stores objects in FoundationDB, one subspace per object type under a root subspace.
+/// - `fdb:///etc/foundationdb/fdb.cluster?prefix=storage`, without a path the default cluster file is used
+/// - Every operation runs in its own FDB transaction and is retried on conflicts
+pub struct FdbStorageClient<F: StorageFormat> {
+    db: Database,
+    prefix: String,
+    root: Subspace,
+    _formatter: PhantomData<F>,
+}
+
+/// The cluster file path of the url, `None` for the default one, and the root subspace name
+fn cluster_options(storage_url: &Url) -> (Option<&str>, String) {
+    let prefix = storage_url.query_pairs()
+        .find(|(name, _)| name == "prefix")
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_else(|| "storage".to_string());
+    let cluster_file = match storage_url.path() {
+        "" | "/" => None,
+        path => Some(path),
+    };
+    (cluster_file, prefix)
+}
+
+/// Subspace of one object type, its keys are the tuple encoded object keys
+fn type_subspace(root: &Subspace, type_name: &str) -> Subspace {
+    root.subspace(&type_name)
+}
+
+impl<F: StorageFormat> FdbStorageClient<F> {
+
+    fn object_subspace<O: StorageObject>(&self) -> Subspace {
+        type_subspace(&self.root, O::type_name())
+    }
+
+    /// Clears everything in `subspace` in one transaction
+    /// - Returns false if the subspace was already empty
+    async fn clear_subspace(&self, subspace: Subspace) -> anyhow::Result<bool> {
+        let had_keys = self.db.run(|trx, _maybe_committed| {
+            let subspace = subspace.clone();
+            async move {
+                let mut range = RangeOption::from(&subspace);
+                range.limit = Some(1);
+                let existing = trx.get_range(&range, 1, false).await?;
+                let (begin, end) = subspace.range();
+                trx.clear_range(&begin, &end);
+                Ok(!existing.is_empty())
+            }
+        }).await?;
+        Ok(had_keys)
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for FdbStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        // SAFETY: the network is booted once and the guard is kept in a static, so it is never dropped
+        NETWORK.get_or_init(|| unsafe { foundationdb::boot() });
+
+        let (cluster_file, prefix) = cluster_options(&storage_url);
+
+        let db = Database::new(cluster_file).with_context(|| {
+            format!("Failed to open FoundationDB with cluster file: {:?}", cluster_file)
+        })?;
+        let root = Subspace::all().subspace(&prefix.as_str());
+
+        Ok(Self { db, prefix, root, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    // subspaces are just key prefixes, there is nothing to create
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let fdb_key = self.object_subspace::<O>().pack(&key);
+        let data = self.db.run(|trx, _maybe_committed| {
+            let fdb_key = fdb_key.clone();
+            async move {
+                let data = trx.get(&fdb_key, false).await?;
+                Ok(data.map(|data| data.to_vec()))
+            }
+        }).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let fdb_key = self.object_subspace::<O>().pack(&key);
+        self.db.run(|trx, _maybe_committed| {
+            let fdb_key = fdb_key.clone();
+            let data = data.clone();
+            async move {
+                trx.set(&fdb_key, &data);
+                Ok(())
+            }
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })?;
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let fdb_key = self.object_subspace::<O>().pack(&key);
+        let existed = self.db.run(|trx, _maybe_committed| {
+            let fdb_key = fdb_key.clone();
+            async move {
+                let existed = trx.get(&fdb_key, false).await?.is_some();
+                trx.clear(&fdb_key);
+                Ok(existed)
+            }
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(existed)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.clear_subspace(self.object_subspace::<O>()).await.with_context(|| {
+            format!("Failed to clear subspace of {}", O::type_name())
+        })
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.clear_subspace(self.root.clone()).await.with_context(|| {
+            format!("Failed to clear subspace: {}", self.prefix)
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fdb_cluster_options() {
+        let url = Url::parse("fdb:///etc/foundationdb/fdb.cluster?prefix=app").unwrap();
+        assert_eq!(cluster_options(&url), (Some("/etc/foundationdb/fdb.cluster"), "app".to_string()));
+        let url = Url::parse("fdb:///").unwrap();
+        assert_eq!(cluster_options(&url), (None, "storage".to_string()));
+    }
+
+    #[test]
+    fn test_fdb_type_subspace() {
+        let root = Subspace::all().subspace(&"storage");
+        let objects = type_subspace(&root, "TestObject");
+        let key = objects.pack(&"test_key");
+        assert!(root.is_start_of(&key));
+        assert_eq!(objects.unpack::<String>(&key).unwrap(), "test_key");
+
+        // tuple encoding keeps types with a common name start apart
+        let (begin, end) = type_subspace(&root, "TestObjects").range();
+        assert!(key.as_slice() < begin.as_slice() || key.as_slice() >= end.as_slice());
+    }
+}
@@ -1,18 +1,532 @@
-use std::marker::PhantomData;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context;
 use async_trait::async_trait;
+use futures::{stream, Stream, TryStreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 use url::Url;
 
-use crate::{StorageClient, StorageFormat, StorageObject};
-use tokio::io::AsyncWriteExt;
+use crate::{content_version, dump, field_value, find_by_scan, HealthStatus, ObjectMetadata, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageSchema, StorageUsage, TransactionOperation, VersionConflictError, SCAN_CONCURRENCY};
+use crate::lease::{epoch_millis, lease_token, LeaseGuard};
+#[cfg(feature = "streaming")]
+use crate::streaming::StreamingStorageFormat;
+#[cfg(feature = "tantivy")]
+use crate::{search::document_text, search_index::SearchIndex};
+#[cfg(feature = "tantivy")]
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 
 pub struct FileStorageClient<F: StorageFormat> {
     storage_url: Url,
+    // the directory is removed when the client is dropped
+    ephemeral: bool,
+    // opened on first use, tantivy allows one writer per index
+    #[cfg(feature = "tantivy")]
+    search_indexes: tokio::sync::Mutex<HashMap<String, Arc<SearchIndex>>>,
     _formatter: PhantomData<F>,
 }
 
+/// A stored `O` read as plain fields, for index upkeep in methods that only know `O: StorageObject`
+/// - Reads under the type name, schema and schema version of `O`, so `Versioned` formats accept it
+#[derive(serde::Deserialize)]
+#[serde(transparent, bound = "")]
+struct StoredFields<O> {
+    fields: serde_json::Value,
+    #[serde(skip)]
+    _object: PhantomData<fn() -> O>,
+}
+
+impl<O: StorageObject> StorageObject for StoredFields<O> {
+    fn type_name() -> &'static str {
+        O::type_name()
+    }
+
+    fn schema() -> StorageSchema {
+        O::schema()
+    }
+
+    fn schema_version() -> u32 {
+        O::schema_version()
+    }
+}
+
+/// Directory inside each object directory holding the records of `append`
+const APPENDED_DIRECTORY: &str = ".appended";
+
+/// Directory inside each object directory holding the index files of `find_by`
+const INDEX_DIRECTORY: &str = ".index";
+
+/// Directory inside each object directory holding the temp files of writes that are not moved into place yet
+const TEMP_DIRECTORY: &str = ".tmp";
+
+/// Directory inside each object directory holding the lock files of `merge` and `put_if_version`
+const LOCK_DIRECTORY: &str = ".locks";
+
+/// Tries at taking the lock file of a key in `merge`, `LOCK_RETRY_DELAY` apart
+const LOCK_ATTEMPTS: usize = 500;
+
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Age after which the lock file of a key is taken to be left by a crashed writer and broken,
+/// well within the `LOCK_ATTEMPTS` a waiting writer makes
+const KEY_LOCK_TTL: Duration = Duration::from_secs(3);
+
+/// Directory inside each object directory holding the full-text index of `search`
+#[cfg(feature = "tantivy")]
+const SEARCH_DIRECTORY: &str = ".search";
+
+/// Fields of an object being put and of the object it replaces, to drop index files of changed values
+struct IndexChange {
+    old: Option<serde_json::Value>,
+    new: serde_json::Value,
+}
+
+/// Tells apart ephemeral directories created by the same process in the same instant
+static EPHEMERAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Tells apart temp files and snapshots written by the same process
+static WRITE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+impl<F: StorageFormat> FileStorageClient<F> {
+
+    /// Creates a client in a new unique directory under the system temp directory.
+    /// - The directory and everything in it is removed when the client is dropped
+    pub async fn ephemeral() -> anyhow::Result<Self> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let name = format!(
+            "storage-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            EPHEMERAL_COUNTER.fetch_add(1, Ordering::Relaxed),
+        );
+        let path = std::env::temp_dir().join(name);
+        tokio::fs::create_dir_all(&path).await.with_context(|| {
+            format!("Failed to create directory at path: {}", path.display())
+        })?;
+        let storage_url = Url::from_directory_path(&path)
+            .map_err(|_| anyhow::anyhow!("Temp directory is not an absolute path: {}", path.display()))?;
+
+        Ok(Self {
+            storage_url,
+            ephemeral: true,
+            #[cfg(feature = "tantivy")]
+            search_indexes: Default::default(),
+            _formatter: PhantomData::<F>,
+        })
+    }
+}
+
+impl<F: StorageFormat + Send + Sync> FileStorageClient<F> {
+
+    /// `{directory}/{type_name}/.appended/{key}`, hidden from `list_keys` which only lists files
+    fn appended_path<O: StorageObject>(&self, key: &str) -> String {
+        format!("{}/{}/{}/{}", self.directory(), self.object_directory::<O>(), APPENDED_DIRECTORY, key)
+    }
+
+    /// `{directory}/{type_name}/.index/{field}/{hash of the value}`, holding an empty file per key with that value
+    fn index_directory(&self, type_name: &str, field: &str, value: &serde_json::Value) -> String {
+        let hash = content_version(value.to_string().as_bytes());
+        format!("{}/{}/{}/{}/{}", self.directory(), type_name, INDEX_DIRECTORY, field, hash)
+    }
+
+    /// Adds the index files of `key` for the values of its indexed fields
+    async fn index(&self, type_name: &str, indexed_fields: &[&str], key: &str, fields: &serde_json::Value) -> anyhow::Result<()> {
+        for field in indexed_fields {
+            let value = fields.get(*field).unwrap_or(&serde_json::Value::Null);
+            let entry = format!("{}/{}", self.index_directory(type_name, field, value), key);
+            if let Some(parent) = Path::new(&entry).parent() {
+                tokio::fs::create_dir_all(parent).await.with_context(|| {
+                    format!("Failed to create directory at path: {}", parent.display())
+                })?;
+            }
+            tokio::fs::write(&entry, b"").await.with_context(|| {
+                format!("Failed to write index file at path: {}", entry)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Removes the index files of `key` for the values in `old` that `new` no longer has
+    /// - Failures are ignored, `find_by` checks every indexed key against its object
+    async fn unindex(&self, type_name: &str, indexed_fields: &[&str], key: &str, old: &serde_json::Value, new: Option<&serde_json::Value>) {
+        for field in indexed_fields {
+            let value = old.get(*field).unwrap_or(&serde_json::Value::Null);
+            if new.is_some_and(|new| new.get(*field).unwrap_or(&serde_json::Value::Null) == value) {
+                continue;
+            }
+            let _ = tokio::fs::remove_file(format!("{}/{}", self.index_directory(type_name, field, value), key)).await;
+        }
+    }
+
+    /// Fields of the `O` stored at `file_path`, `None` if there is none or the format cannot read it without its type
+    async fn stored_fields<O: StorageObject>(&self, file_path: &str) -> Option<serde_json::Value> {
+        let data = tokio::fs::read(file_path).await.ok()?;
+        F::deserialize::<StoredFields<O>>(&data).ok().map(|stored| stored.fields)
+    }
+
+    /// Indexes `value` before it is written under `key`, nothing for types without indexed fields
+    async fn index_put<O: StorageObject + Serialize>(&self, key: &str, value: &O) -> anyhow::Result<Option<IndexChange>> {
+        let indexed_fields = O::indexed_fields();
+        if indexed_fields.is_empty() {
+            return Ok(None);
+        }
+        let new = serde_json::to_value(value).with_context(|| {
+            format!("Failed to extract fields of {} for key: {}", O::type_name(), key)
+        })?;
+        let old = self.stored_fields::<O>(&self.object_path::<O>(key)).await;
+        self.index(O::type_name(), &indexed_fields, key, &new).await?;
+        Ok(Some(IndexChange { old, new }))
+    }
+
+    /// Drops the index files of the values the object written by `index_put` replaced
+    async fn unindex_put<O: StorageObject>(&self, key: &str, change: Option<IndexChange>) {
+        if let Some(IndexChange { old: Some(old), new }) = change {
+            self.unindex(O::type_name(), &O::indexed_fields(), key, &old, Some(&new)).await;
+        }
+    }
+
+    /// The search index of an object type, opened on first use
+    #[cfg(feature = "tantivy")]
+    async fn search_index(&self, type_name: &str) -> anyhow::Result<Arc<SearchIndex>> {
+        let mut indexes = self.search_indexes.lock().await;
+        if let Some(index) = indexes.get(type_name) {
+            return Ok(index.clone());
+        }
+        let path = PathBuf::from(format!("{}/{}/{}", self.directory(), type_name, SEARCH_DIRECTORY));
+        let index = Arc::new(tokio::task::spawn_blocking(move || SearchIndex::open(&path)).await??);
+        indexes.insert(type_name.to_string(), index.clone());
+        Ok(index)
+    }
+
+    /// Puts the text fields of `key` in the search index of its type, or removes `key` for `None`
+    /// - Nothing for types without text fields
+    #[cfg(feature = "tantivy")]
+    async fn update_search_index(&self, type_name: &str, text_fields: &[&str], key: &str, fields: Option<&serde_json::Value>) -> anyhow::Result<()> {
+        if text_fields.is_empty() {
+            return Ok(());
+        }
+        let index = self.search_index(type_name).await?;
+        let key = key.to_string();
+        let text = fields.map(|fields| document_text(fields, text_fields));
+        tokio::task::spawn_blocking(move || match text {
+            Some(text) => index.upsert(&key, &text),
+            None => index.remove(&key),
+        }).await?
+    }
+
+    #[cfg(not(feature = "tantivy"))]
+    async fn update_search_index(&self, _type_name: &str, _text_fields: &[&str], _key: &str, _fields: Option<&serde_json::Value>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Puts `value` in the search index of its type once it is written under `key`
+    async fn index_text<O: StorageObject + Serialize>(&self, key: &str, value: &O) -> anyhow::Result<()> {
+        let text_fields = O::text_fields();
+        if text_fields.is_empty() || !cfg!(feature = "tantivy") {
+            return Ok(());
+        }
+        let fields = serde_json::to_value(value).with_context(|| {
+            format!("Failed to extract fields of {} for key: {}", O::type_name(), key)
+        })?;
+        self.update_search_index(O::type_name(), &text_fields, key, Some(&fields)).await
+    }
+
+    /// Drops the open search indexes, after their directories were removed or replaced
+    async fn close_search_indexes(&self) {
+        #[cfg(feature = "tantivy")]
+        self.search_indexes.lock().await.clear();
+    }
+
+    /// Takes the lock file of `key`, waiting while another writer holds it, returns its path and the token to give it back with `unlock_key`
+    /// - Only the conditional writes `merge` and `put_if_version` take it
+    /// - The file holds the token of its holder, one older than `KEY_LOCK_TTL` is broken so a crashed writer does not block the key
+    async fn lock_key<O: StorageObject>(&self, key: &str) -> anyhow::Result<(String, String)> {
+        let lock_path = format!("{}/{}/{}/{}", self.directory(), self.object_directory::<O>(), LOCK_DIRECTORY, key);
+        if let Some(parent) = Path::new(&lock_path).parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+        let token = lease_token();
+        let mut attempts = 1;
+        loop {
+            match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&lock_path).await {
+                Ok(mut file) => {
+                    if let Err(e) = file.write_all(token.as_bytes()).await {
+                        let _ = tokio::fs::remove_file(&lock_path).await;
+                        return Err(e).with_context(|| format!("Failed to write lock file at path: {}", lock_path));
+                    }
+                    return Ok((lock_path, token));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if break_stale_lock(Path::new(&lock_path), &token).await {
+                        continue;
+                    }
+                    if attempts >= LOCK_ATTEMPTS {
+                        return Err(anyhow::anyhow!("Timed out waiting for the lock of {} for key: {} at path: {}", O::type_name(), key, lock_path));
+                    }
+                    attempts += 1;
+                    tokio::time::sleep(LOCK_RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to create lock file at path: {}", lock_path)),
+            }
+        }
+    }
+}
+
+/// True if the lock file at `path` was last written more than `KEY_LOCK_TTL` ago
+async fn is_stale_lock(path: &Path) -> bool {
+    match tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified.elapsed().is_ok_and(|age| age > KEY_LOCK_TTL),
+        Err(_) => false,
+    }
+}
+
+/// Removes the lock file at `lock_path` if it is stale, true if it was broken and taking it can be tried again
+/// - Moved aside rather than removed, so a lock another writer took over meanwhile is put back
+async fn break_stale_lock(lock_path: &Path, token: &str) -> bool {
+    if !is_stale_lock(lock_path).await {
+        return false;
+    }
+    let mut stale_path = lock_path.as_os_str().to_owned();
+    stale_path.push(format!(".stale-{}", token));
+    if tokio::fs::rename(lock_path, &stale_path).await.is_err() {
+        return false;
+    }
+    let stale = is_stale_lock(Path::new(&stale_path)).await;
+    if !stale {
+        let _ = tokio::fs::hard_link(&stale_path, lock_path).await;
+    }
+    let _ = tokio::fs::remove_file(&stale_path).await;
+    stale
+}
+
+/// Gives back a lock taken by `lock_key`, unless it was broken as stale and another writer holds it by now
+async fn unlock_key(lock_path: &str, token: &str) {
+    if tokio::fs::read_to_string(lock_path).await.is_ok_and(|holder| holder == token) {
+        let _ = tokio::fs::remove_file(lock_path).await;
+    }
+}
+
+/// Path of a temp file in the `.tmp` directory next to `file_path`, moved into place once it is fully written
+/// - Listings skip directories starting with '.', so a file still being written never shows up as a key
+/// - Next to `file_path` and not in the system temp directory, so moving it into place is a rename on the same file system
+async fn temp_path(file_path: &str) -> anyhow::Result<String> {
+    let path = Path::new(file_path);
+    let directory = path.parent().unwrap_or(Path::new(".")).join(TEMP_DIRECTORY);
+    tokio::fs::create_dir_all(&directory).await.with_context(|| {
+        format!("Failed to create directory at path: {}", directory.display())
+    })?;
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(format!(
+        "{}/{}-{}-{}",
+        directory.display(),
+        name,
+        std::process::id(),
+        WRITE_COUNTER.fetch_add(1, Ordering::Relaxed),
+    ))
+}
+
+/// `{directory}.snapshots`, next to the store so `delete_all` keeps the snapshots
+fn snapshots_directory(directory: &str) -> PathBuf {
+    PathBuf::from(format!("{}.snapshots", directory.trim_end_matches('/')))
+}
+
+/// `{directory}.leases`, next to the store so leases are not mistaken for an object type and outlive `delete_all`
+fn leases_directory(directory: &str) -> PathBuf {
+    PathBuf::from(format!("{}.leases", directory.trim_end_matches('/')))
+}
+
+/// Expiry in epoch milliseconds and token of the lease file at `path`, `None` if it is gone or still being written
+async fn read_lease(path: &Path) -> anyhow::Result<Option<(u64, String)>> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read lease at path: {}", path.display())),
+    };
+    let lease = content.split_once('\n')
+        .and_then(|(expires, token)| expires.parse().ok().map(|expires| (expires, token.to_string())));
+    Ok(lease)
+}
+
+/// Recreates the tree `from` at `to` with hard links, so a snapshot costs no copies of the objects
+/// - Objects are always replaced by renaming a new file into place, never rewritten, so the links stay intact
+/// - Appended records are written in place and are copied instead
+fn link_tree(from: &Path, to: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to).with_context(|| {
+        format!("Failed to create directory at path: {}", to.display())
+    })?;
+    let entries = std::fs::read_dir(from).with_context(|| {
+        format!("Failed to read directory at path: {}", from.display())
+    })?;
+    let appended = from.file_name().is_some_and(|name| name == APPENDED_DIRECTORY);
+    for entry in entries {
+        let entry = entry?;
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            link_tree(&source, &target)?;
+        } else if appended {
+            std::fs::copy(&source, &target).with_context(|| {
+                format!("Failed to copy file from {} to {}", source.display(), target.display())
+            })?;
+        } else {
+            std::fs::hard_link(&source, &target).with_context(|| {
+                format!("Failed to link file from {} to {}", source.display(), target.display())
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Keys and sizes of the files under `directory`, nothing if it does not exist
+/// - Composite keys are nested directories, a file at `a/1` is the key `a/1`
+/// - Directories starting with '.' hold appended records, indexes and temp files and are skipped
+async fn object_files(directory: &Path) -> anyhow::Result<Vec<(String, u64)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![(directory.to_path_buf(), String::new())];
+    while let Some((path, prefix)) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read directory at path: {}", path.display())),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() && !name.starts_with('.') {
+                pending.push((entry.path(), format!("{}{}/", prefix, name)));
+            } else if file_type.is_file() {
+                files.push((format!("{}{}", prefix, name), entry.metadata().await?.len()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Directories left to walk and the one being read, each with the key prefix of its objects
+type KeyWalk = (Vec<(PathBuf, String)>, Option<(tokio::fs::ReadDir, String)>);
+
+/// Keys of the objects under `directory` like `object_files`, read one directory entry at a time as the stream is polled
+fn object_keys(directory: PathBuf) -> impl Stream<Item = anyhow::Result<String>> + Send {
+    let walk: KeyWalk = (vec![(directory, String::new())], None);
+    stream::try_unfold(walk, |(mut pending, mut current)| async move {
+        loop {
+            let Some((entries, prefix)) = current.as_mut() else {
+                let Some((path, prefix)) = pending.pop() else {
+                    return Ok(None);
+                };
+                match tokio::fs::read_dir(&path).await {
+                    Ok(entries) => current = Some((entries, prefix)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e).with_context(|| format!("Failed to read directory at path: {}", path.display())),
+                }
+                continue;
+            };
+            let Some(entry) = entries.next_entry().await? else {
+                current = None;
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() && !name.starts_with('.') {
+                pending.push((entry.path(), format!("{}{}/", prefix, name)));
+            } else if file_type.is_file() {
+                let key = format!("{}{}", prefix, name);
+                return Ok(Some((key, (pending, current))));
+            }
+        }
+    })
+}
+
+/// Number and total size of the objects under `directory`, nothing if it does not exist
+async fn directory_usage(directory: &Path) -> anyhow::Result<StorageUsage> {
+    let files = object_files(directory).await?;
+    Ok(StorageUsage {
+        objects: files.len() as u64,
+        bytes: files.iter().map(|(_, size)| size).sum(),
+    })
+}
+
+/// Creates the directories of a composite key, `{type}/a` for the key `a/1`
+async fn create_parent(key: &str, file_path: &str) -> anyhow::Result<()> {
+    if !key.contains('/') {
+        return Ok(());
+    }
+    if let Some(parent) = Path::new(file_path).parent() {
+        tokio::fs::create_dir_all(parent).await.with_context(|| {
+            format!("Failed to create directory at path: {}", parent.display())
+        })?;
+    }
+    Ok(())
+}
+
+/// Removes the temp files of a transaction that failed before any of them was moved into place
+async fn remove_staged(staged: &[(String, Option<String>)]) {
+    for temp_path in staged.iter().filter_map(|(_, temp_path)| temp_path.as_ref()) {
+        let _ = tokio::fs::remove_file(temp_path).await;
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<F: StreamingStorageFormat + Send + Sync> FileStorageClient<F> {
+
+    /// Like `get`, but reads the file as a stream instead of loading it first
+    /// - Returns `None` if the key does not exist
+    pub async fn get_streaming<O: StorageObject + DeserializeOwned>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let file_path = self.object_path::<O>(key);
+        let mut file = match tokio::fs::File::open(&file_path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let obj = F::deserialize_from(&mut file).await.with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    /// Like `put`, but writes the object to the file as it is serialized
+    pub async fn put_streaming<O: StorageObject + Serialize + Sync>(&self, key: &str, value: &O) -> anyhow::Result<()> {
+        let file_path = self.object_path::<O>(key);
+        create_parent(key, &file_path).await?;
+        let change = self.index_put(key, value).await?;
+        let temp_path = temp_path(&file_path).await?;
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        let written = F::serialize_into(value, &mut file).await.with_context(|| {
+            format!("Failed to write object to file for key: {}", key)
+        });
+        let moved = match written {
+            Ok(()) => tokio::fs::rename(&temp_path, &file_path).await.with_context(|| {
+                format!("Failed to move file into place at path: {}", file_path)
+            }),
+            Err(e) => Err(e),
+        };
+        if moved.is_err() {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return moved;
+        }
+        self.unindex_put::<O>(key, change).await;
+        self.index_text(key, value).await
+    }
+}
+
+impl<F: StorageFormat> Drop for FileStorageClient<F> {
+    fn drop(&mut self) {
+        if self.ephemeral {
+            let _ = std::fs::remove_dir_all(self.storage_url.path());
+            let _ = std::fs::remove_dir_all(snapshots_directory(self.storage_url.path()));
+            let _ = std::fs::remove_dir_all(leases_directory(self.storage_url.path()));
+        }
+    }
+}
+
 #[async_trait]
 impl<F> StorageClient<F> for FileStorageClient<F>
 where 
@@ -28,7 +542,13 @@ where
             format!("Failed to create directory at path: {}", path)
         })?;
 
-        Ok(Self { storage_url, _formatter: PhantomData::<F> })
+        Ok(Self {
+            storage_url,
+            ephemeral: false,
+            #[cfg(feature = "tantivy")]
+            search_indexes: Default::default(),
+            _formatter: PhantomData::<F>,
+        })
     }
 
     fn directory(&self) -> &str {
@@ -49,52 +569,66 @@ where
     // - key = the file name
     async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
         let file_path = self.object_path::<O>(key);
-        match tokio::fs::read(file_path).await {
-            Ok(data) => {
-                let obj = F::deserialize(&data).with_context(|| {
-                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
-                })?;
-                Ok(Some(obj))
-            }
-            Err(e) => {
-                Err(e.into())
-            }
-        }
+        let data = match tokio::fs::read(&file_path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read file at path: {}", file_path)),
+        };
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
     }
 
+    // written to a temp file and renamed into place, so a file shared with a snapshot is never modified
+    // index files are added before the object is written and dropped after, a stale one is filtered by `find_by`
     async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
         let file_path = self.object_path::<O>(key);
-        let mut file = match tokio::fs::File::create(&file_path).await {
-            Ok(file) => file,
-            Err(e) => return Err(e.into()),
-        };
-
         let data = F::serialize(&value).with_context(|| {
             format!("Failed to serialize object for key: {}", key)
         })?;
 
-        file.write_all(&data).await.with_context(|| {
+        create_parent(key, &file_path).await?;
+        let change = self.index_put(key, &value).await?;
+        let temp_path = temp_path(&file_path).await?;
+        tokio::fs::write(&temp_path, &data).await.with_context(|| {
             format!("Failed to write object to file for key: {}", key)
         })?;
+        if let Err(e) = tokio::fs::rename(&temp_path, &file_path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e).with_context(|| format!("Failed to move file into place at path: {}", file_path));
+        }
+        self.unindex_put::<O>(key, change).await;
+        self.index_text(key, &value).await?;
 
         Ok(())
     }
 
     async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
         let file_path = self.object_path::<O>(key);
-        tokio::fs::remove_file(file_path).await
+        let indexed_fields = O::indexed_fields();
+        let old = if indexed_fields.is_empty() { None } else { self.stored_fields::<O>(&file_path).await };
+        let deleted = tokio::fs::remove_file(file_path).await
             .map(|_| true)
             .or_else(|e| {
                 if e.kind() == std::io::ErrorKind::NotFound {
                     Ok(false)
                 } else {
-                    Err(e.into())
+                    Err::<bool, anyhow::Error>(e.into())
                 }
-            })
+            })?;
+        if let Some(old) = old.filter(|_| deleted) {
+            self.unindex(O::type_name(), &indexed_fields, key, &old, None).await;
+        }
+        if deleted {
+            self.update_search_index(O::type_name(), &O::text_fields(), key, None).await?;
+        }
+        Ok(deleted)
     }
 
     async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
         let full_path = format!("{}/{}", self.directory(), self.object_directory::<O>());
+        self.close_search_indexes().await;
         tokio::fs::remove_dir_all(full_path).await
             .map(|_| true)
             .or_else(|e| {
@@ -111,48 +645,598 @@ where
         if path.is_empty() {
             return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
         }
+        self.close_search_indexes().await;
         tokio::fs::remove_dir_all(path).await.with_context(|| {
             format!("Failed to remove directory at path: {}", path)
         })?;
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
+    // writes and removes a probe file, which fails on a missing, full or read-only directory
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        let probe_path = format!(
+            "{}/.health-{}-{}",
+            self.directory().trim_end_matches('/'),
+            std::process::id(),
+            WRITE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        );
+        if let Err(e) = tokio::fs::write(&probe_path, b"ok").await {
+            return Ok(HealthStatus::unhealthy(format!("Directory {} is not writable: {}", self.directory(), e)));
+        }
+        let _ = tokio::fs::remove_file(&probe_path).await;
+        Ok(HealthStatus::Healthy)
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let full_path = format!("{}/{}", self.directory(), self.object_directory::<O>());
+        let files = object_files(Path::new(&full_path)).await?;
+        Ok(files.into_iter().map(|(key, _)| key).collect())
+    }
+
+    // walks the object directory as the stream is polled instead of listing every key first,
+    // a key deleted between being listed and being read is left out
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        let full_path = PathBuf::from(format!("{}/{}", self.directory(), self.object_directory::<O>()));
+        object_keys(full_path)
+            .map_ok(move |key| async move {
+                let value = self.get::<O>(&key).await?;
+                Ok(value.map(|value| (key, value)))
+            })
+            .try_buffered(SCAN_CONCURRENCY)
+            .try_filter_map(|item| async move { Ok(item) })
+    }
+
 
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        let full_path = format!("{}/{}", self.directory(), self.object_directory::<O>());
+        Ok(object_files(Path::new(&full_path)).await?.len() as u64)
+    }
 
-    use crate::{json::JsonStorageFormat, RustStandardType, StorageSchema};
 
-    use super::*;
-    use ordermap::OrderMap;
-    use serde::{Deserialize, Serialize};
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let file_path = self.object_path::<O>(key);
+        tokio::fs::try_exists(&file_path).await.with_context(|| {
+            format!("Failed to check file at path: {}", file_path)
+        })
+    }
+
+
+    // the etag would need the whole file to be read, so files do not report one
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let file_path = self.object_path::<O>(key);
+        let metadata = match tokio::fs::metadata(&file_path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read metadata at path: {}", file_path)),
+        };
+        Ok(Some(ObjectMetadata {
+            size: metadata.len(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            etag: None,
+        }))
+    }
+
+
+    // counts the files under the type directory and sums their sizes, hidden directories are skipped
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        directory_usage(Path::new(&format!("{}/{}", self.directory(), self.object_directory::<O>()))).await
+    }
 
-    #[derive(Serialize, Deserialize, Debug, Clone)]
-    struct TestObject {
-        key: String,
-        value: String,
+    // every directory of the store is an object type
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        let mut usages = HashMap::new();
+        let mut directories = tokio::fs::read_dir(self.directory()).await.with_context(|| {
+            format!("Failed to read directory at path: {}", self.directory())
+        })?;
+        while let Some(directory) = directories.next_entry().await? {
+            if directory.file_type().await?.is_dir() {
+                let type_name = directory.file_name().to_string_lossy().into_owned();
+                usages.insert(type_name, directory_usage(&directory.path()).await?);
+            }
+        }
+        Ok(usages)
     }
 
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let reads = keys.iter().map(|key| async move {
+            let file_path = self.object_path::<O>(key);
+            match tokio::fs::read(&file_path).await {
+                Ok(data) => Ok(Some((key.to_string(), data))),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e).with_context(|| format!("Failed to read file at path: {}", file_path)),
+            }
+        });
+        let files = futures::future::try_join_all(reads).await?;
+
+        files.into_iter().flatten().map(|(key, data)| {
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
 
-    impl StorageObject for TestObject {
 
-        fn type_name() -> &'static str {
-            "TestObject"
+    // answered from the tantivy index, objects put before their type had text fields, or imported, are not in it
+    #[cfg(feature = "tantivy")]
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        if O::text_fields().is_empty() {
+            return Err(anyhow::anyhow!("{} has no text fields to search", O::type_name()));
         }
+        let index = self.search_index(O::type_name()).await?;
+        let terms = terms.to_string();
+        let keys = tokio::task::spawn_blocking(move || index.search(&terms)).await??;
+        let key_refs: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+        let mut objects: HashMap<String, O> = self.get_many(&key_refs).await?;
+        Ok(keys.into_iter().filter_map(|key| objects.remove(&key).map(|object| (key, object))).collect())
+    }
 
-        fn schema() -> StorageSchema {
-            let mut schema = OrderMap::new();
-            schema.insert("key".to_string(), RustStandardType::String);
-            schema.insert("value".to_string(), RustStandardType::String);
-            StorageSchema::Standard {
-                schema: schema,
-                primary_key: "key".to_string(),
-            } 
+    // keys come from the index files, which can be stale and are checked against the objects
+    // objects put before a field was indexed, or imported, are only found once they are put again
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        let value = serde_json::to_value(value).with_context(|| {
+            format!("Failed to serialize value of field {} of {}", field, O::type_name())
+        })?;
+        if !O::indexed_fields().contains(&field) {
+            return find_by_scan::<F, Self, O>(self, field, &value).await;
         }
+        let directory = self.index_directory(O::type_name(), field, &value);
+        let indexed_keys: Vec<String> = object_files(Path::new(&directory)).await?.into_iter().map(|(key, _)| key).collect();
+        let keys: Vec<&str> = indexed_keys.iter().map(|key| key.as_str()).collect();
+        let objects: HashMap<String, O> = self.get_many(&keys).await?;
 
+        let mut found = HashMap::new();
+        for (key, object) in objects {
+            if field_value(&object, field)? == value {
+                found.insert(key, object);
+            }
+        }
+        Ok(found)
     }
 
+
+    // Staged emulation: every put is written to a temp file in the `.tmp` directory next to its target first and only
+    // renamed into place once all of them are written, so a failed write leaves nothing behind.
+    // A crash or error while renaming can still leave part of the transaction applied.
+    // Index files of replaced values are not dropped, `find_by` filters them out.
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let mut staged: Vec<(String, Option<String>)> = Vec::with_capacity(operations.len());
+        for operation in &operations {
+            let file_path = format!("{}/{}/{}", self.directory(), operation.type_name(), operation.key());
+            let temp_path = match operation {
+                TransactionOperation::Put { data, fields, indexed_fields, .. } => {
+                    let prepared = match create_parent(operation.key(), &file_path).await {
+                        Ok(()) => self.index(operation.type_name(), &indexed_fields(), operation.key(), fields).await,
+                        Err(e) => Err(e),
+                    };
+                    let temp_path = match prepared {
+                        Ok(()) => temp_path(&file_path).await,
+                        Err(e) => Err(e),
+                    };
+                    let temp_path = match temp_path {
+                        Ok(temp_path) => temp_path,
+                        Err(e) => {
+                            remove_staged(&staged).await;
+                            return Err(e);
+                        }
+                    };
+                    if let Err(e) = tokio::fs::write(&temp_path, data).await {
+                        remove_staged(&staged).await;
+                        return Err(e).with_context(|| format!("Failed to stage file at path: {}", temp_path));
+                    }
+                    Some(temp_path)
+                }
+                TransactionOperation::Delete { .. } => None,
+            };
+            staged.push((file_path, temp_path));
+        }
+
+        for (file_path, temp_path) in staged {
+            match temp_path {
+                Some(temp_path) => tokio::fs::rename(&temp_path, &file_path).await.with_context(|| {
+                    format!("Failed to move staged file into place at path: {}", file_path)
+                })?,
+                None => match tokio::fs::remove_file(&file_path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(e).with_context(|| format!("Failed to delete file at path: {}", file_path));
+                    }
+                    _ => {}
+                },
+            }
+        }
+        for operation in &operations {
+            let fields = match operation {
+                TransactionOperation::Put { fields, .. } => Some(fields),
+                TransactionOperation::Delete { .. } => None,
+            };
+            self.update_search_index(operation.type_name(), &operation.text_fields(), operation.key(), fields).await?;
+        }
+        Ok(())
+    }
+
+
+    // each record is written as a 4 byte big endian length followed by the formatted object
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let length = u32::try_from(data.len())
+            .map_err(|_| anyhow::anyhow!("Appended {} for key: {} is larger than 4 GiB", O::type_name(), key))?;
+        let mut record = Vec::with_capacity(4 + data.len());
+        record.extend_from_slice(&length.to_be_bytes());
+        record.extend_from_slice(&data);
+
+        let log_path = self.appended_path::<O>(key);
+        // the parent of a composite key is nested inside the appended directory
+        let log_directory = Path::new(&log_path).parent().unwrap_or(Path::new(self.directory()));
+        tokio::fs::create_dir_all(log_directory).await.with_context(|| {
+            format!("Failed to create directory at path: {}", log_directory.display())
+        })?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .await
+            .with_context(|| format!("Failed to open file at path: {}", log_path))?;
+        // one write per record so concurrent appends do not interleave
+        file.write_all(&record).await.with_context(|| {
+            format!("Failed to append {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(())
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        let log_path = self.appended_path::<O>(key);
+        stream::once(async move {
+            match tokio::fs::File::open(&log_path).await {
+                Ok(file) => Ok(Some(BufReader::new(file))),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e).with_context(|| format!("Failed to open file at path: {}", log_path)),
+            }
+        })
+            .map_ok(|reader| stream::try_unfold(reader, |reader| async move {
+                let Some(mut reader) = reader else {
+                    return Ok(None);
+                };
+                let mut length = [0u8; 4];
+                match reader.read_exact(&mut length).await {
+                    Ok(_) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e).context("Failed to read appended record"),
+                }
+                let mut data = vec![0u8; u32::from_be_bytes(length) as usize];
+                reader.read_exact(&mut data).await.context("Failed to read appended record")?;
+                let record = F::deserialize::<O>(&data).with_context(|| {
+                    format!("Failed to deserialize appended {}", O::type_name())
+                })?;
+                Ok(Some((record, Some(reader))))
+            }))
+            .try_flatten()
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let id = SnapshotId::new(format!("{}-{}", nanos, WRITE_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let source = PathBuf::from(self.directory());
+        let target = snapshots_directory(self.directory()).join(id.as_str());
+        tokio::task::spawn_blocking(move || link_tree(&source, &target)).await??;
+        Ok(id)
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        if id.as_str().is_empty() || id.as_str().contains(['/', '\\']) || id.as_str().starts_with('.') {
+            return Err(anyhow::anyhow!("Invalid snapshot id: {}", id));
+        }
+        let snapshot = snapshots_directory(self.directory()).join(id.as_str());
+        if !tokio::fs::try_exists(&snapshot).await? {
+            return Err(anyhow::anyhow!("Snapshot does not exist: {}", id));
+        }
+        let directory = PathBuf::from(self.directory());
+        self.close_search_indexes().await;
+        tokio::task::spawn_blocking(move || {
+            match std::fs::remove_dir_all(&directory) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(e).with_context(|| format!("Failed to remove directory at path: {}", directory.display()));
+                }
+                _ => {}
+            }
+            link_tree(&snapshot, &directory)
+        }).await?
+    }
+
+    // type names are the directories of the store and keys the files in them
+    async fn export_all(&self, mut sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        dump::write_header(&mut sink).await?;
+        let mut count = 0;
+        let mut directories = tokio::fs::read_dir(self.directory()).await.with_context(|| {
+            format!("Failed to read directory at path: {}", self.directory())
+        })?;
+        while let Some(directory) = directories.next_entry().await? {
+            if !directory.file_type().await?.is_dir() {
+                continue;
+            }
+            let type_name = directory.file_name().to_string_lossy().into_owned();
+            for (key, _) in object_files(&directory.path()).await? {
+                let file_path = directory.path().join(&key);
+                let data = tokio::fs::read(&file_path).await.with_context(|| {
+                    format!("Failed to read file at path: {}", file_path.display())
+                })?;
+                dump::write_entry(&mut sink, &type_name, &key, &data).await?;
+                count += 1;
+            }
+        }
+        sink.flush().await.context("Failed to flush dump")?;
+        Ok(count)
+    }
+
+    async fn import_all(&self, mut source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        dump::read_header(&mut source).await?;
+        let mut count = 0;
+        while let Some(entry) = dump::read_entry(&mut source).await? {
+            // names come from the dump and must not reach outside the store
+            // composite keys are nested directories, each part is checked like a name
+            for name in std::iter::once(entry.type_name.as_str()).chain(entry.key.split('/')) {
+                if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+                    return Err(anyhow::anyhow!("Invalid name in dump: {:?}", name));
+                }
+            }
+            let directory = format!("{}/{}", self.directory(), entry.type_name);
+            tokio::fs::create_dir_all(&directory).await.with_context(|| {
+                format!("Failed to create directory at path: {}", directory)
+            })?;
+            let file_path = format!("{}/{}", directory, entry.key);
+            create_parent(&entry.key, &file_path).await?;
+            let temp_path = temp_path(&file_path).await?;
+            tokio::fs::write(&temp_path, &entry.data).await.with_context(|| {
+                format!("Failed to write file at path: {}", temp_path)
+            })?;
+            tokio::fs::rename(&temp_path, &file_path).await.with_context(|| {
+                format!("Failed to move file into place at path: {}", file_path)
+            })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // linking fails if the file exists, the file system decides the race
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let file_path = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        create_parent(key, &file_path).await?;
+        // an index file left by a lost race is filtered by `find_by`
+        self.index_put(key, &value).await?;
+        // written in full to a temp file first and linked into place, which fails if the key exists,
+        // so a reader never sees a partly written object
+        let temp_path = temp_path(&file_path).await?;
+        tokio::fs::write(&temp_path, &data).await.with_context(|| {
+            format!("Failed to write object to file for key: {}", key)
+        })?;
+        let linked = tokio::fs::hard_link(&temp_path, &file_path).await;
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        match linked {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+            Err(e) => return Err(e).with_context(|| format!("Failed to create file at path: {}", file_path)),
+        }
+        self.index_text(key, &value).await?;
+        Ok(true)
+    }
+
+
+    // the lock file serializes the merges of a key across processes, so one round is enough
+    // instead of the retry loop of the default, plain puts do not take it
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        let (lock_path, token) = self.lock_key::<O>(key).await?;
+        let merged = async {
+            let merged = match self.get::<O>(key).await? {
+                Some(existing) => resolver(existing, incoming),
+                None => incoming,
+            };
+            self.put(key, merged.clone()).await?;
+            Ok(merged)
+        }.await;
+        unlock_key(&lock_path, &token).await;
+        merged
+    }
+
+    // versions are hashes of the file content
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let file_path = self.object_path::<O>(key);
+        let data = match tokio::fs::read(&file_path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read file at path: {}", file_path)),
+        };
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some((obj, content_version(&data))))
+    }
+
+    // the lock file of `merge` makes the compare and the write one step for conditional writers,
+    // a plain `put` in between is not detected until the next conditional write
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let (lock_path, token) = self.lock_key::<O>(key).await?;
+        let written = async {
+            let file_path = self.object_path::<O>(key);
+            let actual = match tokio::fs::read(&file_path).await {
+                Ok(data) => Some(content_version(&data)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_context(|| format!("Failed to read file at path: {}", file_path)),
+            };
+            if actual.as_deref() != Some(expected_version) {
+                return Err(VersionConflictError {
+                    type_name: O::type_name(),
+                    key: key.to_string(),
+                    expected: expected_version.to_string(),
+                    actual,
+                }.into());
+            }
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            self.put(key, value).await?;
+            Ok(content_version(&data))
+        }.await;
+        unlock_key(&lock_path, &token).await;
+        written
+    }
+
+    // a lease is a file holding its expiry and token, created exclusively, an expired one is removed and taken over
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        // nested names are nested lease files, each part must stay inside the leases directory
+        if name.split('/').any(|part| part.is_empty() || part.contains('\\') || part.starts_with('.')) {
+            return Err(anyhow::anyhow!("Invalid lock name: {:?}", name));
+        }
+        let lease_path = leases_directory(self.directory()).join(name);
+        if let Some(parent) = lease_path.parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+        let token = lease_token();
+        let expires = SystemTime::now() + ttl;
+        for _ in 0..2 {
+            match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&lease_path).await {
+                Ok(mut file) => {
+                    file.write_all(format!("{}\n{}", epoch_millis(expires), token).as_bytes()).await.with_context(|| {
+                        format!("Failed to write lease at path: {}", lease_path.display())
+                    })?;
+                    let release_path = lease_path.clone();
+                    let release_token = token.clone();
+                    let release = async move {
+                        // the lease may have expired and been taken over meanwhile
+                        if let Some((_, holder)) = read_lease(&release_path).await?
+                            && holder == release_token
+                        {
+                            let _ = tokio::fs::remove_file(&release_path).await;
+                        }
+                        Ok(())
+                    };
+                    return Ok(Some(LeaseGuard::new(name, token, expires, release)));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    match read_lease(&lease_path).await? {
+                        Some((held_until, holder)) if held_until <= epoch_millis(SystemTime::now()) => {
+                            // moved aside rather than removed, so a lease another worker took over meanwhile can be put back
+                            let mut stale_path = lease_path.clone().into_os_string();
+                            stale_path.push(format!(".stale-{}", token));
+                            if tokio::fs::rename(&lease_path, &stale_path).await.is_ok() {
+                                if let Some((_, moved)) = read_lease(Path::new(&stale_path)).await?
+                                    && moved != holder
+                                {
+                                    let _ = tokio::fs::hard_link(&stale_path, &lease_path).await;
+                                }
+                                let _ = tokio::fs::remove_file(&stale_path).await;
+                            }
+                        }
+                        _ => return Ok(None),
+                    }
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to create lease at path: {}", lease_path.display())),
+            }
+        }
+        Ok(None)
+    }
+
+    // copied to a temp file and renamed into place like `put`, so a file shared with a snapshot is never modified
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        if from == to {
+            return self.exists::<O>(from).await;
+        }
+        let from_path = self.object_path::<O>(from);
+        let to_path = self.object_path::<O>(to);
+        create_parent(to, &to_path).await?;
+        let temp_path = temp_path(&to_path).await?;
+        if let Err(e) = tokio::fs::copy(&from_path, &temp_path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return Ok(false);
+            }
+            return Err(e).with_context(|| format!("Failed to copy file from {} to {}", from_path, temp_path));
+        }
+        let indexed_fields = O::indexed_fields();
+        let text_fields = O::text_fields();
+        let fields = if indexed_fields.is_empty() && text_fields.is_empty() { None } else { self.stored_fields::<O>(&temp_path).await };
+        let old = if indexed_fields.is_empty() { None } else { self.stored_fields::<O>(&to_path).await };
+        if let Some(fields) = &fields
+            && let Err(e) = self.index(O::type_name(), &indexed_fields, to, fields).await
+        {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+        if let Err(e) = tokio::fs::rename(&temp_path, &to_path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e).with_context(|| format!("Failed to move file into place at path: {}", to_path));
+        }
+        if let Some(old) = &old {
+            self.unindex(O::type_name(), &indexed_fields, to, old, fields.as_ref()).await;
+        }
+        if let Some(fields) = &fields {
+            self.update_search_index(O::type_name(), &text_fields, to, Some(fields)).await?;
+        }
+        Ok(true)
+    }
+
+    // a rename within one directory is atomic
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        // unindexing `from` after indexing `to` would drop the entries of the object itself
+        if from == to {
+            return self.exists::<O>(from).await;
+        }
+        let from_path = self.object_path::<O>(from);
+        let to_path = self.object_path::<O>(to);
+        create_parent(to, &to_path).await?;
+        let indexed_fields = O::indexed_fields();
+        let text_fields = O::text_fields();
+        let fields = if indexed_fields.is_empty() && text_fields.is_empty() { None } else { self.stored_fields::<O>(&from_path).await };
+        let old = if indexed_fields.is_empty() { None } else { self.stored_fields::<O>(&to_path).await };
+        if let Some(fields) = &fields {
+            self.index(O::type_name(), &indexed_fields, to, fields).await?;
+        }
+        match tokio::fs::rename(&from_path, &to_path).await {
+            Ok(_) => {
+                if let Some(old) = &old {
+                    self.unindex(O::type_name(), &indexed_fields, to, old, fields.as_ref()).await;
+                }
+                if let Some(fields) = &fields {
+                    self.unindex(O::type_name(), &indexed_fields, from, fields, None).await;
+                    self.update_search_index(O::type_name(), &text_fields, from, None).await?;
+                    self.update_search_index(O::type_name(), &text_fields, to, Some(fields)).await?;
+                }
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to rename file from {} to {}", from_path, to_path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+
+    use crate::{json::JsonStorageFormat, test_object::{IndexedTestObject, TestObject}};
+
+    use super::*;
+
     #[tokio::test]
     async fn test_file_storage_client_json() {
         let current_directory = std::env::current_dir().expect("Failed to get current directory"); 
@@ -201,6 +1285,8 @@ mod tests {
 
         // check the file does not exist
         assert!(tokio::fs::metadata(file_path).await.is_err());
+        let missing: Option<TestObject> = file_storage_client.get("test_key").await.unwrap();
+        assert!(missing.is_none());
 
         // remove the subdirectory
         assert!(file_storage_client.delete_object_directory::<TestObject>().await.is_ok());
@@ -214,4 +1300,342 @@ mod tests {
         // check the directory does not exist
         assert!(tokio::fs::metadata(dir).await.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_file_storage_client_ephemeral() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        let other = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        assert_ne!(client.directory(), other.directory());
+
+        let dir = client.directory().to_string();
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj).await.expect("Failed to put object");
+        assert!(tokio::fs::metadata(&dir).await.is_ok());
+
+        drop(client);
+        assert!(tokio::fs::metadata(&dir).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_list_keys() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        assert!(client.list_keys::<TestObject>().await.unwrap().is_empty());
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 0);
+
+        client.create_object_directory::<TestObject>().await.unwrap();
+        for key in ["b", "a"] {
+            let obj = TestObject {
+                key: key.to_string(),
+                value: "test_value".to_string(),
+            };
+            client.put(key, obj).await.expect("Failed to put object");
+        }
+        let mut keys = client.list_keys::<TestObject>().await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 2);
+
+        assert!(client.exists::<TestObject>("a").await.unwrap());
+        assert!(!client.exists::<TestObject>("missing").await.unwrap());
+
+        let objects: HashMap<String, TestObject> = client.get_many(&["a", "missing", "b"]).await.unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects["b"].key, "b");
+
+        let claim = TestObject { key: "c".to_string(), value: "first".to_string() };
+        assert!(client.put_if_absent("c", claim.clone()).await.unwrap());
+        assert!(!client.put_if_absent("c", TestObject { value: "second".to_string(), ..claim }).await.unwrap());
+        let stored: Option<TestObject> = client.get("c").await.unwrap();
+        assert_eq!(stored.unwrap().value, "first");
+        // the temp file of the losing put is gone
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 3);
+
+        assert!(client.copy::<TestObject>("c", "d").await.unwrap());
+        assert!(client.rename::<TestObject>("d", "e").await.unwrap());
+        assert!(!client.exists::<TestObject>("d").await.unwrap());
+        let renamed: Option<TestObject> = client.get("e").await.unwrap();
+        assert_eq!(renamed.unwrap().value, "first");
+        assert!(!client.rename::<TestObject>("missing", "f").await.unwrap());
+        assert!(client.copy::<TestObject>("e", "e").await.unwrap());
+        assert!(client.rename::<TestObject>("e", "e").await.unwrap());
+        let unchanged: Option<TestObject> = client.get("e").await.unwrap();
+        assert_eq!(unchanged.unwrap().value, "first");
+
+        let metadata = client.metadata::<TestObject>("e").await.unwrap().expect("Expected metadata");
+        let data = tokio::fs::read(client.object_path::<TestObject>("e")).await.unwrap();
+        assert_eq!(metadata.size, data.len() as u64);
+        assert!(metadata.modified.is_some());
+        assert!(client.metadata::<TestObject>("missing").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_hides_temp_files() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        client.put("a", TestObject { key: "a".to_string(), value: "1".to_string() }).await.unwrap();
+
+        // a write that is not moved into place yet
+        let temp_path = temp_path(&client.object_path::<TestObject>("b")).await.unwrap();
+        tokio::fs::write(&temp_path, b"{}").await.unwrap();
+
+        assert_eq!(client.list_keys::<TestObject>().await.unwrap(), vec!["a".to_string()]);
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 1);
+        let scanned: Vec<(String, TestObject)> = client.scan().try_collect().await.unwrap();
+        assert_eq!(scanned.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_concurrent_transactions() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+
+        // both stage the same key at the same position, neither may take the other's staged file
+        let commit = |value: &'static str| client.transaction(move |txn| async move {
+            txn.put("a", &TestObject { key: "a".to_string(), value: value.to_string() })?;
+            Ok(())
+        });
+        let (first, second) = tokio::join!(commit("1"), commit("2"));
+        first.unwrap();
+        second.unwrap();
+
+        assert_eq!(client.list_keys::<TestObject>().await.unwrap(), vec!["a".to_string()]);
+        let stored: Option<TestObject> = client.get("a").await.unwrap();
+        assert!(["1", "2"].contains(&stored.unwrap().value.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_scan_skips_deleted() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        let keys: Vec<String> = (0..SCAN_CONCURRENCY * 4).map(|i| format!("key_{}", i)).collect();
+        for key in &keys {
+            client.put(key, TestObject { key: key.clone(), value: "value".to_string() }).await.unwrap();
+        }
+
+        let mut scan = Box::pin(client.scan::<TestObject>());
+        let (first, _) = scan.try_next().await.unwrap().expect("Expected an object");
+        for key in keys.iter().filter(|key| **key != first) {
+            client.delete::<TestObject>(key).await.unwrap();
+        }
+        // keys already listed but deleted before they were read are skipped instead of failing the scan
+        let rest: Vec<(String, TestObject)> = scan.try_collect().await.unwrap();
+        assert!(rest.len() < keys.len() - 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_append() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        for value in ["first", "second"] {
+            let record = TestObject { key: "events".to_string(), value: value.to_string() };
+            client.append("events", record).await.expect("Failed to append record");
+        }
+
+        let records: Vec<TestObject> = client.read_appended("events").try_collect().await.unwrap();
+        let values: Vec<&str> = records.iter().map(|record| record.value.as_str()).collect();
+        assert_eq!(values, vec!["first", "second"]);
+        assert!(client.list_keys::<TestObject>().await.unwrap().is_empty());
+
+        let missing: Vec<TestObject> = client.read_appended("missing").try_collect().await.unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_snapshot() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        let before = TestObject { key: "test_key".to_string(), value: "before".to_string() };
+        client.put("test_key", before.clone()).await.unwrap();
+
+        client.put("other_key", TestObject { value: "other".to_string(), ..before.clone() }).await.unwrap();
+
+        let id = client.snapshot().await.expect("Failed to take snapshot");
+        client.put("test_key", TestObject { value: "after".to_string(), ..before.clone() }).await.unwrap();
+        client.put("new_key", before).await.unwrap();
+        client.copy::<TestObject>("test_key", "other_key").await.unwrap();
+
+        client.restore(&id).await.expect("Failed to restore snapshot");
+        let restored: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(restored.unwrap().value, "before");
+        let copied_over: Option<TestObject> = client.get("other_key").await.unwrap();
+        assert_eq!(copied_over.unwrap().value, "other");
+        assert!(!client.exists::<TestObject>("new_key").await.unwrap());
+        assert!(client.restore(&SnapshotId::new("missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_health_check() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        assert_eq!(client.health_check().await.unwrap(), HealthStatus::Healthy);
+
+        tokio::fs::remove_dir_all(client.directory()).await.unwrap();
+        assert!(!client.health_check().await.unwrap().is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_composite_key() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        let key = client.encode_key(&("tenant".to_string(), 1u64));
+        assert_eq!(key, "tenant/1");
+        let obj = TestObject { key: key.clone(), value: "nested".to_string() };
+        client.put(&key, obj).await.expect("Failed to put object");
+
+        assert!(tokio::fs::try_exists(format!("{}/TestObject/tenant/1", client.directory())).await.unwrap());
+        assert_eq!(client.list_keys::<TestObject>().await.unwrap(), vec![key.clone()]);
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 1);
+        let stored: Option<TestObject> = client.get(&key).await.unwrap();
+        assert_eq!(stored.unwrap().value, "nested");
+        assert!(client.delete::<TestObject>(&key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_find_by() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<IndexedTestObject>().await.unwrap();
+        for (key, value) in [("a", "red"), ("b", "blue"), ("c", "red")] {
+            client.put(key, IndexedTestObject { key: key.to_string(), value: value.to_string() }).await.unwrap();
+        }
+        client.put("c", IndexedTestObject { key: "c".to_string(), value: "blue".to_string() }).await.unwrap();
+        client.delete::<IndexedTestObject>("b").await.unwrap();
+
+        let red: HashMap<String, IndexedTestObject> = client.find_by("value", "red").await.unwrap();
+        assert_eq!(red.keys().collect::<Vec<_>>(), vec!["a"]);
+        let blue: HashMap<String, IndexedTestObject> = client.find_by("value", "blue").await.unwrap();
+        assert_eq!(blue.keys().collect::<Vec<_>>(), vec!["c"]);
+        assert!(!tokio::fs::try_exists(client.index_directory("IndexedTestObject", "value", &serde_json::json!("red")) + "/c").await.unwrap());
+
+        let by_key: HashMap<String, IndexedTestObject> = client.find_by("key", "a").await.unwrap();
+        assert_eq!(by_key.len(), 1);
+        assert_eq!(client.list_keys::<IndexedTestObject>().await.unwrap().len(), 2);
+
+        // copying or renaming onto a key drops its old index files, onto itself keeps them
+        client.copy::<IndexedTestObject>("a", "c").await.unwrap();
+        assert!(!tokio::fs::try_exists(client.index_directory("IndexedTestObject", "value", &serde_json::json!("blue")) + "/c").await.unwrap());
+        client.rename::<IndexedTestObject>("a", "a").await.unwrap();
+        let red: HashMap<String, IndexedTestObject> = client.find_by("value", "red").await.unwrap();
+        let mut red: Vec<_> = red.into_keys().collect();
+        red.sort();
+        assert_eq!(red, vec!["a", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_merge() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        let concat = |existing: TestObject, incoming: TestObject| TestObject { key: existing.key, value: format!("{}+{}", existing.value, incoming.value) };
+
+        let merged = client.merge("a", TestObject { key: "a".to_string(), value: "1".to_string() }, concat).await.unwrap();
+        assert_eq!(merged.value, "1");
+        let merges = (2..=5).map(|n| client.merge("a", TestObject { key: "a".to_string(), value: n.to_string() }, concat));
+        futures::future::try_join_all(merges).await.unwrap();
+
+        let stored: TestObject = client.get("a").await.unwrap().unwrap();
+        let mut parts: Vec<&str> = stored.value.split('+').collect();
+        parts.sort();
+        assert_eq!(parts, vec!["1", "2", "3", "4", "5"]);
+        assert_eq!(client.list_keys::<TestObject>().await.unwrap(), vec!["a".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_breaks_stale_key_lock() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        let concat = |existing: TestObject, incoming: TestObject| TestObject { key: existing.key, value: format!("{}+{}", existing.value, incoming.value) };
+
+        // a writer crashed while holding the lock of "a"
+        let lock_path = format!("{}/{}/{}/a", client.directory(), client.object_directory::<TestObject>(), LOCK_DIRECTORY);
+        tokio::fs::create_dir_all(Path::new(&lock_path).parent().unwrap()).await.unwrap();
+        let lock = std::fs::File::create(&lock_path).unwrap();
+        lock.set_modified(SystemTime::now() - KEY_LOCK_TTL * 2).unwrap();
+
+        let merged = client.merge("a", TestObject { key: "a".to_string(), value: "1".to_string() }, concat).await.unwrap();
+        assert_eq!(merged.value, "1");
+        assert!(!Path::new(&lock_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_put_if_version() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        client.put("a", TestObject { key: "a".to_string(), value: "1".to_string() }).await.unwrap();
+
+        let (_, version) = client.get_versioned::<TestObject>("a").await.unwrap().unwrap();
+        let written = client.put_if_version("a", TestObject { key: "a".to_string(), value: "2".to_string() }, &version).await.unwrap();
+        assert_ne!(written, version);
+        let error = client.put_if_version("a", TestObject { key: "a".to_string(), value: "3".to_string() }, &version).await.unwrap_err();
+        let conflict = error.downcast_ref::<VersionConflictError>().expect("Expected a version conflict");
+        assert_eq!(conflict.actual.as_deref(), Some(written.as_str()));
+        assert!(client.put_if_version("b", TestObject { key: "b".to_string(), value: "1".to_string() }, &version).await.is_err());
+        assert_eq!(client.list_keys::<TestObject>().await.unwrap(), vec!["a".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_lock() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        let lease = client.try_lock("orders/1", Duration::from_secs(30)).await.unwrap().expect("Expected the lock");
+        assert!(client.try_lock("orders/1", Duration::from_secs(30)).await.unwrap().is_none());
+        assert!(client.try_lock("orders/2", Duration::from_secs(30)).await.unwrap().is_some());
+        lease.release().await.unwrap();
+
+        // an expired lease is taken over, releasing it afterwards leaves the new holder alone
+        let expired = client.try_lock("orders/1", Duration::ZERO).await.unwrap().expect("Expected the lock");
+        let current = client.lock("orders/1", Duration::from_secs(30)).await.unwrap();
+        expired.release().await.unwrap();
+        assert!(client.try_lock("orders/1", Duration::from_secs(30)).await.unwrap().is_none());
+        current.release().await.unwrap();
+        assert!(client.try_lock("orders/1", Duration::from_secs(30)).await.unwrap().is_some());
+        for name in ["", "../escape", "/absolute", "orders/../../escape", "orders//1"] {
+            assert!(client.try_lock(name, Duration::from_secs(30)).await.is_err(), "{:?}", name);
+        }
+
+        let leases = leases_directory(client.directory());
+        assert!(tokio::fs::try_exists(&leases).await.unwrap());
+        drop(client);
+        assert!(!tokio::fs::try_exists(&leases).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_client_stream_query() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<TestObject>().await.unwrap();
+        for (key, value) in [("a", "red"), ("b", "blue"), ("tenant/c", "red"), ("tenant/d", "red")] {
+            client.put(key, TestObject { key: key.to_string(), value: value.to_string() }).await.unwrap();
+        }
+
+        let scanned: HashMap<String, TestObject> = client.scan::<TestObject>().try_collect().await.unwrap();
+        assert_eq!(scanned.len(), 4);
+        assert_eq!(scanned["tenant/c"].value, "red");
+        let mut red: Vec<String> = client.query::<TestObject>().eq("value", "red").stream()
+            .map_ok(|(key, _)| key)
+            .try_collect()
+            .await
+            .unwrap();
+        red.sort();
+        assert_eq!(red, vec!["a", "tenant/c", "tenant/d"]);
+        let limited: Vec<(String, TestObject)> = client.query::<TestObject>().eq("value", "red").limit(2).stream().try_collect().await.unwrap();
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[cfg(feature = "tantivy")]
+    #[tokio::test]
+    async fn test_file_storage_client_search() {
+        let client = FileStorageClient::<JsonStorageFormat>::ephemeral().await.expect("Failed to create ephemeral client");
+        client.create_object_directory::<IndexedTestObject>().await.unwrap();
+        for (key, value) in [("a", "The quick brown fox"), ("b", "A brown dog"), ("c", "Slow green turtle")] {
+            client.put(key, IndexedTestObject { key: key.to_string(), value: value.to_string() }).await.unwrap();
+        }
+        client.delete::<IndexedTestObject>("b").await.unwrap();
+        client.rename::<IndexedTestObject>("c", "d").await.unwrap();
+
+        let found: Vec<(String, IndexedTestObject)> = client.search("brown").await.unwrap();
+        assert_eq!(found.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["a"]);
+        let found: Vec<(String, IndexedTestObject)> = client.search("green turtle").await.unwrap();
+        assert_eq!(found.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["d"]);
+        assert!(client.search::<IndexedTestObject>("brown turtle").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use flexbuffers::{FlexBufferType, Reader, ReaderError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{envelope::FormatInfo, raw::{Payload, RawFormat}, StorageClient, StorageFormat, StorageObject};
+
+
+/// Schemaless binary encoding whose fields can be read in place.
+/// - `read_field` decodes a single top level field without deserializing the rest of the object
+#[derive(Debug, Clone)]
+pub struct FlexbuffersStorageFormat;
+
+impl FlexbuffersStorageFormat {
+
+    /// Reads one field of an object serialized as a struct or map
+    /// - Returns `None` if the object has no such field
+    pub fn read_field<V: DeserializeOwned>(data: &[u8], field: &str) -> anyhow::Result<Option<V>> {
+        let root = Reader::get_root(data)?;
+        if root.flexbuffer_type() != FlexBufferType::Map {
+            return Err(anyhow::anyhow!("Stored object is not a map, found: {:?}", root.flexbuffer_type()));
+        }
+        let value = match root.as_map().index(field) {
+            Ok(value) => value,
+            Err(ReaderError::KeyNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let value: V = Deserialize::deserialize(value).with_context(|| {
+            format!("Failed to deserialize field: {}", field)
+        })?;
+        Ok(Some(value))
+    }
+}
+
+impl FormatInfo for FlexbuffersStorageFormat {
+    const ID: &'static str = "flexbuffers";
+    const VERSION: u16 = 1;
+}
+
+impl StorageFormat for FlexbuffersStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        flexbuffers::to_vec(obj).map_err(|e| e.into())
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        flexbuffers::from_slice(data).map_err(|e| e.into())
+    }
+}
+
+/// Partial reads of objects stored in flexbuffers format, implemented for every `StorageClient<RawFormat>`
+/// - A `StorageClient<RawFormat>` reads the same objects a `StorageClient<FlexbuffersStorageFormat>`
+///   on the same storage writes
+#[async_trait]
+pub trait FlexbuffersStorage: StorageClient<RawFormat> + Sync {
+
+    /// Reads one field of the object stored under the key
+    /// - Returns `None` if the key or the field does not exist
+    async fn get_field<O, V>(&self, key: &str, field: &str) -> anyhow::Result<Option<V>>
+    where
+        O: StorageObject,
+        V: DeserializeOwned,
+    {
+        match self.get::<Payload<O>>(key).await? {
+            Some(payload) => FlexbuffersStorageFormat::read_field(payload.data(), field).with_context(|| {
+                format!("Failed to read field {} of {} for key: {}", field, O::type_name(), key)
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<C: StorageClient<RawFormat> + Sync> FlexbuffersStorage for C {}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use url::Url;
+
+    use crate::{memory_storage_client::MemoryStorageClient, RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        value: String,
+        blob: Vec<u8>,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("value".to_string(), RustStandardType::String);
+            schema.insert("blob".to_string(), RustStandardType::UInt8);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flexbuffers_storage_reads_single_field() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+            blob: vec![7; 1024],
+        };
+        let data = FlexbuffersStorageFormat::serialize(&obj).unwrap();
+        let retrieved: TestObject = FlexbuffersStorageFormat::deserialize(&data).unwrap();
+        assert_eq!(retrieved, obj);
+
+        let client = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://flexbuffers").unwrap()).await.unwrap();
+        client.put("test_key", Payload::<TestObject>::new(data)).await.unwrap();
+
+        let value: Option<String> = client.get_field::<TestObject, _>("test_key", "value").await.unwrap();
+        assert_eq!(value.as_deref(), Some("test_value"));
+        let missing: Option<String> = client.get_field::<TestObject, _>("test_key", "missing").await.unwrap();
+        assert!(missing.is_none());
+        let missing: Option<String> = client.get_field::<TestObject, _>("missing", "value").await.unwrap();
+        assert!(missing.is_none());
+    }
+}
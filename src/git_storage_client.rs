@@ -0,0 +1,318 @@
+use std::{
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use git2::{Commit, Oid, Repository, Signature};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// A commit that changed a stored object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitRevision {
+    pub id: String,
+    pub message: String,
+    // seconds since the unix epoch
+    pub time: i64,
+}
+
+/// Stores objects as files in a local git repository, committing every change.
+/// - `file:///path/to/repo`, the repository is initialized if it does not exist
+/// - Files use the `FileStorageClient` layout `{type_name}/{key}` inside the working tree
+/// - `history` and `get_revision` give access to previous versions of an object
+pub struct GitStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    repository: Arc<Mutex<Repository>>,
+    _formatter: PhantomData<F>,
+}
+
+fn signature(repository: &Repository) -> anyhow::Result<Signature<'static>> {
+    // fall back to a fixed identity when user.name/user.email are not configured
+    repository.signature()
+        .or_else(|_| Signature::now("storage", "storage@localhost"))
+        .map_err(|e| e.into())
+}
+
+fn head_commit(repository: &Repository) -> Option<Commit<'_>> {
+    repository.head().ok().and_then(|head| head.peel_to_commit().ok())
+}
+
+/// Writes the index as a tree and commits it on HEAD
+/// - Nothing is committed if the tree is unchanged
+fn commit_index(repository: &Repository, message: &str) -> anyhow::Result<()> {
+    let mut index = repository.index()?;
+    index.write()?;
+    let tree_id = index.write_tree()?;
+    let parent = head_commit(repository);
+    if parent.as_ref().map(|parent| parent.tree_id()) == Some(tree_id) {
+        return Ok(());
+    }
+
+    let tree = repository.find_tree(tree_id)?;
+    let signature = signature(repository)?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    repository.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .with_context(|| format!("Failed to commit: {}", message))?;
+    Ok(())
+}
+
+fn blob_id(commit: &Commit, path: &Path) -> Option<Oid> {
+    commit.tree().ok()?.get_path(path).ok().map(|entry| entry.id())
+}
+
+impl<F: StorageFormat> GitStorageClient<F> {
+
+    async fn run<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&Repository) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let repository = self.repository.clone();
+        tokio::task::spawn_blocking(move || {
+            let repository = repository
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Repository lock is poisoned"))?;
+            f(&repository)
+        }).await?
+    }
+
+    /// Path of the object relative to the working tree
+    fn relative_path<O: StorageObject>(key: &str) -> PathBuf {
+        Path::new(O::type_name()).join(key)
+    }
+
+    /// Commits that changed the object, newest first
+    pub async fn history<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<GitRevision>> {
+        let path = Self::relative_path::<O>(key);
+        self.run(move |repository| {
+            if head_commit(repository).is_none() {
+                return Ok(Vec::new());
+            }
+            let mut revwalk = repository.revwalk()?;
+            revwalk.push_head()?;
+
+            let mut revisions = Vec::new();
+            for id in revwalk {
+                let commit = repository.find_commit(id?)?;
+                let current = blob_id(&commit, &path);
+                let previous = commit.parent(0).ok().and_then(|parent| blob_id(&parent, &path));
+                if current != previous {
+                    revisions.push(GitRevision {
+                        id: commit.id().to_string(),
+                        message: commit.message().unwrap_or_default().to_string(),
+                        time: commit.time().seconds(),
+                    });
+                }
+            }
+            Ok(revisions)
+        }).await.with_context(|| {
+            format!("Failed to read history of {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    /// The object as it was at `revision` (any git revision such as a commit id, `HEAD~2` or a tag)
+    /// - Returns `None` if the object did not exist at that revision
+    pub async fn get_revision<O: StorageObject + DeserializeOwned>(&self, key: &str, revision: &str) -> anyhow::Result<Option<O>> {
+        let path = Self::relative_path::<O>(key);
+        let revision = revision.to_string();
+        let data = self.run(move |repository| {
+            let commit = repository.revparse_single(&revision)?.peel_to_commit()?;
+            let entry = match commit.tree()?.get_path(&path) {
+                Ok(entry) => entry,
+                Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let blob = repository.find_blob(entry.id())?;
+            Ok(Some(blob.content().to_vec()))
+        }).await?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for GitStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path().to_string();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+
+        let repository = tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&path).with_context(|| {
+                format!("Failed to create directory at path: {}", path)
+            })?;
+            match Repository::open(&path) {
+                Ok(repository) => Ok(repository),
+                Err(_) => Repository::init(&path).with_context(|| {
+                    format!("Failed to initialize git repository at path: {}", path)
+                }),
+            }
+        }).await??;
+
+        Ok(Self { storage_url, repository: Arc::new(Mutex::new(repository)), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        // git does not track empty directories, so this only prepares the working tree
+        let full_path = format!("{}/{}", self.directory(), self.object_directory::<O>());
+        tokio::fs::create_dir_all(&full_path).await.with_context(|| {
+            format!("Failed to create subdirectory at path: {}", full_path)
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let file_path = self.object_path::<O>(key);
+        let data = match tokio::fs::read(&file_path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let file_path = PathBuf::from(self.object_path::<O>(key));
+        let relative_path = Self::relative_path::<O>(key);
+        let message = format!("put {}/{}", O::type_name(), key);
+        self.run(move |repository| {
+            if let Some(parent) = file_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&file_path, data)?;
+            let mut index = repository.index()?;
+            index.add_path(&relative_path)?;
+            index.write()?;
+            commit_index(repository, &message)
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let file_path = PathBuf::from(self.object_path::<O>(key));
+        let relative_path = Self::relative_path::<O>(key);
+        let message = format!("delete {}/{}", O::type_name(), key);
+        self.run(move |repository| {
+            match std::fs::remove_file(&file_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+            let mut index = repository.index()?;
+            index.remove_path(&relative_path)?;
+            index.write()?;
+            commit_index(repository, &message)?;
+            Ok(true)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let full_path = PathBuf::from(format!("{}/{}", self.directory(), self.object_directory::<O>()));
+        let directory = self.object_directory::<O>().to_string();
+        let message = format!("delete {}", O::type_name());
+        self.run(move |repository| {
+            match std::fs::remove_dir_all(&full_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+            let mut index = repository.index()?;
+            index.remove_all([directory.as_str()], None)?;
+            index.write()?;
+            commit_index(repository, &message)?;
+            Ok(true)
+        }).await
+    }
+
+    // the repository and its history are kept, only the stored objects are removed in a final commit
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.run(move |repository| {
+            let workdir = repository.workdir()
+                .ok_or_else(|| anyhow::anyhow!("Repository has no working tree"))?
+                .to_path_buf();
+            for entry in std::fs::read_dir(&workdir)? {
+                let entry = entry?;
+                if entry.file_name() == ".git" {
+                    continue;
+                }
+                if entry.file_type()?.is_dir() {
+                    std::fs::remove_dir_all(entry.path())?;
+                } else {
+                    std::fs::remove_file(entry.path())?;
+                }
+            }
+            let mut index = repository.index()?;
+            index.clear()?;
+            index.write()?;
+            commit_index(repository, "delete all")
+        }).await.context("Failed to delete all objects")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_git_storage_client_history() {
+        let test_directory = std::env::temp_dir().join("git_storage_client_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let url = Url::from_directory_path(&test_directory).expect("Failed to create URL from directory path");
+        let client = GitStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to open repository");
+
+        let first = TestObject { key: "test_key".to_string(), value: "first".to_string() };
+        let second = TestObject { key: "test_key".to_string(), value: "second".to_string() };
+        client.put("test_key", first.clone()).await.expect("Failed to put object");
+        client.put("test_key", second.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(second));
+
+        let history = client.history::<TestObject>("test_key").await.unwrap();
+        assert_eq!(history.len(), 2);
+        let previous: Option<TestObject> = client.get_revision("test_key", &history[1].id).await.unwrap();
+        assert_eq!(previous, Some(first));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+        assert_eq!(client.history::<TestObject>("test_key").await.unwrap().len(), 3);
+
+        assert!(client.delete_all().await.is_ok());
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
@@ -0,0 +1,601 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tonic::{transport::Channel, Request, Response, Status};
+use url::Url;
+
+use crate::{
+    content_version, lease::{epoch_millis, lease_token, LeaseGuard}, StorageClient, StorageFormat, StorageObject,
+    VersionConflictError,
+};
+
+use proto::{
+    storage_service_client::StorageServiceClient,
+    storage_service_server::{StorageService, StorageServiceServer},
+    CreateObjectDirectoryResponse, DeleteAllRequest, DeleteAllResponse, DeleteManyResponse, DeleteRequest, DeleteResponse,
+    DirectoryRequest, DirectoryResponse, ExistsResponse, GetManyRequest, GetManyResponse, GetRequest, GetResponse,
+    GetVersionedResponse, ListKeysResponse, ObjectDirectoryRequest, PutIfAbsentResponse, PutIfVersionRequest,
+    PutIfVersionResponse, PutRequest, PutResponse, ReleaseLockRequest, ReleaseLockResponse, TransferRequest,
+    TransferResponse, TryLockRequest, TryLockResponse,
+};
+
+/// Types generated from `proto/storage.proto`
+pub mod proto {
+    tonic::include_proto!("storage");
+}
+
+/// Proxies every call to a remote `StorageService`.
+/// - `grpc://host:50051`, the directory is the one reported by the server
+/// - Objects are serialized with `F` on the client, the server only sees bytes
+/// - Keys, batches, conditional writes, copies, renames and locks are RPCs, queries, searches and scans
+///   run on the client over `list_keys` and `get_many`
+/// - Snapshots, dumps, transactions, history and appends are not proxied and return an `UnsupportedError`
+pub struct GrpcStorageClient<F: StorageFormat> {
+    client: StorageServiceClient<Channel>,
+    directory: String,
+    _formatter: PhantomData<F>,
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for GrpcStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        if storage_url.scheme() != "grpc" {
+            return Err(anyhow::anyhow!("Storage URL must use the grpc scheme: {}", storage_url));
+        }
+        let host = storage_url.host_str()
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a host"))?;
+        let endpoint = format!("http://{}:{}", host, storage_url.port().unwrap_or(50051));
+
+        let channel = Channel::from_shared(endpoint.clone())?
+            .connect()
+            .await
+            .with_context(|| format!("Failed to connect to storage service at: {}", endpoint))?;
+        let mut client = StorageServiceClient::new(channel);
+
+        let directory = client.directory(DirectoryRequest {}).await
+            .context("Failed to get directory from storage service")?
+            .into_inner()
+            .directory;
+
+        Ok(Self { client, directory, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.directory
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let request = ObjectDirectoryRequest { type_name: O::type_name().to_string() };
+        // tonic clients are cheap to clone and need `&mut self` per call
+        self.client.clone().create_object_directory(request).await.with_context(|| {
+            format!("Failed to create object directory for {}", O::type_name())
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let request = GetRequest { type_name: O::type_name().to_string(), key: key.to_string() };
+        let response = self.client.clone().get(request).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match response.into_inner().value {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let request = PutRequest { type_name: O::type_name().to_string(), key: key.to_string(), value: data };
+        self.client.clone().put(request).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let request = DeleteRequest { type_name: O::type_name().to_string(), key: key.to_string() };
+        let response = self.client.clone().delete(request).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(response.into_inner().deleted)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let request = ObjectDirectoryRequest { type_name: O::type_name().to_string() };
+        let response = self.client.clone().delete_object_directory(request).await.with_context(|| {
+            format!("Failed to delete object directory for {}", O::type_name())
+        })?;
+        Ok(response.into_inner().deleted)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.client.clone().delete_all(DeleteAllRequest {}).await
+            .context("Failed to delete all objects")?;
+        Ok(())
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let request = ObjectDirectoryRequest { type_name: O::type_name().to_string() };
+        let response = self.client.clone().list_keys(request).await.with_context(|| {
+            format!("Failed to list keys of {}", O::type_name())
+        })?;
+        Ok(response.into_inner().keys)
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let request = GetRequest { type_name: O::type_name().to_string(), key: key.to_string() };
+        let response = self.client.clone().exists(request).await.with_context(|| {
+            format!("Failed to check {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(response.into_inner().exists)
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let request = GetManyRequest {
+            type_name: O::type_name().to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        let response = self.client.clone().get_many(request).await.with_context(|| {
+            format!("Failed to get {} objects of {}", keys.len(), O::type_name())
+        })?;
+
+        let mut objects = HashMap::new();
+        for (key, data) in response.into_inner().values {
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            objects.insert(key, obj);
+        }
+        Ok(objects)
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let request = GetManyRequest {
+            type_name: O::type_name().to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        let response = self.client.clone().delete_many(request).await.with_context(|| {
+            format!("Failed to delete {} objects of {}", keys.len(), O::type_name())
+        })?;
+        Ok(response.into_inner().deleted as usize)
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let request = GetRequest { type_name: O::type_name().to_string(), key: key.to_string() };
+        let response = self.client.clone().get_versioned(request).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?.into_inner();
+
+        match response.value {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some((obj, response.version)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let request = PutIfVersionRequest {
+            type_name: O::type_name().to_string(),
+            key: key.to_string(),
+            value: data,
+            expected_version: expected_version.to_string(),
+        };
+        let response = self.client.clone().put_if_version(request).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })?.into_inner();
+
+        if !response.written {
+            return Err(VersionConflictError {
+                type_name: O::type_name(),
+                key: key.to_string(),
+                expected: expected_version.to_string(),
+                actual: response.version,
+            }.into());
+        }
+        Ok(response.version.unwrap_or_default())
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let request = PutRequest { type_name: O::type_name().to_string(), key: key.to_string(), value: data };
+        let response = self.client.clone().put_if_absent(request).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(response.into_inner().written)
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let request = TransferRequest { type_name: O::type_name().to_string(), from: from.to_string(), to: to.to_string() };
+        let response = self.client.clone().copy(request).await.with_context(|| {
+            format!("Failed to copy {} from key: {} to key: {}", O::type_name(), from, to)
+        })?;
+        Ok(response.into_inner().transferred)
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let request = TransferRequest { type_name: O::type_name().to_string(), from: from.to_string(), to: to.to_string() };
+        let response = self.client.clone().rename(request).await.with_context(|| {
+            format!("Failed to rename {} from key: {} to key: {}", O::type_name(), from, to)
+        })?;
+        Ok(response.into_inner().transferred)
+    }
+
+    // the server keeps the leases and sets their expiry, the guard releases through the service
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        let request = TryLockRequest { name: name.to_string(), ttl_millis: ttl.as_millis() as u64 };
+        let response = self.client.clone().try_lock(request).await.with_context(|| {
+            format!("Failed to take lock: {}", name)
+        })?.into_inner();
+
+        let token = match response.token {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        let expires = UNIX_EPOCH + Duration::from_millis(response.expires_millis);
+        let mut client = self.client.clone();
+        let release = ReleaseLockRequest { name: name.to_string(), token: token.clone() };
+        Ok(Some(LeaseGuard::new(name, token, expires, async move {
+            let name = release.name.clone();
+            client.release_lock(release).await.with_context(|| format!("Failed to release lock: {}", name))?;
+            Ok(())
+        })))
+    }
+}
+
+/// `StorageService` implementation that keeps the bytes on disk with the `FileStorageClient` layout,
+/// so a `FileStorageClient` on the server sees the same objects.
+/// ```ignore
+/// tonic::transport::Server::builder()
+///     .add_service(GrpcStorageServer::new("/var/lib/storage").into_service())
+///     .serve("0.0.0.0:50051".parse()?)
+///     .await?;
+/// ```
+/// - Conditional writes and copies are serialized with each other, plain puts are not checked against them
+/// - Leases live in the memory of the server and are gone when it restarts
+pub struct GrpcStorageServer {
+    directory: PathBuf,
+    // held while a conditional write, copy or rename reads and then writes
+    write_lock: tokio::sync::Mutex<()>,
+    // lock name to the token of its holder and when the lease runs out
+    leases: Mutex<HashMap<String, (String, SystemTime)>>,
+}
+
+/// Rejects names that would escape the server directory
+fn path_segment(name: &str) -> Result<&str, Status> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(Status::invalid_argument(format!("Invalid path segment: {:?}", name)));
+    }
+    Ok(name)
+}
+
+fn internal(e: std::io::Error) -> Status {
+    Status::internal(e.to_string())
+}
+
+impl GrpcStorageServer {
+
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: directory.into(), write_lock: tokio::sync::Mutex::new(()), leases: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn into_service(self) -> StorageServiceServer<Self> {
+        StorageServiceServer::new(self)
+    }
+
+    fn object_directory(&self, type_name: &str) -> Result<PathBuf, Status> {
+        Ok(self.directory.join(path_segment(type_name)?))
+    }
+
+    fn object_path(&self, type_name: &str, key: &str) -> Result<PathBuf, Status> {
+        Ok(self.object_directory(type_name)?.join(path_segment(key)?))
+    }
+}
+
+/// Content of the file at `path`, `None` if there is none
+async fn read(path: &Path) -> Result<Option<Vec<u8>>, Status> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(internal(e)),
+    }
+}
+
+/// Removes `path` and reports whether it existed
+async fn remove(path: &Path, is_dir: bool) -> Result<bool, Status> {
+    let result = if is_dir {
+        tokio::fs::remove_dir_all(path).await
+    } else {
+        tokio::fs::remove_file(path).await
+    };
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(internal(e)),
+    }
+}
+
+#[tonic::async_trait]
+impl StorageService for GrpcStorageServer {
+
+    async fn directory(&self, _request: Request<DirectoryRequest>) -> Result<Response<DirectoryResponse>, Status> {
+        Ok(Response::new(DirectoryResponse { directory: self.directory.to_string_lossy().into_owned() }))
+    }
+
+    async fn create_object_directory(&self, request: Request<ObjectDirectoryRequest>) -> Result<Response<CreateObjectDirectoryResponse>, Status> {
+        let path = self.object_directory(&request.into_inner().type_name)?;
+        tokio::fs::create_dir_all(&path).await.map_err(internal)?;
+        Ok(Response::new(CreateObjectDirectoryResponse {}))
+    }
+
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        let value = read(&path).await?;
+        Ok(Response::new(GetResponse { value }))
+    }
+
+    async fn put(&self, request: Request<PutRequest>) -> Result<Response<PutResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        tokio::fs::create_dir_all(self.object_directory(&request.type_name)?).await.map_err(internal)?;
+        tokio::fs::write(&path, request.value).await.map_err(internal)?;
+        Ok(Response::new(PutResponse {}))
+    }
+
+    async fn delete(&self, request: Request<DeleteRequest>) -> Result<Response<DeleteResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        let deleted = remove(&path, false).await?;
+        Ok(Response::new(DeleteResponse { deleted }))
+    }
+
+    async fn delete_object_directory(&self, request: Request<ObjectDirectoryRequest>) -> Result<Response<DeleteResponse>, Status> {
+        let path = self.object_directory(&request.into_inner().type_name)?;
+        let deleted = remove(&path, true).await?;
+        Ok(Response::new(DeleteResponse { deleted }))
+    }
+
+    async fn delete_all(&self, _request: Request<DeleteAllRequest>) -> Result<Response<DeleteAllResponse>, Status> {
+        remove(&self.directory, true).await?;
+        Ok(Response::new(DeleteAllResponse {}))
+    }
+
+    async fn list_keys(&self, request: Request<ObjectDirectoryRequest>) -> Result<Response<ListKeysResponse>, Status> {
+        let path = self.object_directory(&request.into_inner().type_name)?;
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Response::new(ListKeysResponse { keys: Vec::new() })),
+            Err(e) => return Err(internal(e)),
+        };
+        let mut keys = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(internal)? {
+            if entry.file_type().await.map_err(internal)?.is_file() {
+                keys.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(Response::new(ListKeysResponse { keys }))
+    }
+
+    async fn exists(&self, request: Request<GetRequest>) -> Result<Response<ExistsResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        let exists = tokio::fs::try_exists(&path).await.map_err(internal)?;
+        Ok(Response::new(ExistsResponse { exists }))
+    }
+
+    async fn get_many(&self, request: Request<GetManyRequest>) -> Result<Response<GetManyResponse>, Status> {
+        let request = request.into_inner();
+        let mut values = HashMap::new();
+        for key in request.keys {
+            if let Some(data) = read(&self.object_path(&request.type_name, &key)?).await? {
+                values.insert(key, data);
+            }
+        }
+        Ok(Response::new(GetManyResponse { values }))
+    }
+
+    async fn delete_many(&self, request: Request<GetManyRequest>) -> Result<Response<DeleteManyResponse>, Status> {
+        let request = request.into_inner();
+        let mut deleted = 0;
+        for key in request.keys {
+            if remove(&self.object_path(&request.type_name, &key)?, false).await? {
+                deleted += 1;
+            }
+        }
+        Ok(Response::new(DeleteManyResponse { deleted }))
+    }
+
+    async fn get_versioned(&self, request: Request<GetRequest>) -> Result<Response<GetVersionedResponse>, Status> {
+        let request = request.into_inner();
+        let value = read(&self.object_path(&request.type_name, &request.key)?).await?;
+        let version = value.as_deref().map(content_version).unwrap_or_default();
+        Ok(Response::new(GetVersionedResponse { value, version }))
+    }
+
+    async fn put_if_version(&self, request: Request<PutIfVersionRequest>) -> Result<Response<PutIfVersionResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        let _write = self.write_lock.lock().await;
+        let actual = read(&path).await?.map(|data| content_version(&data));
+        if actual.as_deref() != Some(request.expected_version.as_str()) {
+            return Ok(Response::new(PutIfVersionResponse { written: false, version: actual }));
+        }
+        let version = content_version(&request.value);
+        tokio::fs::write(&path, request.value).await.map_err(internal)?;
+        Ok(Response::new(PutIfVersionResponse { written: true, version: Some(version) }))
+    }
+
+    async fn put_if_absent(&self, request: Request<PutRequest>) -> Result<Response<PutIfAbsentResponse>, Status> {
+        let request = request.into_inner();
+        let path = self.object_path(&request.type_name, &request.key)?;
+        tokio::fs::create_dir_all(self.object_directory(&request.type_name)?).await.map_err(internal)?;
+        let _write = self.write_lock.lock().await;
+        // `create_new` fails if the file exists, even if a plain put wrote it meanwhile
+        let file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await;
+        let mut file = match file {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(Response::new(PutIfAbsentResponse { written: false })),
+            Err(e) => return Err(internal(e)),
+        };
+        tokio::io::AsyncWriteExt::write_all(&mut file, &request.value).await.map_err(internal)?;
+        Ok(Response::new(PutIfAbsentResponse { written: true }))
+    }
+
+    async fn copy(&self, request: Request<TransferRequest>) -> Result<Response<TransferResponse>, Status> {
+        let request = request.into_inner();
+        let from = self.object_path(&request.type_name, &request.from)?;
+        let to = self.object_path(&request.type_name, &request.to)?;
+        let _write = self.write_lock.lock().await;
+        let transferred = match read(&from).await? {
+            Some(data) => {
+                if from != to {
+                    tokio::fs::write(&to, data).await.map_err(internal)?;
+                }
+                true
+            }
+            None => false,
+        };
+        Ok(Response::new(TransferResponse { transferred }))
+    }
+
+    async fn rename(&self, request: Request<TransferRequest>) -> Result<Response<TransferResponse>, Status> {
+        let request = request.into_inner();
+        let from = self.object_path(&request.type_name, &request.from)?;
+        let to = self.object_path(&request.type_name, &request.to)?;
+        let _write = self.write_lock.lock().await;
+        let transferred = match tokio::fs::rename(&from, &to).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) => return Err(internal(e)),
+        };
+        Ok(Response::new(TransferResponse { transferred }))
+    }
+
+    async fn try_lock(&self, request: Request<TryLockRequest>) -> Result<Response<TryLockResponse>, Status> {
+        let request = request.into_inner();
+        let now = SystemTime::now();
+        let expires = now + Duration::from_millis(request.ttl_millis);
+        let mut leases = self.leases.lock().map_err(|_| Status::internal("Lease table is poisoned"))?;
+        if leases.get(&request.name).is_some_and(|(_, holder_expires)| *holder_expires > now) {
+            return Ok(Response::new(TryLockResponse { token: None, expires_millis: 0 }));
+        }
+        let token = lease_token();
+        leases.insert(request.name, (token.clone(), expires));
+        Ok(Response::new(TryLockResponse { token: Some(token), expires_millis: epoch_millis(expires) }))
+    }
+
+    async fn release_lock(&self, request: Request<ReleaseLockRequest>) -> Result<Response<ReleaseLockResponse>, Status> {
+        let request = request.into_inner();
+        let mut leases = self.leases.lock().map_err(|_| Status::internal("Lease table is poisoned"))?;
+        // an expired lease may have been taken over, only its holder removes it
+        if leases.get(&request.name).is_some_and(|(token, _)| *token == request.token) {
+            leases.remove(&request.name);
+        }
+        Ok(Response::new(ReleaseLockResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_grpc_storage_server() {
+        let test_directory = std::env::temp_dir().join("grpc_storage_server_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let server = GrpcStorageServer::new(&test_directory);
+
+        let put = PutRequest { type_name: "TestObject".to_string(), key: "test_key".to_string(), value: b"{}".to_vec() };
+        server.put(Request::new(put)).await.expect("Failed to put object");
+
+        let get = GetRequest { type_name: "TestObject".to_string(), key: "test_key".to_string() };
+        let response = server.get(Request::new(get.clone())).await.unwrap().into_inner();
+        assert_eq!(response.value, Some(b"{}".to_vec()));
+
+        let delete = DeleteRequest { type_name: "TestObject".to_string(), key: "test_key".to_string() };
+        assert!(server.delete(Request::new(delete.clone())).await.unwrap().into_inner().deleted);
+        assert!(!server.delete(Request::new(delete)).await.unwrap().into_inner().deleted);
+        assert_eq!(server.get(Request::new(get)).await.unwrap().into_inner().value, None);
+
+        let escape = GetRequest { type_name: "..".to_string(), key: "passwd".to_string() };
+        let status = server.get(Request::new(escape)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        assert!(server.delete_all(Request::new(DeleteAllRequest {})).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_grpc_storage_server_conditional_operations() {
+        let test_directory = std::env::temp_dir().join("grpc_storage_server_conditional_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let server = GrpcStorageServer::new(&test_directory);
+        let put = |key: &str, value: &[u8]| PutRequest { type_name: "TestObject".to_string(), key: key.to_string(), value: value.to_vec() };
+
+        assert!(server.put_if_absent(Request::new(put("a", b"1"))).await.unwrap().into_inner().written);
+        assert!(!server.put_if_absent(Request::new(put("a", b"2"))).await.unwrap().into_inner().written);
+
+        let conditional = |expected_version: String| PutIfVersionRequest {
+            type_name: "TestObject".to_string(),
+            key: "a".to_string(),
+            value: b"3".to_vec(),
+            expected_version,
+        };
+        let stale = server.put_if_version(Request::new(conditional("stale".to_string()))).await.unwrap().into_inner();
+        assert_eq!((stale.written, stale.version), (false, Some(content_version(b"1"))));
+        let written = server.put_if_version(Request::new(conditional(content_version(b"1")))).await.unwrap().into_inner();
+        assert_eq!((written.written, written.version), (true, Some(content_version(b"3"))));
+
+        let transfer = |from: &str, to: &str| TransferRequest { type_name: "TestObject".to_string(), from: from.to_string(), to: to.to_string() };
+        assert!(server.copy(Request::new(transfer("a", "b"))).await.unwrap().into_inner().transferred);
+        assert!(server.rename(Request::new(transfer("b", "c"))).await.unwrap().into_inner().transferred);
+        assert!(!server.rename(Request::new(transfer("b", "d"))).await.unwrap().into_inner().transferred);
+
+        let mut keys = server.list_keys(Request::new(ObjectDirectoryRequest { type_name: "TestObject".to_string() })).await.unwrap().into_inner().keys;
+        keys.sort();
+        assert_eq!(keys, vec!["a", "c"]);
+        let many = GetManyRequest { type_name: "TestObject".to_string(), keys: vec!["a".to_string(), "missing".to_string()] };
+        assert_eq!(server.get_many(Request::new(many.clone())).await.unwrap().into_inner().values.len(), 1);
+        assert_eq!(server.delete_many(Request::new(many)).await.unwrap().into_inner().deleted, 1);
+
+        let lock = |name: &str| TryLockRequest { name: name.to_string(), ttl_millis: 5000 };
+        let token = server.try_lock(Request::new(lock("job"))).await.unwrap().into_inner().token.expect("Expected the lock");
+        assert_eq!(server.try_lock(Request::new(lock("job"))).await.unwrap().into_inner().token, None);
+        server.release_lock(Request::new(ReleaseLockRequest { name: "job".to_string(), token })).await.unwrap();
+        assert!(server.try_lock(Request::new(lock("job"))).await.unwrap().into_inner().token.is_some());
+
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
@@ -0,0 +1,9 @@
+/// 64-bit FNV-1a, stable across Rust versions unlike `DefaultHasher`, so hashes can be stored and compared later
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, marker::PhantomData, pin::pin};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::{future, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, HealthStatus, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage};
+
+/// Keeps every version of every object put through it, so overwritten objects can be recovered.
+/// - Each `put` is appended to the history of its key with the inner client's `append`,
+///   then stored as the current object, the inner client has to support `append`
+/// - Versions are numbered from "1" per key, read them with `list_versions` and `get_version`
+/// - Deleting an object keeps its history, `delete_object_directory` and `delete_all` remove it
+/// - Version numbers are unrelated to the content versions of `get_versioned`
+pub struct HistoryStorageClient<F: StorageFormat, C> {
+    inner: C,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> HistoryStorageClient<F, C> {
+
+    pub fn new(inner: C) -> Self {
+        Self { inner, _formatter: PhantomData::<F> }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+fn deserialize<F: StorageFormat, O: StorageObject + DeserializeOwned>(key: &str, payload: Payload<O>) -> anyhow::Result<O> {
+    F::deserialize(payload.data()).with_context(|| {
+        format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+    })
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for HistoryStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<RawFormat> + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<Payload<O>>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        match self.inner.get::<Payload<O>>(key).await? {
+            Some(payload) => Ok(Some(deserialize::<F, O>(key, payload)?)),
+            None => Ok(None),
+        }
+    }
+
+    // the version is recorded first, a failed put leaves a version that never became current
+    // rather than a current object missing from the history
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        self.inner.append(key, Payload::<O>::new(data.clone())).await.with_context(|| {
+            format!("Failed to record version of {} for key: {}", O::type_name(), key)
+        })?;
+        self.inner.put(key, Payload::<O>::new(data)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.delete::<Payload<O>>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.inner.delete_object_directory::<Payload<O>>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.inner.restore(id).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.import_all(source).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<Payload<O>>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<Payload<O>>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<Payload<O>>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<Payload<O>>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<Payload<O>>(key).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<Payload<O>>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let payloads = self.inner.get_many::<Payload<O>>(keys).await?;
+        payloads.into_iter()
+            .map(|(key, payload)| {
+                let obj = deserialize::<F, O>(&key, payload)?;
+                Ok((key, obj))
+            })
+            .collect()
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.inner.delete_many::<Payload<O>>(keys).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        if !self.inner.put_if_absent(key, Payload::<O>::new(data.clone())).await? {
+            return Ok(false);
+        }
+        self.inner.append(key, Payload::<O>::new(data)).await.with_context(|| {
+            format!("Failed to record version of {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(true)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        let count = self.inner
+            .read_appended::<Payload<O>>(key)
+            .try_fold(0usize, |count, _| future::ready(Ok(count + 1)))
+            .await?;
+        Ok((1..=count).map(|version| version.to_string()).collect())
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        let index = match version.parse::<usize>() {
+            Ok(version) if version > 0 => version - 1,
+            _ => return Ok(None),
+        };
+        let mut versions = pin!(self.inner.read_appended::<Payload<O>>(key).skip(index));
+        match versions.next().await.transpose()? {
+            Some(payload) => Ok(Some(deserialize::<F, O>(key, payload)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_history_storage_client() {
+        let url = Url::parse("memory://history").unwrap();
+        let client = HistoryStorageClient::<JsonStorageFormat, MemoryStorageClient<RawFormat>>::init(url).await.unwrap();
+        assert!(client.list_versions::<TestObject>("test_key").await.unwrap().is_empty());
+
+        let first = TestObject { key: "test_key".to_string(), value: "first".to_string() };
+        let second = TestObject { key: "test_key".to_string(), value: "second".to_string() };
+        client.put("test_key", first.clone()).await.unwrap();
+        client.put("test_key", second.clone()).await.unwrap();
+
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), Some(second.clone()));
+        assert_eq!(client.list_versions::<TestObject>("test_key").await.unwrap(), vec!["1", "2"]);
+        assert_eq!(client.get_version::<TestObject>("test_key", "1").await.unwrap(), Some(first.clone()));
+        assert_eq!(client.get_version::<TestObject>("test_key", "2").await.unwrap(), Some(second));
+        assert_eq!(client.get_version::<TestObject>("test_key", "3").await.unwrap(), None);
+
+        // the history outlives the object
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert_eq!(client.get_version::<TestObject>("test_key", "1").await.unwrap(), Some(first));
+    }
+}
@@ -0,0 +1,197 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use js_sys::Uint8Array;
+use rexie::{KeyRange, ObjectStore, Rexie, TransactionMode};
+use send_wrapper::SendWrapper;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+use wasm_bindgen::JsValue;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+const STORE_NAME: &str = "objects";
+
+/// Stores objects in the browser's IndexedDB, in a single object store keyed by `{type_name}/{key}`.
+/// - `indexeddb://database-name`
+/// - Only available on `wasm32` with the `wasm` feature, the browser is single threaded so the
+///   non `Send` IndexedDB handles are wrapped in `SendWrapper`
+pub struct IndexedDbStorageClient<F: StorageFormat> {
+    name: String,
+    db: SendWrapper<Rexie>,
+    _formatter: PhantomData<F>,
+}
+
+/// rexie errors hold JS values which are not `Send`, so only the message is kept
+fn js_error(e: impl std::fmt::Display) -> anyhow::Error {
+    anyhow::anyhow!("{}", e)
+}
+
+/// Key range covering every key under `prefix/`
+/// - `0` is the character right after `/`
+fn prefix_range(prefix: &str) -> anyhow::Result<KeyRange> {
+    let lower = JsValue::from_str(&format!("{}/", prefix));
+    let upper = JsValue::from_str(&format!("{}0", prefix));
+    KeyRange::bound(&lower, &upper, false, true).map_err(js_error)
+}
+
+impl<F: StorageFormat> IndexedDbStorageClient<F> {
+
+    fn key<O: StorageObject>(key: &str) -> JsValue {
+        JsValue::from_str(&format!("{}/{}", O::type_name(), key))
+    }
+
+    async fn read(&self, key: JsValue) -> anyhow::Result<Option<Vec<u8>>> {
+        let transaction = self.db.transaction(&[STORE_NAME], TransactionMode::ReadOnly).map_err(js_error)?;
+        let store = transaction.store(STORE_NAME).map_err(js_error)?;
+        let value = store.get(key).await.map_err(js_error)?;
+        transaction.done().await.map_err(js_error)?;
+        Ok(value.map(|value| Uint8Array::new(&value).to_vec()))
+    }
+
+    async fn write(&self, key: JsValue, data: Vec<u8>) -> anyhow::Result<()> {
+        let transaction = self.db.transaction(&[STORE_NAME], TransactionMode::ReadWrite).map_err(js_error)?;
+        let store = transaction.store(STORE_NAME).map_err(js_error)?;
+        let value: JsValue = Uint8Array::from(data.as_slice()).into();
+        store.put(&value, Some(&key)).await.map_err(js_error)?;
+        transaction.done().await.map_err(js_error)?;
+        Ok(())
+    }
+
+    /// Deletes every key in `range` and reports whether there were any
+    async fn remove(&self, range: KeyRange) -> anyhow::Result<bool> {
+        let transaction = self.db.transaction(&[STORE_NAME], TransactionMode::ReadWrite).map_err(js_error)?;
+        let store = transaction.store(STORE_NAME).map_err(js_error)?;
+        let count = store.count(Some(range.clone())).await.map_err(js_error)?;
+        if count > 0 {
+            store.delete(range.into()).await.map_err(js_error)?;
+        }
+        transaction.done().await.map_err(js_error)?;
+        Ok(count > 0)
+    }
+
+    async fn clear(&self) -> anyhow::Result<()> {
+        let transaction = self.db.transaction(&[STORE_NAME], TransactionMode::ReadWrite).map_err(js_error)?;
+        let store = transaction.store(STORE_NAME).map_err(js_error)?;
+        store.clear().await.map_err(js_error)?;
+        transaction.done().await.map_err(js_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for IndexedDbStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let name = storage_url.host_str()
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a database name"))?
+            .to_string();
+
+        let db = SendWrapper::new(async {
+            Rexie::builder(&name)
+                .version(1)
+                .add_object_store(ObjectStore::new(STORE_NAME))
+                .build()
+                .await
+                .map_err(js_error)
+        }).await.with_context(|| format!("Failed to open IndexedDB database: {}", name))?;
+
+        Ok(Self { name, db: SendWrapper::new(db), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.name
+    }
+
+    // every type shares one object store, there is nothing to create
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let data = SendWrapper::new(self.read(Self::key::<O>(key))).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        SendWrapper::new(self.write(Self::key::<O>(key), data)).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let db_key = format!("{}/{}", O::type_name(), key);
+        SendWrapper::new(async move {
+            let range = KeyRange::only(&JsValue::from_str(&db_key)).map_err(js_error)?;
+            self.remove(range).await
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let directory = self.object_directory::<O>().to_string();
+        SendWrapper::new(async move {
+            self.remove(prefix_range(&directory)?).await
+        }).await.with_context(|| {
+            format!("Failed to delete objects of {}", O::type_name())
+        })
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        SendWrapper::new(self.clear()).await.with_context(|| {
+            format!("Failed to clear IndexedDB database: {}", self.name)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_indexeddb_storage_client_json() {
+        let url = Url::parse("indexeddb://indexeddb-storage-client-test").unwrap();
+        let client = IndexedDbStorageClient::<JsonStorageFormat>::init(url).await.unwrap();
+        client.delete_all().await.unwrap();
+
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        client.put("test_key", obj.clone()).await.unwrap();
+        client.put("other_key", obj.clone()).await.unwrap();
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), None);
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert_eq!(client.get::<TestObject>("other_key").await.unwrap(), None);
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+    }
+}
@@ -1,11 +1,21 @@
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{StorageFormat, StorageObject};
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject};
 
 
 #[derive(Debug, Clone)]
 pub struct JsonStorageFormat;
 
+impl FormatInfo for JsonStorageFormat {
+    const ID: &'static str = "json";
+    const VERSION: u16 = 1;
+
+    // objects and arrays, scalars are not sniffed
+    fn sniff(data: &[u8]) -> bool {
+        matches!(data.trim_ascii_start().first(), Some(b'{') | Some(b'['))
+    }
+}
+
 impl StorageFormat for JsonStorageFormat {
     fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
         serde_json::to_vec(obj).map_err(|e| e.into())
@@ -14,4 +24,107 @@ impl StorageFormat for JsonStorageFormat {
     fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
         serde_json::from_slice(data).map_err(|e| e.into())
     }
-}
\ No newline at end of file
+}
+
+/// Same encoding as `JsonStorageFormat`, indented with one field per line
+/// - Stored files of file backed stores diff cleanly and can be reviewed in git
+/// - Reads compact JSON as well, so a store can switch between the two
+#[derive(Debug, Clone)]
+pub struct PrettyJsonStorageFormat;
+
+impl FormatInfo for PrettyJsonStorageFormat {
+    const ID: &'static str = "json-pretty";
+    const VERSION: u16 = 1;
+
+    // objects and arrays, scalars are not sniffed
+    fn sniff(data: &[u8]) -> bool {
+        matches!(data.trim_ascii_start().first(), Some(b'{') | Some(b'['))
+    }
+}
+
+impl StorageFormat for PrettyJsonStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let mut data = serde_json::to_vec_pretty(obj)?;
+        // files end with a newline, as editors write them
+        data.push(b'\n');
+        Ok(data)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        JsonStorageFormat::deserialize(data)
+    }
+}
+
+
+/// Writes like `PrettyJsonStorageFormat`, reads JSON5 as well, for stores whose files are edited by hand.
+/// - Comments, trailing commas, single quoted strings and unquoted keys are accepted
+/// - Unknown fields are ignored unless the type denies them with `#[serde(deny_unknown_fields)]`
+#[cfg(feature = "json5")]
+#[derive(Debug, Clone)]
+pub struct LenientJsonStorageFormat;
+
+#[cfg(feature = "json5")]
+impl FormatInfo for LenientJsonStorageFormat {
+    const ID: &'static str = "json";
+    const VERSION: u16 = 1;
+
+    fn sniff(data: &[u8]) -> bool {
+        JsonStorageFormat::sniff(data)
+    }
+}
+
+#[cfg(feature = "json5")]
+impl StorageFormat for LenientJsonStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        PrettyJsonStorageFormat::serialize(obj)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        // strict JSON is the common case and parses faster
+        if let Ok(obj) = serde_json::from_slice(data) {
+            return Ok(obj);
+        }
+        let text = std::str::from_utf8(data)?;
+        json5::from_str(text).map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_object::TestObject;
+
+    use super::*;
+
+    #[test]
+    fn test_pretty_json_storage_format_round_trip() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let data = PrettyJsonStorageFormat::serialize(&obj).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&data).unwrap(),
+            "{\n  \"key\": \"test_key\",\n  \"value\": \"test_value\"\n}\n"
+        );
+        assert_eq!(PrettyJsonStorageFormat::deserialize::<TestObject>(&data).unwrap(), obj);
+
+        // compact JSON reads the same
+        let compact = JsonStorageFormat::serialize(&obj).unwrap();
+        assert_eq!(PrettyJsonStorageFormat::deserialize::<TestObject>(&compact).unwrap(), obj);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_lenient_json_storage_format_accepts_hand_edits() {
+        let edited = br#"{
+            // changed by hand
+            "key": "test_key",
+            "value": 'test_value',
+            "note": "unknown field",
+        }"#;
+        let retrieved: TestObject = LenientJsonStorageFormat::deserialize(edited).unwrap();
+        assert_eq!(retrieved, TestObject { key: "test_key".to_string(), value: "test_value".to_string() });
+        assert!(JsonStorageFormat::deserialize::<TestObject>(edited).is_err());
+
+        let data = LenientJsonStorageFormat::serialize(&retrieved).unwrap();
+        assert_eq!(JsonStorageFormat::deserialize::<TestObject>(&data).unwrap(), retrieved);
+    }
+}
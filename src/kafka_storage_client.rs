@@ -0,0 +1,358 @@
+use std::{collections::HashMap, marker::PhantomData, sync::{Arc, Mutex}, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use rdkafka::{
+    admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
+    client::DefaultClientContext,
+    consumer::{Consumer, StreamConsumer},
+    producer::{FutureProducer, FutureRecord},
+    types::RDKafkaErrorCode,
+    util::Timeout,
+    ClientConfig, Message, Offset, TopicPartitionList,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::{content_version, ObjectMetadata, StorageClient, StorageFormat, StorageObject};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Materialized state of one topic, key to latest value
+type State = Arc<DashMap<String, Vec<u8>>>;
+
+/// Stores every put and delete as an event in a compacted topic per object type and answers
+/// reads from a local state store built from those topics.
+/// - `kafka://broker:9092/prefix?partitions=1&replication=1`, topics are named `{prefix}.{type_name}`
+/// - Puts are records keyed by the object key, deletes are tombstones, so compaction keeps the latest value
+/// - A type's topic is read from the beginning on first use and then followed in the background,
+///   so writes from other clients show up with the consumer's delay
+/// - Writes through this client are applied to the local state once the broker acknowledged them
+pub struct KafkaStorageClient<F: StorageFormat> {
+    prefix: String,
+    brokers: String,
+    partitions: i32,
+    replication: i32,
+    producer: FutureProducer,
+    admin: AdminClient<DefaultClientContext>,
+    states: DashMap<String, State>,
+    followers: Mutex<HashMap<String, JoinHandle<()>>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> KafkaStorageClient<F> {
+
+    fn topic(&self, type_name: &str) -> String {
+        format!("{}.{}", self.prefix, type_name)
+    }
+
+    async fn create_topic(&self, topic: &str) -> anyhow::Result<()> {
+        let new_topic = NewTopic::new(topic, self.partitions, TopicReplication::Fixed(self.replication))
+            .set("cleanup.policy", "compact");
+        let results = self.admin.create_topics(&[new_topic], &AdminOptions::new()).await?;
+        for result in results {
+            match result {
+                Ok(_) | Err((_, RDKafkaErrorCode::TopicAlreadyExists)) => {}
+                Err((topic, code)) => return Err(anyhow::anyhow!("Failed to create topic {}: {}", topic, code)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Local state of a type's topic, reading the topic up to its end the first time
+    async fn state(&self, type_name: &str) -> anyhow::Result<State> {
+        if let Some(state) = self.states.get(type_name) {
+            return Ok(state.clone());
+        }
+
+        let topic = self.topic(type_name);
+        self.create_topic(&topic).await?;
+
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.brokers)
+            .set("group.id", format!("{}-materializer", topic))
+            .set("enable.auto.commit", "false")
+            .create()?;
+        let metadata = consumer.fetch_metadata(Some(&topic), TIMEOUT)?;
+        let mut assignment = TopicPartitionList::new();
+        let mut remaining = HashMap::new();
+        for partition in metadata.topics().iter().flat_map(|t| t.partitions()) {
+            assignment.add_partition_offset(&topic, partition.id(), Offset::Beginning)?;
+            let (low, high) = consumer.fetch_watermarks(&topic, partition.id(), TIMEOUT)?;
+            if high > low {
+                remaining.insert(partition.id(), high - 1);
+            }
+        }
+        consumer.assign(&assignment)?;
+
+        let state: State = Arc::new(DashMap::new());
+        // catch up to the end of every partition before serving reads
+        while !remaining.is_empty() {
+            let message = consumer.recv().await?;
+            apply(&state, &message);
+            if remaining.get(&message.partition()).is_some_and(|last| message.offset() >= *last) {
+                remaining.remove(&message.partition());
+            }
+        }
+
+        let follower_state = state.clone();
+        let follower = tokio::spawn(async move {
+            loop {
+                match consumer.recv().await {
+                    Ok(message) => apply(&follower_state, &message),
+                    Err(e) => log::warn!("Failed to read from topic: {}", e),
+                }
+            }
+        });
+
+        // another task may have materialized the topic meanwhile, keep the first one
+        let state = self.states.entry(type_name.to_string()).or_insert(state).clone();
+        let mut followers = self.followers.lock().unwrap_or_else(|e| e.into_inner());
+        match followers.get(type_name) {
+            Some(_) => follower.abort(),
+            None => {
+                followers.insert(type_name.to_string(), follower);
+            }
+        }
+        Ok(state)
+    }
+
+    fn forget(&self, type_name: &str) {
+        self.states.remove(type_name);
+        if let Some(follower) = self.followers.lock().unwrap_or_else(|e| e.into_inner()).remove(type_name) {
+            follower.abort();
+        }
+    }
+
+    async fn delete_topics(&self, topics: &[String]) -> anyhow::Result<bool> {
+        if topics.is_empty() {
+            return Ok(false);
+        }
+        let names: Vec<&str> = topics.iter().map(|topic| topic.as_str()).collect();
+        let results = self.admin.delete_topics(&names, &AdminOptions::new()).await?;
+        let mut deleted = false;
+        for result in results {
+            match result {
+                Ok(_) => deleted = true,
+                Err((_, RDKafkaErrorCode::UnknownTopicOrPartition)) => {}
+                Err((topic, code)) => return Err(anyhow::anyhow!("Failed to delete topic {}: {}", topic, code)),
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Applies a record to the state, tombstones remove the key
+fn apply(state: &State, message: &impl Message) {
+    let key = match message.key().map(|key| String::from_utf8_lossy(key).into_owned()) {
+        Some(key) => key,
+        None => return,
+    };
+    match message.payload() {
+        Some(payload) => {
+            state.insert(key, payload.to_vec());
+        }
+        None => {
+            state.remove(&key);
+        }
+    }
+}
+
+impl<F: StorageFormat> Drop for KafkaStorageClient<F> {
+    fn drop(&mut self) {
+        for (_, follower) in self.followers.lock().unwrap_or_else(|e| e.into_inner()).drain() {
+            follower.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for KafkaStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let host = storage_url.host_str()
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a host"))?;
+        let brokers = format!("{}:{}", host, storage_url.port().unwrap_or(9092));
+        let prefix = storage_url.path().trim_matches('/').replace('/', ".");
+        if prefix.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a topic prefix"));
+        }
+        let param = |name: &str, default: i32| -> anyhow::Result<i32> {
+            match storage_url.query_pairs().find(|(key, _)| key == name) {
+                Some((_, value)) => value.parse().with_context(|| format!("Invalid {}: {}", name, value)),
+                None => Ok(default),
+            }
+        };
+        let partitions = param("partitions", 1)?;
+        let replication = param("replication", 1)?;
+
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &brokers);
+        let producer: FutureProducer = config.clone()
+            .set("enable.idempotence", "true")
+            .create()
+            .with_context(|| format!("Failed to connect to Kafka brokers: {}", brokers))?;
+        let admin: AdminClient<DefaultClientContext> = config.create()?;
+
+        Ok(Self {
+            prefix,
+            brokers,
+            partitions,
+            replication,
+            producer,
+            admin,
+            states: DashMap::new(),
+            followers: Mutex::new(HashMap::new()),
+            _formatter: PhantomData::<F>,
+        })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.state(O::type_name()).await.with_context(|| {
+            format!("Failed to materialize topic: {}", self.topic(O::type_name()))
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let state = self.state(O::type_name()).await?;
+        let data = state.get(key).map(|data| data.value().clone());
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let state = self.state(O::type_name()).await?;
+        let topic = self.topic(O::type_name());
+        self.producer
+            .send(FutureRecord::to(&topic).key(key).payload(&data), Timeout::After(TIMEOUT))
+            .await
+            .map_err(|(e, _)| e)
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+        state.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let state = self.state(O::type_name()).await?;
+        if !state.contains_key(key) {
+            return Ok(false);
+        }
+
+        let topic = self.topic(O::type_name());
+        self.producer
+            .send(FutureRecord::<str, [u8]>::to(&topic).key(key), Timeout::After(TIMEOUT))
+            .await
+            .map_err(|(e, _)| e)
+            .with_context(|| format!("Failed to delete {} for key: {}", O::type_name(), key))?;
+        Ok(state.remove(key).is_some())
+    }
+
+    // deleting the topic drops the event history of the type as well
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.forget(O::type_name());
+        self.delete_topics(&[self.topic(O::type_name())]).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let type_names: Vec<String> = self.states.iter().map(|entry| entry.key().clone()).collect();
+        for type_name in type_names {
+            self.forget(&type_name);
+        }
+
+        let metadata = self.producer.client().fetch_metadata(None, TIMEOUT)?;
+        let topic_prefix = format!("{}.", self.prefix);
+        let topics: Vec<String> = metadata.topics().iter()
+            .map(|topic| topic.name().to_string())
+            .filter(|name| name.starts_with(&topic_prefix))
+            .collect();
+        self.delete_topics(&topics).await?;
+        Ok(())
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let state = self.state(O::type_name()).await?;
+        Ok(state.iter().map(|entry| entry.key().clone()).collect())
+    }
+
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        let state = self.state(O::type_name()).await?;
+        Ok(state.len() as u64)
+    }
+
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let state = self.state(O::type_name()).await?;
+        let metadata = state.get(key).map(|data| ObjectMetadata {
+            size: data.len() as u64,
+            created: None,
+            modified: None,
+            etag: Some(content_version(&data)),
+        });
+        Ok(metadata)
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let state = self.state(O::type_name()).await?;
+        Ok(state.contains_key(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rdkafka::message::{OwnedMessage, Timestamp};
+
+    use super::*;
+
+    fn record(key: Option<&str>, payload: Option<&str>, offset: i64) -> OwnedMessage {
+        OwnedMessage::new(
+            payload.map(|payload| payload.as_bytes().to_vec()),
+            key.map(|key| key.as_bytes().to_vec()),
+            "prefix.TestObject".to_string(),
+            Timestamp::NotAvailable,
+            0,
+            offset,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_kafka_apply_records_and_tombstones() {
+        let state: State = Arc::new(DashMap::new());
+        apply(&state, &record(Some("test_key"), Some("first"), 0));
+        apply(&state, &record(Some("test_key"), Some("second"), 1));
+        apply(&state, &record(Some("other_key"), Some("other"), 2));
+        // the latest record of a key wins
+        assert_eq!(state.get("test_key").unwrap().as_slice(), b"second");
+
+        // a tombstone removes the key, records without a key are ignored
+        apply(&state, &record(Some("test_key"), None, 3));
+        apply(&state, &record(None, Some("orphan"), 4));
+        assert!(!state.contains_key("test_key"));
+        assert_eq!(state.len(), 1);
+    }
+}
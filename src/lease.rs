@@ -0,0 +1,115 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Makes lease tokens unique within the process
+static TOKEN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Wait between attempts of the default `StorageClient::lock`
+pub const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Release = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+/// A lock held until `release` is called, the guard is dropped or the ttl runs out, from `StorageClient::lock`
+/// - Dropping the guard releases the lock in the background on the current tokio runtime,
+///   call `release` to wait for it and see errors
+/// - Once `expires` has passed another worker can take the lock, keep the work well within the ttl
+pub struct LeaseGuard {
+    name: String,
+    token: String,
+    expires: SystemTime,
+    release: Option<Release>,
+}
+
+impl LeaseGuard {
+
+    /// `release` only runs when the lock is given up, it should remove the lease only if it still holds `token`
+    pub(crate) fn new(name: &str, token: String, expires: SystemTime, release: impl Future<Output = anyhow::Result<()>> + Send + 'static) -> Self {
+        Self { name: name.to_string(), token, expires, release: Some(Box::pin(release)) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Unique per acquisition, tells apart holders of the same lock
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn expires(&self) -> SystemTime {
+        self.expires
+    }
+
+    pub fn is_expired(&self) -> bool {
+        SystemTime::now() >= self.expires
+    }
+
+    pub async fn release(mut self) -> anyhow::Result<()> {
+        match self.release.take() {
+            Some(release) => release.await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for LeaseGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            runtime.spawn(release);
+        }
+    }
+}
+
+impl std::fmt::Debug for LeaseGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaseGuard")
+            .field("name", &self.name)
+            .field("token", &self.token)
+            .field("expires", &self.expires)
+            .finish()
+    }
+}
+
+/// A token no other acquisition, in this or another process, is likely to produce
+pub(crate) fn lease_token() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    format!("{:x}-{:x}-{:x}", std::process::id(), nanos, TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Milliseconds since the unix epoch, how backends store lease expiry
+pub(crate) fn epoch_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lease_guard_releases_once() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let released = sender.clone();
+        let guard = LeaseGuard::new("job", lease_token(), SystemTime::now() + Duration::from_secs(5), async move {
+            released.send(()).unwrap();
+            Ok(())
+        });
+        assert!(!guard.is_expired());
+        guard.release().await.unwrap();
+        assert!(receiver.try_recv().is_ok());
+
+        let dropped = LeaseGuard::new("job", lease_token(), SystemTime::now(), async move {
+            sender.send(()).unwrap();
+            Ok(())
+        });
+        assert!(dropped.is_expired());
+        drop(dropped);
+        assert!(receiver.recv().await.is_some());
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -1,13 +1,253 @@
 mod json;
+mod ndjson;
+mod envelope;
+mod multi_format;
+#[cfg(feature = "streaming")]
+mod streaming;
+#[cfg(feature = "yaml")]
+mod yaml;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+#[cfg(feature = "avro")]
+mod avro;
+#[cfg(feature = "bson")]
+mod bson;
+#[cfg(feature = "postcard")]
+mod postcard;
+#[cfg(feature = "flexbuffers")]
+mod flexbuffers;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "csv")]
+mod csv;
 mod file_stroage_client;
 mod postgres_storage_client;
+mod memory_storage_client;
+mod raw;
+mod dump;
+mod hash;
+mod transaction;
+mod query;
+mod search;
+mod lease;
+#[cfg(test)]
+mod test_object;
+#[cfg(feature = "tantivy")]
+mod search_index;
+mod tiered_storage_client;
+mod replicated_storage_client;
+mod sharded_storage_client;
+mod routed_storage_client;
+mod read_only_storage_client;
+mod failover_storage_client;
+mod namespaced_storage_client;
+mod retrying_storage_client;
+mod rate_limited_storage_client;
+mod observed_storage_client;
+mod metered_storage_client;
+mod audited_storage_client;
+mod dry_run_storage_client;
+mod optimistic_storage_client;
+mod history_storage_client;
+mod quota_storage_client;
+#[cfg(feature = "s3")]
+mod s3_storage_client;
+#[cfg(feature = "redis")]
+mod redis_storage_client;
+#[cfg(feature = "sqlite")]
+mod sqlite_storage_client;
+#[cfg(feature = "mysql")]
+mod mysql_storage_client;
+#[cfg(feature = "mongodb")]
+mod mongo_storage_client;
+#[cfg(feature = "dynamodb")]
+mod dynamo_storage_client;
+#[cfg(feature = "rocksdb")]
+mod rocksdb_storage_client;
+#[cfg(feature = "sled")]
+mod sled_storage_client;
+#[cfg(feature = "etcd")]
+mod etcd_storage_client;
+#[cfg(feature = "sftp")]
+mod sftp_storage_client;
+#[cfg(feature = "lmdb")]
+mod lmdb_storage_client;
+#[cfg(feature = "couchdb")]
+mod couchdb_storage_client;
+#[cfg(feature = "duckdb")]
+mod duckdb_storage_client;
+#[cfg(feature = "clickhouse")]
+mod clickhouse_storage_client;
+#[cfg(feature = "surrealdb")]
+mod surreal_storage_client;
+#[cfg(feature = "memcached")]
+mod memcached_storage_client;
+#[cfg(feature = "foundationdb")]
+mod fdb_storage_client;
+#[cfg(feature = "tikv")]
+mod tikv_storage_client;
+#[cfg(feature = "zip")]
+mod zip_storage_client;
+#[cfg(feature = "tar")]
+mod tar_storage_client;
+#[cfg(feature = "git")]
+mod git_storage_client;
+#[cfg(feature = "grpc")]
+mod grpc_storage_client;
+#[cfg(feature = "encryption")]
+mod encrypted_storage_client;
+#[cfg(feature = "encryption")]
+mod encrypted;
+#[cfg(feature = "checksum")]
+mod checksummed;
+#[cfg(feature = "compression")]
+mod compressed_storage_client;
+#[cfg(feature = "compression")]
+mod compressed;
+#[cfg(feature = "test-util")]
+mod mock_storage_client;
+#[cfg(feature = "mssql")]
+mod mssql_storage_client;
+#[cfg(feature = "kafka")]
+mod kafka_storage_client;
+#[cfg(feature = "opendal")]
+mod opendal_storage_client;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod indexeddb_storage_client;
 
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::{Bound, Range, RangeBounds, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
 use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use ordermap::OrderMap;
-use postgres_storage_client::PostgresType;
 use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use lease::LOCK_POLL_INTERVAL;
 use url::Url;
 
+pub use json::{JsonStorageFormat, PrettyJsonStorageFormat};
+#[cfg(feature = "json5")]
+pub use json::LenientJsonStorageFormat;
+pub use ndjson::{NdjsonCollection, NdjsonStorageFormat};
+pub use envelope::{EnvelopeHeader, FormatInfo, Versioned};
+pub use multi_format::{FormatSet, MultiFormat};
+#[cfg(feature = "streaming")]
+pub use streaming::StreamingStorageFormat;
+#[cfg(feature = "yaml")]
+pub use yaml::YamlStorageFormat;
+#[cfg(feature = "protobuf")]
+pub use protobuf::{ProtobufStorage, ProtobufStorageFormat};
+#[cfg(feature = "avro")]
+pub use avro::AvroStorageFormat;
+#[cfg(feature = "bson")]
+pub use bson::BsonStorageFormat;
+#[cfg(feature = "postcard")]
+pub use postcard::PostcardStorageFormat;
+#[cfg(feature = "flexbuffers")]
+pub use flexbuffers::{FlexbuffersStorage, FlexbuffersStorageFormat};
+#[cfg(feature = "arrow")]
+pub use arrow::{arrow_schema, ArrowIpcStorageFormat, from_record_batch, to_record_batch};
+#[cfg(feature = "parquet")]
+pub use parquet::ParquetStorageFormat;
+#[cfg(feature = "csv")]
+pub use csv::CsvStorageFormat;
+pub use file_stroage_client::FileStorageClient;
+pub use postgres_storage_client::{PostgresStorageClient, PostgresType};
+pub use memory_storage_client::MemoryStorageClient;
+pub use raw::{Payload, RawFormat};
+pub use dump::DumpEntry;
+pub use transaction::{Transaction, TransactionOperation, WriteBatch};
+pub use query::{Aggregate, AggregateSpec, Filter, GroupedQuery, Query, QuerySpec, SortOrder};
+pub use lease::LeaseGuard;
+pub use tiered_storage_client::TieredStorageClient;
+pub use replicated_storage_client::{ReplicatedStorageClient, ReplicationMode};
+pub use sharded_storage_client::{ShardedStorageClient, ShardMove};
+pub use routed_storage_client::RoutedStorageClient;
+pub use read_only_storage_client::{ReadOnlyError, ReadOnlyStorageClient};
+pub use failover_storage_client::FailoverStorageClient;
+pub use namespaced_storage_client::NamespacedStorageClient;
+pub use retrying_storage_client::{is_transient, RetryClassifier, RetryingStorageClient, RetryPolicy};
+pub use rate_limited_storage_client::{RateLimit, RateLimitedStorageClient};
+pub use observed_storage_client::{log_slow_operation, ObservedStorageClient, SlowOperation, SlowOperationHandler};
+pub use metered_storage_client::{MeteredStorageClient, OperationStats, StorageStats};
+pub use audited_storage_client::{AuditedStorageClient, AuditRecord, AuditSink, FileAuditSink, StorageAuditSink};
+pub use dry_run_storage_client::{DryRunOperation, DryRunStorageClient};
+pub use optimistic_storage_client::OptimisticStorageClient;
+pub use history_storage_client::HistoryStorageClient;
+pub use quota_storage_client::{Quota, QuotaExceeded, QuotaLimit, QuotaStorageClient};
+#[cfg(feature = "s3")]
+pub use s3_storage_client::S3StorageClient;
+#[cfg(feature = "redis")]
+pub use redis_storage_client::RedisStorageClient;
+#[cfg(feature = "sqlite")]
+pub use sqlite_storage_client::{sqlite_type, SqliteStorageClient};
+#[cfg(feature = "mysql")]
+pub use mysql_storage_client::{MysqlStorageClient, MysqlType};
+#[cfg(feature = "mongodb")]
+pub use mongo_storage_client::MongoStorageClient;
+#[cfg(feature = "dynamodb")]
+pub use dynamo_storage_client::DynamoStorageClient;
+#[cfg(feature = "rocksdb")]
+pub use rocksdb_storage_client::RocksDbStorageClient;
+#[cfg(feature = "sled")]
+pub use sled_storage_client::SledStorageClient;
+#[cfg(feature = "etcd")]
+pub use etcd_storage_client::EtcdStorageClient;
+#[cfg(feature = "sftp")]
+pub use sftp_storage_client::SftpStorageClient;
+#[cfg(feature = "lmdb")]
+pub use lmdb_storage_client::LmdbStorageClient;
+#[cfg(feature = "couchdb")]
+pub use couchdb_storage_client::CouchDbStorageClient;
+#[cfg(feature = "duckdb")]
+pub use duckdb_storage_client::{duckdb_type, DuckDbStorageClient};
+#[cfg(feature = "clickhouse")]
+pub use clickhouse_storage_client::{clickhouse_type, ClickHouseStorageClient};
+#[cfg(feature = "surrealdb")]
+pub use surreal_storage_client::SurrealStorageClient;
+#[cfg(feature = "memcached")]
+pub use memcached_storage_client::MemcachedStorageClient;
+#[cfg(feature = "foundationdb")]
+pub use fdb_storage_client::FdbStorageClient;
+#[cfg(feature = "tikv")]
+pub use tikv_storage_client::TikvStorageClient;
+#[cfg(feature = "zip")]
+pub use zip_storage_client::ZipStorageClient;
+#[cfg(feature = "tar")]
+pub use tar_storage_client::{TarMode, TarStorageClient};
+#[cfg(feature = "git")]
+pub use git_storage_client::{GitRevision, GitStorageClient};
+#[cfg(feature = "grpc")]
+pub use grpc_storage_client::{GrpcStorageClient, GrpcStorageServer};
+#[cfg(feature = "encryption")]
+pub use encrypted_storage_client::EncryptedStorageClient;
+#[cfg(feature = "encryption")]
+pub use encrypted::{Aes256GcmCipher, Cipher, Encrypted, KeyProvider, XChaCha20Poly1305Cipher};
+#[cfg(feature = "checksum")]
+pub use checksummed::{Checksummed, CorruptionError};
+#[cfg(feature = "compression")]
+pub use compressed_storage_client::{CompressedStorageClient, Compression};
+#[cfg(feature = "compression")]
+pub use compressed::{Compressed, CompressionAlgorithm, Gzip, Lz4, Zstd};
+#[cfg(feature = "test-util")]
+pub use mock_storage_client::{MockCall, MockOperation, MockResponse, MockStorageClient};
+#[cfg(feature = "mssql")]
+pub use mssql_storage_client::{MssqlStorageClient, MssqlType};
+#[cfg(feature = "kafka")]
+pub use kafka_storage_client::KafkaStorageClient;
+#[cfg(feature = "opendal")]
+pub use opendal_storage_client::OpendalStorageClient;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use indexeddb_storage_client::IndexedDbStorageClient;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RustStandardType {
     String,
@@ -30,6 +270,9 @@ pub enum RustStandardType {
     DateTime,
 }
 
+/// Columns of an object type and the column, or columns, of its primary key
+/// - A composite primary key lists its columns separated by commas, e.g. `"tenant_id, object_id"`,
+///   and its objects are keyed by tuples such as `(tenant_id, object_id)`, see `StorageKey`
 pub enum StorageSchema {
     Standard {
         schema: OrderMap<String, RustStandardType>,
@@ -39,11 +282,425 @@ pub enum StorageSchema {
         schema: OrderMap<String, PostgresType>,
         primary_key: String,
     },
+    #[cfg(feature = "mysql")]
+    Mysql {
+        schema: OrderMap<String, MysqlType>,
+        primary_key: String,
+    },
+    #[cfg(feature = "mssql")]
+    Mssql {
+        schema: OrderMap<String, MssqlType>,
+        primary_key: String,
+    },
+}
+
+impl StorageSchema {
+
+    /// The columns of the primary key in order, more than one for a composite key
+    pub fn key_columns(&self) -> Vec<String> {
+        let primary_key = match self {
+            StorageSchema::Standard { primary_key, .. } | StorageSchema::Postgres { primary_key, .. } => primary_key,
+            #[cfg(feature = "mysql")]
+            StorageSchema::Mysql { primary_key, .. } => primary_key,
+            #[cfg(feature = "mssql")]
+            StorageSchema::Mssql { primary_key, .. } => primary_key,
+        };
+        primary_key.split(',').map(|column| column.trim().to_string()).collect()
+    }
 }
 
 pub trait StorageObject  {
     fn type_name() -> &'static str;
     fn schema() -> StorageSchema;
+
+    /// Version of the object's layout, recorded by `Versioned` formats
+    /// - Bump it when a change of the type cannot read objects stored before
+    fn schema_version() -> u32 {
+        1
+    }
+
+    /// Fields of the schema looked up with `StorageClient::find_by`, indexed by the clients that can
+    /// - SQL clients create an index per field, the file client keeps index files next to the objects
+    fn indexed_fields() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// String fields searched by `StorageClient::search`, none by default so search is opt-in
+    /// - Postgres and the file client with the `tantivy` feature keep a full-text index of them
+    fn text_fields() -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+/// The field `field` of `object` as JSON, `Null` if the object has no such field
+pub(crate) fn field_value<O: Serialize>(object: &O, field: &str) -> anyhow::Result<serde_json::Value> {
+    let fields = serde_json::to_value(object).with_context(|| {
+        format!("Failed to extract fields of {}", std::any::type_name::<O>())
+    })?;
+    Ok(fields.get(field).cloned().unwrap_or(serde_json::Value::Null))
+}
+
+/// `find_by` without an index, scanning every object of the type
+pub(crate) async fn find_by_scan<F, C, O>(client: &C, field: &str, value: &serde_json::Value) -> anyhow::Result<HashMap<String, O>>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    client.scan::<O>()
+        .try_filter_map(|(key, object)| {
+            let matched = field_value(&object, field).map(|actual| (actual == *value).then_some((key, object)));
+            async move { matched }
+        })
+        .try_collect()
+        .await
+}
+
+/// A typed key, encoded to the string clients store objects under
+/// - Implemented for strings, integers, `Uuid` with the `uuid` feature and tuples of keys
+/// - Tuples join their parts with '/', a '/' or '%' inside a part is percent-encoded
+pub trait StorageKey {
+    fn encode(&self) -> String;
+}
+
+impl StorageKey for str {
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl StorageKey for String {
+    fn encode(&self) -> String {
+        self.clone()
+    }
+}
+
+impl<K: StorageKey + ?Sized> StorageKey for &K {
+    fn encode(&self) -> String {
+        (**self).encode()
+    }
+}
+
+macro_rules! integer_storage_key {
+    ($($typ:ty),*) => {
+        $(impl StorageKey for $typ {
+            fn encode(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+integer_storage_key!(u32, u64, u128, i32, i64, i128, usize);
+
+#[cfg(feature = "uuid")]
+impl StorageKey for uuid::Uuid {
+    fn encode(&self) -> String {
+        self.hyphenated().to_string()
+    }
+}
+
+/// A part of a tuple key, with the separator escaped
+fn key_part<K: StorageKey + ?Sized>(part: &K) -> String {
+    part.encode().replace('%', "%25").replace('/', "%2F")
+}
+
+/// The parts of a key encoded by a tuple `StorageKey`, decoded again
+/// - A key without a '/' is a single part
+pub(crate) fn split_key(key: &str) -> Vec<String> {
+    key.split('/').map(|part| part.replace("%2F", "/").replace("%25", "%")).collect()
+}
+
+impl<A: StorageKey, B: StorageKey> StorageKey for (A, B) {
+    fn encode(&self) -> String {
+        format!("{}/{}", key_part(&self.0), key_part(&self.1))
+    }
+}
+
+impl<A: StorageKey, B: StorageKey, C: StorageKey> StorageKey for (A, B, C) {
+    fn encode(&self) -> String {
+        format!("{}/{}/{}", key_part(&self.0), key_part(&self.1), key_part(&self.2))
+    }
+}
+
+/// Ties an object type to the type of its keys, so a key meant for one type cannot reach another
+/// - Use it through `get_keyed`, `put_keyed` and `delete_keyed`
+pub trait Keyed: StorageObject {
+    type Key: StorageKey + Send + Sync;
+}
+
+/// Returned by optional `StorageClient` operations a client does not implement
+/// - Recover it with `error.downcast_ref::<UnsupportedError>()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedError {
+    pub operation: &'static str,
+    pub client: &'static str,
+}
+
+impl std::fmt::Display for UnsupportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not supported by {}", self.operation, self.client)
+    }
+}
+
+impl std::error::Error for UnsupportedError {}
+
+/// What a client knows about a stored object without reading it
+/// - Fields a backend does not track are `None`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectMetadata {
+    /// Size of the stored payload in bytes, after formatting
+    pub size: u64,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+    /// Changes whenever the content changes, the same as the version of `get_versioned` where both are supported
+    pub etag: Option<String>,
+}
+
+/// How much an object type takes up in a store, from `usage` and `usage_all`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub objects: u64,
+    /// Total size of the stored payloads, after formatting
+    pub bytes: u64,
+}
+
+/// Outcome of `health_check`, meant for readiness probes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    Healthy,
+    /// The backend cannot serve requests right now, e.g. the directory is not writable
+    Unhealthy { reason: String },
+}
+
+impl HealthStatus {
+
+    pub fn unhealthy(reason: impl Into<String>) -> Self {
+        HealthStatus::Unhealthy { reason: reason.into() }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy)
+    }
+}
+
+/// Names a point-in-time copy of a store taken by `snapshot`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnapshotId(String);
+
+impl SnapshotId {
+
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SnapshotId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Returned by `put_if_version` when the stored object is no longer at the expected version
+/// - Recover it with `error.downcast_ref::<VersionConflictError>()`, then read the object again and retry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConflictError {
+    pub type_name: &'static str,
+    pub key: String,
+    pub expected: String,
+    /// `None` if the object does not exist (anymore)
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for VersionConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.actual {
+            Some(actual) => write!(
+                f,
+                "{} for key: {} is at version {}, expected {}",
+                self.type_name, self.key, actual, self.expected
+            ),
+            None => write!(
+                f,
+                "{} for key: {} does not exist, expected version {}",
+                self.type_name, self.key, self.expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionConflictError {}
+
+/// Version of stored bytes for clients without native versions, a hash of the content
+pub(crate) fn content_version(data: &[u8]) -> String {
+    format!("{:016x}", hash::fnv1a(data))
+}
+
+/// Puts in flight at once in the default `put_many`
+pub const PUT_MANY_CONCURRENCY: usize = 16;
+
+/// Gets in flight at once in the default `scan`
+pub const SCAN_CONCURRENCY: usize = 16;
+
+/// Rounds of read, resolve and conditional write the default `merge` makes before giving up on a contended key
+pub const MERGE_ATTEMPTS: usize = 16;
+
+/// Objects handed to one `put_many` by `import_stream`
+pub const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// Batches `import_stream` writes at once, it stops pulling items while this many are in flight
+pub const IMPORT_CONCURRENCY: usize = 4;
+
+fn unsupported<C: ?Sized, T>(operation: &'static str) -> anyhow::Result<T> {
+    Err(UnsupportedError { operation, client: std::any::type_name::<C>() }.into())
+}
+
+/// One page of a listing, `limit` items at most, following the item named by `token`
+/// - Start with `PageRequest::new(limit)` and continue with `PageRequest::next` until it returns `None`
+/// - Tokens are opaque, only pass on the `next_token` of a previous page of the same listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    pub limit: usize,
+    pub token: Option<String>,
+}
+
+impl PageRequest {
+
+    pub fn new(limit: usize) -> Self {
+        Self { limit, token: None }
+    }
+
+    pub fn after(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Request of the page after `page`, `None` if it was the last one
+    pub fn next<T>(&self, page: &Page<T>) -> Option<Self> {
+        page.next_token.as_ref().map(|token| Self { limit: self.limit, token: Some(token.clone()) })
+    }
+}
+
+/// A page of a listing, `next_token` is `None` on the last page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_token: Option<String>,
+}
+
+/// Pages through keys in ascending order, the token is the last key of the previous page
+/// - A limit of 0 is treated as 1 so that every page makes progress
+fn paginate(mut keys: Vec<String>, page: &PageRequest) -> Page<String> {
+    keys.sort();
+    let start = match &page.token {
+        Some(token) => keys.partition_point(|key| key <= token),
+        None => 0,
+    };
+    let end = keys.len().min(start + page.limit.max(1));
+    let next_token = if end < keys.len() { Some(keys[end - 1].clone()) } else { None };
+    keys.truncate(end);
+    Page { items: keys.split_off(start), next_token }
+}
+
+/// Keys between `start` and `end` for `StorageClient::scan_range`, compared as strings
+/// - Made from `"a".."b"`, `"a"..="b"`, `"a"..`, `.."b"`, `..="b"` or `..`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRange {
+    pub start: Bound<String>,
+    pub end: Bound<String>,
+}
+
+impl KeyRange {
+
+    fn from_bounds<'a>(range: impl RangeBounds<&'a str>) -> Self {
+        Self {
+            start: range.start_bound().map(|key| key.to_string()),
+            end: range.end_bound().map(|key| key.to_string()),
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) => key >= start.as_str(),
+            Bound::Excluded(start) => key > start.as_str(),
+            Bound::Unbounded => true,
+        };
+        let before_end = match &self.end {
+            Bound::Included(end) => key <= end.as_str(),
+            Bound::Excluded(end) => key < end.as_str(),
+            Bound::Unbounded => true,
+        };
+        after_start && before_end
+    }
+
+    /// SQL condition keeping the keys of the range, where `key` is the key as text
+    /// - Returns the keys to bind in order, `placeholder` gives the placeholder of the n-th one, counting from 1
+    /// - `BETWEEN` when both ends are included, `TRUE` for the full range
+    pub(crate) fn sql_condition(&self, key: &str, mut placeholder: impl FnMut(usize) -> String) -> (String, Vec<&str>) {
+        if let (Bound::Included(start), Bound::Included(end)) = (&self.start, &self.end) {
+            return (format!("{} BETWEEN {} AND {}", key, placeholder(1), placeholder(2)), vec![start, end]);
+        }
+        let start = match &self.start {
+            Bound::Included(start) => Some((">=", start)),
+            Bound::Excluded(start) => Some((">", start)),
+            Bound::Unbounded => None,
+        };
+        let end = match &self.end {
+            Bound::Included(end) => Some(("<=", end)),
+            Bound::Excluded(end) => Some(("<", end)),
+            Bound::Unbounded => None,
+        };
+        let mut conditions = Vec::new();
+        let mut binds = Vec::new();
+        for (operator, bound) in start.into_iter().chain(end) {
+            binds.push(bound.as_str());
+            conditions.push(format!("{} {} {}", key, operator, placeholder(binds.len())));
+        }
+        if conditions.is_empty() {
+            return ("TRUE".to_string(), binds);
+        }
+        (conditions.join(" AND "), binds)
+    }
+}
+
+impl From<Range<&str>> for KeyRange {
+    fn from(range: Range<&str>) -> Self {
+        Self::from_bounds(range)
+    }
+}
+
+impl From<RangeInclusive<&str>> for KeyRange {
+    fn from(range: RangeInclusive<&str>) -> Self {
+        Self::from_bounds(range)
+    }
+}
+
+impl From<RangeFrom<&str>> for KeyRange {
+    fn from(range: RangeFrom<&str>) -> Self {
+        Self::from_bounds(range)
+    }
+}
+
+impl From<RangeTo<&str>> for KeyRange {
+    fn from(range: RangeTo<&str>) -> Self {
+        Self::from_bounds(range)
+    }
+}
+
+impl From<RangeToInclusive<&str>> for KeyRange {
+    fn from(range: RangeToInclusive<&str>) -> Self {
+        Self::from_bounds(range)
+    }
+}
+
+impl From<RangeFull> for KeyRange {
+    fn from(_: RangeFull) -> Self {
+        Self { start: Bound::Unbounded, end: Bound::Unbounded }
+    }
 }
 
 pub trait StorageFormat {
@@ -98,4 +755,489 @@ where
     // /// Delete all objects in the storage
     async fn delete_all(&self) -> anyhow::Result<()>;
 
+    /// Keys of all stored objects of the given type, in no particular order
+    /// - Returns an empty list if the subdirectory does not exist
+    /// - Clients that cannot enumerate their keys return an `UnsupportedError`
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        unsupported::<Self, _>("list_keys")
+    }
+
+    /// One page of the keys of the given type, in ascending order
+    /// - Iterate with `PageRequest::next` to go through huge stores without loading every key at once
+    /// - By default pages through `list_keys`, clients that can page natively override it
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        let keys = self.list_keys::<O>().await?;
+        Ok(paginate(keys, &page))
+    }
+
+    /// Objects of the given type whose keys are in `range`, with their keys, in ascending key order
+    /// - Keys compare as strings, so time-prefixed keys like `2024-06-01T12:00:00Z` come back in time order
+    /// - By default filters and sorts `list_keys` and fetches the matches with `get_many`,
+    ///   SQL clients select the range in the backend
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        let range = range.into();
+        let mut keys: Vec<String> = self.list_keys::<O>().await?
+            .into_iter()
+            .filter(|key| range.contains(key))
+            .collect();
+        keys.sort();
+        let key_refs: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+        let mut objects = self.get_many::<O>(&key_refs).await?;
+        Ok(keys.into_iter().filter_map(|key| objects.remove(&key).map(|object| (key, object))).collect())
+    }
+
+    /// The string `key` is stored under by this client
+    /// - `StorageKey::encode` by default, clients with stricter key rules override it
+    /// - SQL clients keep the default, they split tuple keys back into their key columns with `split_key`
+    /// - S3 escapes "." and ".." parts, which URLs would resolve away
+    fn encode_key<K: StorageKey + ?Sized>(&self, key: &K) -> String {
+        key.encode()
+    }
+
+    /// `get` with the key type of `O`
+    async fn get_keyed<O: Keyed + DeserializeOwned + Send + Sync>(&self, key: &O::Key) -> anyhow::Result<Option<O>> {
+        self.get::<O>(&self.encode_key(key)).await
+    }
+
+    /// `put` with the key type of `O`
+    async fn put_keyed<O: Keyed + Serialize + Send + Sync>(&self, key: &O::Key, value: O) -> anyhow::Result<()> {
+        self.put(&self.encode_key(key), value).await
+    }
+
+    /// `delete` with the key type of `O`
+    async fn delete_keyed<O: Keyed>(&self, key: &O::Key) -> anyhow::Result<bool> {
+        self.delete::<O>(&self.encode_key(key)).await
+    }
+
+    /// Number of stored objects of the given type
+    /// - By default counts `list_keys`, clients that can count without listing override it
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        Ok(self.list_keys::<O>().await?.len() as u64)
+    }
+
+    /// Whether an object of the given type is stored under the key, without reading or deserializing it
+    /// - By default looks the key up in `list_keys`, clients that can check a single key override it
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let keys = self.list_keys::<O>().await?;
+        Ok(keys.iter().any(|k| k == key))
+    }
+
+    /// Objects of the given type stored under any of the keys, missing keys are left out
+    /// - By default runs all `get`s concurrently, clients that can fetch a batch at once override it
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let values = futures::future::try_join_all(keys.iter().map(|key| self.get::<O>(key))).await?;
+        Ok(keys.iter()
+            .zip(values)
+            .filter_map(|(key, value)| value.map(|value| (key.to_string(), value)))
+            .collect())
+    }
+
+    /// Stores all items, replacing existing objects with the same keys
+    /// - By default runs up to `PUT_MANY_CONCURRENCY` `put`s at a time, clients that can write a batch at once override it
+    /// - Not atomic, items written before an error stay written
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let items: Vec<(String, O)> = items.into_iter().collect();
+        stream::iter(items)
+            .map(|(key, value)| async move { self.put(&key, value).await })
+            .buffer_unordered(PUT_MANY_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Stores every item of the stream in batches of `IMPORT_BATCH_SIZE`, replacing existing objects with the same keys
+    /// - Backpressure: items are only pulled while fewer than `IMPORT_CONCURRENCY` batches are being written,
+    ///   so a fast source never holds more than those batches in memory
+    /// - `progress` is called with the number of objects written so far after each batch
+    /// - Batches go through `put_many`, so clients with a fast batch write use it, returns the number of objects written
+    /// - Not atomic, batches written before an error stay written
+    async fn import_stream<O: StorageObject + Serialize + Send + Sync>(
+        &self,
+        items: impl Stream<Item = (String, O)> + Send,
+        mut progress: impl FnMut(u64) + Send,
+    ) -> anyhow::Result<u64> {
+        let mut imported = 0;
+        let mut batches = std::pin::pin!(items
+            .chunks(IMPORT_BATCH_SIZE)
+            .map(|batch| async move {
+                let written = batch.len() as u64;
+                self.put_many(batch).await.map(|_| written)
+            })
+            .buffer_unordered(IMPORT_CONCURRENCY));
+        while let Some(written) = batches.next().await {
+            let written = written.with_context(|| {
+                format!("Failed to import {} after {} objects", O::type_name(), imported)
+            })?;
+            imported += written;
+            progress(imported);
+        }
+        Ok(imported)
+    }
+
+    /// Deletes the objects of the given type stored under any of the keys
+    /// - Returns the number of objects that existed and were removed
+    /// - By default runs all `delete`s concurrently, clients that can delete a batch at once override it
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let deleted = futures::future::try_join_all(keys.iter().map(|key| self.delete::<O>(key))).await?;
+        Ok(deleted.into_iter().filter(|deleted| *deleted).count())
+    }
+
+    /// Object stored under the key together with its current version
+    /// - Versions are opaque, they change whenever the object is written
+    /// - Clients without versioned objects return an `UnsupportedError`
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, _key: &str) -> anyhow::Result<Option<(O, String)>> {
+        unsupported::<Self, _>("get_versioned")
+    }
+
+    /// Stores the object only if the stored one is still at `expected_version`, returns the new version
+    /// - Fails with a `VersionConflictError` if the object changed or was deleted since it was read
+    /// - Clients without versioned objects return an `UnsupportedError`
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O, _expected_version: &str) -> anyhow::Result<String> {
+        unsupported::<Self, _>("put_if_version")
+    }
+
+    /// Stores the object only if nothing is stored under the key yet, returns whether it was written
+    /// - The check and the write are one atomic step, so a key can serve as a uniqueness claim
+    /// - Clients that cannot write conditionally return an `UnsupportedError`
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O) -> anyhow::Result<bool> {
+        unsupported::<Self, _>("put_if_absent")
+    }
+
+    /// Object stored under the key, or the one computed by `init` after storing it
+    /// - Built on `put_if_absent`, if another writer stores an object first, that object is returned
+    ///   and the computed one is dropped, so every caller ends up with the same object
+    /// - `init` only runs when the key is missing
+    async fn get_or_insert_with<O, Fut>(&self, key: &str, init: impl FnOnce() -> Fut + Send) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+        Fut: Future<Output = anyhow::Result<O>> + Send,
+    {
+        if let Some(existing) = self.get::<O>(key).await? {
+            return Ok(existing);
+        }
+        let value = init().await.with_context(|| {
+            format!("Failed to compute {} for key: {}", O::type_name(), key)
+        })?;
+        if self.put_if_absent(key, value.clone()).await? {
+            return Ok(value);
+        }
+        self.get::<O>(key).await?.ok_or_else(|| {
+            anyhow::anyhow!("{} for key: {} was deleted while it was being inserted", O::type_name(), key)
+        })
+    }
+
+    /// Stores `incoming`, or `resolver(existing, incoming)` if an object is already stored under the key, returns what was stored
+    /// - Concurrent merges of a key never overwrite each other, each one sees the result of the previous one
+    /// - By default a loop of `get_versioned` and `put_if_version`, or `put_if_absent` for a missing key,
+    ///   that runs `resolver` again whenever another writer got in between
+    /// - `resolver` should be deterministic, it can run more than once for a single merge
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        let mut conflict = None;
+        for _ in 0..MERGE_ATTEMPTS {
+            match self.get_versioned::<O>(key).await? {
+                Some((existing, version)) => {
+                    let merged = resolver(existing, incoming.clone());
+                    match self.put_if_version(key, merged.clone(), &version).await {
+                        Ok(_) => return Ok(merged),
+                        Err(e) if e.downcast_ref::<VersionConflictError>().is_some() => conflict = Some(e),
+                        Err(e) => return Err(e),
+                    }
+                }
+                None => {
+                    if self.put_if_absent(key, incoming.clone()).await? {
+                        return Ok(incoming);
+                    }
+                }
+            }
+        }
+        let error = conflict.unwrap_or_else(|| anyhow::anyhow!("{} for key: {} kept changing", O::type_name(), key));
+        Err(error).with_context(|| format!("Failed to merge {} for key: {} after {} attempts", O::type_name(), key, MERGE_ATTEMPTS))
+    }
+
+    /// Takes the lock `name` for `ttl` if nobody holds it, `None` while another worker's lease is running
+    /// - Locks are advisory, they only keep out workers that lock the same name through the same store
+    /// - Clients without a lock primitive return an `UnsupportedError`
+    async fn try_lock(&self, _name: &str, _ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        unsupported::<Self, _>("try_lock")
+    }
+
+    /// Takes the lock `name` for `ttl`, waiting while another worker holds it
+    /// - `let _lease = client.lock(&format!("order/{}", id), Duration::from_secs(30)).await?;` lets one worker at a time process the order
+    /// - Polls `try_lock` every `LOCK_POLL_INTERVAL` and gives up after waiting `ttl`, the longest another lease of the same ttl can run
+    async fn lock(&self, name: &str, ttl: Duration) -> anyhow::Result<LeaseGuard> {
+        let deadline = tokio::time::Instant::now() + ttl;
+        loop {
+            if let Some(lease) = self.try_lock(name, ttl).await? {
+                return Ok(lease);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("Timed out waiting {:?} for lock: {}", ttl, name));
+            }
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Copies the object stored under `from` to `to`, replacing any object stored there
+    /// - Returns false if nothing is stored under `from`
+    /// - By default a `get` and a `put`, clients that can copy natively override it
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        match self.get::<O>(from).await? {
+            Some(value) => {
+                self.put(to, value).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Moves the object stored under `from` to `to`, replacing any object stored there
+    /// - Returns false if nothing is stored under `from`
+    /// - Atomic where the client renames natively, by default a `copy` followed by a `delete`
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        // deleting after copying onto itself would lose the object
+        if from == to {
+            return Ok(self.get::<O>(from).await?.is_some());
+        }
+        if !self.copy::<O>(from, to).await? {
+            return Ok(false);
+        }
+        self.delete::<O>(from).await?;
+        Ok(true)
+    }
+
+    /// Size, timestamps and etag of the object stored under the key, without fetching it
+    /// - Returns `None` if the key does not exist
+    /// - Clients that cannot describe an object without reading it return an `UnsupportedError`
+    async fn metadata<O: StorageObject>(&self, _key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        unsupported::<Self, _>("metadata")
+    }
+
+    /// Number and total size of the stored objects of the given type
+    /// - By default sums `metadata` over `list_keys`, clients that can add up sizes in one go override it
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        let keys = self.list_keys::<O>().await?;
+        let sizes: Vec<Option<ObjectMetadata>> = stream::iter(keys)
+            .map(|key| async move { self.metadata::<O>(&key).await })
+            .buffer_unordered(SCAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(sizes.into_iter().flatten().fold(StorageUsage::default(), |usage, metadata| StorageUsage {
+            objects: usage.objects + 1,
+            bytes: usage.bytes + metadata.size,
+        }))
+    }
+
+    /// `usage` of every object type in the store, by type name
+    /// - Clients that cannot list the object types they hold return an `UnsupportedError`
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        unsupported::<Self, _>("usage_all")
+    }
+
+    /// Checks that the backend can serve requests, e.g. that the directory is writable or the pool hands out a connection
+    /// - A failed check is reported as `HealthStatus::Unhealthy` with the reason
+    /// - Clients without a cheap check report `Healthy`, they were reachable when `init` succeeded
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        Ok(HealthStatus::Healthy)
+    }
+
+    /// Releases the client's connections, buffers and locks before it is dropped
+    /// - Prefer it over relying on `Drop`, which cannot wait for async cleanup such as closing a pool
+    /// - Wrappers close the clients they wrap
+    async fn close(self) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
+    /// Captures a copy of every object in the store, go back to it with `restore`
+    /// - Meant for cheap rollback before risky batch jobs, writes made while it is taken may or may not be included
+    /// - Snapshots are kept by the backend, `delete_all` does not remove them
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        unsupported::<Self, _>("snapshot")
+    }
+
+    /// Replaces everything in the store with the contents of the snapshot `id`
+    /// - Objects written after the snapshot are lost, the snapshot itself is kept and can be restored again
+    async fn restore(&self, _id: &SnapshotId) -> anyhow::Result<()> {
+        unsupported::<Self, _>("restore")
+    }
+
+    /// Writes every object of every type to `sink` in the dump format, returns how many were written
+    /// - The dump keeps type names, keys and formatted payloads, load it into any client with `import_all`
+    /// - Appended records are not part of the dump
+    async fn export_all(&self, _sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        unsupported::<Self, _>("export_all")
+    }
+
+    /// Puts every object of a dump written by `export_all`, replacing objects under the same keys
+    /// - The payloads are stored as they are, the dump has to come from a client with the same `StorageFormat`
+    async fn import_all(&self, _source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        unsupported::<Self, _>("import_all")
+    }
+
+    /// Runs `f` and commits the puts and deletes it staged on the `Transaction` as one unit
+    /// - If `f` returns an error nothing is written, if the commit fails nothing is written either
+    /// - Operations can mix object types, e.g. `txn.put("a", &order)?; txn.delete::<Cart>("a");`
+    async fn transaction<R, Fut>(&self, f: impl FnOnce(Transaction<F>) -> Fut + Send) -> anyhow::Result<R>
+    where
+        R: Send,
+        Fut: Future<Output = anyhow::Result<R>> + Send,
+    {
+        let transaction = Transaction::new();
+        let result = f(transaction.clone()).await?;
+        let operations = transaction.into_operations();
+        if !operations.is_empty() {
+            self.commit_transaction(operations).await?;
+        }
+        Ok(result)
+    }
+
+    /// Applies the writes of `batch` together, with the same guarantees as `transaction`
+    /// - Clients without transactions return an `UnsupportedError` and write nothing
+    async fn put_all(&self, batch: WriteBatch<F>) -> anyhow::Result<()>
+    where
+        F: 'async_trait,
+    {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.commit_transaction(batch.into_operations()).await
+    }
+
+    /// Applies the operations staged by `transaction` all together or not at all
+    /// - Clients that cannot apply writes atomically return an `UnsupportedError`
+    async fn commit_transaction(&self, _operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        unsupported::<Self, _>("transaction")
+    }
+
+    /// Every stored object of the given type with its key, in no particular order
+    /// - Objects are fetched as the stream is polled, so memory stays bounded however many there are
+    /// - By default lists the keys once and then runs up to `SCAN_CONCURRENCY` `get`s at a time,
+    ///   objects deleted meanwhile are skipped
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        stream::once(async move { self.list_keys::<O>().await })
+            .map_ok(|keys| stream::iter(keys).map(Ok))
+            .try_flatten()
+            .map_ok(move |key| async move {
+                let value = self.get::<O>(&key).await?;
+                Ok(value.map(|value| (key, value)))
+            })
+            .try_buffered(SCAN_CONCURRENCY)
+            .try_filter_map(|item| async move { Ok(item) })
+    }
+
+    /// Every object of the given type whose field `field` is `value`, with its key
+    /// - Fields in `StorageObject::indexed_fields` are answered from an index by clients that keep one
+    /// - By default, and for fields without an index, scans every object and compares the field as JSON
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        let value = serde_json::to_value(value).with_context(|| {
+            format!("Failed to serialize value of field {} of {}", field, O::type_name())
+        })?;
+        find_by_scan::<F, Self, O>(self, field, &value).await
+    }
+
+    /// Objects of the given type whose `StorageObject::text_fields` hold every word of `terms`, best match first
+    /// - Words are compared case-insensitively, types without text fields cannot be searched
+    /// - By default scans every object and ranks matches by how often the words occur
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        search::search_scan::<F, Self, O>(self, terms).await
+    }
+
+    /// Starts a query over the objects of the given type, run with `Query::fetch`
+    fn query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self) -> Query<'_, F, Self, O>
+    where
+        Self: Sync,
+    {
+        Query::new(self)
+    }
+
+    /// The objects of the given type that pass all filters of `spec`, with their keys, sorted and paged as it asks
+    /// - SQL clients filter, sort and page in the backend
+    /// - By default every object is scanned, filtered once deserialized and the matches are sorted in memory
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        query::query_scan::<F, Self, O>(self, spec).await
+    }
+
+    /// The results of `execute_query` as a stream, read from the backend as the stream is polled
+    /// - By default filters `scan` as it goes, so only the page of `spec` is ever in memory;
+    ///   results then come in the order of `scan` rather than by key
+    /// - Sorting by `order_by` needs every match, so sorted queries are run with `execute_query` unless the client sorts in the backend
+    fn stream_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        query::query_stream::<F, Self, O>(self, spec)
+    }
+
+    /// Every object of the given type that passes all filters, formatted by the client's `StorageFormat`
+    /// - `data` of each entry is the stored payload, ready to be written to a file or object store as it is
+    ///   or put into another client with the same format
+    /// - Read through `stream_query` as the stream is polled, a slow consumer holds back the reads instead of buffering them
+    fn export_stream<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: Vec<Filter>) -> impl Stream<Item = anyhow::Result<DumpEntry>> + Send
+    where
+        Self: Sync,
+    {
+        self.stream_query::<O>(QuerySpec { filters, ..QuerySpec::default() })
+            .map(|result| {
+                let (key, object) = result?;
+                let data = F::serialize(&object).with_context(|| {
+                    format!("Failed to serialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(DumpEntry { type_name: O::type_name().to_string(), key, data })
+            })
+    }
+
+    /// Deletes the objects of the given type that pass every filter, returns how many were removed
+    /// - `client.query::<Event>().lt("created_at", cutoff).delete().await?` removes everything older than `cutoff`
+    /// - SQL clients delete in a single statement when every filter is on a column
+    /// - By default the matching keys are collected with `stream_query` and removed with `delete_many`,
+    ///   objects written in between are not considered
+    /// - No filters delete every object of the type
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        query::delete_where_scan::<F, Self, O>(self, filters).await
+    }
+
+    /// The aggregate of `spec` for each group of the matching objects, sorted by the value of the group
+    /// - SQL clients compute it with SQL aggregates, by default every object is scanned and folded into its group
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        query::aggregate_scan::<F, Self, O>(self, spec).await
+    }
+
+    /// Versions kept for `key` by clients that retain history, oldest first, the last one is current
+    /// - Only `HistoryStorageClient` keeps history, other clients return an `UnsupportedError`
+    async fn list_versions<O: StorageObject>(&self, _key: &str) -> anyhow::Result<Vec<String>> {
+        unsupported::<Self, _>("list_versions")
+    }
+
+    /// The object under `key` as it was put at `version`, one of `list_versions`
+    /// - `None` if there is no such version, the object may have been deleted since
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, _key: &str, _version: &str) -> anyhow::Result<Option<O>> {
+        unsupported::<Self, _>("get_version")
+    }
+
+    /// Appends `value` to the records under `key` without rewriting the ones before it
+    /// - For event logs and other growing sequences, read them back with `read_appended`
+    /// - Appended records are kept apart from the object `put` stores under the same key
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O) -> anyhow::Result<()> {
+        unsupported::<Self, _>("append")
+    }
+
+    /// The records appended under `key`, oldest first, empty if nothing was appended
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, _key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        stream::once(async { unsupported::<Self, O>("read_appended") })
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,221 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use heed::{
+    types::{Bytes, DecodeIgnore, Str},
+    Database, Env, EnvOpenOptions,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+type ObjectDatabase = Database<Str, Bytes>;
+
+const DEFAULT_MAP_SIZE: usize = 1024 * 1024 * 1024;
+const DEFAULT_MAX_DBS: u32 = 128;
+
+/// Stores objects in a local LMDB environment, one named database per object type.
+/// - `file:///path/to/env?map_size=1073741824&max_dbs=128`, the directory is created if it does not exist
+/// - Reads deserialize straight from the memory map and run inline, writes run on the blocking thread pool
+pub struct LmdbStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    env: Env,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> LmdbStorageClient<F> {
+
+    async fn write<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&Env, &mut heed::RwTxn) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let env = self.env.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut wtxn = env.write_txn()?;
+            let result = f(&env, &mut wtxn)?;
+            wtxn.commit()?;
+            Ok(result)
+        }).await?
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for LmdbStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path().to_string();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+
+        let mut map_size = DEFAULT_MAP_SIZE;
+        let mut max_dbs = DEFAULT_MAX_DBS;
+        for (name, value) in storage_url.query_pairs() {
+            match name.as_ref() {
+                "map_size" => map_size = value.parse().with_context(|| {
+                    format!("Invalid map_size value: {}", value)
+                })?,
+                "max_dbs" => max_dbs = value.parse().with_context(|| {
+                    format!("Invalid max_dbs value: {}", value)
+                })?,
+                _ => {}
+            }
+        }
+
+        tokio::fs::create_dir_all(&path).await.with_context(|| {
+            format!("Failed to create directory at path: {}", path)
+        })?;
+
+        // SAFETY: the environment is opened once per client and the files are not modified by anything else
+        let env = unsafe {
+            EnvOpenOptions::new()
+                .map_size(map_size)
+                .max_dbs(max_dbs)
+                .open(&path)
+        }.with_context(|| format!("Failed to open lmdb environment at path: {}", path))?;
+
+        Ok(Self { storage_url, env, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let name = self.object_directory::<O>().to_string();
+        self.write(move |env, wtxn| {
+            env.create_database::<Str, Bytes>(wtxn, Some(&name)).with_context(|| {
+                format!("Failed to create database: {}", name)
+            })?;
+            Ok(())
+        }).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let rtxn = self.env.read_txn()?;
+        let db: Option<ObjectDatabase> = self.env.open_database(&rtxn, Some(self.object_directory::<O>()))?;
+        let data = match db {
+            Some(db) => db.get(&rtxn, key)?,
+            None => None,
+        };
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.write(move |env, wtxn| {
+            let db: ObjectDatabase = env.create_database(wtxn, Some(&name))?;
+            db.put(wtxn, &object_key, &data)?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.write(move |env, wtxn| {
+            let db: Option<ObjectDatabase> = env.open_database(wtxn, Some(&name))?;
+            match db {
+                Some(db) => Ok(db.delete(wtxn, &object_key)?),
+                None => Ok(false),
+            }
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    // LMDB cannot drop a named database through heed, so it is emptied instead
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        self.write(move |env, wtxn| {
+            let db: Option<ObjectDatabase> = env.open_database(wtxn, Some(&name))?;
+            match db {
+                Some(db) => {
+                    let had_objects = !db.is_empty(wtxn)?;
+                    db.clear(wtxn)?;
+                    Ok(had_objects)
+                }
+                None => Ok(false),
+            }
+        }).await.context("Failed to delete database")
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.write(move |env, wtxn| {
+            // the unnamed main database holds the names of all named databases
+            let main: Option<Database<Str, DecodeIgnore>> = env.open_database(wtxn, None)?;
+            let names = match main {
+                Some(main) => main.iter(wtxn)?
+                    .map(|entry| entry.map(|(name, _)| name.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
+            for name in names {
+                let db: Option<ObjectDatabase> = env.open_database(wtxn, Some(&name))?;
+                if let Some(db) = db {
+                    db.clear(wtxn)?;
+                }
+            }
+            Ok(())
+        }).await.context("Failed to delete all databases")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lmdb_storage_client_json() {
+        let test_directory = std::env::temp_dir().join("lmdb_storage_client_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let url = Url::from_directory_path(&test_directory).expect("Failed to create URL from directory path");
+        let client = LmdbStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to open lmdb");
+
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj.clone()));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        client.put("test_key", obj).await.expect("Failed to put object");
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        assert!(client.delete_all().await.is_ok());
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
@@ -0,0 +1,209 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+const VERSION_SUFFIX: &str = "__version";
+
+/// Stores objects in memcached, meant for cache-only object types.
+/// - `memcache://host:11211/prefix?ttl=seconds`, objects never expire without a ttl
+/// - Memcached cannot enumerate keys, so `delete_object_directory` and `delete_all` bump a
+///   namespace version that is part of every key, orphaned entries are evicted by memcached
+/// - memcache calls block, so every operation runs on the blocking thread pool
+pub struct MemcachedStorageClient<F: StorageFormat> {
+    client: Arc<memcache::Client>,
+    prefix: String,
+    ttl: u32,
+    _formatter: PhantomData<F>,
+}
+
+/// Current value of a namespace version counter, initializing it to 0 if it does not exist
+fn namespace_version(client: &memcache::Client, key: &str) -> anyhow::Result<u64> {
+    if let Some(version) = client.get::<String>(key)? {
+        return Ok(version.parse()?);
+    }
+    // add only succeeds for the first writer, everyone then reads the same value
+    let _ = client.add(key, "0", 0);
+    let version: Option<String> = client.get(key)?;
+    match version {
+        Some(version) => Ok(version.parse()?),
+        None => Err(anyhow::anyhow!("Failed to initialize namespace version: {}", key)),
+    }
+}
+
+/// Bumps a namespace version counter
+/// - Returns false if the counter did not exist yet
+fn bump_namespace_version(client: &memcache::Client, key: &str) -> anyhow::Result<bool> {
+    match client.increment(key, 1) {
+        Ok(_) => Ok(true),
+        // incrementing a missing key fails, start the namespace over at 1
+        Err(_) => {
+            client.set(key, "1", 0)?;
+            Ok(false)
+        }
+    }
+}
+
+impl<F: StorageFormat> MemcachedStorageClient<F> {
+
+    async fn run<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&memcache::Client) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let client = self.client.clone();
+        tokio::task::spawn_blocking(move || f(&client)).await?
+    }
+
+    fn root_version_key(prefix: &str) -> String {
+        format!("{}:{}", prefix, VERSION_SUFFIX)
+    }
+
+    fn type_version_key(prefix: &str, root_version: u64, type_name: &str) -> String {
+        format!("{}:{}:{}:{}", prefix, root_version, type_name, VERSION_SUFFIX)
+    }
+
+    /// Resolves the versioned key `prefix:root_version:type_name:type_version:key`
+    fn versioned_key(client: &memcache::Client, prefix: &str, type_name: &str, key: &str) -> anyhow::Result<String> {
+        let root_version = namespace_version(client, &Self::root_version_key(prefix))?;
+        let type_version = namespace_version(client, &Self::type_version_key(prefix, root_version, type_name))?;
+        Ok(format!("{}:{}:{}:{}:{}", prefix, root_version, type_name, type_version, key))
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for MemcachedStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let host = storage_url.host_str()
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a host"))?;
+        let port = storage_url.port().unwrap_or(11211);
+        let connection_url = format!("memcache://{}:{}", host, port);
+        let prefix = match storage_url.path().trim_matches('/') {
+            "" => "storage".to_string(),
+            prefix => prefix.to_string(),
+        };
+        let ttl = match storage_url.query_pairs().find(|(name, _)| name == "ttl") {
+            Some((_, value)) => value.parse().with_context(|| format!("Invalid ttl value: {}", value))?,
+            None => 0,
+        };
+
+        let client = tokio::task::spawn_blocking(move || {
+            memcache::Client::connect(connection_url.as_str()).with_context(|| {
+                format!("Failed to connect to memcached at: {}", connection_url)
+            })
+        }).await??;
+
+        Ok(Self { client: Arc::new(client), prefix, ttl, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    // namespaces are created lazily with their version counter
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let prefix = self.prefix.clone();
+        let type_name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        let data = self.run(move |client| {
+            let versioned_key = Self::versioned_key(client, &prefix, &type_name, &object_key)?;
+            Ok(client.get::<Vec<u8>>(&versioned_key)?)
+        }).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let prefix = self.prefix.clone();
+        let type_name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        let ttl = self.ttl;
+        self.run(move |client| {
+            let versioned_key = Self::versioned_key(client, &prefix, &type_name, &object_key)?;
+            client.set(&versioned_key, data.as_slice(), ttl)?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let prefix = self.prefix.clone();
+        let type_name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.run(move |client| {
+            let versioned_key = Self::versioned_key(client, &prefix, &type_name, &object_key)?;
+            Ok(client.delete(&versioned_key)?)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let prefix = self.prefix.clone();
+        let type_name = self.object_directory::<O>().to_string();
+        self.run(move |client| {
+            let root_version = namespace_version(client, &Self::root_version_key(&prefix))?;
+            bump_namespace_version(client, &Self::type_version_key(&prefix, root_version, &type_name))
+        }).await.with_context(|| {
+            format!("Failed to invalidate namespace of {}", O::type_name())
+        })
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let prefix = self.prefix.clone();
+        self.run(move |client| {
+            bump_namespace_version(client, &Self::root_version_key(&prefix))?;
+            Ok(())
+        }).await.context("Failed to invalidate root namespace")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::json::JsonStorageFormat;
+
+    use super::*;
+
+    #[test]
+    fn test_memcached_version_keys() {
+        type Client = MemcachedStorageClient<JsonStorageFormat>;
+        assert_eq!(Client::root_version_key("storage"), "storage:__version");
+        assert_eq!(Client::type_version_key("storage", 3, "TestObject"), "storage:3:TestObject:__version");
+        // bumping the root version moves every type to a fresh namespace
+        assert_ne!(Client::type_version_key("storage", 3, "TestObject"), Client::type_version_key("storage", 4, "TestObject"));
+    }
+
+    #[tokio::test]
+    async fn test_memcached_init_rejects_invalid_ttl() {
+        let result = MemcachedStorageClient::<JsonStorageFormat>::init(Url::parse("memcache://localhost:11211/app?ttl=soon").unwrap()).await;
+        assert!(result.is_err());
+    }
+}
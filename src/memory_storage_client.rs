@@ -0,0 +1,832 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use dashmap::{mapref::entry::Entry, DashMap};
+use futures::{stream, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    sync::RwLock,
+};
+use url::Url;
+
+use crate::{
+    content_version, dump, lease::{lease_token, LeaseGuard}, ObjectMetadata, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation,
+    VersionConflictError,
+};
+
+/// Size of the objects of one type in a `MemoryStorageClient`
+fn objects_usage(objects: &DashMap<String, Vec<u8>>) -> StorageUsage {
+    StorageUsage {
+        objects: objects.len() as u64,
+        bytes: objects.iter().map(|object| object.value().len() as u64).sum(),
+    }
+}
+
+/// Copies of the maps of a `MemoryStorageClient`, taken by `snapshot`
+#[derive(Clone)]
+struct MemorySnapshot {
+    objects: DashMap<String, DashMap<String, Vec<u8>>>,
+    appended: DashMap<String, DashMap<String, Vec<Vec<u8>>>>,
+}
+
+/// Keeps formatted objects in memory, one concurrent map per object type.
+/// - Nothing is persisted, everything is lost when the client is dropped
+/// - `memory://name` urls are accepted, the path is only used as the directory name
+pub struct MemoryStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    objects: DashMap<String, DashMap<String, Vec<u8>>>,
+    // records of `append`, per object type and key
+    appended: DashMap<String, DashMap<String, Vec<Vec<u8>>>>,
+    snapshots: DashMap<String, MemorySnapshot>,
+    snapshot_counter: AtomicU64,
+    // token and expiry of each held lock, shared with the release of its `LeaseGuard`
+    leases: Arc<DashMap<String, (String, SystemTime)>>,
+    // written while a transaction is applied and read by reads, so no read sees half of one
+    commit_lock: RwLock<()>,
+    _formatter: PhantomData<F>,
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for MemoryStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self {
+            storage_url,
+            objects: DashMap::new(),
+            appended: DashMap::new(),
+            snapshots: DashMap::new(),
+            snapshot_counter: AtomicU64::new(0),
+            leases: Arc::new(DashMap::new()),
+            commit_lock: RwLock::new(()),
+            _formatter: PhantomData::<F>,
+        })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.objects
+            .entry(self.object_directory::<O>().to_string())
+            .or_default();
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let _commit = self.commit_lock.read().await;
+        let data = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects.get(key).map(|data| data.value().clone()),
+            None => None,
+        };
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        self.objects
+            .entry(self.object_directory::<O>().to_string())
+            .or_default()
+            .insert(key.to_string(), data);
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let removed = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects.remove(key).is_some(),
+            None => false,
+        };
+        Ok(removed)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let appended = self.appended.remove(self.object_directory::<O>()).is_some();
+        Ok(self.objects.remove(self.object_directory::<O>()).is_some() || appended)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.objects.clear();
+        self.appended.clear();
+        Ok(())
+    }
+
+    // snapshots are full copies, kept until the client is dropped
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        let _commit = self.commit_lock.read().await;
+        let id = SnapshotId::new(self.snapshot_counter.fetch_add(1, Ordering::Relaxed).to_string());
+        let snapshot = MemorySnapshot { objects: self.objects.clone(), appended: self.appended.clone() };
+        self.snapshots.insert(id.to_string(), snapshot);
+        Ok(id)
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        let snapshot = self.snapshots
+            .get(id.as_str())
+            .map(|snapshot| snapshot.value().clone())
+            .ok_or_else(|| anyhow::anyhow!("Snapshot does not exist: {}", id))?;
+        self.objects.clear();
+        for (directory, objects) in snapshot.objects {
+            self.objects.insert(directory, objects);
+        }
+        self.appended.clear();
+        for (directory, records) in snapshot.appended {
+            self.appended.insert(directory, records);
+        }
+        Ok(())
+    }
+
+    async fn export_all(&self, mut sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        // copied out first, the map guards cannot be held across writes
+        let commit = self.commit_lock.read().await;
+        let entries: Vec<(String, String, Vec<u8>)> = self.objects
+            .iter()
+            .flat_map(|objects| {
+                let type_name = objects.key().clone();
+                objects.value()
+                    .iter()
+                    .map(|object| (type_name.clone(), object.key().clone(), object.value().clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        drop(commit);
+
+        dump::write_header(&mut sink).await?;
+        for (type_name, key, data) in &entries {
+            dump::write_entry(&mut sink, type_name, key, data).await?;
+        }
+        sink.flush().await.context("Failed to flush dump")?;
+        Ok(entries.len() as u64)
+    }
+
+    async fn import_all(&self, mut source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        dump::read_header(&mut source).await?;
+        let mut count = 0;
+        while let Some(entry) = dump::read_entry(&mut source).await? {
+            self.objects.entry(entry.type_name).or_default().insert(entry.key, entry.data);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let _commit = self.commit_lock.read().await;
+        let keys = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects.iter().map(|entry| entry.key().clone()).collect(),
+            None => Vec::new(),
+        };
+        Ok(keys)
+    }
+
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        let _commit = self.commit_lock.read().await;
+        Ok(self.objects.get(self.object_directory::<O>()).map_or(0, |objects| objects.len() as u64))
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let _commit = self.commit_lock.read().await;
+        Ok(self.objects
+            .get(self.object_directory::<O>())
+            .is_some_and(|objects| objects.contains_key(key)))
+    }
+
+
+    // nothing records when objects were written, the etag is the content version
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let _commit = self.commit_lock.read().await;
+        let metadata = self.objects
+            .get(self.object_directory::<O>())
+            .and_then(|objects| objects.get(key).map(|data| ObjectMetadata {
+                size: data.len() as u64,
+                created: None,
+                modified: None,
+                etag: Some(content_version(&data)),
+            }));
+        Ok(metadata)
+    }
+
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        let _commit = self.commit_lock.read().await;
+        Ok(self.objects.get(self.object_directory::<O>()).map(|objects| objects_usage(&objects)).unwrap_or_default())
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        let _commit = self.commit_lock.read().await;
+        Ok(self.objects.iter().map(|objects| (objects.key().clone(), objects_usage(objects.value()))).collect())
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let _commit = self.commit_lock.read().await;
+        let objects = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects,
+            None => return Ok(HashMap::new()),
+        };
+        keys.iter()
+            .filter_map(|key| objects.get(*key).map(|data| (key.to_string(), data.value().clone())))
+            .map(|(key, data)| {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok((key, obj))
+            })
+            .collect()
+    }
+
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let objects = self.objects.entry(self.object_directory::<O>().to_string()).or_default();
+        for (key, value) in items {
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            objects.insert(key, data);
+        }
+        Ok(())
+    }
+
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let deleted = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => keys.iter().filter(|key| objects.remove(**key).is_some()).count(),
+            None => 0,
+        };
+        Ok(deleted)
+    }
+
+
+    // staged objects are already formatted, so applying them cannot fail halfway
+    // reads wait for the whole transaction under the commit lock
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let _commit = self.commit_lock.write().await;
+        for operation in operations {
+            match operation {
+                TransactionOperation::Put { type_name, key, data, .. } => {
+                    self.objects.entry(type_name.to_string()).or_default().insert(key, data);
+                }
+                TransactionOperation::Delete { type_name, key, .. } => {
+                    if let Some(objects) = self.objects.get(type_name) {
+                        objects.remove(&key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        self.appended
+            .entry(self.object_directory::<O>().to_string())
+            .or_default()
+            .entry(key.to_string())
+            .or_default()
+            .push(data);
+        Ok(())
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        let records = self.appended
+            .get(self.object_directory::<O>())
+            .and_then(|records| records.get(key).map(|records| records.value().clone()))
+            .unwrap_or_default();
+        let key = key.to_string();
+        stream::iter(records).map(move |data| {
+            F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize appended {} for key: {}", O::type_name(), key)
+            })
+        })
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let _commit = self.commit_lock.read().await;
+        let data = self.objects
+            .get(self.object_directory::<O>())
+            .and_then(|objects| objects.get(key).map(|data| data.value().clone()));
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some((obj, content_version(&data))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // the entry stays locked from the comparison to the write
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let objects = self.objects.entry(self.object_directory::<O>().to_string()).or_default();
+        let actual = match objects.entry(key.to_string()) {
+            Entry::Occupied(mut entry) => {
+                let actual = content_version(entry.get());
+                if actual == expected_version {
+                    let version = content_version(&data);
+                    entry.insert(data);
+                    return Ok(version);
+                }
+                Some(actual)
+            }
+            Entry::Vacant(_) => None,
+        };
+        Err(VersionConflictError {
+            type_name: O::type_name(),
+            key: key.to_string(),
+            expected: expected_version.to_string(),
+            actual,
+        }.into())
+    }
+
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let objects = self.objects.entry(self.object_directory::<O>().to_string()).or_default();
+        let inserted = match objects.entry(key.to_string()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(data);
+                true
+            }
+        };
+        Ok(inserted)
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        let token = lease_token();
+        let expires = SystemTime::now() + ttl;
+        match self.leases.entry(name.to_string()) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().1 > SystemTime::now() {
+                    return Ok(None);
+                }
+                entry.insert((token.clone(), expires));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((token.clone(), expires));
+            }
+        }
+
+        let leases = self.leases.clone();
+        let (lease_name, lease_token) = (name.to_string(), token.clone());
+        Ok(Some(LeaseGuard::new(name, token, expires, async move {
+            leases.remove_if(&lease_name, |_, (holder, _)| *holder == lease_token);
+            Ok(())
+        })))
+    }
+
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let objects = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects,
+            None => return Ok(false),
+        };
+        let data = match objects.get(from) {
+            Some(data) => data.value().clone(),
+            None => return Ok(false),
+        };
+        objects.insert(to.to_string(), data);
+        Ok(true)
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let objects = match self.objects.get(self.object_directory::<O>()) {
+            Some(objects) => objects,
+            None => return Ok(false),
+        };
+        if from == to {
+            return Ok(objects.contains_key(from));
+        }
+        match objects.remove(from) {
+            Some((_, data)) => {
+                objects.insert(to.to_string(), data);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        json::JsonStorageFormat, query::SortOrder, test_object::{IndexedTestObject, TestObject}, Keyed, Page, PageRequest,
+        RustStandardType, StorageSchema, WriteBatch,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_storage_client_json() {
+        let url = Url::parse("memory://test").expect("Failed to parse memory URL");
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(url).await.unwrap();
+
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        let missing: Option<TestObject> = client.get("missing").await.unwrap();
+        assert!(missing.is_none());
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        client.put("test_key", TestObject { key: "test_key".to_string(), value: "v".to_string() }).await.unwrap();
+        assert!(client.delete_all().await.is_ok());
+        let cleared: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(cleared.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_list_page() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        for key in ["c", "a", "e", "b", "d"] {
+            client.put(key, TestObject { key: key.to_string(), value: "v".to_string() }).await.unwrap();
+        }
+
+        let mut request = Some(PageRequest::new(2));
+        let mut pages = Vec::new();
+        while let Some(current) = request {
+            let page = client.list_page::<TestObject>(current.clone()).await.unwrap();
+            request = current.next(&page);
+            pages.push(page.items);
+        }
+        assert_eq!(pages, vec![vec!["a", "b"], vec!["c", "d"], vec!["e"]]);
+
+        let rest = client.list_page::<TestObject>(PageRequest::new(10).after("c")).await.unwrap();
+        assert_eq!(rest, Page { items: vec!["d".to_string(), "e".to_string()], next_token: None });
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_put_if_version() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        client.put("test_key", TestObject { key: "test_key".to_string(), value: "v1".to_string() }).await.unwrap();
+
+        let (obj, version) = client.get_versioned::<TestObject>("test_key").await.unwrap().unwrap();
+        assert_eq!(obj.value, "v1");
+        let updated = TestObject { key: "test_key".to_string(), value: "v2".to_string() };
+        let new_version = client.put_if_version("test_key", updated.clone(), &version).await.unwrap();
+        assert_ne!(new_version, version);
+
+        // a writer still holding the old version loses instead of overwriting
+        let error = client.put_if_version("test_key", updated, &version).await.unwrap_err();
+        let conflict = error.downcast_ref::<VersionConflictError>().expect("Expected a version conflict");
+        assert_eq!(conflict.actual.as_deref(), Some(new_version.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_get_or_insert_with() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let first = TestObject { key: "test_key".to_string(), value: "first".to_string() };
+        let inserted = client.get_or_insert_with("test_key", || async { Ok(first.clone()) }).await.unwrap();
+        assert_eq!(inserted, first);
+
+        // the stored object wins, init does not run again
+        let existing: TestObject = client
+            .get_or_insert_with("test_key", || async { Err(anyhow::anyhow!("init must not run")) })
+            .await
+            .unwrap();
+        assert_eq!(existing, first);
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestCounter {
+        name: String,
+        count: u64,
+    }
+
+    impl StorageObject for TestCounter {
+        fn type_name() -> &'static str {
+            "TestCounter"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("name".to_string(), RustStandardType::String);
+            schema.insert("count".to_string(), RustStandardType::UInt64);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "name".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_transaction() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let counter = TestCounter { name: "objects".to_string(), count: 1 };
+        client.put("stale_key", obj.clone()).await.unwrap();
+
+        let committed = client
+            .transaction(|txn| {
+                let (obj, counter) = (obj.clone(), counter.clone());
+                async move {
+                    txn.put("test_key", &obj)?;
+                    txn.put("objects", &counter)?;
+                    txn.delete::<TestObject>("stale_key");
+                    Ok(2)
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(committed, 2);
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), Some(obj.clone()));
+        assert_eq!(client.get::<TestCounter>("objects").await.unwrap(), Some(counter.clone()));
+        assert!(!client.exists::<TestObject>("stale_key").await.unwrap());
+
+        // an error from the closure discards everything it staged
+        let failed = client
+            .transaction(|txn| {
+                let obj = obj.clone();
+                async move {
+                    txn.put("other_key", &obj)?;
+                    txn.delete::<TestCounter>("objects");
+                    Err::<(), _>(anyhow::anyhow!("abort"))
+                }
+            })
+            .await;
+        assert!(failed.is_err());
+        assert!(!client.exists::<TestObject>("other_key").await.unwrap());
+        assert_eq!(client.get::<TestCounter>("objects").await.unwrap(), Some(counter));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_put_all() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let counter = TestCounter { name: "objects".to_string(), count: 1 };
+
+        let mut batch = WriteBatch::new();
+        batch.put("test_key", &obj).unwrap().put("objects", &counter).unwrap();
+        assert_eq!(batch.len(), 2);
+        client.put_all(batch).await.unwrap();
+
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), Some(obj));
+        assert_eq!(client.get::<TestCounter>("objects").await.unwrap(), Some(counter));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_append() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let empty: Vec<TestObject> = client.read_appended("events").try_collect().await.unwrap();
+        assert!(empty.is_empty());
+
+        let first = TestObject { key: "events".to_string(), value: "first".to_string() };
+        let second = TestObject { key: "events".to_string(), value: "second".to_string() };
+        client.append("events", first.clone()).await.unwrap();
+        client.append("events", second.clone()).await.unwrap();
+
+        let records: Vec<TestObject> = client.read_appended("events").try_collect().await.unwrap();
+        assert_eq!(records, vec![first, second]);
+        // appended records are not the object stored under the key
+        assert_eq!(client.get::<TestObject>("events").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_snapshot() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let before = TestObject { key: "test_key".to_string(), value: "before".to_string() };
+        client.put("test_key", before.clone()).await.unwrap();
+
+        let id = client.snapshot().await.unwrap();
+        client.put("test_key", TestObject { value: "after".to_string(), ..before.clone() }).await.unwrap();
+        client.restore(&id).await.unwrap();
+        assert_eq!(client.get::<TestObject>("test_key").await.unwrap(), Some(before));
+        assert!(client.restore(&SnapshotId::new("missing")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_export_all() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let counter = TestCounter { name: "objects".to_string(), count: 1 };
+        client.put("test_key", obj.clone()).await.unwrap();
+        client.put("objects", counter.clone()).await.unwrap();
+
+        let mut dump = Vec::new();
+        assert_eq!(client.export_all(&mut dump).await.unwrap(), 2);
+
+        let restored = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://restored").unwrap()).await.unwrap();
+        assert_eq!(restored.import_all(dump.as_slice()).await.unwrap(), 2);
+        assert_eq!(restored.get::<TestObject>("test_key").await.unwrap(), Some(obj));
+        assert_eq!(restored.get::<TestCounter>("objects").await.unwrap(), Some(counter));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_usage() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        assert_eq!(client.usage::<TestObject>().await.unwrap(), StorageUsage::default());
+
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let size = JsonStorageFormat::serialize(&obj).unwrap().len() as u64;
+        client.put("a", obj.clone()).await.unwrap();
+        client.put("b", obj).await.unwrap();
+        client.put("objects", TestCounter { name: "objects".to_string(), count: 2 }).await.unwrap();
+
+        assert_eq!(client.usage::<TestObject>().await.unwrap(), StorageUsage { objects: 2, bytes: 2 * size });
+        let usages = client.usage_all().await.unwrap();
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages["TestCounter"].objects, 1);
+    }
+
+    impl Keyed for TestCounter {
+        type Key = (String, u64);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_keyed() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let key = ("tenant/a".to_string(), 7);
+        let counter = TestCounter { name: "objects".to_string(), count: 1 };
+        client.put_keyed(&key, counter.clone()).await.unwrap();
+
+        assert_eq!(client.encode_key(&key), "tenant%2Fa/7");
+        assert_eq!(client.get::<TestCounter>("tenant%2Fa/7").await.unwrap(), Some(counter.clone()));
+        assert_eq!(client.get_keyed::<TestCounter>(&key).await.unwrap(), Some(counter));
+        assert!(client.delete_keyed::<TestCounter>(&key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_query() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        for (name, count) in [("a", 1), ("b", 5), ("c", 9)] {
+            client.put(name, TestCounter { name: name.to_string(), count }).await.unwrap();
+        }
+
+        let found = client.query::<TestCounter>().gt("count", 1).lte("count", 9).fetch().await.unwrap();
+        let keys: Vec<&str> = found.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["b", "c"]);
+        let found = client.query::<TestCounter>().is_in("name", ["a", "c"]).eq("count", 9).fetch().await.unwrap();
+        assert_eq!(found, vec![("c".to_string(), TestCounter { name: "c".to_string(), count: 9 })]);
+
+        let page = client.query::<TestCounter>().order_by("count", SortOrder::Desc).offset(1).limit(1).fetch().await.unwrap();
+        assert_eq!(page[0].0, "b");
+        let streamed: Vec<(String, TestCounter)> = client.query::<TestCounter>().gt("count", 1).stream().try_collect().await.unwrap();
+        assert_eq!(streamed.len(), 2);
+        let streamed: Vec<(String, TestCounter)> = client.query::<TestCounter>().order_by("count", SortOrder::Desc).limit(2).stream().try_collect().await.unwrap();
+        assert_eq!(streamed.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["c", "b"]);
+
+        assert_eq!(client.query::<TestCounter>().gt("count", 1).count().await.unwrap(), 2);
+        assert_eq!(client.query::<TestCounter>().sum("count").await.unwrap(), 15.0);
+        assert_eq!(client.query::<TestCounter>().min("name").await.unwrap(), Some(serde_json::json!("a")));
+        assert_eq!(client.query::<TestCounter>().gt("count", 100).max("count").await.unwrap(), None);
+        let groups = client.query::<TestCounter>().group_by("count").count().await.unwrap();
+        assert_eq!(groups, vec![(serde_json::json!(1), 1), (serde_json::json!(5), 1), (serde_json::json!(9), 1)]);
+
+        assert_eq!(client.query::<TestCounter>().lt("count", 6).delete().await.unwrap(), 2);
+        assert_eq!(client.list_keys::<TestCounter>().await.unwrap(), vec!["c".to_string()]);
+        assert_eq!(client.delete_where::<TestCounter>(&[]).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_search() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        for (key, value) in [("a", "The quick brown fox"), ("b", "A brown dog, a brown cat"), ("c", "Slow green turtle")] {
+            client.put(key, IndexedTestObject { key: key.to_string(), value: value.to_string() }).await.unwrap();
+        }
+
+        let found = client.search::<IndexedTestObject>("Brown").await.unwrap();
+        let keys: Vec<&str> = found.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["b", "a"]);
+        let found = client.search::<IndexedTestObject>("brown fox").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "a");
+        assert!(client.search::<IndexedTestObject>("zebra").await.unwrap().is_empty());
+        assert!(client.search::<TestCounter>("objects").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_scan_range() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        for key in ["2024-06-02T08:00:00Z", "2024-05-31T23:59:59Z", "2024-06-01T00:00:00Z", "2024-07-01T00:00:00Z"] {
+            client.put(key, TestObject { key: key.to_string(), value: "event".to_string() }).await.unwrap();
+        }
+
+        let june: Vec<(String, TestObject)> = client.scan_range("2024-06".."2024-07").await.unwrap();
+        let keys: Vec<&str> = june.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["2024-06-01T00:00:00Z", "2024-06-02T08:00:00Z"]);
+        let until: Vec<(String, TestObject)> = client.scan_range(..="2024-06-01T00:00:00Z").await.unwrap();
+        assert_eq!(until.len(), 2);
+        let all: Vec<(String, TestObject)> = client.scan_range(..).await.unwrap();
+        assert_eq!(all.first().map(|(key, _)| key.as_str()), Some("2024-05-31T23:59:59Z"));
+        assert_eq!(all.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_merge() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let add = |existing: TestCounter, incoming: TestCounter| TestCounter { name: existing.name, count: existing.count + incoming.count };
+
+        let merges = (1..=10).map(|_| client.merge("visits", TestCounter { name: "visits".to_string(), count: 1 }, add));
+        futures::future::try_join_all(merges).await.unwrap();
+        assert_eq!(client.get::<TestCounter>("visits").await.unwrap().map(|counter| counter.count), Some(10));
+
+        let merged = client.merge("visits", TestCounter { name: "visits".to_string(), count: 5 }, add).await.unwrap();
+        assert_eq!(merged.count, 15);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_lock() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let guard = client.try_lock("job", Duration::from_secs(5)).await.unwrap().unwrap();
+        assert!(client.try_lock("job", Duration::from_secs(5)).await.unwrap().is_none());
+        assert!(client.try_lock("other", Duration::from_secs(5)).await.unwrap().is_some());
+        guard.release().await.unwrap();
+
+        // an expired lease can be taken over, the old holder's release leaves the new lease alone
+        let expired = client.lock("job", Duration::from_millis(1)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let taken = client.lock("job", Duration::from_secs(5)).await.unwrap();
+        expired.release().await.unwrap();
+        assert!(client.try_lock("job", Duration::from_secs(5)).await.unwrap().is_none());
+        taken.release().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_import_stream() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let total = crate::IMPORT_BATCH_SIZE * 2 + 10;
+        let items = futures::stream::iter(0..total)
+            .map(|i| (format!("{:05}", i), TestObject { key: format!("{:05}", i), value: i.to_string() }));
+
+        let mut reported = Vec::new();
+        let imported = client.import_stream(items, |count| reported.push(count)).await.unwrap();
+        assert_eq!(imported, total as u64);
+        assert_eq!(reported.len(), 3);
+        assert_eq!(reported.last(), Some(&(total as u64)));
+        assert_eq!(client.count::<TestObject>().await.unwrap(), total as u64);
+        let object: Option<TestObject> = client.get("00042").await.unwrap();
+        assert_eq!(object.map(|object| object.value), Some("42".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_client_export_stream() {
+        let client = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        for (key, value) in [("a", "keep"), ("b", "skip"), ("c", "keep")] {
+            client.put(key, TestObject { key: key.to_string(), value: value.to_string() }).await.unwrap();
+        }
+
+        let filters = vec![crate::query::Filter::Eq { field: "value".to_string(), value: serde_json::json!("keep") }];
+        let mut entries: Vec<crate::dump::DumpEntry> = client.export_stream::<TestObject>(filters).try_collect().await.unwrap();
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(entries.iter().map(|entry| entry.key.as_str()).collect::<Vec<_>>(), vec!["a", "c"]);
+        assert_eq!(entries[0].type_name, "TestObject");
+        let object: TestObject = JsonStorageFormat::deserialize(&entries[0].data).unwrap();
+        assert_eq!(object.value, "keep");
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{lease::LeaseGuard, query::{AggregateSpec, Filter, QuerySpec}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Bucket `i` holds latencies up to 2^i microseconds, the last one everything above ~36 minutes
+const BUCKETS: usize = 32;
+
+/// Latency histogram with power of two buckets, percentiles are reported as the bucket's upper bound
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS],
+}
+
+impl Histogram {
+
+    fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros().max(1);
+        let bucket = (u128::BITS - (micros - 1).leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *bucket += count;
+        }
+    }
+
+    fn percentile(&self, percentile: f64) -> Duration {
+        let total: u64 = self.buckets.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((total as f64) * percentile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(1 << bucket);
+            }
+        }
+        Duration::from_micros(1 << (BUCKETS - 1))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Recorder {
+    count: u64,
+    errors: u64,
+    latency: Histogram,
+}
+
+impl Recorder {
+
+    fn merge(&mut self, other: &Recorder) {
+        self.count += other.count;
+        self.errors += other.errors;
+        self.latency.merge(&other.latency);
+    }
+
+    fn stats(&self) -> OperationStats {
+        OperationStats {
+            count: self.count,
+            errors: self.errors,
+            p50: self.latency.percentile(0.50),
+            p99: self.latency.percentile(0.99),
+        }
+    }
+}
+
+/// Counters and latency percentiles of one operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OperationStats {
+    pub count: u64,
+    pub errors: u64,
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+/// Snapshot returned by `MeteredStorageClient::stats`
+#[derive(Debug, Clone, Default)]
+pub struct StorageStats {
+    /// by operation name, e.g. `get`
+    pub operations: BTreeMap<&'static str, OperationStats>,
+    /// by operation name and object type, operations without a type like `delete_all` are not included
+    pub types: BTreeMap<(&'static str, &'static str), OperationStats>,
+}
+
+/// Counts operations, errors and latencies of the inner client per operation and object type.
+/// - `stats` returns a snapshot, `reset` starts over
+pub struct MeteredStorageClient<C> {
+    inner: C,
+    recorders: Mutex<HashMap<(&'static str, Option<&'static str>), Recorder>>,
+}
+
+impl<C> MeteredStorageClient<C> {
+
+    pub fn new(inner: C) -> Self {
+        Self { inner, recorders: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> StorageStats {
+        let recorders = self.recorders.lock().unwrap_or_else(|e| e.into_inner());
+        let mut operations: BTreeMap<&'static str, Recorder> = BTreeMap::new();
+        let mut stats = StorageStats::default();
+        for (&(operation, type_name), recorder) in recorders.iter() {
+            operations.entry(operation).or_default().merge(recorder);
+            if let Some(type_name) = type_name {
+                stats.types.insert((operation, type_name), recorder.stats());
+            }
+        }
+        stats.operations = operations.into_iter().map(|(operation, recorder)| (operation, recorder.stats())).collect();
+        stats
+    }
+
+    pub fn reset(&self) {
+        self.recorders.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    async fn measure<R>(
+        &self,
+        operation: &'static str,
+        type_name: Option<&'static str>,
+        f: impl Future<Output = anyhow::Result<R>>,
+    ) -> anyhow::Result<R> {
+        let start = Instant::now();
+        let result = f.await;
+        let elapsed = start.elapsed();
+
+        let mut recorders = self.recorders.lock().unwrap_or_else(|e| e.into_inner());
+        let recorder = recorders.entry((operation, type_name)).or_default();
+        recorder.count += 1;
+        if result.is_err() {
+            recorder.errors += 1;
+        }
+        recorder.latency.record(elapsed);
+        result
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for MeteredStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.measure("create_object_directory", Some(O::type_name()), self.inner.create_object_directory::<O>()).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.measure("get", Some(O::type_name()), self.inner.get(key)).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.measure("put", Some(O::type_name()), self.inner.put(key, value)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.measure("delete", Some(O::type_name()), self.inner.delete::<O>(key)).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.measure("delete_object_directory", Some(O::type_name()), self.inner.delete_object_directory::<O>()).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.measure("delete_all", None, self.inner.delete_all()).await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.measure("health_check", None, self.inner.health_check()).await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.measure("snapshot", None, self.inner.snapshot()).await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.measure("restore", None, self.inner.restore(id)).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.measure("export_all", None, self.inner.export_all(sink)).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.measure("import_all", None, self.inner.import_all(source)).await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        self.measure("transaction", None, self.inner.commit_transaction(operations)).await
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.measure("append", Some(O::type_name()), self.inner.append(key, value)).await
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        self.measure("list_versions", Some(O::type_name()), self.inner.list_versions::<O>(key)).await
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        self.measure("get_version", Some(O::type_name()), self.inner.get_version::<O>(key, version)).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.measure("list_keys", Some(O::type_name()), self.inner.list_keys::<O>()).await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.measure("list_page", Some(O::type_name()), self.inner.list_page::<O>(page)).await
+    }
+
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        self.measure("scan_range", Some(O::type_name()), self.inner.scan_range::<O>(range)).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.measure("count", Some(O::type_name()), self.inner.count::<O>()).await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.measure("exists", Some(O::type_name()), self.inner.exists::<O>(key)).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.measure("metadata", Some(O::type_name()), self.inner.metadata::<O>(key)).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.measure("usage", Some(O::type_name()), self.inner.usage::<O>()).await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.measure("usage_all", None, self.inner.usage_all()).await
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        self.measure("find_by", Some(O::type_name()), self.inner.find_by::<O, V>(field, value)).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        self.measure("search", Some(O::type_name()), self.inner.search::<O>(terms)).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        self.measure("query", Some(O::type_name()), self.inner.execute_query::<O>(spec)).await
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        self.measure("aggregate", Some(O::type_name()), self.inner.execute_aggregate::<O>(spec)).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.measure("get_many", Some(O::type_name()), self.inner.get_many(keys)).await
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        self.measure("put_many", Some(O::type_name()), self.inner.put_many(items)).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.measure("delete_many", Some(O::type_name()), self.inner.delete_many::<O>(keys)).await
+    }
+
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        self.measure("delete_where", Some(O::type_name()), self.inner.delete_where::<O>(filters)).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.measure("get_versioned", Some(O::type_name()), self.inner.get_versioned(key)).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        self.measure("put_if_version", Some(O::type_name()), self.inner.put_if_version(key, value, expected_version)).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        self.measure("put_if_absent", Some(O::type_name()), self.inner.put_if_absent(key, value)).await
+    }
+
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        self.measure("merge", Some(O::type_name()), self.inner.merge::<O>(key, incoming, resolver)).await
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        self.measure("try_lock", None, self.inner.try_lock(name, ttl)).await
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.measure("copy", Some(O::type_name()), self.inner.copy::<O>(from, to)).await
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.measure("rename", Some(O::type_name()), self.inner.rename::<O>(from, to)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        json::JsonStorageFormat, memory_storage_client::MemoryStorageClient,
+        read_only_storage_client::ReadOnlyStorageClient, test_object::TestObject,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = Histogram::default();
+        for _ in 0..99 {
+            histogram.record(Duration::from_micros(3));
+        }
+        histogram.record(Duration::from_millis(1));
+        assert_eq!(histogram.percentile(0.50), Duration::from_micros(4));
+        assert_eq!(histogram.percentile(0.99), Duration::from_micros(4));
+        assert_eq!(histogram.percentile(1.0), Duration::from_micros(1024));
+    }
+
+    #[tokio::test]
+    async fn test_metered_storage_client_counts_operations() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://metered").unwrap()).await.unwrap();
+        let client = MeteredStorageClient::new(ReadOnlyStorageClient::new(inner));
+
+        let _: Option<TestObject> = client.get("test_key").await.unwrap();
+        let _: Option<TestObject> = client.get("other_key").await.unwrap();
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        assert!(client.put("test_key", obj).await.is_err());
+
+        let stats = client.stats();
+        assert_eq!(stats.operations["get"].count, 2);
+        assert_eq!(stats.operations["put"].errors, 1);
+        assert_eq!(stats.types[&("get", "TestObject")].count, 2);
+
+        client.reset();
+        assert!(client.stats().operations.is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{memory_storage_client::MemoryStorageClient, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockOperation {
+    CreateObjectDirectory,
+    Get,
+    Put,
+    Delete,
+    DeleteObjectDirectory,
+    DeleteAll,
+}
+
+/// Scripted outcome of the next call of an operation
+#[derive(Debug)]
+pub enum MockResponse {
+    /// run the operation against the in-memory store
+    Passthrough,
+    /// `get` returns `None`, `delete` and `delete_object_directory` return false, the store is not touched
+    Missing,
+    Error(anyhow::Error),
+}
+
+/// A call made to a `MockStorageClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    pub operation: MockOperation,
+    pub type_name: Option<&'static str>,
+    pub key: Option<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    calls: Vec<MockCall>,
+    responses: HashMap<MockOperation, VecDeque<MockResponse>>,
+    failures: HashMap<usize, anyhow::Error>,
+    latency: HashMap<MockOperation, Duration>,
+}
+
+/// In-memory client for tests of code built on `StorageClient`, with scripted responses,
+/// errors on the nth call and artificial latency per operation.
+/// - Without scripting it behaves like `MemoryStorageClient`
+/// - Only available with the `test-util` feature
+pub struct MockStorageClient<F: StorageFormat> {
+    store: MemoryStorageClient<F>,
+    state: Mutex<MockState>,
+}
+
+impl<F: StorageFormat> MockStorageClient<F> {
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queues the response of the next call of `operation`, responses are used in the order they were queued
+    pub fn respond(&self, operation: MockOperation, response: MockResponse) {
+        self.state().responses.entry(operation).or_default().push_back(response);
+    }
+
+    /// Fails call number `call` counted over all operations, starting at 1
+    pub fn fail_on_call(&self, call: usize, error: anyhow::Error) {
+        self.state().failures.insert(call, error);
+    }
+
+    /// Delays every call of `operation`
+    pub fn set_latency(&self, operation: MockOperation, latency: Duration) {
+        self.state().latency.insert(operation, latency);
+    }
+
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state().calls.clone()
+    }
+
+    pub fn call_count(&self, operation: MockOperation) -> usize {
+        self.state().calls.iter().filter(|call| call.operation == operation).count()
+    }
+
+    /// Records the call and returns how it should be answered
+    async fn call(&self, operation: MockOperation, type_name: Option<&'static str>, key: Option<&str>) -> anyhow::Result<bool> {
+        let (response, latency) = {
+            let mut state = self.state();
+            state.calls.push(MockCall { operation, type_name, key: key.map(|key| key.to_string()) });
+            let call = state.calls.len();
+            let response = match state.failures.remove(&call) {
+                Some(error) => MockResponse::Error(error),
+                None => state.responses
+                    .get_mut(&operation)
+                    .and_then(|responses| responses.pop_front())
+                    .unwrap_or(MockResponse::Passthrough),
+            };
+            (response, state.latency.get(&operation).copied())
+        };
+
+        if let Some(latency) = latency {
+            tokio::time::sleep(latency).await;
+        }
+        match response {
+            MockResponse::Passthrough => Ok(true),
+            MockResponse::Missing => Ok(false),
+            MockResponse::Error(error) => Err(error),
+        }
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for MockStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let store = MemoryStorageClient::init(storage_url).await?;
+        Ok(Self { store, state: Mutex::new(MockState::default()) })
+    }
+
+    fn directory(&self) -> &str {
+        self.store.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        if self.call(MockOperation::CreateObjectDirectory, Some(O::type_name()), None).await? {
+            self.store.create_object_directory::<O>().await?;
+        }
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        if self.call(MockOperation::Get, Some(O::type_name()), Some(key)).await? {
+            self.store.get(key).await
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        if self.call(MockOperation::Put, Some(O::type_name()), Some(key)).await? {
+            self.store.put(key, value).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        if self.call(MockOperation::Delete, Some(O::type_name()), Some(key)).await? {
+            self.store.delete::<O>(key).await
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        if self.call(MockOperation::DeleteObjectDirectory, Some(O::type_name()), None).await? {
+            self.store.delete_object_directory::<O>().await
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        if self.call(MockOperation::DeleteAll, None, None).await? {
+            self.store.delete_all().await?;
+        }
+        Ok(())
+    }
+
+    // not scripted, listing answers from the in-memory store
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.store.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.store.list_page::<O>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.store.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.store.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.store.metadata::<O>(key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{
+        json::JsonStorageFormat, raw::RawFormat,
+        retrying_storage_client::{RetryPolicy, RetryingStorageClient},
+        test_object::TestObject,
+    };
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_mock_storage_client_injects_failures() {
+        let mock = MockStorageClient::<RawFormat>::init(Url::parse("memory://mock").unwrap()).await.unwrap();
+        mock.fail_on_call(1, std::io::Error::from(std::io::ErrorKind::ConnectionReset).into());
+        mock.respond(MockOperation::Get, MockResponse::Missing);
+        mock.set_latency(MockOperation::Put, Duration::from_secs(1));
+
+        // the retrying client recovers from the injected connection reset
+        let client = RetryingStorageClient::<JsonStorageFormat, _>::new(mock, RetryPolicy::default());
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        let start = tokio::time::Instant::now();
+        client.put("test_key", obj.clone()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(2));
+        assert_eq!(client.inner().call_count(MockOperation::Put), 2);
+
+        // the scripted response hides the object once, then the store answers
+        let missing: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(missing.is_none());
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert_eq!(client.inner().calls().last(), Some(&MockCall {
+            operation: MockOperation::Get,
+            type_name: Some("TestObject"),
+            key: Some("test_key".to_string()),
+        }));
+    }
+}
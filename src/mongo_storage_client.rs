@@ -0,0 +1,154 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use mongodb::{
+    bson::{self, doc, Document},
+    Client, Collection, Database,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{json::JsonStorageFormat, StorageClient, StorageFormat, StorageObject};
+
+/// Stores objects as BSON documents, one collection per object type with `_id` set to the key.
+/// - `mongodb://host:port/database`, the database defaults to `storage`
+/// - Documents are always native BSON, `F` does not change the stored representation
+pub struct MongoStorageClient<F: StorageFormat = JsonStorageFormat> {
+    database: Database,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> MongoStorageClient<F> {
+
+    fn collection<O: StorageObject>(&self) -> Collection<Document> {
+        self.database.collection(O::type_name())
+    }
+
+    async fn collection_exists(&self, name: &str) -> anyhow::Result<bool> {
+        let names = self.database
+            .list_collection_names()
+            .filter(doc! { "name": name })
+            .await
+            .with_context(|| format!("Failed to list collections of database: {}", self.database.name()))?;
+        Ok(!names.is_empty())
+    }
+}
+
+/// The stored document of an object, its fields with `_id` set to the key
+fn object_document<O: StorageObject + Serialize>(key: &str, value: &O) -> anyhow::Result<Document> {
+    let mut document = bson::to_document(value).with_context(|| {
+        format!("Failed to serialize object for key: {}", key)
+    })?;
+    document.insert("_id", key);
+    Ok(document)
+}
+
+fn document_object<O: StorageObject + DeserializeOwned>(key: &str, mut document: Document) -> anyhow::Result<O> {
+    document.remove("_id");
+    bson::from_document(document).with_context(|| {
+        format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+    })
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for MongoStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let client = Client::with_uri_str(storage_url.as_str()).await.with_context(|| {
+            format!("Failed to connect to mongodb at: {}", storage_url)
+        })?;
+        let database = client
+            .default_database()
+            .unwrap_or_else(|| client.database("storage"));
+
+        database.run_command(doc! { "ping": 1 }).await.with_context(|| {
+            format!("Failed to reach database: {}", database.name())
+        })?;
+
+        Ok(Self { database, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.database.name()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let name = self.object_directory::<O>();
+        if self.collection_exists(name).await? {
+            return Ok(());
+        }
+        self.database.create_collection(name).await.with_context(|| {
+            format!("Failed to create collection: {}", name)
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let document = self.collection::<O>()
+            .find_one(doc! { "_id": key })
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        document.map(|document| document_object(key, document)).transpose()
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let document = object_document(key, &value)?;
+        self.collection::<O>()
+            .replace_one(doc! { "_id": key }, document)
+            .upsert(true)
+            .await
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let result = self.collection::<O>()
+            .delete_one(doc! { "_id": key })
+            .await
+            .with_context(|| format!("Failed to delete {} for key: {}", O::type_name(), key))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>();
+        if !self.collection_exists(name).await? {
+            return Ok(false);
+        }
+        self.collection::<O>().drop().await.with_context(|| {
+            format!("Failed to drop collection: {}", name)
+        })?;
+        Ok(true)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.database.drop().await.with_context(|| {
+            format!("Failed to drop database: {}", self.database.name())
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_object::TestObject;
+
+    use super::*;
+
+    #[test]
+    fn test_mongo_document_round_trip() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let document = object_document("stored_key", &obj).unwrap();
+        assert_eq!(document.get_str("_id").unwrap(), "stored_key");
+        assert_eq!(document.get_str("value").unwrap(), "test_value");
+
+        let retrieved: TestObject = document_object("stored_key", document).unwrap();
+        assert_eq!(retrieved, obj);
+    }
+}
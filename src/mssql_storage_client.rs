@@ -0,0 +1,173 @@
+use std::{fmt::{Display, Formatter}, marker::PhantomData};
+
+use crate::{StorageFormat, StorageObject, StorageSchema};
+use tiberius::Client;
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_util::compat::Compat;
+use url::Url;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MssqlType {
+    // 1 byte, unsigned
+    TinyInt,
+    // 2 bytes
+    SmallInt,
+    // 4 bytes
+    Int,
+    // 8 bytes
+    BigInt,
+    Decimal {
+        precision: Option<u8>,
+        scale: Option<u8>,
+    },
+    // 4 bytes
+    Real,
+    // 8 bytes
+    Float,
+    // 0 or 1
+    BIT,
+    // fixed length, non-unicode
+    CHAR {
+        n: u32,
+    },
+    // variable length, non-unicode, None for MAX
+    VARCHAR {
+        n: Option<u32>,
+    },
+    // fixed length, unicode
+    NCHAR {
+        n: u32,
+    },
+    // variable length, unicode, None for MAX
+    NVARCHAR {
+        n: Option<u32>,
+    },
+    // variable length binary, None for MAX
+    VARBINARY {
+        n: Option<u32>,
+    },
+    DATE,
+    TIME,
+    DATETIME2,
+    DATETIMEOFFSET,
+    UNIQUEIDENTIFIER,
+}
+
+impl Display for MssqlType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MssqlType::TinyInt => write!(f, "TINYINT"),
+            MssqlType::SmallInt => write!(f, "SMALLINT"),
+            MssqlType::Int => write!(f, "INT"),
+            MssqlType::BigInt => write!(f, "BIGINT"),
+            MssqlType::Decimal { precision, scale } => {
+                match (precision, scale) {
+                    (Some(p), Some(s)) => write!(f, "DECIMAL({}, {})", p, s),
+                    (Some(p), None) => write!(f, "DECIMAL({})", p),
+                    _ => write!(f, "DECIMAL"),
+                }
+            }
+            MssqlType::Real => write!(f, "REAL"),
+            MssqlType::Float => write!(f, "FLOAT"),
+            MssqlType::BIT => write!(f, "BIT"),
+            MssqlType::CHAR { n } => write!(f, "CHAR({})", n),
+            MssqlType::VARCHAR { n } => write_length(f, "VARCHAR", *n),
+            MssqlType::NCHAR { n } => write!(f, "NCHAR({})", n),
+            MssqlType::NVARCHAR { n } => write_length(f, "NVARCHAR", *n),
+            MssqlType::VARBINARY { n } => write_length(f, "VARBINARY", *n),
+            MssqlType::DATE => write!(f, "DATE"),
+            MssqlType::TIME => write!(f, "TIME"),
+            MssqlType::DATETIME2 => write!(f, "DATETIME2"),
+            MssqlType::DATETIMEOFFSET => write!(f, "DATETIMEOFFSET"),
+            MssqlType::UNIQUEIDENTIFIER => write!(f, "UNIQUEIDENTIFIER"),
+        }
+    }
+}
+
+fn write_length(f: &mut Formatter<'_>, name: &str, n: Option<u32>) -> std::fmt::Result {
+    match n {
+        Some(n) => write!(f, "{}({})", name, n),
+        None => write!(f, "{}(MAX)", name),
+    }
+}
+
+
+pub struct MssqlStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    // tiberius has no pool, queries on one connection are serialized
+    client: Mutex<Client<Compat<TcpStream>>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> MssqlStorageClient<F> {
+
+    /// IF OBJECT_ID(N'table_name', N'U') IS NULL CREATE TABLE [table_name]
+    /// - ([column_name1] column_type1, [column_name2] column_type2, ...)
+    /// - PRIMARY KEY ([primary_key_name])
+    /// - SQL Server has no CREATE TABLE IF NOT EXISTS, the table is looked up first
+    pub fn create_table_if_not_exists_query<O: StorageObject>() -> anyhow::Result<String> {
+        match O::schema() {
+            StorageSchema::Mssql { schema, primary_key } => {
+                let columns: Vec<String> = schema.iter()
+                    .map(|(name, typ)| format!("[{}] {}", name, typ))
+                    .collect();
+                let columns_str = columns.join(", ");
+                Ok(format!(
+                    "IF OBJECT_ID(N'{}', N'U') IS NULL CREATE TABLE [{}] ({}, PRIMARY KEY ([{}]))",
+                    O::type_name(),
+                    O::type_name(),
+                    columns_str,
+                    primary_key
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Mssql"))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{json::JsonStorageFormat, mssql_storage_client::MssqlStorageClient, StorageObject, StorageSchema};
+
+    use super::MssqlType;
+
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct TestObject {
+        key: i32,
+        value: String,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> crate::StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), MssqlType::Int);
+            schema.insert("value".to_string(), MssqlType::NVARCHAR { n: None });
+            StorageSchema::Mssql {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_table_if_not_exists_query() {
+        let query = MssqlStorageClient::<JsonStorageFormat>::create_table_if_not_exists_query::<TestObject>();
+        assert!(query.is_ok());
+        let query = query.unwrap();
+        assert_eq!(
+            query,
+            "IF OBJECT_ID(N'TestObject', N'U') IS NULL CREATE TABLE [TestObject] ([key] INT, [value] NVARCHAR(MAX), PRIMARY KEY ([key]))"
+        );
+    }
+}
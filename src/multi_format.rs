@@ -0,0 +1,144 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::{EnvelopeHeader, FormatInfo, Versioned}, StorageFormat, StorageObject};
+
+/// Formats a `MultiFormat` can read, a tuple of up to six formats
+pub trait FormatSet {
+    /// Deserializes with the first format that recognizes the data
+    /// - Returns `None` if no format does
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> Option<anyhow::Result<T>>;
+
+    fn ids() -> Vec<&'static str>;
+}
+
+impl FormatSet for () {
+    fn deserialize<T: StorageObject + DeserializeOwned>(_data: &[u8]) -> Option<anyhow::Result<T>> {
+        None
+    }
+
+    fn ids() -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+/// Reads the data with `F` if it is an envelope of `F` or, without an envelope, if `F` sniffs it
+fn recognize<F, T>(data: &[u8]) -> Option<anyhow::Result<T>>
+where
+    F: StorageFormat + FormatInfo,
+    T: StorageObject + DeserializeOwned,
+{
+    if EnvelopeHeader::is_envelope(data) {
+        match EnvelopeHeader::read(data) {
+            Ok((header, _)) if header.format_id == F::ID => Some(Versioned::<F>::deserialize(data)),
+            _ => None,
+        }
+    } else if F::sniff(data) {
+        Some(F::deserialize(data))
+    } else {
+        None
+    }
+}
+
+macro_rules! impl_format_set {
+    ($($format:ident),+) => {
+        impl<$($format: StorageFormat + FormatInfo),+> FormatSet for ($($format,)+) {
+            fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> Option<anyhow::Result<T>> {
+                None$(.or_else(|| recognize::<$format, T>(data)))+
+            }
+
+            fn ids() -> Vec<&'static str> {
+                vec![$($format::ID),+]
+            }
+        }
+    };
+}
+
+impl_format_set!(A);
+impl_format_set!(A, B);
+impl_format_set!(A, B, C);
+impl_format_set!(A, B, C, D);
+impl_format_set!(A, B, C, D, E);
+impl_format_set!(A, B, C, D, E, G);
+
+/// Writes with the preferred format `W` and reads whichever of `W` and the formats `R` stored the data,
+/// e.g. `MultiFormat<PostcardStorageFormat, (JsonStorageFormat,)>` to migrate a JSON store
+/// to postcard object by object.
+/// - Writes are `Versioned` envelopes of `W`, so they are recognized by their header
+/// - Data without an envelope is recognized by `FormatInfo::sniff`, the first format in `W, R...` order wins
+pub struct MultiFormat<W, R = ()> {
+    _formats: PhantomData<(W, R)>,
+}
+
+impl<W, R> StorageFormat for MultiFormat<W, R>
+where
+    W: StorageFormat + FormatInfo,
+    R: FormatSet,
+{
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        Versioned::<W>::serialize(obj)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        match recognize::<W, T>(data).or_else(|| R::deserialize(data)) {
+            Some(result) => result,
+            None => {
+                let mut ids = vec![W::ID];
+                ids.extend(R::ids());
+                Err(anyhow::anyhow!("Stored {} is in none of the formats: {}", T::type_name(), ids.join(", ")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    /// JSON under another id, stands in for a binary format without magic bytes
+    struct OtherFormat;
+
+    impl FormatInfo for OtherFormat {
+        const ID: &'static str = "other";
+        const VERSION: u16 = 1;
+    }
+
+    impl StorageFormat for OtherFormat {
+        fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+            let mut data = b"other:".to_vec();
+            data.extend(JsonStorageFormat::serialize(obj)?);
+            Ok(data)
+        }
+
+        fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+            let data = data.strip_prefix(b"other:").ok_or_else(|| anyhow::anyhow!("Not other"))?;
+            JsonStorageFormat::deserialize(data)
+        }
+    }
+
+    #[test]
+    fn test_multi_format_reads_old_and_new() {
+        type Migrating = MultiFormat<OtherFormat, (JsonStorageFormat,)>;
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+
+        // objects stored before the migration are plain JSON
+        let old = JsonStorageFormat::serialize(&obj).unwrap();
+        assert_eq!(Migrating::deserialize::<TestObject>(&old).unwrap(), obj);
+
+        let new = Migrating::serialize(&obj).unwrap();
+        let (header, _) = EnvelopeHeader::read(&new).unwrap();
+        assert_eq!(header.format_id, "other");
+        assert_eq!(Migrating::deserialize::<TestObject>(&new).unwrap(), obj);
+
+        let error = Migrating::deserialize::<TestObject>(b"unknown").unwrap_err();
+        assert!(error.to_string().contains("other, json"));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fmt::{Display, Formatter}, marker::PhantomData};
+
+use crate::{StorageFormat, StorageObject, StorageSchema};
+use sqlx::{MySql, Pool};
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MysqlType {
+    // 1 byte
+    TinyInt {
+        unsigned: bool,
+    },
+    // 2 bytes
+    SmallInt {
+        unsigned: bool,
+    },
+    // 3 bytes
+    MediumInt {
+        unsigned: bool,
+    },
+    // 4 bytes
+    Int {
+        unsigned: bool,
+    },
+    // 8 bytes
+    BigInt {
+        unsigned: bool,
+    },
+    Decimal {
+        precision: Option<u8>,
+        scale: Option<u8>,
+    },
+    // 4 bytes
+    Float,
+    // 8 bytes
+    Double,
+    // variable length with limit
+    VARCHAR {
+        n: u32,
+    },
+    // fixed length, right-padded
+    CHAR {
+        n: u32,
+    },
+    // up to 64KB
+    TEXT,
+    // up to 16MB
+    MEDIUMTEXT,
+    // up to 4GB
+    LONGTEXT,
+    // variable length binary string with limit
+    VARBINARY {
+        n: u32,
+    },
+    BLOB,
+    LONGBLOB,
+    DATETIME,
+    TIMESTAMP,
+    DATE,
+    TIME,
+    // alias of TINYINT(1)
+    BOOLEAN,
+    JSON,
+}
+
+impl Display for MysqlType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MysqlType::TinyInt { unsigned } => write_integer(f, "TINYINT", *unsigned),
+            MysqlType::SmallInt { unsigned } => write_integer(f, "SMALLINT", *unsigned),
+            MysqlType::MediumInt { unsigned } => write_integer(f, "MEDIUMINT", *unsigned),
+            MysqlType::Int { unsigned } => write_integer(f, "INT", *unsigned),
+            MysqlType::BigInt { unsigned } => write_integer(f, "BIGINT", *unsigned),
+            MysqlType::Decimal { precision, scale } => {
+                match (precision, scale) {
+                    (Some(p), Some(s)) => write!(f, "DECIMAL({}, {})", p, s),
+                    (Some(p), None) => write!(f, "DECIMAL({})", p),
+                    _ => write!(f, "DECIMAL"),
+                }
+            }
+            MysqlType::Float => write!(f, "FLOAT"),
+            MysqlType::Double => write!(f, "DOUBLE"),
+            MysqlType::VARCHAR { n } => write!(f, "VARCHAR({})", n),
+            MysqlType::CHAR { n } => write!(f, "CHAR({})", n),
+            MysqlType::TEXT => write!(f, "TEXT"),
+            MysqlType::MEDIUMTEXT => write!(f, "MEDIUMTEXT"),
+            MysqlType::LONGTEXT => write!(f, "LONGTEXT"),
+            MysqlType::VARBINARY { n } => write!(f, "VARBINARY({})", n),
+            MysqlType::BLOB => write!(f, "BLOB"),
+            MysqlType::LONGBLOB => write!(f, "LONGBLOB"),
+            MysqlType::DATETIME => write!(f, "DATETIME"),
+            MysqlType::TIMESTAMP => write!(f, "TIMESTAMP"),
+            MysqlType::DATE => write!(f, "DATE"),
+            MysqlType::TIME => write!(f, "TIME"),
+            MysqlType::BOOLEAN => write!(f, "BOOLEAN"),
+            MysqlType::JSON => write!(f, "JSON"),
+        }
+    }
+}
+
+fn write_integer(f: &mut Formatter<'_>, name: &str, unsigned: bool) -> std::fmt::Result {
+    if unsigned {
+        write!(f, "{} UNSIGNED", name)
+    } else {
+        write!(f, "{}", name)
+    }
+}
+
+
+pub struct MysqlStorageClient<F: StorageFormat> {
+    pool: Pool<MySql>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> MysqlStorageClient<F> {
+
+    /// Closes the connection pool, waiting for checked out connections to be returned
+    pub async fn close(self) {
+        self.pool.close().await;
+    }
+
+    /// CREATE TABLE IF NOT EXISTS table_name
+    /// - (column_name1 column_type1, column_name2 column_type2, ...)
+    /// - PRIMARY KEY (primary_key_name)
+    pub fn create_table_if_not_exists_query<O: StorageObject>() -> anyhow::Result<String> {
+        match O::schema() {
+            StorageSchema::Mysql { schema, primary_key } => {
+                let columns: Vec<String> = schema.iter()
+                    .map(|(name, typ)| format!("{} {}", name, typ))
+                    .collect();
+                let columns_str = columns.join(", ");
+                Ok(format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({}, PRIMARY KEY ({}))",
+                    O::type_name(),
+                    columns_str,
+                    primary_key
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Mysql"))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{json::JsonStorageFormat, mysql_storage_client::MysqlStorageClient, StorageObject, StorageSchema};
+
+    use super::MysqlType;
+
+
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    struct TestObject {
+        key: u32,
+        value: String,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> crate::StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), MysqlType::Int { unsigned: true });
+            schema.insert("value".to_string(), MysqlType::VARCHAR { n: 255 });
+            StorageSchema::Mysql {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_table_if_not_exists_query() {
+        let query = MysqlStorageClient::<JsonStorageFormat>::create_table_if_not_exists_query::<TestObject>();
+        assert!(query.is_ok());
+        let query = query.unwrap();
+        assert_eq!(
+            query,
+            "CREATE TABLE IF NOT EXISTS TestObject (key INT UNSIGNED, value VARCHAR(255), PRIMARY KEY (key))"
+        );
+    }
+}
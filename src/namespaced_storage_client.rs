@@ -0,0 +1,316 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{lease::LeaseGuard, query::{AggregateSpec, Filter, QuerySpec}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Query parameter holding the namespace for `NamespacedStorageClient::init`
+const NAMESPACE_PARAM: &str = "namespace";
+
+/// Gives each tenant its own part of a shared backend by adding the namespace to the storage url path.
+/// - `file:///data?namespace=acme` stores objects under `/data/acme/{type_name}/{key}`
+/// - Works with backends whose url path is a directory or key prefix (file, s3, redis, etcd, tikv, ...),
+///   not with backends where the path names a single file or database
+/// - `delete_all` only removes the objects of the namespace
+pub struct NamespacedStorageClient<C> {
+    namespace: String,
+    inner: C,
+}
+
+/// Namespaces become part of paths and key prefixes, so they are limited to a safe character set
+fn validate_namespace(namespace: &str) -> anyhow::Result<()> {
+    let valid = !namespace.is_empty()
+        && namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid namespace: {:?}, use letters, digits, '-' and '_'", namespace));
+    }
+    Ok(())
+}
+
+/// `storage_url` with the namespace appended to its path
+fn namespaced_url(storage_url: &Url, namespace: &str) -> Url {
+    let mut url = storage_url.clone();
+    let path = format!("{}/{}", storage_url.path().trim_end_matches('/'), namespace);
+    url.set_path(&path);
+    url
+}
+
+impl<C> NamespacedStorageClient<C> {
+
+    /// Initializes the inner client for `namespace` inside `storage_url`
+    pub async fn new<F>(storage_url: Url, namespace: &str) -> anyhow::Result<Self>
+    where
+        F: StorageFormat + Send + Sync,
+        C: StorageClient<F>,
+    {
+        validate_namespace(namespace)?;
+        let inner = C::init(namespaced_url(&storage_url, namespace)).await?;
+        Ok(Self { namespace: namespace.to_string(), inner })
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for NamespacedStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    /// Takes the namespace from the `namespace` query parameter
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let namespace = storage_url.query_pairs()
+            .find(|(name, _)| name == NAMESPACE_PARAM)
+            .map(|(_, value)| value.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a namespace parameter"))?;
+
+        // the inner client gets the remaining query parameters
+        let mut url = storage_url.clone();
+        let pairs: Vec<(String, String)> = storage_url.query_pairs()
+            .filter(|(name, _)| name != NAMESPACE_PARAM)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        url.set_query(None);
+        if !pairs.is_empty() {
+            url.query_pairs_mut().extend_pairs(pairs);
+        }
+
+        Self::new::<F>(url, &namespace).await
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<O>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.inner.put(key, value).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.delete::<O>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.inner.delete_object_directory::<O>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.inner.restore(id).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.import_all(source).await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        self.inner.commit_transaction(operations).await
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.inner.append(key, value).await
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        self.inner.list_versions::<O>(key).await
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get_version::<O>(key, version).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.scan_range::<O>(range).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<O>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        self.inner.find_by::<O, V>(field, value).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.search::<O>(terms).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.execute_query::<O>(spec).await
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        self.inner.execute_aggregate::<O>(spec).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.inner.get_many(keys).await
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        self.inner.put_many(items).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.inner.delete_many::<O>(keys).await
+    }
+
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        self.inner.delete_where::<O>(filters).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.inner.get_versioned(key).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        self.inner.put_if_version(key, value, expected_version).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        self.inner.put_if_absent(key, value).await
+    }
+
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        self.inner.merge::<O>(key, incoming, resolver).await
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        self.inner.try_lock(name, ttl).await
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.inner.copy::<O>(from, to).await
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.inner.rename::<O>(from, to).await
+    }
+
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.scan::<O>()
+    }
+
+    fn stream_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.stream_query::<O>(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{file_stroage_client::FileStorageClient, json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_namespaced_storage_client_isolates_tenants() {
+        let test_directory = std::env::temp_dir().join("namespaced_storage_client_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let url = Url::from_directory_path(&test_directory).unwrap();
+
+        assert!(NamespacedStorageClient::<FileStorageClient<JsonStorageFormat>>::new(url.clone(), "../escape").await.is_err());
+        let acme = NamespacedStorageClient::<FileStorageClient<JsonStorageFormat>>::new(url.clone(), "acme").await.unwrap();
+        let mut globex_url = url.clone();
+        globex_url.set_query(Some("namespace=globex"));
+        let globex = NamespacedStorageClient::<FileStorageClient<JsonStorageFormat>>::init(globex_url).await.unwrap();
+        assert!(acme.directory().ends_with("/acme"));
+
+        for client in [&acme, &globex] {
+            client.create_object_directory::<TestObject>().await.unwrap();
+            let obj = TestObject { key: "test_key".to_string(), value: client.namespace().to_string() };
+            client.put("test_key", obj).await.unwrap();
+        }
+
+        let retrieved: Option<TestObject> = acme.get("test_key").await.unwrap();
+        assert_eq!(retrieved.map(|obj| obj.value), Some("acme".to_string()));
+
+        acme.delete_all().await.unwrap();
+        let retrieved: Option<TestObject> = globex.get("test_key").await.unwrap();
+        assert_eq!(retrieved.map(|obj| obj.value), Some("globex".to_string()));
+
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
@@ -0,0 +1,177 @@
+use std::{marker::PhantomData, path::{Path, PathBuf}};
+
+use anyhow::Context;
+use futures::{stream, Stream};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject};
+
+
+/// One JSON object per line, the line format of `NdjsonCollection`.
+/// - A single object serializes to one line ending with a newline
+#[derive(Debug, Clone)]
+pub struct NdjsonStorageFormat;
+
+impl FormatInfo for NdjsonStorageFormat {
+    const ID: &'static str = "ndjson";
+    const VERSION: u16 = 1;
+
+    // objects and arrays, scalars are not sniffed
+    fn sniff(data: &[u8]) -> bool {
+        matches!(data.trim_ascii_start().first(), Some(b'{') | Some(b'['))
+    }
+}
+
+impl StorageFormat for NdjsonStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        let mut line = serde_json::to_vec(obj)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        serde_json::from_slice(data.trim_ascii_end()).map_err(|e| e.into())
+    }
+}
+
+/// Many objects of one type in a single newline delimited JSON file.
+/// - Objects are only appended, reading streams them back in order without loading the whole file
+/// - Objects have no keys, use a `StorageClient` for lookups by key
+pub struct NdjsonCollection<O> {
+    path: PathBuf,
+    _object: PhantomData<fn() -> O>,
+}
+
+impl<O: StorageObject> NdjsonCollection<O> {
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), _object: PhantomData }
+    }
+
+    /// Collection stored as `{directory}/{type_name}.ndjson`
+    pub fn in_directory(directory: impl AsRef<Path>) -> Self {
+        Self::new(directory.as_ref().join(format!("{}.ndjson", O::type_name())))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn append(&self, obj: &O) -> anyhow::Result<()>
+    where
+        O: Serialize,
+    {
+        self.append_many(std::iter::once(obj)).await?;
+        Ok(())
+    }
+
+    /// Appends the objects with a single write
+    /// - Returns the number of appended objects
+    pub async fn append_many<'a, I>(&self, objects: I) -> anyhow::Result<usize>
+    where
+        O: Serialize + 'a,
+        I: IntoIterator<Item = &'a O>,
+    {
+        let mut data = Vec::new();
+        let mut count = 0;
+        for obj in objects {
+            data.extend(NdjsonStorageFormat::serialize(obj)?);
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open collection at path: {}", self.path.display()))?;
+        file.write_all(&data).await?;
+        file.flush().await?;
+        Ok(count)
+    }
+
+    /// Streams the objects in the order they were appended
+    /// - A missing file is an empty collection, blank lines are skipped
+    pub async fn stream(&self) -> anyhow::Result<impl Stream<Item = anyhow::Result<O>>>
+    where
+        O: DeserializeOwned,
+    {
+        let lines = match tokio::fs::File::open(&self.path).await {
+            Ok(file) => Some(BufReader::new(file).lines()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| {
+                format!("Failed to open collection at path: {}", self.path.display())
+            }),
+        };
+
+        Ok(stream::unfold((lines, 0usize), |(lines, mut number)| async move {
+            let mut lines = lines?;
+            loop {
+                number += 1;
+                match lines.next_line().await {
+                    Ok(Some(line)) if line.trim().is_empty() => continue,
+                    Ok(Some(line)) => {
+                        let obj = NdjsonStorageFormat::deserialize(line.as_bytes()).with_context(|| {
+                            format!("Failed to deserialize {} on line: {}", O::type_name(), number)
+                        });
+                        return Some((obj, (Some(lines), number)));
+                    }
+                    Ok(None) => return None,
+                    // stop after a read error, the position in the file is unknown
+                    Err(e) => return Some((Err(e.into()), (None, number))),
+                }
+            }
+        }))
+    }
+
+    /// Removes the file
+    /// - Returns true if the collection existed
+    pub async fn clear(&self) -> anyhow::Result<bool> {
+        match tokio::fs::remove_file(&self.path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).with_context(|| {
+                format!("Failed to remove collection at path: {}", self.path.display())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use crate::test_object::TestObject;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ndjson_collection_append_and_stream() {
+        let dir = std::env::temp_dir().join(format!("ndjson-test-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let collection = NdjsonCollection::<TestObject>::in_directory(&dir);
+        collection.clear().await.unwrap();
+
+        let empty: Vec<TestObject> = collection.stream().await.unwrap().try_collect().await.unwrap();
+        assert!(empty.is_empty());
+
+        let objects: Vec<TestObject> = (0..3)
+            .map(|i| TestObject { key: format!("key_{}", i), value: format!("value_{}", i) })
+            .collect();
+        collection.append(&objects[0]).await.unwrap();
+        assert_eq!(collection.append_many(&objects[1..]).await.unwrap(), 2);
+
+        let content = tokio::fs::read_to_string(collection.path()).await.unwrap();
+        assert_eq!(content.lines().count(), 3);
+
+        let retrieved: Vec<TestObject> = collection.stream().await.unwrap().try_collect().await.unwrap();
+        assert_eq!(retrieved, objects);
+
+        assert!(collection.clear().await.unwrap());
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
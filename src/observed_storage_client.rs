@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{lease::LeaseGuard, query::{AggregateSpec, Filter, QuerySpec}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// An operation that took longer than the threshold
+#[derive(Debug, Clone)]
+pub struct SlowOperation {
+    pub operation: &'static str,
+    pub type_name: Option<&'static str>,
+    pub key: Option<String>,
+    pub elapsed: Duration,
+    pub failed: bool,
+}
+
+pub type SlowOperationHandler = Arc<dyn Fn(&SlowOperation) + Send + Sync>;
+
+/// Logs slow operations as warnings with the `log` crate
+pub fn log_slow_operation(slow: &SlowOperation) {
+    log::warn!(
+        "Slow storage operation: {} type={} key={} elapsed={:?}{}",
+        slow.operation,
+        slow.type_name.unwrap_or("-"),
+        slow.key.as_deref().unwrap_or("-"),
+        slow.elapsed,
+        if slow.failed { " (failed)" } else { "" },
+    );
+}
+
+/// Reports every operation of the inner client that takes longer than a threshold.
+/// - Slow operations are logged through `log_slow_operation` unless another handler is set
+pub struct ObservedStorageClient<C> {
+    inner: C,
+    threshold: Duration,
+    handler: SlowOperationHandler,
+}
+
+impl<C> ObservedStorageClient<C> {
+
+    pub fn new(inner: C, threshold: Duration) -> Self {
+        Self { inner, threshold, handler: Arc::new(log_slow_operation) }
+    }
+
+    pub fn with_handler(mut self, handler: impl Fn(&SlowOperation) + Send + Sync + 'static) -> Self {
+        self.handler = Arc::new(handler);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn observe<R>(
+        &self,
+        operation: &'static str,
+        type_name: Option<&'static str>,
+        key: Option<&str>,
+        f: impl Future<Output = anyhow::Result<R>>,
+    ) -> anyhow::Result<R> {
+        let start = Instant::now();
+        let result = f.await;
+        let elapsed = start.elapsed();
+        if elapsed >= self.threshold {
+            (self.handler)(&SlowOperation {
+                operation,
+                type_name,
+                key: key.map(|key| key.to_string()),
+                elapsed,
+                failed: result.is_err(),
+            });
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for ObservedStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    /// Initializes the inner client with the url and reports operations slower than 500ms
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?, Duration::from_millis(500)))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.observe("create_object_directory", Some(O::type_name()), None, self.inner.create_object_directory::<O>()).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.observe("get", Some(O::type_name()), Some(key), self.inner.get(key)).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.observe("put", Some(O::type_name()), Some(key), self.inner.put(key, value)).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.observe("delete", Some(O::type_name()), Some(key), self.inner.delete::<O>(key)).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.observe("delete_object_directory", Some(O::type_name()), None, self.inner.delete_object_directory::<O>()).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.observe("delete_all", None, None, self.inner.delete_all()).await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.observe("health_check", None, None, self.inner.health_check()).await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.observe("snapshot", None, None, self.inner.snapshot()).await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.observe("restore", None, None, self.inner.restore(id)).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.observe("export_all", None, None, self.inner.export_all(sink)).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.observe("import_all", None, None, self.inner.import_all(source)).await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        self.observe("transaction", None, None, self.inner.commit_transaction(operations)).await
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.observe("append", Some(O::type_name()), Some(key), self.inner.append(key, value)).await
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        self.observe("list_versions", Some(O::type_name()), Some(key), self.inner.list_versions::<O>(key)).await
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        self.observe("get_version", Some(O::type_name()), Some(key), self.inner.get_version::<O>(key, version)).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.observe("list_keys", Some(O::type_name()), None, self.inner.list_keys::<O>()).await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.observe("list_page", Some(O::type_name()), None, self.inner.list_page::<O>(page)).await
+    }
+
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        self.observe("scan_range", Some(O::type_name()), None, self.inner.scan_range::<O>(range)).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.observe("count", Some(O::type_name()), None, self.inner.count::<O>()).await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.observe("exists", Some(O::type_name()), Some(key), self.inner.exists::<O>(key)).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.observe("metadata", Some(O::type_name()), Some(key), self.inner.metadata::<O>(key)).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.observe("usage", Some(O::type_name()), None, self.inner.usage::<O>()).await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.observe("usage_all", None, None, self.inner.usage_all()).await
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        self.observe("find_by", Some(O::type_name()), None, self.inner.find_by::<O, V>(field, value)).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        self.observe("search", Some(O::type_name()), None, self.inner.search::<O>(terms)).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        self.observe("query", Some(O::type_name()), None, self.inner.execute_query::<O>(spec)).await
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        self.observe("aggregate", Some(O::type_name()), None, self.inner.execute_aggregate::<O>(spec)).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.observe("get_many", Some(O::type_name()), None, self.inner.get_many(keys)).await
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        self.observe("put_many", Some(O::type_name()), None, self.inner.put_many(items)).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.observe("delete_many", Some(O::type_name()), None, self.inner.delete_many::<O>(keys)).await
+    }
+
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        self.observe("delete_where", Some(O::type_name()), None, self.inner.delete_where::<O>(filters)).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.observe("get_versioned", Some(O::type_name()), Some(key), self.inner.get_versioned(key)).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        self.observe("put_if_version", Some(O::type_name()), Some(key), self.inner.put_if_version(key, value, expected_version)).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        self.observe("put_if_absent", Some(O::type_name()), Some(key), self.inner.put_if_absent(key, value)).await
+    }
+
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        self.observe("merge", Some(O::type_name()), Some(key), self.inner.merge::<O>(key, incoming, resolver)).await
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        self.observe("try_lock", None, Some(name), self.inner.try_lock(name, ttl)).await
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.observe("copy", Some(O::type_name()), Some(from), self.inner.copy::<O>(from, to)).await
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        self.observe("rename", Some(O::type_name()), Some(from), self.inner.rename::<O>(from, to)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_observed_storage_client_reports_slow_operations() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://observed").unwrap()).await.unwrap();
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        // every operation takes at least zero time, so everything is reported
+        let client = ObservedStorageClient::new(inner, Duration::ZERO)
+            .with_handler(move |slow| sink.lock().unwrap().push(slow.clone()));
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj).await.unwrap();
+        let _: Option<TestObject> = client.get("test_key").await.unwrap();
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[0].operation, "put");
+        assert_eq!(reported[1].type_name, Some("TestObject"));
+        assert_eq!(reported[1].key.as_deref(), Some("test_key"));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, marker::PhantomData, str::FromStr, time::SystemTime};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use opendal::{ErrorKind, Operator, Scheme};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{ObjectMetadata, StorageClient, StorageFormat, StorageObject};
+
+/// Stores objects in any service supported by OpenDAL, the service is selected by the URL scheme.
+/// - `s3://bucket/prefix`, `gcs://bucket/prefix`, `azblob://container/prefix`, `fs:///path`, `memory:///`
+/// - The URL path is the root of the operator, the host is the bucket or container of object stores
+/// - Query parameters are passed to the service as configuration,
+///   e.g. `s3://bucket/prefix?region=eu-west-1&endpoint=http://localhost:9000`
+/// - Only the services enabled as `opendal` features are available, more can be enabled in the depending crate
+pub struct OpendalStorageClient<F: StorageFormat> {
+    operator: Operator,
+    root: String,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> OpendalStorageClient<F> {
+
+    /// Wraps an operator built by hand, for services whose configuration does not fit in a URL
+    pub fn from_operator(operator: Operator) -> Self {
+        let root = operator.info().root().to_string();
+        Self { operator, root, _formatter: PhantomData::<F> }
+    }
+
+    pub fn operator(&self) -> &Operator {
+        &self.operator
+    }
+
+    /// Path relative to the operator root under which all objects of type `O` are stored, ending with `/`
+    fn object_prefix<O: StorageObject>(&self) -> String {
+        format!("{}/", O::type_name())
+    }
+}
+
+/// Service configuration from a storage URL
+fn config(storage_url: &Url) -> HashMap<String, String> {
+    let mut config: HashMap<String, String> = storage_url.query_pairs()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    let root = storage_url.path();
+    config.entry("root".to_string()).or_insert_with(|| if root.is_empty() { "/".to_string() } else { root.to_string() });
+
+    if let Some(host) = storage_url.host_str().filter(|host| !host.is_empty()) {
+        let name = match storage_url.scheme() {
+            "s3" | "gcs" | "oss" | "cos" | "obs" | "b2" => Some("bucket"),
+            "azblob" => Some("container"),
+            _ => None,
+        };
+        if let Some(name) = name {
+            config.entry(name.to_string()).or_insert_with(|| host.to_string());
+        }
+    }
+    config
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for OpendalStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let scheme = Scheme::from_str(storage_url.scheme()).with_context(|| {
+            format!("Unknown OpenDAL service: {}", storage_url.scheme())
+        })?;
+        let operator = Operator::via_iter(scheme, config(&storage_url)).with_context(|| {
+            format!("Failed to build OpenDAL operator for service: {}", storage_url.scheme())
+        })?;
+        operator.check().await.with_context(|| {
+            format!("Failed to access storage: {}", storage_url)
+        })?;
+
+        Ok(Self::from_operator(operator))
+    }
+
+    fn directory(&self) -> &str {
+        &self.root
+    }
+
+    // paths are relative to the operator root
+    fn object_path<O: StorageObject>(&self, key: &str) -> String {
+        format!("{}{}", self.object_prefix::<O>(), key)
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let prefix = self.object_prefix::<O>();
+        self.operator.create_dir(&prefix).await.with_context(|| {
+            format!("Failed to create directory: {}", prefix)
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let path = self.object_path::<O>(key);
+        let data = match self.operator.read(&path).await {
+            Ok(data) => data.to_vec(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read object at path: {}", path)),
+        };
+
+        let obj = F::deserialize(&data).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let path = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        self.operator.write(&path, data).await.with_context(|| {
+            format!("Failed to write object at path: {}", path)
+        })?;
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let path = self.object_path::<O>(key);
+        // deleting a missing path succeeds, so check first to report whether it existed
+        if !self.operator.exists(&path).await? {
+            return Ok(false);
+        }
+        self.operator.delete(&path).await.with_context(|| {
+            format!("Failed to delete object at path: {}", path)
+        })?;
+        Ok(true)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let prefix = self.object_prefix::<O>();
+        let existed = match self.operator.list(&prefix).await {
+            Ok(entries) => !entries.is_empty(),
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            Err(e) => return Err(e).with_context(|| format!("Failed to list directory: {}", prefix)),
+        };
+        if !existed {
+            return Ok(false);
+        }
+        self.operator.remove_all(&prefix).await.with_context(|| {
+            format!("Failed to delete directory: {}", prefix)
+        })?;
+        Ok(true)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.operator.remove_all("/").await.with_context(|| {
+            format!("Failed to delete all objects under root: {}", self.root)
+        })?;
+        Ok(())
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let prefix = self.object_prefix::<O>();
+        let entries = match self.operator.list(&prefix).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to list directory: {}", prefix)),
+        };
+        Ok(entries.into_iter()
+            .filter(|entry| entry.metadata().is_file())
+            .map(|entry| entry.name().to_string())
+            .collect())
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let path = self.object_path::<O>(key);
+        self.operator.exists(&path).await.with_context(|| {
+            format!("Failed to check object at path: {}", path)
+        })
+    }
+
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let path = self.object_path::<O>(key);
+        let metadata = match self.operator.stat(&path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read metadata at path: {}", path)),
+        };
+        Ok(Some(ObjectMetadata {
+            size: metadata.content_length(),
+            created: None,
+            modified: metadata.last_modified().map(SystemTime::from),
+            etag: metadata.etag().map(str::to_string),
+        }))
+    }
+
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let path = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        match self.operator.write_with(&path, data).if_not_exists(true).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::ConditionNotMatch => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to write object at path: {}", path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_config_from_url() {
+        let config = config(&Url::parse("s3://bucket/prefix?region=eu-west-1").unwrap());
+        assert_eq!(config.get("bucket").map(String::as_str), Some("bucket"));
+        assert_eq!(config.get("root").map(String::as_str), Some("/prefix"));
+        assert_eq!(config.get("region").map(String::as_str), Some("eu-west-1"));
+    }
+
+    #[tokio::test]
+    async fn test_opendal_storage_client_memory() {
+        let client = OpendalStorageClient::<JsonStorageFormat>::init(Url::parse("memory:///objects").unwrap()).await.unwrap();
+        client.create_object_directory::<TestObject>().await.unwrap();
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.unwrap();
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+        let missing: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(missing.is_none());
+
+        client.delete_all().await.unwrap();
+    }
+}
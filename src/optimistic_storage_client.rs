@@ -0,0 +1,269 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{content_version, lease::LeaseGuard, HealthStatus, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, VersionConflictError};
+
+/// `VersionConflictError::expected` of a put that could only create the object
+const ABSENT_VERSION: &str = "none";
+
+/// Optimistic concurrency without version fields in the objects: remembers the version of every object read through it
+/// and turns each `put` into a conditional write against that version.
+/// - A `put` fails with a `VersionConflictError` if the object changed since it was read, or if an object
+///   that was missing or never read exists by now
+/// - Versions are those of the inner client's `get_versioned`, so it needs a client with `put_if_version`
+///   (memory, file, sqlite, s3, ...)
+/// - Remembered versions belong to one unit of work, give each request or job its own client
+///   over a shared inner client with `session`
+/// - `delete` is not checked and transactions are not supported, both would go around the versions
+pub struct OptimisticStorageClient<C> {
+    inner: std::sync::Arc<C>,
+    /// version of each (type, key) as last read or written, `None` if it was read as missing
+    versions: DashMap<(&'static str, String), Option<String>>,
+}
+
+impl<C> OptimisticStorageClient<C> {
+
+    pub fn new(inner: C) -> Self {
+        Self { inner: std::sync::Arc::new(inner), versions: DashMap::new() }
+    }
+
+    /// A client over the same inner client that has not read anything yet
+    pub fn session(&self) -> Self {
+        Self { inner: self.inner.clone(), versions: DashMap::new() }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Version of the object as it was last read or written through this client, `None` if it was not
+    pub fn version<O: StorageObject>(&self, key: &str) -> Option<String> {
+        self.versions.get(&(O::type_name(), key.to_string())).and_then(|version| version.clone())
+    }
+
+    fn forget_type(&self, type_name: &str) {
+        self.versions.retain(|(stored_type, _), _| *stored_type != type_name);
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for OptimisticStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<O>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let versioned = self.inner.get_versioned::<O>(key).await?;
+        let (value, version) = match versioned {
+            Some((value, version)) => (Some(value), Some(version)),
+            None => (None, None),
+        };
+        self.versions.insert((O::type_name(), key.to_string()), version);
+        Ok(value)
+    }
+
+    // the version is taken out before writing, so a second put of the same read cannot slip through
+    // while the first one is in flight
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let id = (O::type_name(), key.to_string());
+        let read = self.versions.remove(&id).and_then(|(_, version)| version);
+        match read {
+            Some(version) => match self.inner.put_if_version(key, value, &version).await {
+                Ok(written) => {
+                    self.versions.insert(id, Some(written));
+                }
+                Err(e) => {
+                    // still the version this client read, a conflict stays a conflict on retry
+                    self.versions.insert(id, Some(version));
+                    return Err(e);
+                }
+            },
+            None => {
+                // clients without an etag version objects by their content, as in `get_versioned`
+                let data = F::serialize(&value)?;
+                if !self.inner.put_if_absent(key, value).await? {
+                    let actual = self.inner.metadata::<O>(key).await.ok().flatten().and_then(|metadata| metadata.etag);
+                    return Err(VersionConflictError {
+                        type_name: O::type_name(),
+                        key: key.to_string(),
+                        expected: ABSENT_VERSION.to_string(),
+                        actual: Some(actual.unwrap_or_else(|| "unknown".to_string())),
+                    }.into());
+                }
+                let etag = self.inner.metadata::<O>(key).await.ok().flatten().and_then(|metadata| metadata.etag);
+                self.versions.insert(id, Some(etag.unwrap_or_else(|| content_version(&data))));
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let deleted = self.inner.delete::<O>(key).await?;
+        self.versions.insert((O::type_name(), key.to_string()), None);
+        Ok(deleted)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.forget_type(O::type_name());
+        self.inner.delete_object_directory::<O>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.versions.clear();
+        self.inner.delete_all().await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.inner.get_versioned::<O>(key).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        self.inner.put_if_version(key, value, expected_version).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        self.inner.put_if_absent(key, value).await
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        self.inner.try_lock(name, ttl).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<O>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+
+    /// Closes the inner client once no other session holds it
+    async fn close(self) -> anyhow::Result<()> {
+        match std::sync::Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.close().await,
+            Err(_) => Ok(()),
+        }
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        self.versions.clear();
+        self.inner.restore(id).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        self.versions.clear();
+        self.inner.import_all(source).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    fn object(value: &str) -> TestObject {
+        TestObject { key: "a".to_string(), value: value.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_optimistic_storage_client_conflicts() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let first = OptimisticStorageClient::new(inner);
+        let second = first.session();
+
+        // creating works once, a second blind put is a conflict
+        first.put("a", object("1")).await.unwrap();
+        let error = second.put("a", object("2")).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<VersionConflictError>().unwrap().expected, ABSENT_VERSION);
+
+        // both read the same version, only the first write wins
+        let read: Option<TestObject> = first.get("a").await.unwrap();
+        assert_eq!(read, Some(object("1")));
+        let _: Option<TestObject> = second.get("a").await.unwrap();
+        first.put("a", object("3")).await.unwrap();
+        let error = second.put("a", object("4")).await.unwrap_err();
+        assert!(error.downcast_ref::<VersionConflictError>().is_some());
+
+        // the writer keeps the new version and can write again without reading
+        first.put("a", object("5")).await.unwrap();
+        let _: Option<TestObject> = second.get("a").await.unwrap();
+        second.put("a", object("6")).await.unwrap();
+        let stored: Option<TestObject> = first.get("a").await.unwrap();
+        assert_eq!(stored, Some(object("6")));
+    }
+
+    #[tokio::test]
+    async fn test_optimistic_storage_client_create_then_put() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let first = OptimisticStorageClient::new(inner);
+        let second = first.session();
+
+        // the creator knows the version it wrote and can update without reading
+        first.put("a", object("1")).await.unwrap();
+        assert!(first.version::<TestObject>("a").is_some());
+        first.put("a", object("2")).await.unwrap();
+
+        // a failed write keeps the version it was checked against
+        let _: Option<TestObject> = second.get("a").await.unwrap();
+        let read = second.version::<TestObject>("a");
+        first.put("a", object("3")).await.unwrap();
+        assert!(second.put("a", object("4")).await.is_err());
+        assert_eq!(second.version::<TestObject>("a"), read);
+        assert!(second.put("a", object("5")).await.unwrap_err().downcast_ref::<VersionConflictError>().is_some());
+
+        let stored: Option<TestObject> = first.get("a").await.unwrap();
+        assert_eq!(stored, Some(object("3")));
+    }
+}
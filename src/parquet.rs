@@ -0,0 +1,124 @@
+use std::io::Write;
+
+use anyhow::Context;
+use bytes::Bytes;
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression,
+    file::properties::WriterProperties,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, arrow::{from_record_batch, to_record_batch}, StorageFormat, StorageObject};
+
+
+/// Parquet files with column types from `StorageObject::schema()`, loadable by DuckDB, Pandas or Spark.
+/// - `write` and `serialize_many` put many objects of a type into a single file
+/// - As a `StorageFormat` every object is a file with one row
+#[derive(Debug, Clone)]
+pub struct ParquetStorageFormat;
+
+impl ParquetStorageFormat {
+
+    /// Writes the objects as one row group to a Parquet file
+    pub fn write<O, W>(writer: W, objects: &[O]) -> anyhow::Result<W>
+    where
+        O: StorageObject + Serialize,
+        W: Write + Send,
+    {
+        let batch = to_record_batch(objects)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(writer, batch.schema(), Some(properties))?;
+        writer.write(&batch).with_context(|| {
+            format!("Failed to write {} rows of {}", objects.len(), O::type_name())
+        })?;
+        writer.into_inner().map_err(|e| e.into())
+    }
+
+    pub fn serialize_many<O: StorageObject + Serialize>(objects: &[O]) -> anyhow::Result<Vec<u8>> {
+        Self::write(Vec::new(), objects)
+    }
+
+    /// Reads every row of a Parquet file
+    pub fn deserialize_many<O: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<Vec<O>> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::copy_from_slice(data))?.build()?;
+        let mut objects = Vec::new();
+        for batch in reader {
+            objects.extend(from_record_batch::<O>(&batch?)?);
+        }
+        Ok(objects)
+    }
+}
+
+impl FormatInfo for ParquetStorageFormat {
+    const ID: &'static str = "parquet";
+    const VERSION: u16 = 1;
+
+    fn sniff(data: &[u8]) -> bool {
+        data.starts_with(b"PAR1")
+    }
+}
+
+impl StorageFormat for ParquetStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        Self::serialize_many(std::slice::from_ref(obj))
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        Self::deserialize_many(data)?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Parquet file of {} has no rows", T::type_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::Deserialize;
+
+    use crate::{RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        value: i64,
+        enabled: bool,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("value".to_string(), RustStandardType::Int64);
+            schema.insert("enabled".to_string(), RustStandardType::Bool);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parquet_storage_format_many_rows() {
+        let objects: Vec<TestObject> = (0..100)
+            .map(|i| TestObject { key: format!("key_{}", i), value: i, enabled: i % 2 == 0 })
+            .collect();
+        let data = ParquetStorageFormat::serialize_many(&objects).unwrap();
+        assert!(data.starts_with(b"PAR1"));
+
+        let retrieved: Vec<TestObject> = ParquetStorageFormat::deserialize_many(&data).unwrap();
+        assert_eq!(retrieved, objects);
+
+        let single = ParquetStorageFormat::serialize(&objects[0]).unwrap();
+        let retrieved: TestObject = ParquetStorageFormat::deserialize(&single).unwrap();
+        assert_eq!(retrieved, objects[0]);
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject};
+
+
+/// Compact binary encoding with varint integers, for syncing objects to constrained devices.
+/// - The device side can decode with `postcard` in `no_std` without an allocator
+/// - Not self describing, field order is the schema and types using `deserialize_any`
+///   (untagged enums, flatten) are not supported
+#[derive(Debug, Clone)]
+pub struct PostcardStorageFormat;
+
+impl FormatInfo for PostcardStorageFormat {
+    const ID: &'static str = "postcard";
+    const VERSION: u16 = 1;
+}
+
+impl StorageFormat for PostcardStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        postcard::to_allocvec(obj).map_err(|e| e.into())
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        postcard::from_bytes(data).map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_object::TestObject;
+
+    use super::*;
+
+    #[test]
+    fn test_postcard_storage_format_round_trip() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let data = PostcardStorageFormat::serialize(&obj).unwrap();
+        // each string is its varint length followed by the bytes, no field names
+        let mut expected = vec![8];
+        expected.extend_from_slice(b"test_key");
+        expected.push(10);
+        expected.extend_from_slice(b"test_value");
+        assert_eq!(data, expected);
+        assert_eq!(PostcardStorageFormat::deserialize::<TestObject>(&data).unwrap(), obj);
+
+        assert!(PostcardStorageFormat::deserialize::<TestObject>(&data[..5]).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use prost::Message;
+
+use crate::{raw::{Payload, RawFormat}, StorageClient, StorageObject};
+
+
+/// Protobuf wire format for messages generated by prost.
+/// - prost messages do not implement serde, so this is not a `StorageFormat`,
+///   messages are stored through a `StorageClient<RawFormat>` with `ProtobufStorage`
+#[derive(Debug, Clone)]
+pub struct ProtobufStorageFormat;
+
+impl ProtobufStorageFormat {
+    pub fn serialize<M: Message>(message: &M) -> Vec<u8> {
+        message.encode_to_vec()
+    }
+
+    pub fn deserialize<M: Message + Default>(data: &[u8]) -> anyhow::Result<M> {
+        M::decode(data).map_err(|e| e.into())
+    }
+}
+
+/// Reads and writes prost messages in protobuf wire format, implemented for every `StorageClient<RawFormat>`
+/// - The message type also has to implement `StorageObject` for its type name and schema
+#[async_trait]
+pub trait ProtobufStorage: StorageClient<RawFormat> + Sync {
+
+    async fn get_message<M>(&self, key: &str) -> anyhow::Result<Option<M>>
+    where
+        M: StorageObject + Message + Default,
+    {
+        match self.get::<Payload<M>>(key).await? {
+            Some(payload) => {
+                let message = ProtobufStorageFormat::deserialize(payload.data()).with_context(|| {
+                    format!("Failed to decode {} for key: {}", M::type_name(), key)
+                })?;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put_message<M>(&self, key: &str, message: &M) -> anyhow::Result<()>
+    where
+        M: StorageObject + Message,
+    {
+        let data = ProtobufStorageFormat::serialize(message);
+        self.put(key, Payload::<M>::new(data)).await
+    }
+}
+
+impl<C: StorageClient<RawFormat> + Sync> ProtobufStorage for C {}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use url::Url;
+
+    use crate::{memory_storage_client::MemoryStorageClient, RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, Message)]
+    struct TestMessage {
+        #[prost(string, tag = "1")]
+        key: String,
+        #[prost(string, tag = "2")]
+        value: String,
+    }
+
+    impl StorageObject for TestMessage {
+        fn type_name() -> &'static str {
+            "TestMessage"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("value".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_storage_roundtrip() {
+        let client = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://protobuf").unwrap()).await.unwrap();
+        let message = TestMessage {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put_message("test_key", &message).await.unwrap();
+
+        // stored bytes are plain protobuf
+        let payload: Option<Payload<TestMessage>> = client.get("test_key").await.unwrap();
+        assert_eq!(payload.unwrap().data(), message.encode_to_vec().as_slice());
+
+        let retrieved: Option<TestMessage> = client.get_message("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(message));
+        let missing: Option<TestMessage> = client.get_message("missing").await.unwrap();
+        assert!(missing.is_none());
+    }
+}
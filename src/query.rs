@@ -0,0 +1,494 @@
+use std::{cmp::Ordering, collections::HashMap, marker::PhantomData};
+
+use anyhow::Context;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// A condition on one field of an object, compared as JSON
+/// - Numbers compare as numbers and strings lexicographically, values of different kinds never match
+/// - `null` and missing fields match no filter, as in SQL
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Eq { field: String, value: Value },
+    Gt { field: String, value: Value },
+    Gte { field: String, value: Value },
+    Lt { field: String, value: Value },
+    Lte { field: String, value: Value },
+    In { field: String, values: Vec<Value> },
+}
+
+impl Filter {
+
+    pub fn field(&self) -> &str {
+        match self {
+            Filter::Eq { field, .. }
+            | Filter::Gt { field, .. }
+            | Filter::Gte { field, .. }
+            | Filter::Lt { field, .. }
+            | Filter::Lte { field, .. }
+            | Filter::In { field, .. } => field,
+        }
+    }
+
+    /// Whether the object, as the JSON of its fields, passes the filter
+    pub fn matches(&self, fields: &Value) -> bool {
+        let actual = fields.get(self.field()).unwrap_or(&Value::Null);
+        match self {
+            Filter::Eq { value, .. } => compare(actual, value) == Some(Ordering::Equal),
+            Filter::Gt { value, .. } => compare(actual, value) == Some(Ordering::Greater),
+            Filter::Gte { value, .. } => matches!(compare(actual, value), Some(Ordering::Greater | Ordering::Equal)),
+            Filter::Lt { value, .. } => compare(actual, value) == Some(Ordering::Less),
+            Filter::Lte { value, .. } => matches!(compare(actual, value), Some(Ordering::Less | Ordering::Equal)),
+            Filter::In { values, .. } => values.iter().any(|value| compare(actual, value) == Some(Ordering::Equal)),
+        }
+    }
+}
+
+/// Whether `object` passes every filter
+pub(crate) fn matches_all<O: Serialize>(object: &O, filters: &[Filter]) -> anyhow::Result<bool> {
+    if filters.is_empty() {
+        return Ok(true);
+    }
+    let fields = serde_json::to_value(object).with_context(|| {
+        format!("Failed to extract fields of {}", std::any::type_name::<O>())
+    })?;
+    Ok(filters.iter().all(|filter| filter.matches(&fields)))
+}
+
+/// Order of two JSON values of the same kind, `None` for values that do not compare
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        },
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// What a `Query` asks for, handed to `StorageClient::execute_query`
+/// - Results are sorted by `order_by` and then by key, so pages of the same query do not overlap
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuerySpec {
+    pub filters: Vec<Filter>,
+    pub order_by: Vec<(String, SortOrder)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl QuerySpec {
+
+    /// Sorts the matching objects and cuts out the page of `offset` and `limit`
+    pub(crate) fn page<O: Serialize>(&self, mut objects: Vec<(String, O)>) -> anyhow::Result<Vec<(String, O)>> {
+        if !self.order_by.is_empty() {
+            let mut sortable = Vec::with_capacity(objects.len());
+            for (key, object) in objects {
+                let fields = serde_json::to_value(&object).with_context(|| {
+                    format!("Failed to extract fields of {}", std::any::type_name::<O>())
+                })?;
+                sortable.push((fields, key, object));
+            }
+            sortable.sort_by(|(a, a_key, _), (b, b_key, _)| self.compare_fields(a, b).then_with(|| a_key.cmp(b_key)));
+            objects = sortable.into_iter().map(|(_, key, object)| (key, object)).collect();
+        } else {
+            objects.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+        Ok(objects.into_iter().skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect())
+    }
+
+    fn compare_fields(&self, a: &Value, b: &Value) -> Ordering {
+        for (field, order) in &self.order_by {
+            let null = Value::Null;
+            let ordering = sort_order(a.get(field).unwrap_or(&null), b.get(field).unwrap_or(&null));
+            let ordering = match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// A value computed over the objects matching a query
+/// - `null` and missing fields are skipped like in SQL, `Sum` adds up numbers only and is 0 without any
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    Count,
+    Min(String),
+    Max(String),
+    Sum(String),
+}
+
+impl Aggregate {
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Aggregate::Count => None,
+            Aggregate::Min(field) | Aggregate::Max(field) | Aggregate::Sum(field) => Some(field),
+        }
+    }
+}
+
+/// What an aggregate query asks for, handed to `StorageClient::execute_aggregate`
+/// - Without `group_by` there is a single group keyed by `null`, present even when nothing matches
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateSpec {
+    pub filters: Vec<Filter>,
+    pub aggregate: Aggregate,
+    pub group_by: Option<String>,
+}
+
+/// Running value of an aggregate while objects are folded into it
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    extreme: Option<Value>,
+}
+
+impl Accumulator {
+
+    fn add(&mut self, aggregate: &Aggregate, fields: &Value) {
+        let value = aggregate.field().and_then(|field| fields.get(field)).filter(|value| !value.is_null());
+        match (aggregate, value) {
+            (Aggregate::Count, _) => self.count += 1,
+            (Aggregate::Sum(_), Some(value)) => self.sum += value.as_f64().unwrap_or(0.0),
+            (Aggregate::Min(_), Some(value)) if self.extreme.as_ref().is_none_or(|min| sort_order(value, min) == Ordering::Less) => {
+                self.extreme = Some(value.clone());
+            }
+            (Aggregate::Max(_), Some(value)) if self.extreme.as_ref().is_none_or(|max| sort_order(value, max) == Ordering::Greater) => {
+                self.extreme = Some(value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self, aggregate: &Aggregate) -> Value {
+        match aggregate {
+            Aggregate::Count => Value::from(self.count),
+            Aggregate::Sum(_) => Value::from(self.sum),
+            Aggregate::Min(_) | Aggregate::Max(_) => self.extreme.unwrap_or(Value::Null),
+        }
+    }
+}
+
+/// Total order of JSON values for sorting, like SQLite: `null`, then booleans, numbers and strings
+fn sort_order(a: &Value, b: &Value) -> Ordering {
+    let rank = |value: &Value| match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) | Value::Object(_) => 4,
+    };
+    compare(a, b).unwrap_or_else(|| rank(a).cmp(&rank(b)))
+}
+
+/// Objects of one type matching every filter, built by `StorageClient::query`
+/// - `client.query::<Order>().eq("customer", "X").order_by("total", SortOrder::Desc).limit(20).fetch().await?`
+/// - SQL clients compile the query on schema columns to SQL, other clients filter and sort the objects as they read them
+pub struct Query<'a, F, C: ?Sized, O> {
+    client: &'a C,
+    spec: QuerySpec,
+    _marker: PhantomData<(F, O)>,
+}
+
+impl<'a, F, C, O> Query<'a, F, C, O>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+
+    pub(crate) fn new(client: &'a C) -> Self {
+        Self { client, spec: QuerySpec::default(), _marker: PhantomData }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.spec.filters.push(filter);
+        self
+    }
+
+    pub fn eq(self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter(Filter::Eq { field: field.to_string(), value: value.into() })
+    }
+
+    pub fn gt(self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter(Filter::Gt { field: field.to_string(), value: value.into() })
+    }
+
+    pub fn gte(self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter(Filter::Gte { field: field.to_string(), value: value.into() })
+    }
+
+    pub fn lt(self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter(Filter::Lt { field: field.to_string(), value: value.into() })
+    }
+
+    pub fn lte(self, field: &str, value: impl Into<Value>) -> Self {
+        self.filter(Filter::Lte { field: field.to_string(), value: value.into() })
+    }
+
+    /// The field is one of `values`, no values match nothing
+    pub fn is_in<V: Into<Value>>(self, field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.filter(Filter::In { field: field.to_string(), values: values.into_iter().map(Into::into).collect() })
+    }
+
+    /// Sorts by `field`, later calls break ties of earlier ones and the key breaks the rest
+    pub fn order_by(mut self, field: &str, order: SortOrder) -> Self {
+        self.spec.order_by.push((field.to_string(), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.spec.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.spec.offset = offset;
+        self
+    }
+
+    pub fn spec(&self) -> &QuerySpec {
+        &self.spec
+    }
+
+    /// Runs the query, the matching objects with their keys in order
+    pub async fn fetch(self) -> anyhow::Result<Vec<(String, O)>> {
+        self.client.execute_query::<O>(&self.spec).await
+    }
+
+    /// Runs the query as a stream, for result sets too large to hold in memory at once
+    /// - Without `order_by` results may come in any order, see `StorageClient::stream_query`
+    pub fn stream(self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send {
+        self.client.stream_query::<O>(self.spec)
+    }
+
+    /// Deletes every matching object, returns how many were removed, sorting and paging are ignored
+    pub async fn delete(self) -> anyhow::Result<usize> {
+        self.client.delete_where::<O>(&self.spec.filters).await
+    }
+
+    /// Aggregates per value of `field` instead of over every matching object
+    pub fn group_by(self, field: &str) -> GroupedQuery<'a, F, C, O> {
+        GroupedQuery { query: self, field: field.to_string() }
+    }
+
+    /// Computes `aggregate` over the matching objects, sorting and paging are ignored
+    pub async fn aggregate(self, aggregate: Aggregate) -> anyhow::Result<Value> {
+        let spec = AggregateSpec { filters: self.spec.filters, aggregate, group_by: None };
+        let groups = self.client.execute_aggregate::<O>(&spec).await?;
+        Ok(groups.into_iter().next().map(|(_, value)| value).unwrap_or(Value::Null))
+    }
+
+    pub async fn count(self) -> anyhow::Result<u64> {
+        Ok(self.aggregate(Aggregate::Count).await?.as_u64().unwrap_or(0))
+    }
+
+    pub async fn sum(self, field: &str) -> anyhow::Result<f64> {
+        Ok(self.aggregate(Aggregate::Sum(field.to_string())).await?.as_f64().unwrap_or(0.0))
+    }
+
+    /// The smallest value of `field`, `None` if no matching object has one
+    pub async fn min(self, field: &str) -> anyhow::Result<Option<Value>> {
+        Ok(Some(self.aggregate(Aggregate::Min(field.to_string())).await?).filter(|value| !value.is_null()))
+    }
+
+    /// The largest value of `field`, `None` if no matching object has one
+    pub async fn max(self, field: &str) -> anyhow::Result<Option<Value>> {
+        Ok(Some(self.aggregate(Aggregate::Max(field.to_string())).await?).filter(|value| !value.is_null()))
+    }
+}
+
+/// A `Query` aggregated per value of a field, built by `Query::group_by`
+/// - `client.query::<Order>().group_by("status").count().await?` counts the orders of each status
+/// - Groups are sorted by their value, objects without the field fall in the `null` group
+pub struct GroupedQuery<'a, F, C: ?Sized, O> {
+    query: Query<'a, F, C, O>,
+    field: String,
+}
+
+impl<'a, F, C, O> GroupedQuery<'a, F, C, O>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+
+    /// Computes `aggregate` for each group, with the value of the grouped field
+    pub async fn aggregate(self, aggregate: Aggregate) -> anyhow::Result<Vec<(Value, Value)>> {
+        let spec = AggregateSpec { filters: self.query.spec.filters, aggregate, group_by: Some(self.field) };
+        self.query.client.execute_aggregate::<O>(&spec).await
+    }
+
+    pub async fn count(self) -> anyhow::Result<Vec<(Value, u64)>> {
+        let groups = self.aggregate(Aggregate::Count).await?;
+        Ok(groups.into_iter().map(|(group, count)| (group, count.as_u64().unwrap_or(0))).collect())
+    }
+
+    pub async fn sum(self, field: &str) -> anyhow::Result<Vec<(Value, f64)>> {
+        let groups = self.aggregate(Aggregate::Sum(field.to_string())).await?;
+        Ok(groups.into_iter().map(|(group, sum)| (group, sum.as_f64().unwrap_or(0.0))).collect())
+    }
+
+    pub async fn min(self, field: &str) -> anyhow::Result<Vec<(Value, Value)>> {
+        self.aggregate(Aggregate::Min(field.to_string())).await
+    }
+
+    pub async fn max(self, field: &str) -> anyhow::Result<Vec<(Value, Value)>> {
+        self.aggregate(Aggregate::Max(field.to_string())).await
+    }
+}
+
+/// Sorts aggregate groups by their value, the order SQL clients return them in
+pub(crate) fn sort_groups(groups: &mut [(Value, Value)]) {
+    groups.sort_by(|(a, _), (b, _)| sort_order(a, b));
+}
+
+/// A query without backend support, scanning every object and filtering and sorting them after deserializing
+pub(crate) async fn query_scan<F, C, O>(client: &C, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    let objects: Vec<(String, O)> = client.scan::<O>()
+        .try_filter_map(|(key, object)| {
+            let matched = matches_all(&object, &spec.filters).map(|matched| matched.then_some((key, object)));
+            async move { matched }
+        })
+        .try_collect()
+        .await?;
+    spec.page(objects)
+}
+
+/// A streamed query without backend support, filtering a scan as it is polled
+/// - Sorted queries are run with `execute_query` and streamed from the sorted results
+pub(crate) fn query_stream<F, C, O>(client: &C, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    if !spec.order_by.is_empty() {
+        return stream::once(async move { client.execute_query::<O>(&spec).await })
+            .map_ok(|objects| stream::iter(objects).map(Ok))
+            .try_flatten()
+            .left_stream();
+    }
+    let (offset, limit) = (spec.offset, spec.limit.unwrap_or(usize::MAX));
+    client.scan::<O>()
+        .try_filter_map(move |(key, object)| {
+            let matched = matches_all(&object, &spec.filters).map(|matched| matched.then_some((key, object)));
+            async move { matched }
+        })
+        .skip(offset)
+        .take(limit)
+        .right_stream()
+}
+
+/// A predicate deletion without backend support, collecting the matching keys with `stream_query` and removing them with `delete_many`
+pub(crate) async fn delete_where_scan<F, C, O>(client: &C, filters: &[Filter]) -> anyhow::Result<usize>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    let spec = QuerySpec { filters: filters.to_vec(), ..Default::default() };
+    let keys: Vec<String> = client.stream_query::<O>(spec).map_ok(|(key, _)| key).try_collect().await?;
+    let keys: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+    client.delete_many::<O>(&keys).await
+}
+
+/// An aggregate without backend support, folding every object of a scan into its group
+pub(crate) async fn aggregate_scan<F, C, O>(client: &C, spec: &AggregateSpec) -> anyhow::Result<Vec<(Value, Value)>>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    // groups are keyed by the JSON text of their value
+    let mut initial = HashMap::new();
+    if spec.group_by.is_none() {
+        initial.insert(Value::Null.to_string(), (Value::Null, Accumulator::default()));
+    }
+    let groups = client.scan::<O>()
+        .try_fold(initial, |mut groups, (key, object)| {
+            let folded = serde_json::to_value(&object)
+                .with_context(|| format!("Failed to extract fields of {} for key: {}", O::type_name(), key))
+                .map(|fields| {
+                    if spec.filters.iter().all(|filter| filter.matches(&fields)) {
+                        let group = match &spec.group_by {
+                            Some(field) => fields.get(field).cloned().unwrap_or(Value::Null),
+                            None => Value::Null,
+                        };
+                        groups.entry(group.to_string())
+                            .or_insert_with(|| (group, Accumulator::default()))
+                            .1
+                            .add(&spec.aggregate, &fields);
+                    }
+                    groups
+                });
+            async move { folded }
+        })
+        .await?;
+    let mut groups: Vec<(Value, Value)> = groups.into_values()
+        .map(|(group, accumulator)| (group, accumulator.finish(&spec.aggregate)))
+        .collect();
+    sort_groups(&mut groups);
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        let fields = json!({ "customer": "X", "total": 120, "paid": true, "note": null });
+        assert!(Filter::Eq { field: "customer".to_string(), value: json!("X") }.matches(&fields));
+        assert!(Filter::Gte { field: "total".to_string(), value: json!(120) }.matches(&fields));
+        assert!(Filter::Lt { field: "total".to_string(), value: json!(120.5) }.matches(&fields));
+        assert!(!Filter::Gt { field: "total".to_string(), value: json!(120) }.matches(&fields));
+        assert!(Filter::In { field: "customer".to_string(), values: vec![json!("Y"), json!("X")] }.matches(&fields));
+        assert!(!Filter::In { field: "customer".to_string(), values: Vec::new() }.matches(&fields));
+        assert!(!Filter::Gt { field: "customer".to_string(), value: json!(1) }.matches(&fields));
+        assert!(!Filter::Eq { field: "note".to_string(), value: Value::Null }.matches(&fields));
+        assert!(Filter::Eq { field: "paid".to_string(), value: json!(true) }.matches(&fields));
+    }
+
+    #[test]
+    fn test_query_spec_page() {
+        let spec = QuerySpec {
+            order_by: vec![("total".to_string(), SortOrder::Desc)],
+            limit: Some(2),
+            offset: 1,
+            ..QuerySpec::default()
+        };
+        let objects = vec![
+            ("a".to_string(), json!({ "total": 5 })),
+            ("b".to_string(), json!({ "total": 9 })),
+            ("c".to_string(), json!({ "total": 5 })),
+            ("d".to_string(), json!({ "total": null })),
+        ];
+        let page: Vec<String> = spec.page(objects).unwrap().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(page, vec!["a".to_string(), "c".to_string()]);
+    }
+}
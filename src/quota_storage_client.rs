@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::Stream;
+use ordermap::OrderMap;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::{AggregateSpec, HealthStatus, ObjectMetadata, Page, PageRequest, QuerySpec, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Limits of one object type in a `QuotaStorageClient`, `None` is no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// size of a single object, after formatting
+    pub max_object_bytes: Option<u64>,
+    pub max_objects: Option<u64>,
+    /// total size of the objects of the type, after formatting
+    pub max_bytes: Option<u64>,
+}
+
+/// Which limit of a `Quota` a write would go over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaLimit {
+    ObjectBytes,
+    Objects,
+    Bytes,
+}
+
+impl std::fmt::Display for QuotaLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaLimit::ObjectBytes => write!(f, "object size"),
+            QuotaLimit::Objects => write!(f, "object count"),
+            QuotaLimit::Bytes => write!(f, "total size"),
+        }
+    }
+}
+
+/// Returned by writes of a `QuotaStorageClient` that would go over a quota, nothing of the write is stored
+/// - Recover it with `error.downcast_ref::<QuotaExceeded>()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub type_name: &'static str,
+    /// the object that is too large, or the first one of the write that does not fit anymore
+    pub key: String,
+    pub limit: QuotaLimit,
+    pub max: u64,
+    /// object size, count or total size the write would have reached
+    pub requested: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} for key: {} would exceed the {} quota, {} over the maximum of {}",
+            self.type_name, self.key, self.limit, self.requested, self.max
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Keeps object types within size and count limits, rejecting writes that would go over with `QuotaExceeded`.
+/// - Each type gets the quota set with `with_quota`, or the default quota
+/// - Usage is read once per type with the inner client's `usage`, then tracked from the writes and deletes going through this client;
+///   writes to the inner client from elsewhere are not seen until `refresh`
+/// - Writes of limited types are checked and applied one at a time, they need the inner client's `metadata` to tell new objects from replaced ones
+/// - Objects are formatted once to be measured and again by the inner client
+/// - Transactions only know their types by name, so each of their puts counts as a new object
+/// - Appended records are not objects, they are only held to `max_object_bytes`
+pub struct QuotaStorageClient<C> {
+    inner: C,
+    default_quota: Quota,
+    quotas: HashMap<String, Quota>,
+    // usage of each limited type as of the last write, guards the writes that change it
+    usage: Mutex<HashMap<&'static str, StorageUsage>>,
+}
+
+impl<C> QuotaStorageClient<C> {
+
+    pub fn new(inner: C, default_quota: Quota) -> Self {
+        Self { inner, default_quota, quotas: HashMap::new(), usage: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn with_quota(mut self, type_name: impl Into<String>, quota: Quota) -> Self {
+        self.quotas.insert(type_name.into(), quota);
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn quota(&self, type_name: &str) -> Quota {
+        self.quotas.get(type_name).copied().unwrap_or(self.default_quota)
+    }
+
+    /// Forgets the tracked usage, the next write of each type reads it from the inner client again
+    pub async fn refresh(&self) {
+        self.usage.lock().await.clear();
+    }
+
+    /// Tracked usage of the type once `writes` of (key, formatted size) are stored, or the `QuotaExceeded` of the first write that does not fit
+    async fn admit<F, O>(&self, tracked: &mut HashMap<&'static str, StorageUsage>, writes: &OrderMap<&str, u64>) -> anyhow::Result<StorageUsage>
+    where
+        F: StorageFormat + Send + Sync,
+        C: StorageClient<F> + Send + Sync,
+        O: StorageObject,
+    {
+        let quota = self.quota(O::type_name());
+        let exceeded = |key: &str, limit: QuotaLimit, max: u64, requested: u64| QuotaExceeded {
+            type_name: O::type_name(),
+            key: key.to_string(),
+            limit,
+            max,
+            requested,
+        };
+        for (key, size) in writes {
+            if let Some(max) = quota.max_object_bytes.filter(|max| size > max) {
+                return Err(exceeded(key, QuotaLimit::ObjectBytes, max, *size).into());
+            }
+        }
+
+        let mut usage = match tracked.get(O::type_name()) {
+            Some(usage) => *usage,
+            None => self.inner.usage::<O>().await.with_context(|| {
+                format!("Failed to read usage of {} for its quota", O::type_name())
+            })?,
+        };
+        let existing = futures::future::try_join_all(writes.keys().map(|key| self.inner.metadata::<O>(key))).await?;
+        for ((key, size), existing) in writes.iter().zip(existing) {
+            match existing {
+                Some(existing) => usage.bytes = usage.bytes.saturating_sub(existing.size),
+                None => usage.objects += 1,
+            }
+            usage.bytes += size;
+            if let Some(max) = quota.max_objects.filter(|max| usage.objects > *max) {
+                return Err(exceeded(key, QuotaLimit::Objects, max, usage.objects).into());
+            }
+            if let Some(max) = quota.max_bytes.filter(|max| usage.bytes > *max) {
+                return Err(exceeded(key, QuotaLimit::Bytes, max, usage.bytes).into());
+            }
+        }
+        Ok(usage)
+    }
+}
+
+/// Formatted size of the object, what quotas are measured in
+fn formatted_size<F: StorageFormat, O: StorageObject + Serialize>(key: &str, value: &O) -> anyhow::Result<u64> {
+    let data = F::serialize(value).with_context(|| {
+        format!("Failed to serialize object for key: {}", key)
+    })?;
+    Ok(data.len() as u64)
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for QuotaStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("QuotaStorageClient needs quotas, use QuotaStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.inner.create_object_directory::<O>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        if self.quota(O::type_name()) == Quota::default() {
+            return self.inner.put(key, value).await;
+        }
+        let mut writes = OrderMap::new();
+        writes.insert(key, formatted_size::<F, O>(key, &value)?);
+
+        let mut tracked = self.usage.lock().await;
+        let usage = self.admit::<F, O>(&mut tracked, &writes).await?;
+        self.inner.put(key, value).await?;
+        tracked.insert(O::type_name(), usage);
+        Ok(())
+    }
+
+    // the whole batch is checked before anything is written, so a batch over the quota stores nothing
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let items: Vec<(String, O)> = items.into_iter().collect();
+        if self.quota(O::type_name()) == Quota::default() {
+            return self.inner.put_many(items).await;
+        }
+        let mut writes = OrderMap::new();
+        for (key, value) in &items {
+            writes.insert(key.as_str(), formatted_size::<F, O>(key, value)?);
+        }
+
+        let mut tracked = self.usage.lock().await;
+        let usage = self.admit::<F, O>(&mut tracked, &writes).await?;
+        let written = self.inner.put_many(items).await;
+        match written {
+            Ok(()) => {
+                tracked.insert(O::type_name(), usage);
+                Ok(())
+            }
+            Err(error) => {
+                // part of the batch may be stored, read the usage again on the next write
+                tracked.remove(O::type_name());
+                Err(error)
+            }
+        }
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        if self.quota(O::type_name()) == Quota::default() {
+            return self.inner.put_if_absent(key, value).await;
+        }
+        let mut writes = OrderMap::new();
+        writes.insert(key, formatted_size::<F, O>(key, &value)?);
+
+        let mut tracked = self.usage.lock().await;
+        let usage = self.admit::<F, O>(&mut tracked, &writes).await?;
+        let written = self.inner.put_if_absent(key, value).await?;
+        if written {
+            tracked.insert(O::type_name(), usage);
+        }
+        Ok(written)
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.inner.get_versioned(key).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        if self.quota(O::type_name()) == Quota::default() {
+            return self.inner.put_if_version(key, value, expected_version).await;
+        }
+        let mut writes = OrderMap::new();
+        writes.insert(key, formatted_size::<F, O>(key, &value)?);
+
+        let mut tracked = self.usage.lock().await;
+        let usage = self.admit::<F, O>(&mut tracked, &writes).await?;
+        let version = self.inner.put_if_version(key, value, expected_version).await?;
+        tracked.insert(O::type_name(), usage);
+        Ok(version)
+    }
+
+    // the usage of a type is read with `usage_all` as only its name is known, which puts replace an object is not known either,
+    // so every put counts as a new object and the usage of the types is read again on their next write
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let mut limited: Vec<&'static str> = operations.iter()
+            .map(|operation| operation.type_name())
+            .filter(|type_name| self.quota(type_name) != Quota::default())
+            .collect();
+        limited.sort();
+        limited.dedup();
+        if limited.is_empty() {
+            return self.inner.commit_transaction(operations).await;
+        }
+
+        let mut tracked = self.usage.lock().await;
+        let mut stored: Option<HashMap<String, StorageUsage>> = None;
+        let mut usages: HashMap<&'static str, StorageUsage> = HashMap::new();
+        for operation in &operations {
+            let TransactionOperation::Put { type_name, key, data, .. } = operation else {
+                continue;
+            };
+            let quota = self.quota(type_name);
+            if quota == Quota::default() {
+                continue;
+            }
+            let exceeded = |limit: QuotaLimit, max: u64, requested: u64| QuotaExceeded {
+                type_name,
+                key: key.clone(),
+                limit,
+                max,
+                requested,
+            };
+            let size = data.len() as u64;
+            if let Some(max) = quota.max_object_bytes.filter(|max| size > *max) {
+                return Err(exceeded(QuotaLimit::ObjectBytes, max, size).into());
+            }
+            let usage = match usages.get_mut(type_name) {
+                Some(usage) => usage,
+                None => {
+                    let usage = match tracked.get(type_name) {
+                        Some(usage) => *usage,
+                        None => {
+                            if stored.is_none() {
+                                stored = Some(self.inner.usage_all().await.context("Failed to read usage for the quotas of a transaction")?);
+                            }
+                            stored.as_ref().and_then(|stored| stored.get(*type_name)).copied().unwrap_or_default()
+                        }
+                    };
+                    usages.entry(type_name).or_insert(usage)
+                }
+            };
+            usage.objects += 1;
+            usage.bytes += size;
+            if let Some(max) = quota.max_objects.filter(|max| usage.objects > *max) {
+                return Err(exceeded(QuotaLimit::Objects, max, usage.objects).into());
+            }
+            if let Some(max) = quota.max_bytes.filter(|max| usage.bytes > *max) {
+                return Err(exceeded(QuotaLimit::Bytes, max, usage.bytes).into());
+            }
+        }
+
+        let committed = self.inner.commit_transaction(operations).await;
+        for type_name in limited {
+            tracked.remove(type_name);
+        }
+        committed
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        if let Some(max) = self.quota(O::type_name()).max_object_bytes {
+            let size = formatted_size::<F, O>(key, &value)?;
+            if size > max {
+                return Err(QuotaExceeded {
+                    type_name: O::type_name(),
+                    key: key.to_string(),
+                    limit: QuotaLimit::ObjectBytes,
+                    max,
+                    requested: size,
+                }.into());
+            }
+        }
+        self.inner.append(key, value).await
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    // the copy is as large as the object stored under `from`
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        if self.quota(O::type_name()) == Quota::default() {
+            return self.inner.copy::<O>(from, to).await;
+        }
+        let mut tracked = self.usage.lock().await;
+        let Some(existing) = self.inner.metadata::<O>(from).await? else {
+            return Ok(false);
+        };
+        let mut writes = OrderMap::new();
+        writes.insert(to, existing.size);
+        let usage = self.admit::<F, O>(&mut tracked, &writes).await?;
+        let copied = self.inner.copy::<O>(from, to).await?;
+        if copied {
+            tracked.insert(O::type_name(), usage);
+        }
+        Ok(copied)
+    }
+
+    // a rename never adds to the usage, it drops the object it replaces, the usage is read again on the next write
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let mut tracked = self.usage.lock().await;
+        let renamed = self.inner.rename::<O>(from, to).await;
+        tracked.remove(O::type_name());
+        renamed
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut tracked = self.usage.lock().await;
+        if !tracked.contains_key(O::type_name()) {
+            drop(tracked);
+            return self.inner.delete::<O>(key).await;
+        }
+        let existing = self.inner.metadata::<O>(key).await?;
+        let deleted = self.inner.delete::<O>(key).await?;
+        if let (true, Some(existing), Some(usage)) = (deleted, existing, tracked.get_mut(O::type_name())) {
+            usage.objects = usage.objects.saturating_sub(1);
+            usage.bytes = usage.bytes.saturating_sub(existing.size);
+        }
+        Ok(deleted)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let mut tracked = self.usage.lock().await;
+        tracked.remove(O::type_name());
+        self.inner.delete_object_directory::<O>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let mut tracked = self.usage.lock().await;
+        tracked.clear();
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.inner.get_many(keys).await
+    }
+
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.scan::<O>()
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        self.inner.find_by::<O, V>(field, value).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.search::<O>(terms).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.execute_query::<O>(spec).await
+    }
+
+    fn stream_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.stream_query::<O>(spec)
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        self.inner.execute_aggregate::<O>(spec).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<O>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    fn object(key: &str, value: &str) -> (String, TestObject) {
+        (key.to_string(), TestObject { key: key.to_string(), value: value.to_string() })
+    }
+
+    fn exceeded(error: anyhow::Error) -> QuotaExceeded {
+        error.downcast::<QuotaExceeded>().expect("Expected a QuotaExceeded error")
+    }
+
+    #[tokio::test]
+    async fn test_quota_storage_client_limits() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let quota = Quota { max_object_bytes: Some(64), max_objects: Some(2), max_bytes: None };
+        let client = QuotaStorageClient::new(inner, Quota::default()).with_quota("TestObject", quota);
+
+        let (key, value) = object("a", &"x".repeat(100));
+        let error = exceeded(client.put(&key, value).await.unwrap_err());
+        assert_eq!((error.limit, error.max), (QuotaLimit::ObjectBytes, 64));
+
+        let (key, value) = object("a", "1");
+        client.put(&key, value).await.unwrap();
+        // a batch going over the count stores nothing, replacing an object does not count
+        let error = exceeded(client.put_many(vec![object("b", "2"), object("c", "3")]).await.unwrap_err());
+        assert_eq!((error.key.as_str(), error.limit, error.requested), ("c", QuotaLimit::Objects, 3));
+        assert!(!client.exists::<TestObject>("b").await.unwrap());
+        client.put_many(vec![object("a", "4"), object("b", "5")]).await.unwrap();
+        let (key, value) = object("c", "6");
+        assert!(client.put(&key, value).await.is_err());
+
+        assert!(client.delete::<TestObject>("a").await.unwrap());
+        let (key, value) = object("c", "6");
+        client.put(&key, value).await.unwrap();
+        assert_eq!(client.count::<TestObject>().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_quota_storage_client_total_size() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let size = JsonStorageFormat::serialize(&object("a", "1").1).unwrap().len() as u64;
+        let client = QuotaStorageClient::new(inner, Quota { max_bytes: Some(size * 2), ..Quota::default() });
+
+        client.put_many(vec![object("a", "1"), object("b", "2")]).await.unwrap();
+        let (key, value) = object("c", "3");
+        let error = exceeded(client.put(&key, value).await.unwrap_err());
+        assert_eq!((error.limit, error.requested), (QuotaLimit::Bytes, size * 3));
+    }
+
+    #[tokio::test]
+    async fn test_quota_storage_client_conditional_writes() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://test").unwrap()).await.unwrap();
+        let client = QuotaStorageClient::new(inner, Quota { max_objects: Some(2), ..Quota::default() });
+
+        let (key, value) = object("a", "1");
+        assert!(client.put_if_absent(&key, value.clone()).await.unwrap());
+        assert!(!client.put_if_absent(&key, value).await.unwrap());
+        let (_, version) = client.get_versioned::<TestObject>("a").await.unwrap().expect("Expected an object");
+        let (key, value) = object("a", "2");
+        client.put_if_version(&key, value, &version).await.unwrap();
+        assert_eq!(client.get::<TestObject>("a").await.unwrap().unwrap().value, "2");
+
+        // copies and transactions count against the quota as well
+        assert!(client.copy::<TestObject>("a", "b").await.unwrap());
+        let error = exceeded(client.copy::<TestObject>("a", "c").await.unwrap_err());
+        assert_eq!((error.limit, error.requested), (QuotaLimit::Objects, 3));
+        let error = client.transaction(|txn| async move {
+            txn.put("c", &object("c", "3").1)?;
+            Ok(())
+        }).await.unwrap_err();
+        assert_eq!(exceeded(error).limit, QuotaLimit::Objects);
+        assert!(!client.exists::<TestObject>("c").await.unwrap());
+
+        assert!(client.rename::<TestObject>("b", "c").await.unwrap());
+        let (key, value) = object("d", "4");
+        assert!(client.put_if_absent(&key, value).await.is_err());
+    }
+}
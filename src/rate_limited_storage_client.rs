@@ -0,0 +1,324 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{Semaphore, SemaphorePermit},
+    time::Instant,
+};
+use url::Url;
+
+use crate::{lease::LeaseGuard, query::{AggregateSpec, Filter, QuerySpec}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Traffic budget of a `RateLimitedStorageClient`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub ops_per_second: f64,
+    /// operations that can start at once after an idle period
+    pub burst: u32,
+    pub max_concurrent: usize,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Delays operations so the inner client sees at most `ops_per_second` operations on average
+/// and never more than `max_concurrent` operations in flight.
+/// - Operations wait for their turn instead of failing
+pub struct RateLimitedStorageClient<C> {
+    inner: C,
+    limit: RateLimit,
+    bucket: Mutex<TokenBucket>,
+    concurrency: Semaphore,
+}
+
+impl<C> RateLimitedStorageClient<C> {
+
+    pub fn new(inner: C, limit: RateLimit) -> anyhow::Result<Self> {
+        if limit.ops_per_second.is_nan() || limit.ops_per_second <= 0.0 || limit.burst == 0 || limit.max_concurrent == 0 {
+            return Err(anyhow::anyhow!("Rate limit must allow at least one operation: {:?}", limit));
+        }
+        let bucket = TokenBucket { tokens: limit.burst as f64, refilled_at: Instant::now() };
+        Ok(Self { inner, limit, bucket: Mutex::new(bucket), concurrency: Semaphore::new(limit.max_concurrent) })
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Waits for a concurrency slot and a token, the slot is released when the permit is dropped
+    async fn acquire(&self) -> anyhow::Result<SemaphorePermit<'_>> {
+        let permit = self.concurrency.acquire().await?;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * self.limit.ops_per_second;
+                bucket.tokens = (bucket.tokens + refill).min(self.limit.burst as f64);
+                bucket.refilled_at = now;
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return Ok(permit);
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.limit.ops_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for RateLimitedStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("RateLimitedStorageClient needs a rate limit, use RateLimitedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.create_object_directory::<O>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let _permit = self.acquire().await?;
+        self.inner.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.put(key, value).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.delete::<O>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.delete_object_directory::<O>().await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        // probes are not throttled, a saturated budget is not an unhealthy backend
+        self.inner.health_check().await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        let _permit = self.acquire().await?;
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, id: &SnapshotId) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.restore(id).await
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        let _permit = self.acquire().await?;
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        let _permit = self.acquire().await?;
+        self.inner.import_all(source).await
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.commit_transaction(operations).await
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.append(key, value).await
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        let _permit = self.acquire().await?;
+        self.inner.list_versions::<O>(key).await
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        let _permit = self.acquire().await?;
+        self.inner.get_version::<O>(key, version).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let _permit = self.acquire().await?;
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        let _permit = self.acquire().await?;
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        let _permit = self.acquire().await?;
+        self.inner.scan_range::<O>(range).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        let _permit = self.acquire().await?;
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let _permit = self.acquire().await?;
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        let _permit = self.acquire().await?;
+        self.inner.usage::<O>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        let _permit = self.acquire().await?;
+        self.inner.usage_all().await
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        let _permit = self.acquire().await?;
+        self.inner.find_by::<O, V>(field, value).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        let _permit = self.acquire().await?;
+        self.inner.search::<O>(terms).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        let _permit = self.acquire().await?;
+        self.inner.execute_query::<O>(spec).await
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        let _permit = self.acquire().await?;
+        self.inner.execute_aggregate::<O>(spec).await
+    }
+
+    // one permit for the whole batch, like any other call
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let _permit = self.acquire().await?;
+        self.inner.get_many(keys).await
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let _permit = self.acquire().await?;
+        self.inner.put_many(items).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let _permit = self.acquire().await?;
+        self.inner.delete_many::<O>(keys).await
+    }
+
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        let _permit = self.acquire().await?;
+        self.inner.delete_where::<O>(filters).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let _permit = self.acquire().await?;
+        self.inner.get_versioned(key).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let _permit = self.acquire().await?;
+        self.inner.put_if_version(key, value, expected_version).await
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.put_if_absent(key, value).await
+    }
+
+    async fn merge<O>(&self, key: &str, incoming: O, resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        let _permit = self.acquire().await?;
+        self.inner.merge::<O>(key, incoming, resolver).await
+    }
+
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        let _permit = self.acquire().await?;
+        self.inner.try_lock(name, ttl).await
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.copy::<O>(from, to).await
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let _permit = self.acquire().await?;
+        self.inner.rename::<O>(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limited_storage_client_spaces_operations() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://rate_limited").unwrap()).await.unwrap();
+        let limit = RateLimit { ops_per_second: 2.0, burst: 1, max_concurrent: 1 };
+        let client = RateLimitedStorageClient::new(inner, limit).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..5 {
+            let missing: Option<TestObject> = client.get("test_key").await.unwrap();
+            assert!(missing.is_none());
+        }
+        // the first operation uses the burst token, the other four wait half a second each
+        assert!(start.elapsed() >= Duration::from_secs(2));
+
+        let zero = RateLimit { ops_per_second: 0.0, burst: 1, max_concurrent: 1 };
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://zero").unwrap()).await.unwrap();
+        assert!(RateLimitedStorageClient::new(inner, zero).is_err());
+    }
+}
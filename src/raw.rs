@@ -0,0 +1,224 @@
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, DeserializeOwned, value::BytesDeserializer, SeqAccess, Visitor},
+    ser::{self, Impossible},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{StorageFormat, StorageObject, StorageSchema};
+
+/// Already formatted bytes of an `O`, stored under the type name and schema of `O`.
+/// - Lets wrapper clients move objects between clients without knowing how to serialize `O`
+pub struct Payload<O> {
+    data: Vec<u8>,
+    _object: PhantomData<fn() -> O>,
+}
+
+impl<O> Payload<O> {
+
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data, _object: PhantomData }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl<O> Clone for Payload<O> {
+    fn clone(&self) -> Self {
+        Self::new(self.data.clone())
+    }
+}
+
+impl<O> fmt::Debug for Payload<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Payload").field("len", &self.data.len()).finish()
+    }
+}
+
+impl<O: StorageObject> StorageObject for Payload<O> {
+    fn type_name() -> &'static str {
+        O::type_name()
+    }
+
+    fn schema() -> StorageSchema {
+        O::schema()
+    }
+}
+
+impl<O> Serialize for Payload<O> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.data)
+    }
+}
+
+struct PayloadVisitor;
+
+impl<'de> Visitor<'de> for PayloadVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte payload")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    // formats without a byte type, like json, store bytes as a sequence of numbers
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            data.push(byte);
+        }
+        Ok(data)
+    }
+}
+
+impl<'de, O> Deserialize<'de> for Payload<O> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(PayloadVisitor).map(Self::new)
+    }
+}
+
+/// Stores `Payload`s verbatim, any other object fails to serialize.
+/// - Used for the inner clients of wrappers that transform the formatted bytes, e.g.
+///   `TieredStorageClient<MemoryStorageClient<RawFormat>, FileStorageClient<RawFormat>>`
+///   implements `StorageClient<JsonStorageFormat>`
+#[derive(Debug, Clone)]
+pub struct RawFormat;
+
+impl StorageFormat for RawFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        obj.serialize(BytesSerializer).map_err(|e| e.into())
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        T::deserialize(BytesDeserializer::<de::value::Error>::new(data)).map_err(|e| e.into())
+    }
+}
+
+#[derive(Debug)]
+struct RawError(String);
+
+impl fmt::Display for RawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RawError {}
+
+impl ser::Error for RawError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RawError(msg.to_string())
+    }
+}
+
+fn unsupported() -> RawError {
+    RawError("RawFormat can only serialize byte payloads".to_string())
+}
+
+/// Serializer that only accepts bytes
+struct BytesSerializer;
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, RawError> {
+                Err(unsupported())
+            }
+        )*
+    };
+}
+
+impl Serializer for BytesSerializer {
+    type Ok = Vec<u8>;
+    type Error = RawError;
+    type SerializeSeq = Impossible<Vec<u8>, RawError>;
+    type SerializeTuple = Impossible<Vec<u8>, RawError>;
+    type SerializeTupleStruct = Impossible<Vec<u8>, RawError>;
+    type SerializeTupleVariant = Impossible<Vec<u8>, RawError>;
+    type SerializeMap = Impossible<Vec<u8>, RawError>;
+    type SerializeStruct = Impossible<Vec<u8>, RawError>;
+    type SerializeStructVariant = Impossible<Vec<u8>, RawError>;
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>, RawError> {
+        Ok(v.to_vec())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Vec<u8>, RawError> {
+        value.serialize(self)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Vec<u8>, RawError> {
+        Err(unsupported())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<Vec<u8>, RawError> {
+        Err(unsupported())
+    }
+
+    unsupported! {
+        serialize_bool(bool) -> Vec<u8>;
+        serialize_i8(i8) -> Vec<u8>;
+        serialize_i16(i16) -> Vec<u8>;
+        serialize_i32(i32) -> Vec<u8>;
+        serialize_i64(i64) -> Vec<u8>;
+        serialize_u8(u8) -> Vec<u8>;
+        serialize_u16(u16) -> Vec<u8>;
+        serialize_u32(u32) -> Vec<u8>;
+        serialize_u64(u64) -> Vec<u8>;
+        serialize_f32(f32) -> Vec<u8>;
+        serialize_f64(f64) -> Vec<u8>;
+        serialize_char(char) -> Vec<u8>;
+        serialize_str(&str) -> Vec<u8>;
+        serialize_none() -> Vec<u8>;
+        serialize_unit() -> Vec<u8>;
+        serialize_unit_struct(&'static str) -> Vec<u8>;
+        serialize_unit_variant(&'static str, u32, &'static str) -> Vec<u8>;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_raw_format_payload() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let data = JsonStorageFormat::serialize(&obj).unwrap();
+
+        let raw = RawFormat::serialize(&Payload::<TestObject>::new(data.clone())).unwrap();
+        assert_eq!(raw, data);
+        let payload: Payload<TestObject> = RawFormat::deserialize(&raw).unwrap();
+        assert_eq!(payload.data(), data.as_slice());
+
+        // payloads also survive formats without a byte type
+        let json = JsonStorageFormat::serialize(&payload).unwrap();
+        let payload: Payload<TestObject> = JsonStorageFormat::deserialize(&json).unwrap();
+        assert_eq!(JsonStorageFormat::deserialize::<TestObject>(payload.data()).unwrap(), obj);
+
+        assert!(RawFormat::serialize(&obj).is_err());
+    }
+}
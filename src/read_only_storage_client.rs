@@ -0,0 +1,274 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+use url::Url;
+
+use crate::{lease::LeaseGuard, query::{AggregateSpec, Filter, QuerySpec}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, SnapshotId, StorageClient, StorageFormat, StorageObject, StorageUsage, TransactionOperation};
+
+/// Returned for every mutation through a `ReadOnlyStorageClient`
+/// - Recover it with `error.downcast_ref::<ReadOnlyError>()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    pub operation: &'static str,
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not allowed on a read-only storage client", self.operation)
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+fn denied<T>(operation: &'static str) -> anyhow::Result<T> {
+    Err(ReadOnlyError { operation }.into())
+}
+
+/// Allows reads through to the inner client and rejects every mutation with a `ReadOnlyError`.
+pub struct ReadOnlyStorageClient<C> {
+    inner: C,
+}
+
+impl<C> ReadOnlyStorageClient<C> {
+
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for ReadOnlyStorageClient<C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        denied("create_object_directory")
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O) -> anyhow::Result<()> {
+        denied("put")
+    }
+
+    async fn delete<O: StorageObject>(&self, _key: &str) -> anyhow::Result<bool> {
+        denied("delete")
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        denied("delete_object_directory")
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        denied("delete_all")
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        self.inner.health_check().await
+    }
+
+    async fn snapshot(&self) -> anyhow::Result<SnapshotId> {
+        self.inner.snapshot().await
+    }
+
+    async fn restore(&self, _id: &SnapshotId) -> anyhow::Result<()> {
+        denied("restore")
+    }
+
+    async fn export_all(&self, sink: impl AsyncWrite + Unpin + Send) -> anyhow::Result<u64> {
+        self.inner.export_all(sink).await
+    }
+
+    async fn import_all(&self, _source: impl AsyncRead + Unpin + Send) -> anyhow::Result<u64> {
+        denied("import_all")
+    }
+
+    async fn commit_transaction(&self, _operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        denied("transaction")
+    }
+
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O) -> anyhow::Result<()> {
+        denied("append")
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.read_appended::<O>(key)
+    }
+
+    async fn list_versions<O: StorageObject>(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        self.inner.list_versions::<O>(key).await
+    }
+
+    async fn get_version<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str, version: &str) -> anyhow::Result<Option<O>> {
+        self.inner.get_version::<O>(key, version).await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.inner.list_keys::<O>().await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.inner.list_page::<O>(page).await
+    }
+
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.scan_range::<O>(range).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.inner.count::<O>().await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.inner.exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.inner.metadata::<O>(key).await
+    }
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        self.inner.usage::<O>().await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        self.inner.usage_all().await
+    }
+
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        self.inner.find_by::<O, V>(field, value).await
+    }
+
+    async fn search<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, terms: &str) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.search::<O>(terms).await
+    }
+
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        self.inner.execute_query::<O>(spec).await
+    }
+
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        self.inner.execute_aggregate::<O>(spec).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        self.inner.get_many(keys).await
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, _items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        denied("put_many")
+    }
+
+    async fn delete_many<O: StorageObject>(&self, _keys: &[&str]) -> anyhow::Result<usize> {
+        denied("delete_many")
+    }
+
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, _filters: &[Filter]) -> anyhow::Result<usize> {
+        denied("delete_where")
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        self.inner.get_versioned(key).await
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O, _expected_version: &str) -> anyhow::Result<String> {
+        denied("put_if_version")
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, _key: &str, _value: O) -> anyhow::Result<bool> {
+        denied("put_if_absent")
+    }
+
+    async fn merge<O>(&self, _key: &str, _incoming: O, _resolver: impl Fn(O, O) -> O + Send + Sync) -> anyhow::Result<O>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Clone + Send + Sync,
+    {
+        denied("merge")
+    }
+
+    // locks coordinate workers and change no objects, so reading clients can take them
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        self.inner.try_lock(name, ttl).await
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, _from: &str, _to: &str) -> anyhow::Result<bool> {
+        denied("copy")
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, _from: &str, _to: &str) -> anyhow::Result<bool> {
+        denied("rename")
+    }
+
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.scan::<O>()
+    }
+
+    fn stream_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        self.inner.stream_query::<O>(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_only_storage_client_rejects_mutations() {
+        let inner = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://read_only").unwrap()).await.unwrap();
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        inner.put("test_key", obj.clone()).await.unwrap();
+        let client = ReadOnlyStorageClient::new(inner);
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj.clone()));
+
+        let error = client.put("test_key", obj).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<ReadOnlyError>(), Some(&ReadOnlyError { operation: "put" }));
+        assert!(client.delete::<TestObject>("test_key").await.unwrap_err().is::<ReadOnlyError>());
+        assert!(StorageClient::<JsonStorageFormat>::delete_all(&client).await.unwrap_err().is::<ReadOnlyError>());
+
+        assert!(client.inner().get::<TestObject>("test_key").await.unwrap().is_some());
+    }
+}
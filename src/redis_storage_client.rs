@@ -0,0 +1,275 @@
+use std::{collections::{HashMap, HashSet}, marker::PhantomData};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{HealthStatus, StorageClient, StorageFormat, StorageObject};
+
+const SCAN_COUNT: usize = 1000;
+
+/// Stores objects in Redis under `type_name:key`.
+/// - `redis://host:port/db` selects the server and database
+/// - an optional `prefix` query parameter namespaces every key as `prefix:type_name:key`
+pub struct RedisStorageClient<F: StorageFormat> {
+    connection: ConnectionManager,
+    prefix: String,
+    _formatter: PhantomData<F>,
+}
+
+/// Composes the redis key for an object
+/// - `prefix:type_name:key`, or `type_name:key` without a prefix
+fn redis_key(prefix: &str, type_name: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        format!("{}:{}", type_name, key)
+    } else {
+        format!("{}:{}:{}", prefix, type_name, key)
+    }
+}
+
+impl<F: StorageFormat> RedisStorageClient<F> {
+
+    /// Deletes every key matching the glob `pattern` using SCAN so the server is never blocked
+    /// - Returns the number of deleted keys
+    async fn delete_matching(&self, pattern: &str) -> anyhow::Result<usize> {
+        let mut conn = self.connection.clone();
+        let mut cursor: u64 = 0;
+        let mut deleted = 0;
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut conn)
+                .await
+                .with_context(|| format!("Failed to scan keys matching: {}", pattern))?;
+
+            if !keys.is_empty() {
+                let removed: usize = conn.del(&keys).await.with_context(|| {
+                    format!("Failed to delete keys matching: {}", pattern)
+                })?;
+                deleted += removed;
+            }
+
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+        Ok(deleted)
+    }
+
+    /// Every key matching the glob `pattern`, collected with SCAN so the server is never blocked
+    /// - SCAN may return a key more than once while the keyspace changes, each key is kept once
+    async fn scan_matching(&self, pattern: &str) -> anyhow::Result<Vec<String>> {
+        let mut conn = self.connection.clone();
+        let mut cursor: u64 = 0;
+        let mut matching = HashSet::new();
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut conn)
+                .await
+                .with_context(|| format!("Failed to scan keys matching: {}", pattern))?;
+            matching.extend(keys);
+
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+        Ok(matching.into_iter().collect())
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for RedisStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let prefix = storage_url.query_pairs()
+            .find(|(name, _)| name == "prefix")
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default();
+
+        let mut connection_url = storage_url.clone();
+        connection_url.set_query(None);
+        let client = redis::Client::open(connection_url.as_str()).with_context(|| {
+            format!("Invalid redis URL: {}", connection_url)
+        })?;
+        let connection = ConnectionManager::new(client).await.with_context(|| {
+            format!("Failed to connect to redis at: {}", connection_url)
+        })?;
+
+        Ok(Self { connection, prefix, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    fn object_path<O: StorageObject>(&self, key: &str) -> String {
+        redis_key(&self.prefix, self.object_directory::<O>(), key)
+    }
+
+    // Redis keys are flat, there is nothing to create
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let mut conn = self.connection.clone();
+        let redis_key = self.object_path::<O>(key);
+        let data: Option<Vec<u8>> = conn.get(&redis_key).await.with_context(|| {
+            format!("Failed to get redis key: {}", redis_key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let mut conn = self.connection.clone();
+        let redis_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let _: () = conn.set(&redis_key, data).await.with_context(|| {
+            format!("Failed to set redis key: {}", redis_key)
+        })?;
+
+        Ok(())
+    }
+
+    // SETNX writes only when the key is missing, in one step on the server
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let mut conn = self.connection.clone();
+        let redis_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let written: bool = conn.set_nx(&redis_key, data).await.with_context(|| {
+            format!("Failed to set redis key: {}", redis_key)
+        })?;
+        Ok(written)
+    }
+
+    // keys of the type are found with SCAN over `type_name:*`, the prefix is cut off again
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let directory = self.object_path::<O>("");
+        let keys = self.scan_matching(&format!("{}*", directory)).await?;
+        Ok(keys.into_iter()
+            .filter_map(|redis_key| redis_key.strip_prefix(&directory).map(str::to_string))
+            .collect())
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut conn = self.connection.clone();
+        let redis_key = self.object_path::<O>(key);
+        let exists: bool = conn.exists(&redis_key).await.with_context(|| {
+            format!("Failed to check redis key: {}", redis_key)
+        })?;
+        Ok(exists)
+    }
+
+    // one MGET for the whole batch, missing keys come back as nil
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut conn = self.connection.clone();
+        let redis_keys: Vec<String> = keys.iter().map(|key| self.object_path::<O>(key)).collect();
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(&redis_keys)
+            .query_async(&mut conn)
+            .await
+            .with_context(|| format!("Failed to get {} redis keys of {}", redis_keys.len(), O::type_name()))?;
+
+        let mut objects = HashMap::new();
+        for (key, data) in keys.iter().zip(values) {
+            if let Some(data) = data {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                objects.insert(key.to_string(), obj);
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut conn = self.connection.clone();
+        let redis_key = self.object_path::<O>(key);
+        let removed: usize = conn.del(&redis_key).await.with_context(|| {
+            format!("Failed to delete redis key: {}", redis_key)
+        })?;
+        Ok(removed > 0)
+    }
+
+    // one DEL for the whole batch, it counts only the keys that existed
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.connection.clone();
+        let redis_keys: Vec<String> = keys.iter().map(|key| self.object_path::<O>(key)).collect();
+        let removed: usize = conn.del(&redis_keys).await.with_context(|| {
+            format!("Failed to delete {} redis keys of {}", redis_keys.len(), O::type_name())
+        })?;
+        Ok(removed)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let pattern = self.object_path::<O>("*");
+        let deleted = self.delete_matching(&pattern).await?;
+        Ok(deleted > 0)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let pattern = if self.prefix.is_empty() {
+            "*".to_string()
+        } else {
+            format!("{}:*", self.prefix)
+        };
+        self.delete_matching(&pattern).await?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        let mut conn = self.connection.clone();
+        match redis::cmd("PING").query_async::<String>(&mut conn).await {
+            Ok(_) => Ok(HealthStatus::Healthy),
+            Err(e) => Ok(HealthStatus::unhealthy(format!("Redis is not reachable: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redis_key;
+
+    #[test]
+    fn test_redis_key() {
+        assert_eq!(redis_key("", "TestObject", "test_key"), "TestObject:test_key");
+        assert_eq!(redis_key("app", "TestObject", "test_key"), "app:TestObject:test_key");
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, StorageClient, StorageFormat, StorageObject};
+
+/// How many replicas have to accept a write for it to succeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationMode {
+    All,
+    Quorum(usize),
+}
+
+/// Writes every change to all replicas and reads from the first replica that answers without an error.
+/// - Writes run concurrently and succeed once `mode` is satisfied, failed replicas are not rolled back
+/// - Objects are formatted once with `F` and the same bytes are stored in every replica through `RawFormat`
+/// - Mixing backend types needs a client enum that implements `StorageClient` for each of them
+pub struct ReplicatedStorageClient<F: StorageFormat, C> {
+    replicas: Vec<C>,
+    required: usize,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> ReplicatedStorageClient<F, C> {
+
+    pub fn new(replicas: Vec<C>, mode: ReplicationMode) -> anyhow::Result<Self> {
+        if replicas.is_empty() {
+            return Err(anyhow::anyhow!("ReplicatedStorageClient needs at least one replica"));
+        }
+        let required = match mode {
+            ReplicationMode::All => replicas.len(),
+            ReplicationMode::Quorum(quorum) if quorum > 0 && quorum <= replicas.len() => quorum,
+            ReplicationMode::Quorum(quorum) => {
+                return Err(anyhow::anyhow!("Quorum of {} is not possible with {} replicas", quorum, replicas.len()));
+            }
+        };
+        Ok(Self { replicas, required, _formatter: PhantomData::<F> })
+    }
+
+    pub fn replicas(&self) -> &[C] {
+        &self.replicas
+    }
+
+    /// Collects the results of a write and fails if fewer than `required` replicas succeeded
+    fn check<R>(&self, operation: &str, results: Vec<anyhow::Result<R>>) -> anyhow::Result<Vec<R>> {
+        let total = results.len();
+        let mut succeeded = Vec::with_capacity(total);
+        let mut first_error = None;
+        for result in results {
+            match result {
+                Ok(value) => succeeded.push(value),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        if succeeded.len() < self.required {
+            let message = format!("{} succeeded on {} of {} replicas, {} required", operation, succeeded.len(), total, self.required);
+            return Err(match first_error {
+                Some(e) => e.context(message),
+                None => anyhow::anyhow!(message),
+            });
+        }
+        Ok(succeeded)
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for ReplicatedStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<RawFormat> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("ReplicatedStorageClient wraps several clients, use ReplicatedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.replicas[0].directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let results = join_all(self.replicas.iter().map(|replica| replica.create_object_directory::<Payload<O>>())).await;
+        self.check("create_object_directory", results)?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let mut payload = None;
+        let mut last_error = None;
+        for replica in &self.replicas {
+            match replica.get::<Payload<O>>(key).await {
+                Ok(value) => {
+                    payload = Some(value);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let payload = match (payload, last_error) {
+            (Some(payload), _) => payload,
+            (None, Some(e)) => {
+                return Err(e.context(format!("Failed to get {} for key: {} from any replica", O::type_name(), key)));
+            }
+            (None, None) => None,
+        };
+
+        match payload {
+            Some(payload) => {
+                let obj = F::deserialize(payload.data()).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let payload = Payload::<O>::new(data);
+
+        let results = join_all(self.replicas.iter().map(|replica| replica.put(key, payload.clone()))).await;
+        self.check(&format!("put {} for key: {}", O::type_name(), key), results)?;
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let results = join_all(self.replicas.iter().map(|replica| replica.delete::<Payload<O>>(key))).await;
+        let deleted = self.check(&format!("delete {} for key: {}", O::type_name(), key), results)?;
+        Ok(deleted.into_iter().any(|deleted| deleted))
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let results = join_all(self.replicas.iter().map(|replica| replica.delete_object_directory::<Payload<O>>())).await;
+        let deleted = self.check("delete_object_directory", results)?;
+        Ok(deleted.into_iter().any(|deleted| deleted))
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let results = join_all(self.replicas.iter().map(|replica| replica.delete_all())).await;
+        self.check("delete_all", results)?;
+        Ok(())
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        let results = join_all(self.replicas.into_iter().map(|replica| replica.close())).await;
+        results.into_iter().collect()
+    }
+
+    // a quorum write can miss some replicas, so the keys of all answering replicas are merged
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let results = join_all(self.replicas.iter().map(|replica| replica.list_keys::<Payload<O>>())).await;
+        let keys: BTreeSet<String> = self.check("list_keys", results)?.into_iter().flatten().collect();
+        Ok(keys.into_iter().collect())
+    }
+
+
+    // like `get`, the first replica that answers decides
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut last_error = None;
+        for replica in &self.replicas {
+            match replica.exists::<Payload<O>>(key).await {
+                Ok(exists) => return Ok(exists),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        match last_error {
+            Some(e) => Err(e.context(format!("Failed to check {} for key: {} on any replica", O::type_name(), key))),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replicated_storage_client_writes_all_replicas() {
+        let mut replicas = Vec::new();
+        for name in ["memory://a", "memory://b", "memory://c"] {
+            replicas.push(MemoryStorageClient::<RawFormat>::init(Url::parse(name).unwrap()).await.unwrap());
+        }
+        assert!(ReplicatedStorageClient::<JsonStorageFormat, MemoryStorageClient<RawFormat>>::new(Vec::new(), ReplicationMode::All).is_err());
+        let client = ReplicatedStorageClient::<JsonStorageFormat, _>::new(replicas, ReplicationMode::Quorum(2)).unwrap();
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+        for replica in client.replicas() {
+            assert!(replica.get::<Payload<TestObject>>("test_key").await.unwrap().is_some());
+        }
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+/// Decides whether a failed operation is worth retrying
+pub type RetryClassifier = fn(&anyhow::Error) -> bool;
+
+/// Retries transient connection failures and nothing else
+/// - io errors like refused or reset connections and timeouts
+/// - sqlx io errors and pool timeouts
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::WouldBlock
+            );
+        }
+        if let Some(e) = cause.downcast_ref::<sqlx::Error>() {
+            return matches!(e, sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed);
+        }
+        cause.is::<tokio::time::error::Elapsed>()
+    })
+}
+
+/// Backoff settings of a `RetryingStorageClient`
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// retries after the first attempt, 0 disables retrying
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// fraction of each backoff that is randomized, 0.0 for none and 1.0 for full jitter
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+
+    /// Delay before retry number `retry`, starting at 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(retry as i32);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::from_secs_f64(backoff * (1.0 - jitter + jitter * random_fraction()))
+    }
+}
+
+/// Random number in [0, 1) without pulling in a rng, `RandomState` is seeded randomly per instance
+fn random_fraction() -> f64 {
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Retries operations of the inner client that fail with a retryable error, waiting with
+/// exponential backoff and jitter between attempts.
+/// - Errors are classified with `is_transient` unless another classifier is set
+/// - The inner client stores the bytes formatted by `F` through `RawFormat`, so a put can be repeated
+pub struct RetryingStorageClient<F: StorageFormat, C> {
+    inner: C,
+    policy: RetryPolicy,
+    classifier: RetryClassifier,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> RetryingStorageClient<F, C> {
+
+    pub fn new(inner: C, policy: RetryPolicy) -> Self {
+        Self { inner, policy, classifier: is_transient, _formatter: PhantomData::<F> }
+    }
+
+    pub fn with_classifier(mut self, classifier: RetryClassifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    async fn retry<R, Fut>(&self, operation: &str, mut f: impl FnMut() -> Fut) -> anyhow::Result<R>
+    where
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let mut retry = 0;
+        loop {
+            match f().await {
+                Ok(result) => return Ok(result),
+                Err(e) if retry < self.policy.max_retries && (self.classifier)(&e) => {
+                    tokio::time::sleep(self.policy.backoff(retry)).await;
+                    retry += 1;
+                }
+                Err(e) if retry > 0 => {
+                    return Err(e.context(format!("Failed to {} after {} retries", operation, retry)));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for RetryingStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<RawFormat> + Send + Sync,
+{
+
+    /// Initializes the inner client with the url and retries with the default `RetryPolicy`
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        Ok(Self::new(C::init(storage_url).await?, RetryPolicy::default()))
+    }
+
+    fn directory(&self) -> &str {
+        self.inner.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.retry("create object directory", || self.inner.create_object_directory::<Payload<O>>()).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let operation = format!("get {} for key: {}", O::type_name(), key);
+        let payload = self.retry(&operation, || self.inner.get::<Payload<O>>(key)).await?;
+
+        match payload {
+            Some(payload) => {
+                let obj = F::deserialize(payload.data()).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let payload = Payload::<O>::new(data);
+
+        let operation = format!("put {} for key: {}", O::type_name(), key);
+        self.retry(&operation, || self.inner.put(key, payload.clone())).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let operation = format!("delete {} for key: {}", O::type_name(), key);
+        self.retry(&operation, || self.inner.delete::<Payload<O>>(key)).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        self.retry("delete object directory", || self.inner.delete_object_directory::<Payload<O>>()).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.retry("delete all", || self.inner.delete_all()).await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        self.inner.close().await
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.retry("list keys", || self.inner.list_keys::<Payload<O>>()).await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.retry("list page", || self.inner.list_page::<Payload<O>>(page.clone())).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.retry("count", || self.inner.count::<Payload<O>>()).await
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.retry("check existence", || self.inner.exists::<Payload<O>>(key)).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.retry("read metadata", || self.inner.metadata::<Payload<O>>(key)).await
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let operation = format!("get {} for {} keys", O::type_name(), keys.len());
+        let payloads = self.retry(&operation, || self.inner.get_many::<Payload<O>>(keys)).await?;
+
+        payloads.into_iter().map(|(key, payload)| {
+            let obj = F::deserialize(payload.data()).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let payloads = items.into_iter().map(|(key, value)| {
+            let data = F::serialize(&value).with_context(|| {
+                format!("Failed to serialize object for key: {}", key)
+            })?;
+            Ok((key, Payload::<O>::new(data)))
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+
+        let operation = format!("put {} {} objects", payloads.len(), O::type_name());
+        self.retry(&operation, || self.inner.put_many(payloads.clone())).await
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        self.retry("delete many", || self.inner.delete_many::<Payload<O>>(keys)).await
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let operation = format!("get {} for key: {}", O::type_name(), key);
+        let versioned = self.retry(&operation, || self.inner.get_versioned::<Payload<O>>(key)).await?;
+
+        match versioned {
+            Some((payload, version)) => {
+                let obj = F::deserialize(payload.data()).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some((obj, version)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // not retried, a write that landed before a lost response would come back as a conflict
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        self.inner.put_if_version(key, Payload::<O>::new(data), expected_version).await
+    }
+
+    // not retried either, a retry after a lost response would report the claim as taken
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        self.inner.put_if_absent(key, Payload::<O>::new(data)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_backoff_and_classification() {
+        let policy = RetryPolicy { jitter: 0.0, ..RetryPolicy::default() };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(20), Duration::from_secs(5));
+
+        let jittered = RetryPolicy { jitter: 1.0, ..RetryPolicy::default() };
+        assert!(jittered.backoff(1) <= Duration::from_millis(200));
+
+        let reset = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset)).context("Failed to get");
+        assert!(is_transient(&reset));
+        let not_found = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(!is_transient(&not_found));
+        assert!(!is_transient(&anyhow::anyhow!("Failed to deserialize")));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use rocksdb::{DBWithThreadMode, MultiThreaded, Options, DEFAULT_COLUMN_FAMILY_NAME};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+type Db = DBWithThreadMode<MultiThreaded>;
+
+/// Stores objects in a local RocksDB database, one column family per object type.
+/// - `file:///path/to/db`, the database is created if it does not exist
+/// - RocksDB calls block, so every operation runs on the blocking thread pool
+pub struct RocksDbStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    db: Arc<Db>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> RocksDbStorageClient<F> {
+
+    async fn run<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&Db) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db)).await?
+    }
+}
+
+fn create_cf_if_not_exists(db: &Db, name: &str) -> anyhow::Result<()> {
+    if db.cf_handle(name).is_none() {
+        db.create_cf(name, &Options::default()).with_context(|| {
+            format!("Failed to create column family: {}", name)
+        })?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for RocksDbStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path().to_string();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+
+        let db = tokio::task::spawn_blocking(move || {
+            let mut options = Options::default();
+            options.create_if_missing(true);
+            options.create_missing_column_families(true);
+            // a new database has no column families to list yet
+            let column_families = Db::list_cf(&options, &path)
+                .unwrap_or_else(|_| vec![DEFAULT_COLUMN_FAMILY_NAME.to_string()]);
+            Db::open_cf(&options, &path, column_families).with_context(|| {
+                format!("Failed to open rocksdb at path: {}", path)
+            })
+        }).await??;
+
+        Ok(Self { storage_url, db: Arc::new(db), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let name = self.object_directory::<O>().to_string();
+        self.run(move |db| create_cf_if_not_exists(db, &name)).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        let data = self.run(move |db| {
+            match db.cf_handle(&name) {
+                Some(cf) => Ok(db.get_cf(&cf, &object_key)?),
+                None => Ok(None),
+            }
+        }).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.run(move |db| {
+            create_cf_if_not_exists(db, &name)?;
+            let cf = db.cf_handle(&name)
+                .ok_or_else(|| anyhow::anyhow!("Column family {} does not exist", name))?;
+            db.put_cf(&cf, &object_key, data)?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.run(move |db| {
+            let cf = match db.cf_handle(&name) {
+                Some(cf) => cf,
+                None => return Ok(false),
+            };
+            if db.get_pinned_cf(&cf, &object_key)?.is_none() {
+                return Ok(false);
+            }
+            db.delete_cf(&cf, &object_key)?;
+            Ok(true)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        self.run(move |db| {
+            if db.cf_handle(&name).is_none() {
+                return Ok(false);
+            }
+            db.drop_cf(&name).with_context(|| {
+                format!("Failed to drop column family: {}", name)
+            })?;
+            Ok(true)
+        }).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let path = self.directory().to_string();
+        self.run(move |db| {
+            let column_families = Db::list_cf(&Options::default(), &path).with_context(|| {
+                format!("Failed to list column families at path: {}", path)
+            })?;
+            for name in column_families {
+                if name != DEFAULT_COLUMN_FAMILY_NAME && db.cf_handle(&name).is_some() {
+                    db.drop_cf(&name).with_context(|| {
+                        format!("Failed to drop column family: {}", name)
+                    })?;
+                }
+            }
+            Ok(())
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rocksdb_storage_client_json() {
+        let test_directory = std::env::temp_dir().join("rocksdb_storage_client_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let url = Url::from_directory_path(&test_directory).expect("Failed to create URL from directory path");
+        let client = RocksDbStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to open rocksdb");
+
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        assert!(client.delete_all().await.is_ok());
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
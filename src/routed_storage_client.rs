@@ -0,0 +1,284 @@
+use std::{collections::{HashMap, HashSet}, marker::PhantomData};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+/// Sends the object types in a routing table to one client and every other type to a default client.
+/// - `RoutedStorageClient::new(postgres, redis, ["Session"])` keeps `Session`s in redis and the rest in postgres
+/// - More than two backends are routed by nesting, the default or routed client can itself be a `RoutedStorageClient`
+pub struct RoutedStorageClient<F: StorageFormat, A, B> {
+    default: A,
+    routed: B,
+    routes: HashSet<String>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, A, B> RoutedStorageClient<F, A, B> {
+
+    pub fn new<I, S>(default: A, routed: B, type_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let routes = type_names.into_iter().map(|type_name| type_name.into()).collect();
+        Self { default, routed, routes, _formatter: PhantomData::<F> }
+    }
+
+    pub fn default_client(&self) -> &A {
+        &self.default
+    }
+
+    pub fn routed_client(&self) -> &B {
+        &self.routed
+    }
+
+    /// Whether objects of type `O` go to the routed client
+    pub fn is_routed<O: StorageObject>(&self) -> bool {
+        self.routes.contains(O::type_name())
+    }
+}
+
+#[async_trait]
+impl<F, A, B> StorageClient<F> for RoutedStorageClient<F, A, B>
+where
+    F: StorageFormat + Send + Sync,
+    A: StorageClient<F> + Send + Sync,
+    B: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("RoutedStorageClient wraps two clients, use RoutedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.default.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        if self.is_routed::<O>() {
+            self.routed.create_object_directory::<O>().await
+        } else {
+            self.default.create_object_directory::<O>().await
+        }
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        if self.is_routed::<O>() {
+            self.routed.get(key).await
+        } else {
+            self.default.get(key).await
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        if self.is_routed::<O>() {
+            self.routed.put(key, value).await
+        } else {
+            self.default.put(key, value).await
+        }
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.delete::<O>(key).await
+        } else {
+            self.default.delete::<O>(key).await
+        }
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.delete_object_directory::<O>().await
+        } else {
+            self.default.delete_object_directory::<O>().await
+        }
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.routed.delete_all().await?;
+        self.default.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        // both are closed even if the first one fails
+        let default = self.default.close().await;
+        let routed = self.routed.close().await;
+        default.and(routed)
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        if self.is_routed::<O>() {
+            self.routed.list_keys::<O>().await
+        } else {
+            self.default.list_keys::<O>().await
+        }
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        if self.is_routed::<O>() {
+            self.routed.list_page::<O>(page).await
+        } else {
+            self.default.list_page::<O>(page).await
+        }
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        if self.is_routed::<O>() {
+            self.routed.count::<O>().await
+        } else {
+            self.default.count::<O>().await
+        }
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.exists::<O>(key).await
+        } else {
+            self.default.exists::<O>(key).await
+        }
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        if self.is_routed::<O>() {
+            self.routed.metadata::<O>(key).await
+        } else {
+            self.default.metadata::<O>(key).await
+        }
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        if self.is_routed::<O>() {
+            self.routed.get_many(keys).await
+        } else {
+            self.default.get_many(keys).await
+        }
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        if self.is_routed::<O>() {
+            self.routed.put_many(items).await
+        } else {
+            self.default.put_many(items).await
+        }
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        if self.is_routed::<O>() {
+            self.routed.delete_many::<O>(keys).await
+        } else {
+            self.default.delete_many::<O>(keys).await
+        }
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        if self.is_routed::<O>() {
+            self.routed.get_versioned(key).await
+        } else {
+            self.default.get_versioned(key).await
+        }
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        if self.is_routed::<O>() {
+            self.routed.put_if_version(key, value, expected_version).await
+        } else {
+            self.default.put_if_version(key, value, expected_version).await
+        }
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.put_if_absent(key, value).await
+        } else {
+            self.default.put_if_absent(key, value).await
+        }
+    }
+
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.copy::<O>(from, to).await
+        } else {
+            self.default.copy::<O>(from, to).await
+        }
+    }
+
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        if self.is_routed::<O>() {
+            self.routed.rename::<O>(from, to).await
+        } else {
+            self.default.rename::<O>(from, to).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Session {
+        key: String,
+    }
+
+    impl StorageObject for Session {
+        fn type_name() -> &'static str {
+            "Session"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Invoice {
+        key: String,
+    }
+
+    impl StorageObject for Invoice {
+        fn type_name() -> &'static str {
+            "Invoice"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routed_storage_client_routes_by_type() {
+        let default = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://default").unwrap()).await.unwrap();
+        let routed = MemoryStorageClient::<JsonStorageFormat>::init(Url::parse("memory://routed").unwrap()).await.unwrap();
+        let client = RoutedStorageClient::<JsonStorageFormat, _, _>::new(default, routed, ["Session"]);
+
+        client.put("s", Session { key: "s".to_string() }).await.unwrap();
+        client.put("i", Invoice { key: "i".to_string() }).await.unwrap();
+
+        assert!(client.routed_client().get::<Session>("s").await.unwrap().is_some());
+        assert!(client.default_client().get::<Session>("s").await.unwrap().is_none());
+        assert!(client.default_client().get::<Invoice>("i").await.unwrap().is_some());
+        assert!(client.routed_client().get::<Invoice>("i").await.unwrap().is_none());
+
+        let session: Option<Session> = client.get("s").await.unwrap();
+        assert_eq!(session, Some(Session { key: "s".to_string() }));
+        assert!(client.delete::<Invoice>("i").await.unwrap());
+    }
+}
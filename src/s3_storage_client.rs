@@ -0,0 +1,505 @@
+use std::{marker::PhantomData, time::SystemTime};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    primitives::ByteStream,
+    types::{Delete, ObjectIdentifier},
+    Client,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{HealthStatus, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageKey, StorageObject, VersionConflictError};
+
+// DeleteObjects accepts at most 1000 keys per request
+const DELETE_BATCH_SIZE: usize = 1000;
+// ListObjectsV2 returns at most 1000 keys per request
+const LIST_PAGE_SIZE: usize = 1000;
+
+/// `key` with the segments "." and ".." percent-encoded as "%2E" and "%2E%2E"
+/// - URLs with such segments are resolved by the console, presigned links in browsers and the
+///   copy source of CopyObject, so the object would be read or copied from another key
+fn dot_segments_escaped(key: &str) -> String {
+    key.split('/')
+        .map(|segment| match segment {
+            "." => "%2E",
+            ".." => "%2E%2E",
+            segment => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `bucket/key` for CopyObject, URL-encoded except for the separating slashes
+fn copy_source(bucket: &str, key: &str) -> String {
+    let mut source = format!("{}/", bucket);
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => source.push(byte as char),
+            _ => source.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    source
+}
+
+/// Stores objects in an S3-compatible bucket (AWS S3, MinIO, R2).
+/// - `s3://bucket/prefix` selects the bucket and the key prefix
+/// - `endpoint`, `region` and `force_path_style` can be passed as query parameters
+///   e.g. `s3://bucket/prefix?endpoint=http://localhost:9000&force_path_style=true`
+pub struct S3StorageClient<F: StorageFormat> {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> S3StorageClient<F> {
+
+    pub fn bucket(&self) -> &str {
+        &self.bucket
+    }
+
+    /// Key prefix under which all objects of type `O` are stored, ending with `/`
+    fn object_prefix<O: StorageObject>(&self) -> String {
+        if self.prefix.is_empty() {
+            format!("{}/", O::type_name())
+        } else {
+            format!("{}/{}/", self.prefix, O::type_name())
+        }
+    }
+
+    async fn exists_key(&self, key: &str) -> anyhow::Result<bool> {
+        match self.client.head_object().bucket(&self.bucket).key(key).send().await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                if e.as_service_error().map(|e| e.is_not_found()).unwrap_or(false) {
+                    Ok(false)
+                } else {
+                    Err(e.into())
+                }
+            }
+        }
+    }
+
+    /// Keys of every object whose key starts with `prefix`
+    async fn list_prefix(&self, prefix: &str) -> anyhow::Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut pages = self.client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.with_context(|| {
+                format!("Failed to list objects with prefix: {}", prefix)
+            })?;
+            keys.extend(page.contents().iter().filter_map(|o| o.key().map(str::to_string)));
+        }
+        Ok(keys)
+    }
+
+    /// Deletes every object whose key starts with `prefix`
+    /// - Returns the number of deleted objects
+    async fn delete_prefix(&self, prefix: &str) -> anyhow::Result<usize> {
+        let keys = self.list_prefix(prefix).await?;
+
+        let deleted = keys.len();
+        for chunk in keys.chunks(DELETE_BATCH_SIZE) {
+            let objects = chunk.iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+            let delete = Delete::builder().set_objects(Some(objects)).quiet(true).build()?;
+            self.client
+                .delete_objects()
+                .bucket(&self.bucket)
+                .delete(delete)
+                .send()
+                .await
+                .with_context(|| format!("Failed to delete objects with prefix: {}", prefix))?;
+        }
+
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for S3StorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        if storage_url.scheme() != "s3" {
+            return Err(anyhow::anyhow!("Storage URL scheme must be s3, got: {}", storage_url.scheme()));
+        }
+        let bucket = match storage_url.host_str() {
+            Some(bucket) if !bucket.is_empty() => bucket.to_string(),
+            _ => return Err(anyhow::anyhow!("Storage URL does not have a bucket")),
+        };
+        let prefix = storage_url.path().trim_matches('/').to_string();
+
+        let mut endpoint = None;
+        let mut region = None;
+        let mut force_path_style = false;
+        for (name, value) in storage_url.query_pairs() {
+            match name.as_ref() {
+                "endpoint" => endpoint = Some(value.to_string()),
+                "region" => region = Some(value.to_string()),
+                "force_path_style" => force_path_style = value.parse().with_context(|| {
+                    format!("Invalid force_path_style value: {}", value)
+                })?,
+                _ => {}
+            }
+        }
+
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
+        if let Some(region) = region {
+            loader = loader.region(aws_config::Region::new(region));
+        }
+        let sdk_config = loader.load().await;
+
+        let mut config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(force_path_style);
+        if let Some(endpoint) = endpoint {
+            config = config.endpoint_url(endpoint);
+        }
+        let client = Client::from_conf(config.build());
+
+        client.head_bucket().bucket(&bucket).send().await.with_context(|| {
+            format!("Failed to access bucket: {}", bucket)
+        })?;
+
+        Ok(Self { client, bucket, prefix, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    fn object_path<O: StorageObject>(&self, key: &str) -> String {
+        format!("{}{}", self.object_prefix::<O>(), key)
+    }
+
+    // tuple keys become nested prefixes as they are, only dot segments cannot be part of a key
+    fn encode_key<K: StorageKey + ?Sized>(&self, key: &K) -> String {
+        dot_segments_escaped(&key.encode())
+    }
+
+    // S3 has no real directories, prefixes exist as soon as an object is put under them
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let object_key = self.object_path::<O>(key);
+        let output = match self.client.get_object().bucket(&self.bucket).key(&object_key).send().await {
+            Ok(output) => output,
+            Err(e) => {
+                if e.as_service_error().map(|e| e.is_no_such_key()).unwrap_or(false) {
+                    return Ok(None);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let data = output.body.collect().await.with_context(|| {
+            format!("Failed to read object body for key: {}", object_key)
+        })?;
+        let obj = F::deserialize(&data.into_bytes()).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let object_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .with_context(|| format!("Failed to put object for key: {}", object_key))?;
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let object_key = self.object_path::<O>(key);
+        // DeleteObject succeeds for missing keys, so check first to report whether it existed
+        if !self.exists_key(&object_key).await? {
+            return Ok(false);
+        }
+
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete object for key: {}", object_key))?;
+
+        Ok(true)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let prefix = self.object_prefix::<O>();
+        let deleted = self.delete_prefix(&prefix).await?;
+        Ok(deleted > 0)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let prefix = if self.prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.prefix)
+        };
+        self.delete_prefix(&prefix).await?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        match self.client.head_bucket().bucket(&self.bucket).send().await {
+            Ok(_) => Ok(HealthStatus::Healthy),
+            Err(e) => Ok(HealthStatus::unhealthy(format!("Bucket {} is not reachable: {}", self.bucket, e))),
+        }
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let prefix = self.object_prefix::<O>();
+        let keys = self.list_prefix(&prefix).await?;
+        Ok(keys.into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .collect())
+    }
+
+
+    // S3 lists keys in ascending order, so the last key of a page is where the next one starts after
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        let prefix = self.object_prefix::<O>();
+        let limit = page.limit.max(1);
+        let mut start_after = page.token.map(|token| format!("{}{}", prefix, token));
+        let mut keys = Vec::new();
+        let mut truncated = true;
+        while truncated && keys.len() < limit {
+            let output = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&prefix)
+                .set_start_after(start_after.clone())
+                .max_keys((limit - keys.len()).min(LIST_PAGE_SIZE) as i32)
+                .send()
+                .await
+                .with_context(|| format!("Failed to list objects with prefix: {}", prefix))?;
+            truncated = output.is_truncated().unwrap_or(false);
+            for key in output.contents().iter().filter_map(|o| o.key()) {
+                start_after = Some(key.to_string());
+                if let Some(key) = key.strip_prefix(&prefix) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+        let next_token = if truncated { keys.last().cloned() } else { None };
+        Ok(Page { items: keys, next_token })
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let path = self.object_path::<O>(key);
+        self.exists_key(&path).await.with_context(|| {
+            format!("Failed to check object at key: {}", path)
+        })
+    }
+
+    // the ETag is the version, S3 checks it with a conditional write
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let object_key = self.object_path::<O>(key);
+        let output = match self.client.get_object().bucket(&self.bucket).key(&object_key).send().await {
+            Ok(output) => output,
+            Err(e) => {
+                if e.as_service_error().map(|e| e.is_no_such_key()).unwrap_or(false) {
+                    return Ok(None);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let version = output.e_tag().map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("Object has no ETag for key: {}", object_key))?;
+        let data = output.body.collect().await.with_context(|| {
+            format!("Failed to read object body for key: {}", object_key)
+        })?;
+        let obj = F::deserialize(&data.into_bytes()).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some((obj, version)))
+    }
+
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let object_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let result = self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .if_match(expected_version)
+            .body(ByteStream::from(data))
+            .send()
+            .await;
+        match result {
+            Ok(output) => output.e_tag().map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("Put returned no ETag for key: {}", object_key)),
+            // 412 when the ETag changed, 404 when the object is gone
+            Err(e) if matches!(e.raw_response().map(|r| r.status().as_u16()), Some(412 | 404)) => {
+                let actual = match self.client.head_object().bucket(&self.bucket).key(&object_key).send().await {
+                    Ok(head) => head.e_tag().map(str::to_string),
+                    Err(_) => None,
+                };
+                Err(VersionConflictError {
+                    type_name: O::type_name(),
+                    key: key.to_string(),
+                    expected: expected_version.to_string(),
+                    actual,
+                }.into())
+            }
+            Err(e) => Err(e).with_context(|| format!("Failed to put object for key: {}", object_key)),
+        }
+    }
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let object_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let result = self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&object_key)
+            .if_none_match("*")
+            .body(ByteStream::from(data))
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            // 412 when the object exists, 409 when a concurrent conditional write won
+            Err(e) if matches!(e.raw_response().map(|r| r.status().as_u16()), Some(412 | 409)) => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to put object for key: {}", object_key)),
+        }
+    }
+
+    // S3 has no rename, only the copy happens inside the bucket
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let from_key = self.object_path::<O>(from);
+        let to_key = self.object_path::<O>(to);
+        if !self.exists_key(&from_key).await? {
+            return Ok(false);
+        }
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(copy_source(&self.bucket, &from_key))
+            .key(&to_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to copy object from key: {} to key: {}", from_key, to_key))?;
+        Ok(true)
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        let object_key = self.object_path::<O>(key);
+        let head = match self.client.head_object().bucket(&self.bucket).key(&object_key).send().await {
+            Ok(head) => head,
+            Err(e) => {
+                if e.as_service_error().map(|e| e.is_not_found()).unwrap_or(false) {
+                    return Ok(None);
+                }
+                return Err(e).with_context(|| format!("Failed to read metadata for key: {}", object_key));
+            }
+        };
+        Ok(Some(ObjectMetadata {
+            size: head.content_length().unwrap_or(0) as u64,
+            created: None,
+            modified: head.last_modified().and_then(|time| SystemTime::try_from(*time).ok()),
+            etag: head.e_tag().map(str::to_string),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use aws_sdk_s3::config::BehaviorVersion;
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject, UnsupportedError};
+
+    use super::*;
+
+    fn offline_client(prefix: &str) -> S3StorageClient<JsonStorageFormat> {
+        let config = aws_sdk_s3::Config::builder().behavior_version(BehaviorVersion::latest()).build();
+        S3StorageClient {
+            client: Client::from_conf(config),
+            bucket: "bucket".to_string(),
+            prefix: prefix.to_string(),
+            _formatter: PhantomData::<JsonStorageFormat>,
+        }
+    }
+
+    #[test]
+    fn test_s3_object_path() {
+        assert_eq!(offline_client("").object_path::<TestObject>("test_key"), "TestObject/test_key");
+        assert_eq!(offline_client("app/data").object_path::<TestObject>("test_key"), "app/data/TestObject/test_key");
+    }
+
+    #[test]
+    fn test_s3_encode_key_escapes_dot_segments() {
+        let client = offline_client("");
+        assert_eq!(client.encode_key(&("tenant", 7u64)), "tenant/7");
+        assert_eq!(client.encode_key(&("..", "a.b")), "%2E%2E/a.b");
+        assert_eq!(client.encode_key("./x/.."), "%2E/x/%2E%2E");
+    }
+
+    #[test]
+    fn test_s3_copy_source_encodes_key() {
+        assert_eq!(copy_source("bucket", "TestObject/plain-key_1.v~2"), "bucket/TestObject/plain-key_1.v~2");
+        assert_eq!(copy_source("bucket", "TestObject/a b+c"), "bucket/TestObject/a%20b%2Bc");
+        assert_eq!(copy_source("bucket", "TestObject/é"), "bucket/TestObject/%C3%A9");
+    }
+
+    // the offline client has no region, so a request fails before reaching the network instead of being unsupported
+    #[tokio::test]
+    async fn test_s3_overrides_conditional_operations() {
+        let client = offline_client("");
+        let object = || TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let errors = [
+            client.metadata::<TestObject>("test_key").await.err(),
+            client.get_versioned::<TestObject>("test_key").await.err(),
+            client.put_if_version("test_key", object(), "\"etag\"").await.err(),
+            client.put_if_absent("test_key", object()).await.err(),
+            client.copy::<TestObject>("test_key", "other_key").await.err(),
+        ];
+        for error in errors {
+            let error = error.expect("offline client should fail");
+            assert!(error.downcast_ref::<UnsupportedError>().is_none(), "{:?}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_s3_init_rejects_invalid_url() {
+        let wrong_scheme = S3StorageClient::<JsonStorageFormat>::init(Url::parse("file:///bucket").unwrap()).await;
+        assert!(wrong_scheme.is_err());
+        let invalid_flag = S3StorageClient::<JsonStorageFormat>::init(Url::parse("s3://bucket?force_path_style=yes").unwrap()).await;
+        assert!(invalid_flag.is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use futures::TryStreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// Lowercased words of `text`, split on anything that is not a letter or digit
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// The text fields of an object joined into one document, fields that are not strings are left out
+pub(crate) fn document_text(fields: &Value, text_fields: &[&str]) -> String {
+    text_fields.iter()
+        .filter_map(|field| fields.get(*field).and_then(|value| value.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A search without an index, scanning every object for documents holding every word of `terms`
+/// - Matches are ordered by how often the words occur, then by key
+pub(crate) async fn search_scan<F, C, O>(client: &C, terms: &str) -> anyhow::Result<Vec<(String, O)>>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Sync + ?Sized,
+    O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+{
+    let text_fields = O::text_fields();
+    if text_fields.is_empty() {
+        return Err(anyhow::anyhow!("{} has no text fields to search", O::type_name()));
+    }
+    let terms = tokenize(terms);
+    let mut matches: Vec<(usize, String, O)> = client.scan::<O>()
+        .try_filter_map(|(key, object)| {
+            let scored = serde_json::to_value(&object).map_err(anyhow::Error::from).map(|fields| {
+                let words = tokenize(&document_text(&fields, &text_fields));
+                let counts: Vec<usize> = terms.iter().map(|term| words.iter().filter(|word| *word == term).count()).collect();
+                (!terms.is_empty() && counts.iter().all(|count| *count > 0)).then(|| (counts.iter().sum(), key, object))
+            });
+            async move { scored }
+        })
+        .try_collect()
+        .await?;
+    matches.sort_by(|(a_score, a_key, _), (b_score, b_key, _)| b_score.cmp(a_score).then_with(|| a_key.cmp(b_key)));
+    Ok(matches.into_iter().map(|(_, key, object)| (key, object)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tokenize_document() {
+        let fields = json!({ "title": "Hello, World", "body": "Storage-clients say hello", "views": 3 });
+        let text = document_text(&fields, &["title", "body", "views"]);
+        assert_eq!(tokenize(&text), vec!["hello", "world", "storage", "clients", "say", "hello"]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::{path::Path, sync::Mutex};
+
+use anyhow::Context;
+use tantivy::{
+    collector::TopDocs,
+    directory::MmapDirectory,
+    doc,
+    query::QueryParser,
+    schema::{Field, Schema, Value, STORED, STRING, TEXT},
+    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term,
+};
+
+/// Memory the single writer thread of a search index buffers documents in, the least tantivy accepts
+const WRITER_MEMORY: usize = 15_000_000;
+
+/// A tantivy index of the text fields of one object type, kept by the file client next to the objects
+/// - One document per key, holding the text fields joined together
+/// - Every change is committed right away so the next search sees it, which makes writes slower
+pub(crate) struct SearchIndex {
+    index: Index,
+    writer: Mutex<IndexWriter>,
+    reader: IndexReader,
+    key: Field,
+    text: Field,
+}
+
+impl SearchIndex {
+
+    /// Opens the index in `directory`, creating it if there is none
+    /// - Only one `SearchIndex` can be open on a directory at a time, tantivy locks its writer
+    pub(crate) fn open(directory: &Path) -> anyhow::Result<Self> {
+        let mut builder = Schema::builder();
+        let key = builder.add_text_field("key", STRING | STORED);
+        let text = builder.add_text_field("text", TEXT);
+        let schema = builder.build();
+
+        std::fs::create_dir_all(directory).with_context(|| {
+            format!("Failed to create directory at path: {}", directory.display())
+        })?;
+        let mmap = MmapDirectory::open(directory).with_context(|| {
+            format!("Failed to open search index at path: {}", directory.display())
+        })?;
+        let index = Index::open_or_create(mmap, schema).with_context(|| {
+            format!("Failed to open search index at path: {}", directory.display())
+        })?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY).context("Failed to create search index writer")?;
+        let reader = index.reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .context("Failed to create search index reader")?;
+        Ok(Self { index, writer: Mutex::new(writer), reader, key, text })
+    }
+
+    /// Replaces the document of `key` with `text`
+    pub(crate) fn upsert(&self, key: &str, text: &str) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.delete_term(Term::from_field_text(self.key, key));
+        writer.add_document(doc!(self.key => key, self.text => text))?;
+        writer.commit().with_context(|| format!("Failed to index key: {}", key))?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    pub(crate) fn remove(&self, key: &str) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.delete_term(Term::from_field_text(self.key, key));
+        writer.commit().with_context(|| format!("Failed to remove key: {} from search index", key))?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Keys of the documents holding every word of `terms`, best match first
+    pub(crate) fn search(&self, terms: &str) -> anyhow::Result<Vec<String>> {
+        let searcher = self.reader.searcher();
+        let mut parser = QueryParser::for_index(&self.index, vec![self.text]);
+        parser.set_conjunction_by_default();
+        let (query, _) = parser.parse_query_lenient(terms);
+        let limit = (searcher.num_docs() as usize).max(1);
+        let found = searcher.search(&query, &TopDocs::with_limit(limit)).context("Failed to search index")?;
+
+        let mut keys = Vec::with_capacity(found.len());
+        for (_, address) in found {
+            let document: TantivyDocument = searcher.doc(address)?;
+            if let Some(key) = document.get_first(self.key).and_then(|value| value.as_str()) {
+                keys.push(key.to_string());
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_search_index_upsert_search_remove() {
+        let directory = std::env::temp_dir().join("search_index_test");
+        let _ = std::fs::remove_dir_all(&directory);
+        let index = SearchIndex::open(&directory).unwrap();
+
+        index.upsert("first", "quick brown fox").unwrap();
+        index.upsert("second", "lazy brown dog").unwrap();
+        assert_eq!(index.search("fox").unwrap(), vec!["first"]);
+        // every word has to match
+        assert_eq!(index.search("brown dog").unwrap(), vec!["second"]);
+
+        // upserting replaces the document of the key
+        index.upsert("first", "slow red fox").unwrap();
+        assert!(index.search("quick").unwrap().is_empty());
+        assert_eq!(index.search("red").unwrap(), vec!["first"]);
+
+        index.remove("first").unwrap();
+        assert!(index.search("fox").unwrap().is_empty());
+        let mut brown = index.search("brown").unwrap();
+        brown.sort();
+        assert_eq!(brown, vec!["second"]);
+
+        drop(index);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::{BTreeMap, HashMap}, marker::PhantomData};
+
+use async_trait::async_trait;
+use futures::future::{join_all, try_join_all};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{hash::fnv1a, ObjectMetadata, StorageClient, StorageFormat, StorageObject};
+
+/// Points each shard gets on the ring, more points spread keys more evenly
+const VIRTUAL_NODES: usize = 160;
+
+/// Position on the ring, FNV-1a with a final mix so keys that only differ in their last bytes
+/// don't all land next to each other
+fn ring_hash(data: &[u8]) -> u64 {
+    let mut hash = fnv1a(data);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+/// A key that is stored on a shard other than the one the ring assigns it to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardMove {
+    pub key: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Spreads keys over several clients with consistent hashing on `{type_name}/{key}`.
+/// - Adding or removing a shard only moves the keys that hashed to it, `plan` and `rebalance`
+///   find and move them
+/// - Shards are identified by name, the name and not the position decides the ring
+pub struct ShardedStorageClient<F: StorageFormat, C> {
+    names: Vec<String>,
+    shards: Vec<C>,
+    ring: BTreeMap<u64, usize>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, C> ShardedStorageClient<F, C> {
+
+    pub fn new(shards: Vec<(String, C)>) -> anyhow::Result<Self> {
+        if shards.is_empty() {
+            return Err(anyhow::anyhow!("ShardedStorageClient needs at least one shard"));
+        }
+
+        let mut ring = BTreeMap::new();
+        let mut names = Vec::with_capacity(shards.len());
+        let mut clients = Vec::with_capacity(shards.len());
+        for (index, (name, client)) in shards.into_iter().enumerate() {
+            if names.contains(&name) {
+                return Err(anyhow::anyhow!("Duplicate shard name: {}", name));
+            }
+            for node in 0..VIRTUAL_NODES {
+                ring.insert(ring_hash(format!("{}#{}", name, node).as_bytes()), index);
+            }
+            names.push(name);
+            clients.push(client);
+        }
+
+        Ok(Self { names, shards: clients, ring, _formatter: PhantomData::<F> })
+    }
+
+    /// Gives the shards back, e.g. to build a client with a different set of shards
+    pub fn into_shards(self) -> Vec<(String, C)> {
+        self.names.into_iter().zip(self.shards).collect()
+    }
+
+    pub fn shards(&self) -> impl Iterator<Item = (&str, &C)> {
+        self.names.iter().map(|name| name.as_str()).zip(self.shards.iter())
+    }
+
+    fn shard_index<O: StorageObject>(&self, key: &str) -> usize {
+        let hash = ring_hash(format!("{}/{}", O::type_name(), key).as_bytes());
+        self.ring
+            .range(hash..)
+            .next()
+            .or_else(|| self.ring.iter().next())
+            .map(|(_, index)| *index)
+            .unwrap_or(0)
+    }
+
+    /// Name of the shard that owns the key
+    pub fn shard_for<O: StorageObject>(&self, key: &str) -> &str {
+        &self.names[self.shard_index::<O>(key)]
+    }
+
+    fn shard<O: StorageObject>(&self, key: &str) -> &C {
+        &self.shards[self.shard_index::<O>(key)]
+    }
+}
+
+impl<F, C> ShardedStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    /// Finds which of `keys` are stored on a shard other than their owner
+    pub async fn plan<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[String]) -> anyhow::Result<Vec<ShardMove>> {
+        let mut moves = Vec::new();
+        for key in keys {
+            let owner = self.shard_index::<O>(key);
+            for (index, shard) in self.shards.iter().enumerate() {
+                if index != owner && shard.get::<O>(key).await?.is_some() {
+                    moves.push(ShardMove {
+                        key: key.clone(),
+                        from: self.names[index].clone(),
+                        to: self.names[owner].clone(),
+                    });
+                }
+            }
+        }
+        Ok(moves)
+    }
+
+    /// Moves every key of `keys` that is on the wrong shard to its owner
+    /// - The copy is written before the misplaced object is deleted, so a failure never loses objects
+    /// - Returns the number of moved objects
+    pub async fn rebalance<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, keys: &[String]) -> anyhow::Result<usize> {
+        let moves = self.plan::<O>(keys).await?;
+        for shard_move in &moves {
+            let index = self.names.iter().position(|name| *name == shard_move.from)
+                .ok_or_else(|| anyhow::anyhow!("Unknown shard: {} for key: {}", shard_move.from, shard_move.key))?;
+            let from = &self.shards[index];
+            let to = self.shard::<O>(&shard_move.key);
+            if let Some(obj) = from.get::<O>(&shard_move.key).await? {
+                to.put(&shard_move.key, obj).await?;
+                from.delete::<O>(&shard_move.key).await?;
+            }
+        }
+        Ok(moves.len())
+    }
+}
+
+#[async_trait]
+impl<F, C> StorageClient<F> for ShardedStorageClient<F, C>
+where
+    F: StorageFormat + Send + Sync,
+    C: StorageClient<F> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("ShardedStorageClient wraps several clients, use ShardedStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.shards[0].directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        try_join_all(self.shards.iter().map(|shard| shard.create_object_directory::<O>())).await?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        self.shard::<O>(key).get(key).await
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        self.shard::<O>(key).put(key, value).await
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.shard::<O>(key).delete::<O>(key).await
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let deleted = try_join_all(self.shards.iter().map(|shard| shard.delete_object_directory::<O>())).await?;
+        Ok(deleted.into_iter().any(|deleted| deleted))
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        try_join_all(self.shards.iter().map(|shard| shard.delete_all())).await?;
+        Ok(())
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        let results = join_all(self.shards.into_iter().map(|shard| shard.close())).await;
+        results.into_iter().collect()
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let keys = try_join_all(self.shards.iter().map(|shard| shard.list_keys::<O>())).await?;
+        Ok(keys.into_iter().flatten().collect())
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        let counts = try_join_all(self.shards.iter().map(|shard| shard.count::<O>())).await?;
+        Ok(counts.into_iter().sum())
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        self.shard::<O>(key).exists::<O>(key).await
+    }
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.shard::<O>(key).metadata::<O>(key).await
+    }
+
+
+    // keys are grouped by shard so that each shard gets a single batch
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let mut batches: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        for key in keys {
+            batches.entry(self.shard_index::<O>(key)).or_default().push(*key);
+        }
+        let results = try_join_all(batches.iter().map(|(index, keys)| self.shards[*index].get_many::<O>(keys))).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let mut batches: BTreeMap<usize, Vec<(String, O)>> = BTreeMap::new();
+        for (key, value) in items {
+            batches.entry(self.shard_index::<O>(&key)).or_default().push((key, value));
+        }
+        try_join_all(batches.into_iter().map(|(index, items)| self.shards[index].put_many(items))).await?;
+        Ok(())
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let mut batches: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        for key in keys {
+            batches.entry(self.shard_index::<O>(key)).or_default().push(*key);
+        }
+        let deleted = try_join_all(batches.iter().map(|(index, keys)| self.shards[*index].delete_many::<O>(keys))).await?;
+        Ok(deleted.into_iter().sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    async fn shard(name: &str) -> (String, MemoryStorageClient<JsonStorageFormat>) {
+        let client = MemoryStorageClient::init(Url::parse(&format!("memory://{}", name)).unwrap()).await.unwrap();
+        (name.to_string(), client)
+    }
+
+    #[tokio::test]
+    async fn test_sharded_storage_client_rebalance() {
+        let client = ShardedStorageClient::<JsonStorageFormat, _>::new(vec![shard("a").await, shard("b").await, shard("c").await]).unwrap();
+
+        let keys: Vec<String> = (0..100).map(|i| format!("key_{}", i)).collect();
+        for key in &keys {
+            client.put(key, TestObject { key: key.clone(), value: "value".to_string() }).await.unwrap();
+        }
+        // every shard owns part of the keys
+        for (name, shard) in client.shards() {
+            let owned = keys.iter().filter(|key| client.shard_for::<TestObject>(key) == name).count();
+            assert!(owned > 0);
+            let mut stored = 0;
+            for key in &keys {
+                if shard.get::<TestObject>(key).await.unwrap().is_some() {
+                    stored += 1;
+                }
+            }
+            assert_eq!(owned, stored);
+        }
+
+        let mut shards = client.into_shards();
+        shards.push(shard("d").await);
+        let client = ShardedStorageClient::<JsonStorageFormat, _>::new(shards).unwrap();
+
+        let moves = client.plan::<TestObject>(&keys).await.unwrap();
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|shard_move| shard_move.to == "d"));
+        assert_eq!(client.rebalance::<TestObject>(&keys).await.unwrap(), moves.len());
+        assert!(client.plan::<TestObject>(&keys).await.unwrap().is_empty());
+
+        for key in &keys {
+            let retrieved: Option<TestObject> = client.get(key).await.unwrap();
+            assert!(retrieved.is_some());
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// Stores objects in a local sled database, one tree per object type.
+/// - `file:///path/to/db`, the database is created if it does not exist
+/// - sled calls block, so every operation runs on the blocking thread pool
+pub struct SledStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    db: sled::Db,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> SledStorageClient<F> {
+
+    async fn run<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&sled::Db) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db)).await?
+    }
+}
+
+/// Opens the tree only if it exists, `open_tree` would otherwise create it
+fn existing_tree(db: &sled::Db, name: &str) -> anyhow::Result<Option<sled::Tree>> {
+    if db.tree_names().iter().any(|tree| tree.as_ref() == name.as_bytes()) {
+        Ok(Some(db.open_tree(name)?))
+    } else {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for SledStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path().to_string();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+
+        let db = tokio::task::spawn_blocking(move || {
+            sled::open(&path).with_context(|| {
+                format!("Failed to open sled database at path: {}", path)
+            })
+        }).await??;
+
+        Ok(Self { storage_url, db, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let name = self.object_directory::<O>().to_string();
+        self.run(move |db| {
+            db.open_tree(&name).with_context(|| {
+                format!("Failed to create tree: {}", name)
+            })?;
+            Ok(())
+        }).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        let data = self.run(move |db| {
+            match existing_tree(db, &name)? {
+                Some(tree) => Ok(tree.get(&object_key)?),
+                None => Ok(None),
+            }
+        }).await.with_context(|| {
+            format!("Failed to get {} for key: {}", O::type_name(), key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.run(move |db| {
+            db.open_tree(&name)?.insert(&object_key, data)?;
+            Ok(())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        let object_key = key.to_string();
+        self.run(move |db| {
+            match existing_tree(db, &name)? {
+                Some(tree) => Ok(tree.remove(&object_key)?.is_some()),
+                None => Ok(false),
+            }
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let name = self.object_directory::<O>().to_string();
+        self.run(move |db| {
+            db.drop_tree(&name).with_context(|| {
+                format!("Failed to drop tree: {}", name)
+            })
+        }).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.run(move |db| {
+            for name in db.tree_names() {
+                // the default tree cannot be dropped, only cleared
+                if name == db.name() {
+                    db.clear()?;
+                } else {
+                    db.drop_tree(&name)?;
+                }
+            }
+            db.flush()?;
+            Ok(())
+        }).await.context("Failed to delete all trees")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sled_storage_client_json() {
+        let test_directory = std::env::temp_dir().join("sled_storage_client_test");
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+        let url = Url::from_directory_path(&test_directory).expect("Failed to create URL from directory path");
+        let client = SledStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to open sled");
+
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        assert!(client.delete_all().await.is_ok());
+        let _ = tokio::fs::remove_dir_all(&test_directory).await;
+    }
+}
@@ -0,0 +1,1355 @@
+use std::{collections::HashMap, marker::PhantomData, time::{Duration, SystemTime}};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use ordermap::OrderMap;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
+    query::Query,
+    Pool, Row, Sqlite,
+};
+use url::Url;
+
+use crate::{
+    content_version, find_by_scan, lease::{epoch_millis, lease_token, LeaseGuard}, HealthStatus, KeyRange, ObjectMetadata, Page, PageRequest, RustStandardType, split_key, StorageClient, StorageFormat, StorageObject,
+    StorageSchema, StorageUsage, TransactionOperation, VersionConflictError,
+    query::{aggregate_scan, delete_where_scan, matches_all, query_stream, sort_groups, Aggregate, AggregateSpec, Filter, QuerySpec, SortOrder},
+};
+
+/// Column holding the formatted object, the schema columns are kept alongside it for querying
+const PAYLOAD_COLUMN: &str = "__payload";
+
+/// Suffix of the table holding the records of `append` for an object type
+const APPENDED_SUFFIX: &str = "__appended";
+
+/// Rows read per query while scanning
+const SCAN_BATCH_SIZE: usize = 500;
+
+/// Table holding the leases of `try_lock`, shared by all object types
+const LEASES_TABLE: &str = "__leases";
+
+/// SQLite column type for a rust standard type
+/// - 128 bit integers do not fit SQLite's 8 byte INTEGER and are stored as TEXT
+pub fn sqlite_type(typ: &RustStandardType) -> &'static str {
+    match typ {
+        RustStandardType::Int8
+        | RustStandardType::Int16
+        | RustStandardType::Int32
+        | RustStandardType::Int64
+        | RustStandardType::UInt8
+        | RustStandardType::UInt16
+        | RustStandardType::UInt32
+        | RustStandardType::UInt64
+        | RustStandardType::ISize
+        | RustStandardType::USize => "INTEGER",
+        RustStandardType::Int128 | RustStandardType::UInt128 => "TEXT",
+        RustStandardType::Float32 | RustStandardType::Float64 => "REAL",
+        RustStandardType::String | RustStandardType::Char => "TEXT",
+        RustStandardType::Bool => "BOOLEAN",
+        RustStandardType::DateTime => "TEXT",
+    }
+}
+
+/// Stores objects in a SQLite database file, one table per object type.
+/// - `sqlite:///path/to/store.db`, the file is created if it does not exist
+/// - Each table has one column per field of `StorageSchema::Standard` plus the formatted object
+pub struct SqliteStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    pool: Pool<Sqlite>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> SqliteStorageClient<F> {
+
+    /// CREATE TABLE IF NOT EXISTS table_name
+    /// - (column_name1 column_type1, column_name2 column_type2, ..., __payload BLOB NOT NULL)
+    /// - PRIMARY KEY (primary_key_name)
+    pub fn create_table_if_not_exists_query<O: StorageObject>() -> anyhow::Result<String> {
+        match O::schema() {
+            StorageSchema::Standard { schema, primary_key } => {
+                let columns: Vec<String> = schema.iter()
+                    .map(|(name, typ)| format!("{} {}", name, sqlite_type(typ)))
+                    .collect();
+                let columns_str = columns.join(", ");
+                Ok(format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({}, {} BLOB NOT NULL, PRIMARY KEY ({}))",
+                    O::type_name(),
+                    columns_str,
+                    PAYLOAD_COLUMN,
+                    primary_key
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Standard"))
+            },
+        }
+    }
+
+    /// CREATE INDEX IF NOT EXISTS table_name_field ON table_name (field), one per indexed field
+    pub fn create_indexes_queries<O: StorageObject>() -> anyhow::Result<Vec<String>> {
+        match O::schema() {
+            StorageSchema::Standard { schema, .. } => O::indexed_fields().into_iter()
+                .map(|field| {
+                    if !schema.contains_key(field) {
+                        return Err(anyhow::anyhow!("Indexed field {} is not a column of {}", field, O::type_name()));
+                    }
+                    Ok(format!("CREATE INDEX IF NOT EXISTS {table}_{field} ON {table} ({field})", table = O::type_name(), field = field))
+                })
+                .collect(),
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Standard"))
+            },
+        }
+    }
+
+    /// INSERT OR REPLACE INTO table_name (column_name1, ..., __payload) VALUES (?, ..., ?)
+    pub fn upsert_query<O: StorageObject>() -> anyhow::Result<String> {
+        Self::insert_query::<O>("INSERT OR REPLACE INTO", "")
+    }
+
+    /// INSERT INTO table_name (column_name1, ..., __payload) VALUES (?, ..., ?) ON CONFLICT DO NOTHING
+    /// - Binds like `upsert_query`, an existing row is left untouched
+    pub fn insert_if_absent_query<O: StorageObject>() -> anyhow::Result<String> {
+        Self::insert_query::<O>("INSERT INTO", " ON CONFLICT DO NOTHING")
+    }
+
+    fn insert_query<O: StorageObject>(insert: &str, conflict: &str) -> anyhow::Result<String> {
+        Self::insert_query_for(O::type_name(), O::schema(), insert, conflict)
+    }
+
+    fn insert_query_for(type_name: &str, schema: StorageSchema, insert: &str, conflict: &str) -> anyhow::Result<String> {
+        match schema {
+            StorageSchema::Standard { schema, .. } => {
+                let mut columns: Vec<&str> = schema.keys().map(|name| name.as_str()).collect();
+                columns.push(PAYLOAD_COLUMN);
+                let placeholders = vec!["?"; columns.len()].join(", ");
+                Ok(format!(
+                    "{} {} ({}) VALUES ({}){}",
+                    insert,
+                    type_name,
+                    columns.join(", "),
+                    placeholders,
+                    conflict
+                ))
+            }
+            _ => {
+                Err(anyhow::anyhow!("Schema is not Standard"))
+            },
+        }
+    }
+
+    fn bind_upsert<'q, O: StorageObject + Serialize>(
+        query_str: &'q str,
+        key: &'q str,
+        value: &O,
+    ) -> anyhow::Result<Query<'q, Sqlite, SqliteArguments<'q>>> {
+        let data = F::serialize(value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let fields = serde_json::to_value(value).with_context(|| {
+            format!("Failed to extract columns of {} for key: {}", O::type_name(), key)
+        })?;
+        Self::bind_row(query_str, O::schema(), key, data, &fields)
+    }
+
+    /// Binds the schema columns from `fields`, the key into the primary key column and the formatted object
+    fn bind_row<'q>(
+        query_str: &'q str,
+        schema: StorageSchema,
+        key: &'q str,
+        data: Vec<u8>,
+        fields: &serde_json::Value,
+    ) -> anyhow::Result<Query<'q, Sqlite, SqliteArguments<'q>>> {
+        let key_columns = schema.key_columns();
+        let key_values = Self::key_values(&schema, key)?;
+        let schema = match schema {
+            StorageSchema::Standard { schema, .. } => schema,
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+
+        let mut query = sqlx::query(query_str);
+        for (name, typ) in schema.iter() {
+            match key_columns.iter().position(|column| column == name) {
+                // the key passed to put always wins over the field value
+                Some(index) => query = query.bind(key_values[index].clone()),
+                None => query = bind_field(query, typ, fields.get(name)),
+            }
+        }
+        Ok(query.bind(data))
+    }
+
+    fn key_expression<O: StorageObject>() -> anyhow::Result<String> {
+        Self::key_expression_for(&O::schema())
+    }
+
+    /// The stored key of a row, the primary key column or the encoded tuple of a composite key
+    /// - Composite keys are compared as their encoded text, which the primary key index cannot answer
+    fn key_expression_for(schema: &StorageSchema) -> anyhow::Result<String> {
+        if !matches!(schema, StorageSchema::Standard { .. }) {
+            return Err(anyhow::anyhow!("Schema is not Standard"));
+        }
+        let columns = schema.key_columns();
+        if let [column] = columns.as_slice() {
+            return Ok(column.clone());
+        }
+        let parts: Vec<String> = columns.iter()
+            .map(|column| format!("replace(replace(CAST({} AS TEXT), '%', '%25'), '/', '%2F')", column))
+            .collect();
+        Ok(parts.join(" || '/' || "))
+    }
+
+    /// The key split into one value per primary key column
+    fn key_values(schema: &StorageSchema, key: &str) -> anyhow::Result<Vec<String>> {
+        let columns = schema.key_columns();
+        if columns.len() == 1 {
+            return Ok(vec![key.to_string()]);
+        }
+        let parts = split_key(key);
+        if parts.len() != columns.len() {
+            return Err(anyhow::anyhow!("Key {} does not have a part for each of the columns {}", key, columns.join(", ")));
+        }
+        Ok(parts)
+    }
+
+    /// Up to `SCAN_BATCH_SIZE` objects following the key `after`, in key order
+    async fn scan_batch<O: StorageObject + DeserializeOwned>(&self, after: Option<&str>) -> anyhow::Result<Vec<(String, O)>> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(Vec::new());
+        }
+        let query = format!(
+            "SELECT CAST({pk} AS TEXT) AS key, {payload} FROM {table} WHERE ? IS NULL OR CAST({pk} AS TEXT) > ? ORDER BY key LIMIT ?",
+            pk = Self::key_expression::<O>()?,
+            payload = PAYLOAD_COLUMN,
+            table = O::type_name()
+        );
+        let rows = sqlx::query(&query)
+            .bind(after)
+            .bind(after)
+            .bind(SCAN_BATCH_SIZE as i64)
+            .fetch_all(&self.pool)
+            .await
+            .with_context(|| format!("Failed to scan {}", O::type_name()))?;
+
+        rows.iter().map(|row| {
+            let key: String = row.try_get(0)?;
+            let data: Vec<u8> = row.try_get(1)?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    /// Rows of `table` and the total size of their formatted objects
+    async fn table_usage(&self, table: &str) -> anyhow::Result<StorageUsage> {
+        let query = format!("SELECT COUNT(*), COALESCE(SUM(length({})), 0) FROM {}", PAYLOAD_COLUMN, table);
+        let (objects, bytes): (i64, i64) = sqlx::query_as(&query)
+            .fetch_one(&self.pool)
+            .await
+            .with_context(|| format!("Failed to read usage of table: {}", table))?;
+        Ok(StorageUsage { objects: objects as u64, bytes: bytes as u64 })
+    }
+
+    async fn table_exists(&self, table: &str) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to look up table: {}", table))?;
+        Ok(row.is_some())
+    }
+}
+
+/// Reads back a column written by `bind_field` as JSON, `null` for NULL
+fn decode_field(row: &SqliteRow, index: usize, typ: &RustStandardType) -> anyhow::Result<serde_json::Value> {
+    // numbers kept as TEXT are parsed back, anything else stays a string
+    let parse = |text: String| serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+    let value = match typ {
+        RustStandardType::Float32 | RustStandardType::Float64 => row.try_get::<Option<f64>, _>(index)?.map(serde_json::Value::from),
+        RustStandardType::Bool => row.try_get::<Option<bool>, _>(index)?.map(serde_json::Value::from),
+        RustStandardType::Int128 | RustStandardType::UInt128 => row.try_get::<Option<String>, _>(index)?.map(parse),
+        RustStandardType::String | RustStandardType::Char | RustStandardType::DateTime => {
+            row.try_get::<Option<String>, _>(index)?.map(serde_json::Value::from)
+        }
+        _ => match row.try_get::<Option<i64>, _>(index) {
+            Ok(value) => value.map(serde_json::Value::from),
+            Err(_) => row.try_get::<Option<String>, _>(index)?.map(parse),
+        },
+    };
+    Ok(value.unwrap_or(serde_json::Value::Null))
+}
+
+/// Binds a field of the serialized object as the SQLite type of its schema column
+fn bind_field<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    typ: &RustStandardType,
+    value: Option<&serde_json::Value>,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let value = value.filter(|v| !v.is_null());
+    match typ {
+        RustStandardType::Float32 | RustStandardType::Float64 => {
+            query.bind(value.and_then(|v| v.as_f64()))
+        }
+        RustStandardType::Bool => query.bind(value.and_then(|v| v.as_bool())),
+        RustStandardType::Int128 | RustStandardType::UInt128 => {
+            query.bind(value.map(|v| v.to_string()))
+        }
+        RustStandardType::String | RustStandardType::Char | RustStandardType::DateTime => {
+            query.bind(value.map(|v| match v.as_str() {
+                Some(s) => s.to_string(),
+                None => v.to_string(),
+            }))
+        }
+        _ => match value.and_then(|v| v.as_i64()) {
+            Some(i) => query.bind(Some(i)),
+            // u64 values above i64::MAX keep their digits as TEXT
+            None => query.bind(value.map(|v| v.to_string())),
+        },
+    }
+}
+
+/// `WHERE` conditions for the filters on schema columns, with the type to bind each value as
+/// - Filters on other fields are returned as they are, to apply to the deserialized objects
+fn compile_filters(schema: &OrderMap<String, RustStandardType>, filters: &[Filter]) -> (Vec<String>, Vec<(RustStandardType, serde_json::Value)>, Vec<Filter>) {
+    let mut conditions = Vec::new();
+    let mut binds = Vec::new();
+    let mut remaining = Vec::new();
+    for filter in filters {
+        let Some(typ) = schema.get(filter.field()) else {
+            remaining.push(filter.clone());
+            continue;
+        };
+        let (operator, value) = match filter {
+            Filter::Eq { value, .. } => ("=", value),
+            Filter::Gt { value, .. } => (">", value),
+            Filter::Gte { value, .. } => (">=", value),
+            Filter::Lt { value, .. } => ("<", value),
+            Filter::Lte { value, .. } => ("<=", value),
+            Filter::In { values, .. } => {
+                if values.is_empty() {
+                    conditions.push("0".to_string());
+                } else {
+                    conditions.push(format!("{} IN ({})", filter.field(), vec!["?"; values.len()].join(", ")));
+                    binds.extend(values.iter().map(|value| (typ.clone(), value.clone())));
+                }
+                continue;
+            }
+        };
+        conditions.push(format!("{} {} ?", filter.field(), operator));
+        binds.push((typ.clone(), value.clone()));
+    }
+    (conditions, binds, remaining)
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for SqliteStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = storage_url.path();
+        if path.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .with_context(|| format!("Failed to open sqlite database at path: {}", path))?;
+
+        Ok(Self { storage_url, pool, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let query = Self::create_table_if_not_exists_query::<O>()?;
+        sqlx::query(&query).execute(&self.pool).await.with_context(|| {
+            format!("Failed to create table for {}", O::type_name())
+        })?;
+        for query in Self::create_indexes_queries::<O>()? {
+            sqlx::query(&query).execute(&self.pool).await.with_context(|| {
+                format!("Failed to create index for {}", O::type_name())
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let query = format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            Self::key_expression::<O>()?
+        );
+        let row = sqlx::query(&query)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        match row {
+            Some(row) => {
+                let data: Vec<u8> = row.try_get(0)?;
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let query_str = Self::upsert_query::<O>()?;
+        let query = Self::bind_upsert(&query_str, key, &value)?;
+        query.execute(&self.pool).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })?;
+
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let query = format!(
+            "DELETE FROM {} WHERE {} = ?",
+            O::type_name(),
+            Self::key_expression::<O>()?
+        );
+        let result = sqlx::query(&query)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to delete {} for key: {}", O::type_name(), key))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let query = format!("DROP TABLE IF EXISTS {}{}", O::type_name(), APPENDED_SUFFIX);
+        sqlx::query(&query).execute(&self.pool).await.with_context(|| {
+            format!("Failed to drop appended table for {}", O::type_name())
+        })?;
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(false);
+        }
+        let query = format!("DROP TABLE {}", O::type_name());
+        sqlx::query(&query).execute(&self.pool).await.with_context(|| {
+            format!("Failed to drop table for {}", O::type_name())
+        })?;
+        Ok(true)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'"
+        )
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to list tables")?;
+
+        // held locks outlive the data, as they do in the other backends
+        for table in tables.into_iter().filter(|table| table != LEASES_TABLE) {
+            let query = format!("DROP TABLE {}", table);
+            sqlx::query(&query).execute(&self.pool).await.with_context(|| {
+                format!("Failed to drop table: {}", table)
+            })?;
+        }
+        Ok(())
+    }
+
+    // waits for checked out connections to be returned, then closes them all
+    async fn close(self) -> anyhow::Result<()> {
+        self.pool.close().await;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> anyhow::Result<HealthStatus> {
+        match sqlx::query("SELECT 1").execute(&self.pool).await {
+            Ok(_) => Ok(HealthStatus::Healthy),
+            Err(e) => Ok(HealthStatus::unhealthy(format!("SQLite database is not reachable: {}", e))),
+        }
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(Vec::new());
+        }
+        let query = format!(
+            "SELECT CAST({} AS TEXT) FROM {}",
+            Self::key_expression::<O>()?,
+            O::type_name()
+        );
+        sqlx::query_scalar(&query).fetch_all(&self.pool).await.with_context(|| {
+            format!("Failed to list keys of {}", O::type_name())
+        })
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(false);
+        }
+        let query = format!(
+            "SELECT 1 FROM {} WHERE {} = ?",
+            O::type_name(),
+            Self::key_expression::<O>()?
+        );
+        let row = sqlx::query(&query)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to check {} for key: {}", O::type_name(), key))?;
+        Ok(row.is_some())
+    }
+
+
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(None);
+        }
+        let query = format!(
+            "SELECT length({}) FROM {} WHERE {} = ?",
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            Self::key_expression::<O>()?
+        );
+        let size: Option<i64> = sqlx::query_scalar(&query)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to read metadata of {} for key: {}", O::type_name(), key))?;
+        Ok(size.map(|size| ObjectMetadata { size: size as u64, created: None, modified: None, etag: None }))
+    }
+
+
+    async fn usage<O: StorageObject>(&self) -> anyhow::Result<StorageUsage> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(StorageUsage::default());
+        }
+        self.table_usage(O::type_name()).await
+    }
+
+    async fn usage_all(&self) -> anyhow::Result<HashMap<String, StorageUsage>> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'"
+        )
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to list tables")?;
+        let mut usages = HashMap::new();
+        for table in tables.into_iter().filter(|table| !table.ends_with(APPENDED_SUFFIX) && table != LEASES_TABLE) {
+            let usage = self.table_usage(&table).await?;
+            usages.insert(table, usage);
+        }
+        Ok(usages)
+    }
+
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let key_expression = Self::key_expression::<O>()?;
+        let placeholders = vec!["?"; keys.len()].join(", ");
+        let query = format!(
+            "SELECT CAST({} AS TEXT), {} FROM {} WHERE {} IN ({})",
+            key_expression,
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            key_expression,
+            placeholders
+        );
+        let mut select = sqlx::query(&query);
+        for key in keys {
+            select = select.bind(*key);
+        }
+        let rows = select.fetch_all(&self.pool).await.with_context(|| {
+            format!("Failed to get {} for {} keys", O::type_name(), keys.len())
+        })?;
+
+        rows.iter().map(|row| {
+            let key: String = row.try_get(0)?;
+            let data: Vec<u8> = row.try_get(1)?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    // keys are compared as text, so integer keys are in string order like on every other client
+    async fn scan_range<O: StorageObject + DeserializeOwned + Send + Sync>(&self, range: impl Into<KeyRange> + Send) -> anyhow::Result<Vec<(String, O)>> {
+        let range = range.into();
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(Vec::new());
+        }
+        let key = format!("CAST({} AS TEXT)", Self::key_expression::<O>()?);
+        let (condition, binds) = range.sql_condition(&key, |_| "?".to_string());
+        let query = format!(
+            "SELECT {key}, {payload} FROM {table} WHERE {condition} ORDER BY {key}",
+            key = key,
+            payload = PAYLOAD_COLUMN,
+            table = O::type_name(),
+            condition = condition
+        );
+        let mut select = sqlx::query(&query);
+        for bind in binds {
+            select = select.bind(bind);
+        }
+        let rows = select.fetch_all(&self.pool).await.with_context(|| {
+            format!("Failed to scan a key range of {}", O::type_name())
+        })?;
+
+        rows.iter().map(|row| {
+            let key: String = row.try_get(0)?;
+            let data: Vec<u8> = row.try_get(1)?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    // any column can be compared in SQL, only fields outside the schema need a scan
+    async fn find_by<O, V>(&self, field: &str, value: &V) -> anyhow::Result<HashMap<String, O>>
+    where
+        O: StorageObject + Serialize + DeserializeOwned + Send + Sync,
+        V: Serialize + Sync + ?Sized,
+    {
+        let value = serde_json::to_value(value).with_context(|| {
+            format!("Failed to serialize value of field {} of {}", field, O::type_name())
+        })?;
+        let typ = match O::schema() {
+            StorageSchema::Standard { schema, .. } => schema.get(field).cloned(),
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        let Some(typ) = typ else {
+            return find_by_scan::<F, Self, O>(self, field, &value).await;
+        };
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(HashMap::new());
+        }
+        let query = format!(
+            "SELECT CAST({} AS TEXT), {} FROM {} WHERE {} = ?",
+            Self::key_expression::<O>()?,
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            field
+        );
+        let rows = bind_field(sqlx::query(&query), &typ, Some(&value))
+            .fetch_all(&self.pool)
+            .await
+            .with_context(|| format!("Failed to find {} by {}", O::type_name(), field))?;
+
+        rows.iter().map(|row| {
+            let key: String = row.try_get(0)?;
+            let data: Vec<u8> = row.try_get(1)?;
+            let obj = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            Ok((key, obj))
+        }).collect()
+    }
+
+    // filters on schema columns become the WHERE clause, the others are applied to the rows it returns,
+    // sorting and paging only happen in SQL when every filter and sorted field is a column
+    async fn execute_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &QuerySpec) -> anyhow::Result<Vec<(String, O)>> {
+        let schema = match O::schema() {
+            StorageSchema::Standard { schema, .. } => schema,
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(Vec::new());
+        }
+        let key_expression = Self::key_expression::<O>()?;
+        let (conditions, binds, remaining) = compile_filters(&schema, &spec.filters);
+        let mut query = format!("SELECT CAST({} AS TEXT) AS key, {} FROM {}", key_expression, PAYLOAD_COLUMN, O::type_name());
+        if !conditions.is_empty() {
+            query = format!("{} WHERE {}", query, conditions.join(" AND "));
+        }
+        let in_sql = remaining.is_empty() && spec.order_by.iter().all(|(field, _)| schema.contains_key(field));
+        if in_sql {
+            let mut order: Vec<String> = spec.order_by.iter()
+                .map(|(field, order)| format!("{} {}", field, if *order == SortOrder::Asc { "ASC" } else { "DESC" }))
+                .collect();
+            order.push("key ASC".to_string());
+            // a negative LIMIT is no limit in SQLite
+            query = format!("{} ORDER BY {} LIMIT ? OFFSET ?", query, order.join(", "));
+        }
+        let mut select = sqlx::query(&query);
+        for (typ, value) in &binds {
+            select = bind_field(select, typ, Some(value));
+        }
+        if in_sql {
+            select = select
+                .bind(spec.limit.map(|limit| limit.min(i64::MAX as usize) as i64).unwrap_or(-1))
+                .bind(spec.offset.min(i64::MAX as usize) as i64);
+        }
+        let rows = select.fetch_all(&self.pool).await.with_context(|| {
+            format!("Failed to query {}", O::type_name())
+        })?;
+
+        let mut objects = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let key: String = row.try_get(0)?;
+            let data: Vec<u8> = row.try_get(1)?;
+            let obj: O = F::deserialize(&data).with_context(|| {
+                format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+            })?;
+            if matches_all(&obj, &remaining)? {
+                objects.push((key, obj));
+            }
+        }
+        if in_sql {
+            return Ok(objects);
+        }
+        spec.page(objects)
+    }
+
+    // one DELETE statement when every filter is on a column
+    async fn delete_where<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, filters: &[Filter]) -> anyhow::Result<usize> {
+        let schema = match O::schema() {
+            StorageSchema::Standard { schema, .. } => schema,
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        let (conditions, binds, remaining) = compile_filters(&schema, filters);
+        if !remaining.is_empty() {
+            return delete_where_scan::<F, Self, O>(self, filters).await;
+        }
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(0);
+        }
+        let mut query = format!("DELETE FROM {}", O::type_name());
+        if !conditions.is_empty() {
+            query = format!("{} WHERE {}", query, conditions.join(" AND "));
+        }
+        let mut delete = sqlx::query(&query);
+        for (typ, value) in &binds {
+            delete = bind_field(delete, typ, Some(value));
+        }
+        let result = delete.execute(&self.pool).await.with_context(|| {
+            format!("Failed to delete matching {}", O::type_name())
+        })?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    // computed in SQL when every filtered, aggregated and grouped field is a column
+    async fn execute_aggregate<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: &AggregateSpec) -> anyhow::Result<Vec<(serde_json::Value, serde_json::Value)>> {
+        let schema = match O::schema() {
+            StorageSchema::Standard { schema, .. } => schema,
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        let in_sql = spec.filters.iter().map(|filter| filter.field())
+            .chain(spec.aggregate.field())
+            .chain(spec.group_by.as_deref())
+            .all(|field| schema.contains_key(field));
+        if !in_sql {
+            return aggregate_scan::<F, Self, O>(self, spec).await;
+        }
+        if !self.table_exists(O::type_name()).await? {
+            let empty = match spec.aggregate {
+                Aggregate::Count => serde_json::Value::from(0),
+                Aggregate::Sum(_) => serde_json::Value::from(0.0),
+                Aggregate::Min(_) | Aggregate::Max(_) => serde_json::Value::Null,
+            };
+            return Ok(if spec.group_by.is_some() { Vec::new() } else { vec![(serde_json::Value::Null, empty)] });
+        }
+
+        let expression = match &spec.aggregate {
+            Aggregate::Count => "COUNT(*)".to_string(),
+            Aggregate::Min(field) => format!("MIN({})", field),
+            Aggregate::Max(field) => format!("MAX({})", field),
+            // TOTAL is 0.0 instead of NULL when nothing is added up
+            Aggregate::Sum(field) => format!("TOTAL({})", field),
+        };
+        let (conditions, binds, _) = compile_filters(&schema, &spec.filters);
+        let mut query = format!("SELECT {}, {} FROM {}", spec.group_by.as_deref().unwrap_or("NULL"), expression, O::type_name());
+        if !conditions.is_empty() {
+            query = format!("{} WHERE {}", query, conditions.join(" AND "));
+        }
+        if let Some(group_by) = &spec.group_by {
+            query = format!("{} GROUP BY {}", query, group_by);
+        }
+        let mut select = sqlx::query(&query);
+        for (typ, value) in &binds {
+            select = bind_field(select, typ, Some(value));
+        }
+        let rows = select.fetch_all(&self.pool).await.with_context(|| {
+            format!("Failed to aggregate {}", O::type_name())
+        })?;
+
+        let mut groups = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let group = match spec.group_by.as_deref().and_then(|field| schema.get(field)) {
+                Some(typ) => decode_field(row, 0, typ)?,
+                None => serde_json::Value::Null,
+            };
+            let value = match &spec.aggregate {
+                Aggregate::Count => serde_json::Value::from(row.try_get::<i64, _>(1)?),
+                Aggregate::Sum(_) => serde_json::Value::from(row.try_get::<f64, _>(1)?),
+                Aggregate::Min(field) | Aggregate::Max(field) => decode_field(row, 1, &schema[field])?,
+            };
+            groups.push((group, value));
+        }
+        sort_groups(&mut groups);
+        Ok(groups)
+    }
+
+    // one transaction for the whole batch instead of a commit per object
+    async fn put_many<O: StorageObject + Serialize + Send + Sync>(&self, items: impl IntoIterator<Item = (String, O)> + Send) -> anyhow::Result<()> {
+        let items: Vec<(String, O)> = items.into_iter().collect();
+        let query_str = Self::upsert_query::<O>()?;
+        let mut transaction = self.pool.begin().await?;
+        for (key, value) in &items {
+            let query = Self::bind_upsert(&query_str, key, value)?;
+            query.execute(&mut *transaction).await.with_context(|| {
+                format!("Failed to put {} for key: {}", O::type_name(), key)
+            })?;
+        }
+        transaction.commit().await.with_context(|| {
+            format!("Failed to commit {} {} objects", items.len(), O::type_name())
+        })?;
+        Ok(())
+    }
+
+
+    // records go to a `{type_name}__appended` table, ordered by an autoincrement sequence
+    async fn append<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let table = format!("{}{}", O::type_name(), APPENDED_SUFFIX);
+        let create = format!(
+            "CREATE TABLE IF NOT EXISTS {} (__seq INTEGER PRIMARY KEY AUTOINCREMENT, __key TEXT NOT NULL, {} BLOB NOT NULL)",
+            table,
+            PAYLOAD_COLUMN
+        );
+        sqlx::query(&create).execute(&self.pool).await.with_context(|| {
+            format!("Failed to create table: {}", table)
+        })?;
+        let insert = format!("INSERT INTO {} (__key, {}) VALUES (?, ?)", table, PAYLOAD_COLUMN);
+        sqlx::query(&insert)
+            .bind(key)
+            .bind(data)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to append {} for key: {}", O::type_name(), key))?;
+        Ok(())
+    }
+
+    fn read_appended<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> impl Stream<Item = anyhow::Result<O>> + Send
+    where
+        Self: Sync,
+    {
+        let key = key.to_string();
+        stream::once(async move {
+            let table = format!("{}{}", O::type_name(), APPENDED_SUFFIX);
+            if !self.table_exists(&table).await? {
+                return Ok(Vec::new());
+            }
+            let query = format!("SELECT {} FROM {} WHERE __key = ? ORDER BY __seq", PAYLOAD_COLUMN, table);
+            let rows: Vec<Vec<u8>> = sqlx::query_scalar(&query)
+                .bind(&key)
+                .fetch_all(&self.pool)
+                .await
+                .with_context(|| format!("Failed to read appended {} for key: {}", O::type_name(), key))?;
+            rows.into_iter()
+                .map(|data| F::deserialize::<O>(&data).with_context(|| {
+                    format!("Failed to deserialize appended {} for key: {}", O::type_name(), key)
+                }))
+                .collect::<anyhow::Result<Vec<O>>>()
+        })
+            .map_ok(|records| stream::iter(records).map(Ok))
+            .try_flatten()
+    }
+
+    async fn commit_transaction(&self, operations: Vec<TransactionOperation>) -> anyhow::Result<()> {
+        let count = operations.len();
+        let mut transaction = self.pool.begin().await?;
+        for operation in operations {
+            match operation {
+                TransactionOperation::Put { type_name, schema, key, data, fields, .. } => {
+                    let query_str = Self::insert_query_for(type_name, schema(), "INSERT OR REPLACE INTO", "")?;
+                    let query = Self::bind_row(&query_str, schema(), &key, data, &fields)?;
+                    query.execute(&mut *transaction).await.with_context(|| {
+                        format!("Failed to put {} for key: {}", type_name, key)
+                    })?;
+                }
+                TransactionOperation::Delete { type_name, schema, key, .. } => {
+                    let query = format!("DELETE FROM {} WHERE {} = ?", type_name, Self::key_expression_for(&schema())?);
+                    sqlx::query(&query).bind(&key).execute(&mut *transaction).await.with_context(|| {
+                        format!("Failed to delete {} for key: {}", type_name, key)
+                    })?;
+                }
+            }
+        }
+        // dropping the transaction on an error above rolls it back
+        transaction.commit().await.with_context(|| {
+            format!("Failed to commit transaction of {} operations", count)
+        })?;
+        Ok(())
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        if keys.is_empty() || !self.table_exists(O::type_name()).await? {
+            return Ok(0);
+        }
+        let placeholders = vec!["?"; keys.len()].join(", ");
+        let query = format!(
+            "DELETE FROM {} WHERE {} IN ({})",
+            O::type_name(),
+            Self::key_expression::<O>()?,
+            placeholders
+        );
+        let mut delete = sqlx::query(&query);
+        for key in keys {
+            delete = delete.bind(*key);
+        }
+        let result = delete.execute(&self.pool).await.with_context(|| {
+            format!("Failed to delete {} for {} keys", O::type_name(), keys.len())
+        })?;
+        Ok(result.rows_affected() as usize)
+    }
+
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let query = format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            PAYLOAD_COLUMN,
+            O::type_name(),
+            Self::key_expression::<O>()?
+        );
+        let data: Option<Vec<u8>> = sqlx::query_scalar(&query)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some((obj, content_version(&data))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // the old row is only deleted if its payload is still the one that was compared,
+    // so a concurrent write in between makes the transaction a conflict instead of a lost update
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let key_expression = Self::key_expression::<O>()?;
+        let conflict = |actual: Option<String>| -> anyhow::Error {
+            VersionConflictError {
+                type_name: O::type_name(),
+                key: key.to_string(),
+                expected: expected_version.to_string(),
+                actual,
+            }.into()
+        };
+
+        let mut transaction = self.pool.begin().await?;
+        let select = format!("SELECT {} FROM {} WHERE {} = ?", PAYLOAD_COLUMN, O::type_name(), key_expression);
+        let current: Option<Vec<u8>> = sqlx::query_scalar(&select)
+            .bind(key)
+            .fetch_optional(&mut *transaction)
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+        let current = match current {
+            Some(current) => current,
+            None => return Err(conflict(None)),
+        };
+        let actual = content_version(&current);
+        if actual != expected_version {
+            return Err(conflict(Some(actual)));
+        }
+
+        let delete = format!("DELETE FROM {} WHERE {} = ? AND {} = ?", O::type_name(), key_expression, PAYLOAD_COLUMN);
+        let deleted = sqlx::query(&delete)
+            .bind(key)
+            .bind(&current)
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| format!("Failed to replace {} for key: {}", O::type_name(), key))?;
+        if deleted.rows_affected() == 0 {
+            return Err(conflict(None));
+        }
+
+        let upsert = Self::upsert_query::<O>()?;
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        Self::bind_upsert(&upsert, key, &value)?
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+        transaction.commit().await.with_context(|| {
+            format!("Failed to commit {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(content_version(&data))
+    }
+
+
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let query_str = Self::insert_if_absent_query::<O>()?;
+        let result = Self::bind_upsert(&query_str, key, &value)?
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+
+    // leases are rows of a `__leases` table, an expired row is taken over by the same upsert that creates one
+    async fn try_lock(&self, name: &str, ttl: Duration) -> anyhow::Result<Option<LeaseGuard>> {
+        let create = format!(
+            "CREATE TABLE IF NOT EXISTS {} (name TEXT PRIMARY KEY, token TEXT NOT NULL, expires INTEGER NOT NULL)",
+            LEASES_TABLE
+        );
+        sqlx::query(&create).execute(&self.pool).await.with_context(|| {
+            format!("Failed to create table: {}", LEASES_TABLE)
+        })?;
+
+        let token = lease_token();
+        let now = SystemTime::now();
+        let expires = now + ttl;
+        let acquire = format!(
+            "INSERT INTO {table} (name, token, expires) VALUES (?, ?, ?) \
+             ON CONFLICT(name) DO UPDATE SET token = excluded.token, expires = excluded.expires WHERE {table}.expires <= ?",
+            table = LEASES_TABLE
+        );
+        let result = sqlx::query(&acquire)
+            .bind(name)
+            .bind(&token)
+            .bind(epoch_millis(expires) as i64)
+            .bind(epoch_millis(now) as i64)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to acquire lock: {}", name))?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let pool = self.pool.clone();
+        let (lease_name, lease_token) = (name.to_string(), token.clone());
+        Ok(Some(LeaseGuard::new(name, token, expires, async move {
+            let release = format!("DELETE FROM {} WHERE name = ? AND token = ?", LEASES_TABLE);
+            sqlx::query(&release)
+                .bind(&lease_name)
+                .bind(&lease_token)
+                .execute(&pool)
+                .await
+                .with_context(|| format!("Failed to release lock: {}", lease_name))?;
+            Ok(())
+        })))
+    }
+
+
+    // the row is copied inside the database, with the primary key replaced
+    async fn copy<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        let schema = O::schema();
+        let key_columns = schema.key_columns();
+        let to_values = Self::key_values(&schema, to)?;
+        let key_expression = Self::key_expression_for(&schema)?;
+        let schema = match schema {
+            StorageSchema::Standard { schema, .. } => schema,
+            _ => return Err(anyhow::anyhow!("Schema is not Standard")),
+        };
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(false);
+        }
+        let mut columns: Vec<&str> = schema.keys().map(|name| name.as_str()).collect();
+        columns.push(PAYLOAD_COLUMN);
+        let values: Vec<&str> = columns.iter()
+            .map(|name| if key_columns.iter().any(|column| column == name) { "?" } else { *name })
+            .collect();
+        let query = format!(
+            "INSERT OR REPLACE INTO {table} ({}) SELECT {} FROM {table} WHERE {} = ?",
+            columns.join(", "),
+            values.join(", "),
+            key_expression,
+            table = O::type_name()
+        );
+        // the placeholders follow the schema order of the key columns
+        let mut insert = sqlx::query(&query);
+        for name in schema.keys() {
+            if let Some(index) = key_columns.iter().position(|column| column == name) {
+                insert = insert.bind(to_values[index].clone());
+            }
+        }
+        let result = insert
+            .bind(from)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to copy {} from key: {} to key: {}", O::type_name(), from, to))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // a single UPDATE, replacing any row already stored under the new key
+    async fn rename<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, from: &str, to: &str) -> anyhow::Result<bool> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(false);
+        }
+        let schema = O::schema();
+        let assignments: Vec<String> = schema.key_columns().iter().map(|column| format!("{} = ?", column)).collect();
+        let query = format!(
+            "UPDATE OR REPLACE {} SET {} WHERE {} = ?",
+            O::type_name(),
+            assignments.join(", "),
+            Self::key_expression_for(&schema)?
+        );
+        let mut update = sqlx::query(&query);
+        for value in Self::key_values(&schema, to)? {
+            update = update.bind(value);
+        }
+        let result = update
+            .bind(from)
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to rename {} from key: {} to key: {}", O::type_name(), from, to))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+
+    // pages through the table in key order, one query per batch
+    fn scan<O: StorageObject + DeserializeOwned + Send + Sync>(&self) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        stream::unfold(Some(None), move |after: Option<Option<String>>| async move {
+            let after = after?;
+            match self.scan_batch::<O>(after.as_deref()).await {
+                Ok(batch) => {
+                    let next = match batch.last() {
+                        Some((key, _)) if batch.len() == SCAN_BATCH_SIZE => Some(Some(key.clone())),
+                        _ => None,
+                    };
+                    Some((Ok(batch), next))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+        .map_ok(|batch| stream::iter(batch).map(Ok))
+        .try_flatten()
+    }
+
+    // queries SQLite can filter and sort on its own are read `SCAN_BATCH_SIZE` rows per query,
+    // the others go through the default
+    fn stream_query<O: StorageObject + Serialize + DeserializeOwned + Send + Sync>(&self, spec: QuerySpec) -> impl Stream<Item = anyhow::Result<(String, O)>> + Send
+    where
+        Self: Sync,
+    {
+        let in_sql = match O::schema() {
+            StorageSchema::Standard { schema, .. } => {
+                compile_filters(&schema, &spec.filters).2.is_empty()
+                    && spec.order_by.iter().all(|(field, _)| schema.contains_key(field))
+            }
+            _ => false,
+        };
+        if !in_sql {
+            return query_stream::<F, Self, O>(self, spec).left_stream();
+        }
+        let remaining = spec.limit.unwrap_or(usize::MAX);
+        stream::unfold(Some((spec, remaining)), move |state: Option<(QuerySpec, usize)>| async move {
+            let (spec, remaining) = state?;
+            let batch = QuerySpec { limit: Some(remaining.min(SCAN_BATCH_SIZE)), ..spec.clone() };
+            match self.execute_query::<O>(&batch).await {
+                Ok(objects) => {
+                    let read = objects.len();
+                    let next = (read == SCAN_BATCH_SIZE && remaining > read)
+                        .then(|| (QuerySpec { offset: spec.offset + read, ..spec }, remaining - read));
+                    Some((Ok(objects), next))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+        .map_ok(|batch| stream::iter(batch).map(Ok))
+        .try_flatten()
+        .right_stream()
+    }
+
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(Page { items: Vec::new(), next_token: None });
+        }
+        // one row more than the limit tells whether another page follows
+        let limit = page.limit.max(1);
+        let query = format!(
+            "SELECT CAST({pk} AS TEXT) AS key FROM {table} WHERE ? IS NULL OR CAST({pk} AS TEXT) > ? ORDER BY key LIMIT ?",
+            pk = Self::key_expression::<O>()?,
+            table = O::type_name()
+        );
+        let mut keys: Vec<String> = sqlx::query_scalar(&query)
+            .bind(&page.token)
+            .bind(&page.token)
+            .bind(limit as i64 + 1)
+            .fetch_all(&self.pool)
+            .await
+            .with_context(|| format!("Failed to list keys of {}", O::type_name()))?;
+        let next_token = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+        Ok(Page { items: keys, next_token })
+    }
+
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        if !self.table_exists(O::type_name()).await? {
+            return Ok(0);
+        }
+        let query = format!("SELECT COUNT(*) FROM {}", O::type_name());
+        let count: i64 = sqlx::query_scalar(&query).fetch_one(&self.pool).await.with_context(|| {
+            format!("Failed to count {}", O::type_name())
+        })?;
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{json::JsonStorageFormat, RustStandardType, StorageObject, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: i64,
+        value: String,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::Int64);
+            schema.insert("value".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+
+        fn indexed_fields() -> Vec<&'static str> {
+            vec!["value"]
+        }
+    }
+
+    #[test]
+    fn test_create_table_if_not_exists_query() {
+        let query = SqliteStorageClient::<JsonStorageFormat>::create_table_if_not_exists_query::<TestObject>();
+        assert_eq!(
+            query.unwrap(),
+            "CREATE TABLE IF NOT EXISTS TestObject (key INTEGER, value TEXT, __payload BLOB NOT NULL, PRIMARY KEY (key))"
+        );
+        let indexes = SqliteStorageClient::<JsonStorageFormat>::create_indexes_queries::<TestObject>().unwrap();
+        assert_eq!(indexes, vec!["CREATE INDEX IF NOT EXISTS TestObject_value ON TestObject (value)".to_string()]);
+    }
+
+    #[test]
+    fn test_composite_key_expression() {
+        let mut schema = OrderMap::new();
+        schema.insert("tenant_id".to_string(), RustStandardType::String);
+        schema.insert("object_id".to_string(), RustStandardType::Int64);
+        let schema = StorageSchema::Standard { schema, primary_key: "tenant_id, object_id".to_string() };
+        assert_eq!(
+            SqliteStorageClient::<JsonStorageFormat>::key_expression_for(&schema).unwrap(),
+            "replace(replace(CAST(tenant_id AS TEXT), '%', '%25'), '/', '%2F') || '/' || \
+             replace(replace(CAST(object_id AS TEXT), '%', '%25'), '/', '%2F')"
+        );
+        let values = SqliteStorageClient::<JsonStorageFormat>::key_values(&schema, "a%2Fb/7").unwrap();
+        assert_eq!(values, vec!["a/b".to_string(), "7".to_string()]);
+        assert!(SqliteStorageClient::<JsonStorageFormat>::key_values(&schema, "a").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_storage_client_json() {
+        let path = std::env::temp_dir().join("sqlite_storage_client_test").join("store.db");
+        let _ = tokio::fs::remove_file(&path).await;
+        let url = Url::parse(&format!("sqlite://{}", path.display())).expect("Failed to create sqlite URL");
+        let client = SqliteStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to init sqlite client");
+
+        client.create_object_directory::<TestObject>().await.expect("Failed to create table");
+
+        let obj = TestObject { key: 1, value: "test_value".to_string() };
+        client.put("1", obj.clone()).await.expect("Failed to put object");
+
+        let retrieved: Option<TestObject> = client.get("1").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        let missing: Option<TestObject> = client.get("2").await.unwrap();
+        assert!(missing.is_none());
+
+        assert!(client.delete::<TestObject>("1").await.unwrap());
+        assert!(!client.delete::<TestObject>("1").await.unwrap());
+
+        let items = (1..=3).map(|key| (key.to_string(), TestObject { key, value: format!("value_{}", key) }));
+        client.put_many(items).await.expect("Failed to put objects");
+        let objects: HashMap<String, TestObject> = client.get_many(&["1", "3", "4"]).await.unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects["3"].value, "value_3");
+        let page = client.list_page::<TestObject>(PageRequest::new(2)).await.unwrap();
+        assert_eq!(page.items, vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(page.next_token.as_deref(), Some("2"));
+        let scanned: HashMap<String, TestObject> = client.scan::<TestObject>().try_collect().await.unwrap();
+        assert_eq!(scanned.len(), 3);
+        assert_eq!(scanned["2"].value, "value_2");
+        let found: HashMap<String, TestObject> = client.find_by("value", "value_3").await.unwrap();
+        assert_eq!(found.keys().collect::<Vec<_>>(), vec!["3"]);
+        let queried = client.query::<TestObject>().gte("key", 2).is_in("value", ["value_1", "value_2"]).fetch().await.unwrap();
+        assert_eq!(queried.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["2"]);
+        let page = client.query::<TestObject>().order_by("value", SortOrder::Desc).offset(1).limit(1).fetch().await.unwrap();
+        assert_eq!(page.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["2"]);
+        let streamed: Vec<(String, TestObject)> = client.query::<TestObject>().gte("key", 2).order_by("value", SortOrder::Desc).stream().try_collect().await.unwrap();
+        assert_eq!(streamed.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["3", "2"]);
+        assert_eq!(client.query::<TestObject>().gte("key", 2).count().await.unwrap(), 2);
+        assert_eq!(client.query::<TestObject>().sum("key").await.unwrap(), 6.0);
+        assert_eq!(client.query::<TestObject>().max("value").await.unwrap(), Some(serde_json::json!("value_3")));
+        let groups = client.query::<TestObject>().group_by("value").count().await.unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0], (serde_json::json!("value_1"), 1));
+        let range: Vec<(String, TestObject)> = client.scan_range("2"..).await.unwrap();
+        assert_eq!(range.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["2", "3"]);
+        let range: Vec<(String, TestObject)> = client.scan_range("1"..="2").await.unwrap();
+        assert_eq!(range.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(client.query::<TestObject>().gt("key", 2).delete().await.unwrap(), 1);
+        assert!(!client.exists::<TestObject>("3").await.unwrap());
+        assert_eq!(client.delete_many::<TestObject>(&["1", "2", "9"]).await.unwrap(), 2);
+        assert!(!client.exists::<TestObject>("2").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        let lease = client.try_lock("job", Duration::from_secs(5)).await.unwrap().unwrap();
+        assert!(client.try_lock("job", Duration::from_secs(5)).await.unwrap().is_none());
+        lease.release().await.unwrap();
+        let lease = client.lock("job", Duration::from_secs(5)).await.unwrap();
+        lease.release().await.unwrap();
+
+        client.delete_all().await.expect("Failed to delete all");
+        assert_eq!(client.health_check().await.unwrap(), HealthStatus::Healthy);
+        client.close().await.expect("Failed to close client");
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{BufReader, BufWriter, Write};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    runtime::{Handle, RuntimeFlavor},
+};
+use tokio_util::io::SyncIoBridge;
+
+use crate::{json::JsonStorageFormat, StorageFormat, StorageObject};
+
+/// A `StorageFormat` that writes to and reads from async streams, for objects too large to buffer.
+/// - The default methods buffer the whole object, formats override them to stream
+#[async_trait]
+pub trait StreamingStorageFormat: StorageFormat {
+
+    async fn serialize_into<T, W>(obj: &T, writer: &mut W) -> anyhow::Result<()>
+    where
+        T: StorageObject + Serialize + Sync,
+        W: AsyncWrite + Unpin + Send,
+    {
+        let data = Self::serialize(obj)?;
+        writer.write_all(&data).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    async fn deserialize_from<T, R>(reader: &mut R) -> anyhow::Result<T>
+    where
+        T: StorageObject + DeserializeOwned,
+        R: AsyncRead + Unpin + Send,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        Self::deserialize(&data)
+    }
+}
+
+/// serde only works on blocking readers and writers, which can be bridged to async streams
+/// only on the multi threaded runtime
+fn can_block_in_place() -> bool {
+    Handle::try_current().is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread)
+}
+
+/// Streams through serde_json on the multi threaded runtime, buffers on the current thread runtime
+#[async_trait]
+impl StreamingStorageFormat for JsonStorageFormat {
+
+    async fn serialize_into<T, W>(obj: &T, writer: &mut W) -> anyhow::Result<()>
+    where
+        T: StorageObject + Serialize + Sync,
+        W: AsyncWrite + Unpin + Send,
+    {
+        if !can_block_in_place() {
+            let data = Self::serialize(obj)?;
+            writer.write_all(&data).await?;
+            writer.flush().await?;
+            return Ok(());
+        }
+
+        let handle = Handle::current();
+        tokio::task::block_in_place(|| {
+            let mut writer = BufWriter::new(SyncIoBridge::new_with_handle(&mut *writer, handle));
+            serde_json::to_writer(&mut writer, obj).with_context(|| {
+                format!("Failed to serialize {}", T::type_name())
+            })?;
+            writer.flush()?;
+            Ok(())
+        })
+    }
+
+    async fn deserialize_from<T, R>(reader: &mut R) -> anyhow::Result<T>
+    where
+        T: StorageObject + DeserializeOwned,
+        R: AsyncRead + Unpin + Send,
+    {
+        if !can_block_in_place() {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).await?;
+            return Self::deserialize(&data);
+        }
+
+        let handle = Handle::current();
+        tokio::task::block_in_place(|| {
+            let reader = BufReader::new(SyncIoBridge::new_with_handle(&mut *reader, handle));
+            serde_json::from_reader(reader).with_context(|| {
+                format!("Failed to deserialize {}", T::type_name())
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ordermap::OrderMap;
+    use serde::Deserialize;
+
+    use crate::{RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct TestObject {
+        key: String,
+        values: Vec<u64>,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("key".to_string(), RustStandardType::String);
+            schema.insert("values".to_string(), RustStandardType::UInt64);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "key".to_string(),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_json_streaming_roundtrip() {
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            values: (0..100_000).collect(),
+        };
+        // a small pipe makes the writer wait for the reader
+        let (mut writer, mut reader) = tokio::io::duplex(1024);
+        let written = obj.clone();
+        let write = tokio::spawn(async move {
+            JsonStorageFormat::serialize_into(&written, &mut writer).await.unwrap();
+        });
+        let retrieved: TestObject = JsonStorageFormat::deserialize_from(&mut reader).await.unwrap();
+        write.await.unwrap();
+        assert_eq!(retrieved, obj);
+    }
+}
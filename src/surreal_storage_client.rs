@@ -0,0 +1,344 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use surrealdb::{
+    engine::any::{self, Any},
+    opt::auth::Root,
+    RecordId, Surreal,
+};
+use url::Url;
+
+use crate::{content_version, json::JsonStorageFormat, StorageClient, StorageFormat, StorageObject, VersionConflictError};
+
+// field every record carries next to the object fields, the `content_version` of the fields
+const VERSION_FIELD: &str = "__version";
+
+/// Stores objects in SurrealDB, one table per object type with the key as record id.
+/// - `ws://user:password@host:8000?ns=namespace&db=database`, any engine url SurrealDB accepts can be used
+/// - Records hold the object fields directly, `F` does not change the stored representation
+/// - Each record also holds `__version`, which `put_if_version` compares in the same statement as it writes
+pub struct SurrealStorageClient<F: StorageFormat = JsonStorageFormat> {
+    db: Surreal<Any>,
+    database: String,
+    _formatter: PhantomData<F>,
+}
+
+/// The engine url without credentials and query, and the namespace and database from `ns` and `db`
+fn connection(storage_url: &Url) -> (Url, String, String) {
+    let mut namespace = "storage".to_string();
+    let mut database = "storage".to_string();
+    for (name, value) in storage_url.query_pairs() {
+        match name.as_ref() {
+            "ns" => namespace = value.to_string(),
+            "db" => database = value.to_string(),
+            _ => {}
+        }
+    }
+
+    let mut endpoint = storage_url.clone();
+    endpoint.set_query(None);
+    let _ = endpoint.set_username("");
+    let _ = endpoint.set_password(None);
+    (endpoint, namespace, database)
+}
+
+/// A record read back with its key and version next to the fields of the object
+#[derive(Deserialize)]
+struct Record<O> {
+    #[serde(rename = "__key", default)]
+    key: String,
+    #[serde(rename = "__version", default)]
+    version: String,
+    #[serde(flatten)]
+    object: O,
+}
+
+/// The content of the record of `value`, the object fields and their version, and the version
+fn record_content<O: StorageObject + Serialize>(key: &str, value: &O) -> anyhow::Result<(serde_json::Value, String)> {
+    let mut content = serde_json::to_value(value).with_context(|| {
+        format!("Failed to serialize object for key: {}", key)
+    })?;
+    let version = content_version(content.to_string().as_bytes());
+    content.as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{} for key: {} is not an object, records hold object fields", O::type_name(), key))?
+        .insert(VERSION_FIELD.to_string(), serde_json::Value::String(version.clone()));
+    Ok((content, version))
+}
+
+impl<F: StorageFormat> SurrealStorageClient<F> {
+
+    /// Record ids of the keys in the table of `O`, bound to select or delete them in one statement
+    fn record_ids<O: StorageObject>(&self, keys: &[&str]) -> Vec<RecordId> {
+        keys.iter().map(|key| RecordId::from_table_key(self.object_directory::<O>(), key.to_string())).collect()
+    }
+
+    /// Version of the stored record, `None` if there is no record under the key
+    async fn stored_version<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut response = self.db
+            .query("SELECT VALUE __version FROM type::thing($tb, $id)")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .await
+            .with_context(|| format!("Failed to get version of {} for key: {}", O::type_name(), key))?;
+        let versions: Vec<Option<String>> = response.take(0)?;
+        Ok(versions.into_iter().next().map(Option::unwrap_or_default))
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for SurrealStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let (endpoint, namespace, database) = connection(&storage_url);
+        let db = any::connect(endpoint.as_str()).await.with_context(|| {
+            format!("Failed to connect to surrealdb at: {}", endpoint)
+        })?;
+        if !storage_url.username().is_empty() {
+            db.signin(Root {
+                username: storage_url.username(),
+                password: storage_url.password().unwrap_or_default(),
+            }).await.context("Failed to sign in to surrealdb")?;
+        }
+        db.use_ns(&namespace).use_db(&database).await.with_context(|| {
+            format!("Failed to use namespace {} and database {}", namespace, database)
+        })?;
+
+        Ok(Self { db, database, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.database
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let query = format!("DEFINE TABLE IF NOT EXISTS {} SCHEMALESS", self.object_directory::<O>());
+        self.db.query(query).await?.check().with_context(|| {
+            format!("Failed to define table for {}", O::type_name())
+        })?;
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let mut response = self.db
+            .query("SELECT * OMIT id, __version FROM type::thing($tb, $id)")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        let obj: Option<O> = response.take(0).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(obj)
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let (content, _) = record_content(key, &value)?;
+
+        self.db
+            .query("UPSERT type::thing($tb, $id) CONTENT $content RETURN NONE")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .bind(("content", content))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+
+        Ok(())
+    }
+
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        let mut response = self.db
+            .query("SELECT VALUE record::id(id) FROM type::table($tb)")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .await
+            .with_context(|| format!("Failed to list keys of {}", O::type_name()))?;
+        let keys: Vec<String> = response.take(0)?;
+        Ok(keys)
+    }
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut response = self.db
+            .query("SELECT VALUE true FROM type::thing($tb, $id)")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .await
+            .with_context(|| format!("Failed to check {} for key: {}", O::type_name(), key))?;
+        let existed: Vec<bool> = response.take(0)?;
+        Ok(!existed.is_empty())
+    }
+
+    // one SELECT over the record ids, missing records are skipped by the database
+    async fn get_many<O: StorageObject + DeserializeOwned + Send + Sync>(&self, keys: &[&str]) -> anyhow::Result<HashMap<String, O>> {
+        let mut response = self.db
+            .query("SELECT *, record::id(id) AS __key OMIT id, __version FROM $ids")
+            .bind(("ids", self.record_ids::<O>(keys)))
+            .await
+            .with_context(|| format!("Failed to get {} records of {}", keys.len(), O::type_name()))?;
+        let records: Vec<Record<O>> = response.take(0).with_context(|| {
+            format!("Failed to deserialize records of {}", O::type_name())
+        })?;
+        Ok(records.into_iter().map(|record| (record.key, record.object)).collect())
+    }
+
+    async fn get_versioned<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<(O, String)>> {
+        let mut response = self.db
+            .query("SELECT * OMIT id FROM type::thing($tb, $id)")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .await
+            .with_context(|| format!("Failed to get {} for key: {}", O::type_name(), key))?;
+
+        let record: Option<Record<O>> = response.take(0).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(record.map(|record| (record.object, record.version)))
+    }
+
+    // UPDATE does not create records and its WHERE is checked on the stored record, so the compare
+    // and the write are one statement
+    async fn put_if_version<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O, expected_version: &str) -> anyhow::Result<String> {
+        let (content, version) = record_content(key, &value)?;
+        let mut response = self.db
+            .query("UPDATE type::thing($tb, $id) CONTENT $content WHERE __version = $expected RETURN VALUE true")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .bind(("content", content))
+            .bind(("expected", expected_version.to_string()))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key))?;
+
+        let written: Vec<bool> = response.take(0)?;
+        if written.is_empty() {
+            return Err(VersionConflictError {
+                type_name: O::type_name(),
+                key: key.to_string(),
+                expected: expected_version.to_string(),
+                actual: self.stored_version::<O>(key).await?,
+            }.into());
+        }
+        Ok(version)
+    }
+
+    // CREATE fails if the record exists, the failure only means the key was taken if the record is there
+    async fn put_if_absent<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<bool> {
+        let (content, _) = record_content(key, &value)?;
+        let created = self.db
+            .query("CREATE type::thing($tb, $id) CONTENT $content RETURN NONE")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .bind(("content", content))
+            .await
+            .and_then(|response| response.check());
+        match created {
+            Ok(_) => Ok(true),
+            Err(_) if self.exists::<O>(key).await? => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to put {} for key: {}", O::type_name(), key)),
+        }
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let mut response = self.db
+            .query("SELECT VALUE true FROM type::thing($tb, $id); DELETE type::thing($tb, $id);")
+            .bind(("tb", self.object_directory::<O>().to_string()))
+            .bind(("id", key.to_string()))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to delete {} for key: {}", O::type_name(), key))?;
+
+        let existed: Vec<bool> = response.take(0)?;
+        Ok(!existed.is_empty())
+    }
+
+    async fn delete_many<O: StorageObject>(&self, keys: &[&str]) -> anyhow::Result<usize> {
+        let mut response = self.db
+            .query("SELECT VALUE true FROM $ids; DELETE $ids;")
+            .bind(("ids", self.record_ids::<O>(keys)))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to delete {} records of {}", keys.len(), O::type_name()))?;
+
+        let existed: Vec<bool> = response.take(0)?;
+        Ok(existed.len())
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let table = self.object_directory::<O>();
+        let mut response = self.db
+            .query(format!("SELECT VALUE true FROM type::table($tb) LIMIT 1; REMOVE TABLE IF EXISTS {};", table))
+            .bind(("tb", table.to_string()))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to remove table: {}", table))?;
+
+        let had_records: Vec<bool> = response.take(0)?;
+        Ok(!had_records.is_empty())
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.db
+            .query(format!("REMOVE DATABASE IF EXISTS {}", self.database))
+            .await?
+            .check()
+            .with_context(|| format!("Failed to remove database: {}", self.database))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ordermap::OrderMap;
+
+    use crate::{RustStandardType, StorageSchema};
+
+    use super::*;
+
+    #[test]
+    fn test_surreal_connection() {
+        let (endpoint, namespace, database) = connection(&Url::parse("ws://root:secret@localhost:8000?ns=app&db=objects").unwrap());
+        assert_eq!(endpoint.as_str(), "ws://localhost:8000/");
+        assert_eq!(namespace, "app");
+        assert_eq!(database, "objects");
+
+        let (endpoint, namespace, database) = connection(&Url::parse("ws://localhost:8000").unwrap());
+        assert_eq!(endpoint.as_str(), "ws://localhost:8000/");
+        assert_eq!((namespace.as_str(), database.as_str()), ("storage", "storage"));
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct TestObject {
+        value: String,
+    }
+
+    impl StorageObject for TestObject {
+        fn type_name() -> &'static str {
+            "TestObject"
+        }
+
+        fn schema() -> StorageSchema {
+            let mut schema = OrderMap::new();
+            schema.insert("value".to_string(), RustStandardType::String);
+            StorageSchema::Standard {
+                schema,
+                primary_key: "value".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_surreal_record_content() {
+        let (content, version) = record_content("a", &TestObject { value: "one".to_string() }).unwrap();
+        assert_eq!(version, content_version(br#"{"value":"one"}"#));
+        assert_eq!(content, serde_json::json!({ "value": "one", "__version": version }));
+
+        let record: Record<TestObject> = serde_json::from_value(serde_json::json!({ "__key": "a", "__version": "1", "value": "one" })).unwrap();
+        assert_eq!((record.key.as_str(), record.version.as_str(), record.object.value.as_str()), ("a", "1", "one"));
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const BLOCK_SIZE: u64 = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarMode {
+    /// Every write fails, the tarball is never touched
+    ReadOnly,
+    /// Puts are appended to an uncompressed tarball, later entries shadow earlier ones.
+    /// Deletes and any write to a compressed tarball repack the whole archive
+    Append,
+}
+
+/// Contents of the tarball, kept in memory after the initial read
+#[derive(Default)]
+struct TarArchive {
+    directories: BTreeSet<String>,
+    entries: BTreeMap<String, Vec<u8>>,
+    compressed: bool,
+    // offset right after the data of the last entry, where appended entries go
+    end_offset: u64,
+}
+
+fn padded(size: u64) -> u64 {
+    size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_start_matches("./").trim_end_matches('/').to_string()
+}
+
+impl TarArchive {
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut archive = TarArchive::default();
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(archive),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut magic = [0u8; 2];
+        archive.compressed = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+        file.seek(SeekFrom::Start(0))?;
+
+        let reader: Box<dyn Read> = if archive.compressed {
+            Box::new(GzDecoder::new(BufReader::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = normalize(&entry.path()?.to_string_lossy());
+            archive.end_offset = entry.raw_file_position() + padded(entry.size());
+            if entry.header().entry_type().is_dir() {
+                archive.directories.insert(name);
+            } else if entry.header().entry_type().is_file() {
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data).with_context(|| {
+                    format!("Failed to read tarball entry: {}", name)
+                })?;
+                archive.entries.insert(name, data);
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Rewrites the whole tarball next to `path` and renames it over
+    fn repack(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let file = File::create(&tmp).with_context(|| {
+            format!("Failed to create tarball at path: {}", tmp.display())
+        })?;
+        if self.compressed {
+            let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+            self.append_all(&mut builder)?;
+            builder.into_inner()?.finish()?;
+        } else {
+            let mut builder = tar::Builder::new(file);
+            self.append_all(&mut builder)?;
+            let mut file = builder.into_inner()?;
+            // the end of archive marker starts right after the last entry
+            self.end_offset = file.stream_position()? - 2 * BLOCK_SIZE;
+            file.flush()?;
+        }
+
+        std::fs::rename(&tmp, path).with_context(|| {
+            format!("Failed to replace tarball at path: {}", path.display())
+        })?;
+        Ok(())
+    }
+
+    fn append_all<W: Write>(&self, builder: &mut tar::Builder<W>) -> anyhow::Result<()> {
+        for directory in &self.directories {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder.append_data(&mut header, format!("{}/", directory), std::io::empty())?;
+        }
+        for (name, data) in &self.entries {
+            append_entry(builder, name, data)?;
+        }
+        Ok(())
+    }
+
+    /// Appends a single entry to an uncompressed tarball, overwriting the old end of archive marker
+    fn append(&mut self, path: &Path, name: &str, data: &[u8]) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        file.set_len(self.end_offset)?;
+        file.seek(SeekFrom::Start(self.end_offset))?;
+
+        let mut builder = tar::Builder::new(file);
+        append_entry(&mut builder, name, data)?;
+        self.end_offset = builder.get_mut().stream_position()?;
+        builder.finish()?;
+        Ok(())
+    }
+}
+
+fn append_entry<W: Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) -> anyhow::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_mode(0o644);
+    header.set_size(data.len() as u64);
+    builder.append_data(&mut header, name, data).with_context(|| {
+        format!("Failed to append tarball entry: {}", name)
+    })?;
+    Ok(())
+}
+
+/// Serves objects out of a tarball (`.tar` or gzip compressed) with entries named `{type_name}/{key}`.
+/// - `file:///path/to/seed.tar.gz?mode=append`, the tarball is opened read-only unless `mode=append`
+/// - The tarball is loaded into memory on init, so reads never touch the disk
+pub struct TarStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    mode: TarMode,
+    archive: Arc<Mutex<TarArchive>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> TarStorageClient<F> {
+
+    pub fn mode(&self) -> TarMode {
+        self.mode
+    }
+
+    fn ensure_writable(&self) -> anyhow::Result<()> {
+        match self.mode {
+            TarMode::ReadOnly => Err(anyhow::anyhow!("Tarball {} is opened read-only", self.storage_url.path())),
+            TarMode::Append => Ok(()),
+        }
+    }
+
+    async fn update<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&mut TarArchive, &Path) -> anyhow::Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.ensure_writable()?;
+        let archive = self.archive.clone();
+        let path = PathBuf::from(self.storage_url.path());
+        tokio::task::spawn_blocking(move || {
+            let mut archive = archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Tarball lock is poisoned"))?;
+            f(&mut archive, &path)
+        }).await?
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for TarStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = PathBuf::from(storage_url.path());
+        if path.as_os_str().is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+        let mode = match storage_url.query_pairs().find(|(name, _)| name == "mode") {
+            Some((_, value)) if value == "append" => TarMode::Append,
+            Some((_, value)) if value == "read_only" => TarMode::ReadOnly,
+            Some((_, value)) => return Err(anyhow::anyhow!("Invalid tarball mode: {}", value)),
+            None => TarMode::ReadOnly,
+        };
+        if let (TarMode::Append, Some(parent)) = (mode, path.parent()) {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+
+        let archive = tokio::task::spawn_blocking(move || {
+            let mut archive = TarArchive::read(&path).with_context(|| {
+                format!("Failed to open tarball at path: {}", path.display())
+            })?;
+            if !archive.compressed {
+                let name = path.to_string_lossy();
+                archive.compressed = name.ends_with(".gz") || name.ends_with(".tgz");
+            }
+            anyhow::Ok(archive)
+        }).await??;
+
+        Ok(Self { storage_url, mode, archive: Arc::new(Mutex::new(archive)), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    /// Entry name inside the tarball
+    fn object_path<O: StorageObject>(&self, key: &str) -> String {
+        format!("{}/{}", self.object_directory::<O>(), key)
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        if self.mode == TarMode::ReadOnly {
+            // reading works without the directory entry, so this is not an error
+            return Ok(());
+        }
+        let directory = self.object_directory::<O>().to_string();
+        self.update(move |archive, path| {
+            if archive.directories.insert(directory) {
+                archive.repack(path)?;
+            }
+            Ok(())
+        }).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let name = self.object_path::<O>(key);
+        let data = {
+            let archive = self.archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Tarball lock is poisoned"))?;
+            archive.entries.get(&name).cloned()
+        };
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let name = self.object_path::<O>(key);
+        self.update(move |archive, path| {
+            if archive.compressed {
+                archive.entries.insert(name, data);
+                archive.repack(path)
+            } else {
+                archive.append(path, &name, &data)?;
+                archive.entries.insert(name, data);
+                Ok(())
+            }
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let name = self.object_path::<O>(key);
+        self.update(move |archive, path| {
+            if archive.entries.remove(&name).is_none() {
+                return Ok(false);
+            }
+            archive.repack(path)?;
+            Ok(true)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let directory = self.object_directory::<O>().to_string();
+        self.update(move |archive, path| {
+            let prefix = format!("{}/", directory);
+            let before = archive.entries.len();
+            archive.entries.retain(|name, _| !name.starts_with(&prefix));
+            let removed = archive.directories.remove(&directory) || archive.entries.len() != before;
+            if removed {
+                archive.repack(path)?;
+            }
+            Ok(removed)
+        }).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.ensure_writable()?;
+        {
+            let mut archive = self.archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Tarball lock is poisoned"))?;
+            let compressed = archive.compressed;
+            *archive = TarArchive { compressed, ..TarArchive::default() };
+        }
+        let path = self.storage_url.path();
+        tokio::fs::remove_file(path).await
+            .or_else(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    Ok(())
+                } else {
+                    Err(e)
+                }
+            })
+            .with_context(|| format!("Failed to remove tarball at path: {}", path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    fn test_object(value: &str) -> TestObject {
+        TestObject {
+            key: "test_key".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    async fn round_trip(file_name: &str) {
+        let path = std::env::temp_dir().join("tar_storage_client_test").join(file_name);
+        let _ = tokio::fs::remove_file(&path).await;
+        let mut url = Url::from_file_path(&path).expect("Failed to create URL from file path");
+        url.set_query(Some("mode=append"));
+        let client = TarStorageClient::<JsonStorageFormat>::init(url.clone()).await.expect("Failed to open tarball");
+
+        client.put("test_key", test_object("first")).await.expect("Failed to put object");
+        client.put("test_key", test_object("second")).await.expect("Failed to overwrite object");
+
+        // the last appended entry wins when the tarball is read again
+        let mut read_only_url = url.clone();
+        read_only_url.set_query(None);
+        let read_only = TarStorageClient::<JsonStorageFormat>::init(read_only_url).await.expect("Failed to reopen tarball");
+        let retrieved: Option<TestObject> = read_only.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(test_object("second")));
+        assert!(read_only.put("other_key", test_object("third")).await.is_err());
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        let reopened = TarStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to reopen tarball");
+        let missing: Option<TestObject> = reopened.get("test_key").await.unwrap();
+        assert!(missing.is_none());
+
+        assert!(client.delete_all().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tar_storage_client_json() {
+        round_trip("store.tar").await;
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz_storage_client_json() {
+        round_trip("store.tar.gz").await;
+    }
+}
@@ -0,0 +1,56 @@
+use ordermap::OrderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{RustStandardType, StorageObject, StorageSchema};
+
+/// Object with a string key and value shared by the client and format tests
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct TestObject {
+    pub(crate) key: String,
+    pub(crate) value: String,
+}
+
+impl StorageObject for TestObject {
+    fn type_name() -> &'static str {
+        "TestObject"
+    }
+
+    fn schema() -> StorageSchema {
+        string_schema()
+    }
+}
+
+/// Same shape as `TestObject`, with `value` indexed for `find_by` and searchable with `search`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct IndexedTestObject {
+    pub(crate) key: String,
+    pub(crate) value: String,
+}
+
+impl StorageObject for IndexedTestObject {
+    fn type_name() -> &'static str {
+        "IndexedTestObject"
+    }
+
+    fn schema() -> StorageSchema {
+        string_schema()
+    }
+
+    fn indexed_fields() -> Vec<&'static str> {
+        vec!["value"]
+    }
+
+    fn text_fields() -> Vec<&'static str> {
+        vec!["value"]
+    }
+}
+
+fn string_schema() -> StorageSchema {
+    let mut schema = OrderMap::new();
+    schema.insert("key".to_string(), RustStandardType::String);
+    schema.insert("value".to_string(), RustStandardType::String);
+    StorageSchema::Standard {
+        schema,
+        primary_key: "key".to_string(),
+    }
+}
@@ -0,0 +1,172 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+
+use crate::{raw::{Payload, RawFormat}, ObjectMetadata, Page, PageRequest, StorageClient, StorageFormat, StorageObject};
+
+/// Reads from a fast tier first and falls back to a durable tier, copying objects found only in
+/// the durable tier into the fast tier.
+/// - Writes go to the durable tier first, then the fast tier, so the durable tier is the source of truth
+/// - Both tiers store the bytes formatted by `F` through `RawFormat`
+pub struct TieredStorageClient<F: StorageFormat, A, B> {
+    fast: A,
+    durable: B,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat, A, B> TieredStorageClient<F, A, B> {
+
+    pub fn new(fast: A, durable: B) -> Self {
+        Self { fast, durable, _formatter: PhantomData::<F> }
+    }
+
+    pub fn fast(&self) -> &A {
+        &self.fast
+    }
+
+    pub fn durable(&self) -> &B {
+        &self.durable
+    }
+}
+
+#[async_trait]
+impl<F, A, B> StorageClient<F> for TieredStorageClient<F, A, B>
+where
+    F: StorageFormat + Send + Sync,
+    A: StorageClient<RawFormat> + Send + Sync,
+    B: StorageClient<RawFormat> + Send + Sync,
+{
+
+    async fn init(_storage_url: Url) -> anyhow::Result<Self> {
+        Err(anyhow::anyhow!("TieredStorageClient wraps two clients, use TieredStorageClient::new"))
+    }
+
+    fn directory(&self) -> &str {
+        self.durable.directory()
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        self.durable.create_object_directory::<Payload<O>>().await?;
+        self.fast.create_object_directory::<Payload<O>>().await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let payload = match self.fast.get::<Payload<O>>(key).await? {
+            Some(payload) => payload,
+            None => match self.durable.get::<Payload<O>>(key).await? {
+                Some(payload) => {
+                    // a failed promotion only costs another durable read next time
+                    let _ = self.fast.put(key, payload.clone()).await;
+                    payload
+                }
+                None => return Ok(None),
+            },
+        };
+
+        let obj = F::deserialize(payload.data()).with_context(|| {
+            format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+        })?;
+        Ok(Some(obj))
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let payload = Payload::<O>::new(data);
+
+        self.durable.put(key, payload.clone()).await?;
+        if let Err(e) = self.fast.put(key, payload).await {
+            // never leave the previous value readable from the fast tier
+            let _ = self.fast.delete::<Payload<O>>(key).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let fast = self.fast.delete::<Payload<O>>(key).await?;
+        let durable = self.durable.delete::<Payload<O>>(key).await?;
+        Ok(fast || durable)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let fast = self.fast.delete_object_directory::<Payload<O>>().await?;
+        let durable = self.durable.delete_object_directory::<Payload<O>>().await?;
+        Ok(fast || durable)
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.fast.delete_all().await?;
+        self.durable.delete_all().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        // both are closed even if the first one fails
+        let fast = self.fast.close().await;
+        let durable = self.durable.close().await;
+        fast.and(durable)
+    }
+
+    // every object is written to the durable tier first, so it holds all keys
+    async fn list_keys<O: StorageObject>(&self) -> anyhow::Result<Vec<String>> {
+        self.durable.list_keys::<Payload<O>>().await
+    }
+
+
+    async fn exists<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        if self.fast.exists::<Payload<O>>(key).await? {
+            return Ok(true);
+        }
+        self.durable.exists::<Payload<O>>(key).await
+    }
+
+    // the durable tier holds every object
+    async fn metadata<O: StorageObject>(&self, key: &str) -> anyhow::Result<Option<ObjectMetadata>> {
+        self.durable.metadata::<Payload<O>>(key).await
+    }
+
+    async fn list_page<O: StorageObject>(&self, page: PageRequest) -> anyhow::Result<Page<String>> {
+        self.durable.list_page::<Payload<O>>(page).await
+    }
+
+    async fn count<O: StorageObject>(&self) -> anyhow::Result<u64> {
+        self.durable.count::<Payload<O>>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, memory_storage_client::MemoryStorageClient, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tiered_storage_client_promotes() {
+        let fast = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://fast").unwrap()).await.unwrap();
+        let durable = MemoryStorageClient::<RawFormat>::init(Url::parse("memory://durable").unwrap()).await.unwrap();
+        let client = TieredStorageClient::<JsonStorageFormat, _, _>::new(fast, durable);
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+        assert!(client.durable().get::<Payload<TestObject>>("test_key").await.unwrap().is_some());
+
+        // evict from the fast tier, the next read promotes it again
+        assert!(client.fast().delete::<Payload<TestObject>>("test_key").await.unwrap());
+        let retrieved: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+        assert!(client.fast().get::<Payload<TestObject>>("test_key").await.unwrap().is_some());
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+        let missing: Option<TestObject> = client.get("test_key").await.unwrap();
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use tikv_client::{Key, RawClient};
+use url::Url;
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// Stores objects in TiKV through the raw KV API under `{directory}/{type_name}/{key}`.
+/// - `tikv://pd-host:2379/directory`, more placement driver endpoints can be added with `?pd=host:port`
+pub struct TikvStorageClient<F: StorageFormat> {
+    client: RawClient,
+    prefix: String,
+    _formatter: PhantomData<F>,
+}
+
+/// Key range covering everything under `prefix/`
+/// - `0` is the byte right after `/`, so the range ends just past the last key with the prefix
+fn prefix_range(prefix: &str) -> std::ops::Range<Key> {
+    let start = format!("{}/", prefix);
+    let end = format!("{}0", prefix);
+    Key::from(start)..Key::from(end)
+}
+
+impl<F: StorageFormat> TikvStorageClient<F> {
+
+    /// Deletes every key under `prefix/`
+    /// - Returns false if there was nothing to delete
+    async fn delete_prefix(&self, prefix: &str) -> anyhow::Result<bool> {
+        let existing = self.client.scan(prefix_range(prefix), 1).await.with_context(|| {
+            format!("Failed to scan keys with prefix: {}", prefix)
+        })?;
+        if existing.is_empty() {
+            return Ok(false);
+        }
+        self.client.delete_range(prefix_range(prefix)).await.with_context(|| {
+            format!("Failed to delete keys with prefix: {}", prefix)
+        })?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for TikvStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let host = storage_url.host_str()
+            .ok_or_else(|| anyhow::anyhow!("Storage URL does not have a host"))?;
+        let mut pd_endpoints = vec![format!("{}:{}", host, storage_url.port().unwrap_or(2379))];
+        pd_endpoints.extend(
+            storage_url.query_pairs()
+                .filter(|(name, _)| name == "pd")
+                .map(|(_, value)| value.to_string()),
+        );
+        let prefix = storage_url.path().trim_matches('/').to_string();
+        if prefix.is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a directory"));
+        }
+
+        let client = RawClient::new(pd_endpoints.clone()).await.with_context(|| {
+            format!("Failed to connect to TiKV placement drivers: {:?}", pd_endpoints)
+        })?;
+
+        Ok(Self { client, prefix, _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        &self.prefix
+    }
+
+    // raw keys are flat, the prefix exists as soon as a key is put under it
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let tikv_key = self.object_path::<O>(key);
+        let data = self.client.get(tikv_key.clone()).await.with_context(|| {
+            format!("Failed to get TiKV key: {}", tikv_key)
+        })?;
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let tikv_key = self.object_path::<O>(key);
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        self.client.put(tikv_key.clone(), data).await.with_context(|| {
+            format!("Failed to put TiKV key: {}", tikv_key)
+        })?;
+
+        Ok(())
+    }
+
+    // the raw API has no compare-and-delete without atomic mode, so presence is checked first
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let tikv_key = self.object_path::<O>(key);
+        let existing = self.client.get(tikv_key.clone()).await.with_context(|| {
+            format!("Failed to get TiKV key: {}", tikv_key)
+        })?;
+        if existing.is_none() {
+            return Ok(false);
+        }
+        self.client.delete(tikv_key.clone()).await.with_context(|| {
+            format!("Failed to delete TiKV key: {}", tikv_key)
+        })?;
+        Ok(true)
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let prefix = format!("{}/{}", self.directory(), self.object_directory::<O>());
+        self.delete_prefix(&prefix).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        self.delete_prefix(&self.prefix).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::json::JsonStorageFormat;
+
+    use super::*;
+
+    #[test]
+    fn test_tikv_prefix_range() {
+        let range = prefix_range("app/TestObject");
+        assert!(range.contains(&Key::from("app/TestObject/".to_string())));
+        assert!(range.contains(&Key::from("app/TestObject/test_key".to_string())));
+        assert!(range.contains(&Key::from("app/TestObject/\u{10ffff}".to_string())));
+        // neither the prefix itself nor types sharing its start are covered
+        assert!(!range.contains(&Key::from("app/TestObject".to_string())));
+        assert!(!range.contains(&Key::from("app/TestObjects/test_key".to_string())));
+        assert!(!range.contains(&Key::from("app/TestObject-v2/test_key".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_tikv_init_requires_directory() {
+        let result = TikvStorageClient::<JsonStorageFormat>::init(Url::parse("tikv://localhost:2379").unwrap()).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{StorageFormat, StorageObject, StorageSchema};
+
+/// A write staged in a `Transaction`, applied by the client when the transaction commits
+/// - Objects are formatted when they are staged, so committing cannot fail on formatting
+pub enum TransactionOperation {
+    Put {
+        type_name: &'static str,
+        schema: fn() -> StorageSchema,
+        key: String,
+        /// the formatted object
+        data: Vec<u8>,
+        /// the object as JSON, for clients that store fields in columns
+        fields: serde_json::Value,
+        /// `StorageObject::indexed_fields` of the object type
+        indexed_fields: fn() -> Vec<&'static str>,
+        /// `StorageObject::text_fields` of the object type
+        text_fields: fn() -> Vec<&'static str>,
+    },
+    Delete {
+        type_name: &'static str,
+        schema: fn() -> StorageSchema,
+        key: String,
+        /// `StorageObject::text_fields` of the object type
+        text_fields: fn() -> Vec<&'static str>,
+    },
+}
+
+impl TransactionOperation {
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TransactionOperation::Put { type_name, .. } | TransactionOperation::Delete { type_name, .. } => type_name,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            TransactionOperation::Put { key, .. } | TransactionOperation::Delete { key, .. } => key,
+        }
+    }
+
+    pub fn text_fields(&self) -> Vec<&'static str> {
+        match self {
+            TransactionOperation::Put { text_fields, .. } | TransactionOperation::Delete { text_fields, .. } => text_fields(),
+        }
+    }
+}
+
+/// Collects the puts and deletes of `StorageClient::transaction`, across any object types.
+/// - Nothing is written until the closure returns `Ok`, an error discards every staged write
+/// - Later operations on the same key replace earlier ones when the transaction commits
+/// - Reads inside the closure go to the client and do not see staged writes
+pub struct Transaction<F: StorageFormat> {
+    operations: Arc<Mutex<Vec<TransactionOperation>>>,
+    _formatter: PhantomData<F>,
+}
+
+// a derived Clone would require F: Clone
+impl<F: StorageFormat> Clone for Transaction<F> {
+    fn clone(&self) -> Self {
+        Self { operations: self.operations.clone(), _formatter: PhantomData::<F> }
+    }
+}
+
+impl<F: StorageFormat> Transaction<F> {
+
+    pub(crate) fn new() -> Self {
+        Self { operations: Arc::new(Mutex::new(Vec::new())), _formatter: PhantomData::<F> }
+    }
+
+    fn operations(&self) -> std::sync::MutexGuard<'_, Vec<TransactionOperation>> {
+        self.operations.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Stages a put, formatting the object right away
+    pub fn put<O: StorageObject + Serialize>(&self, key: &str, value: &O) -> anyhow::Result<()> {
+        let data = F::serialize(value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let fields = serde_json::to_value(value).with_context(|| {
+            format!("Failed to extract fields of {} for key: {}", O::type_name(), key)
+        })?;
+        self.operations().push(TransactionOperation::Put {
+            type_name: O::type_name(),
+            schema: O::schema,
+            key: key.to_string(),
+            data,
+            fields,
+            indexed_fields: O::indexed_fields,
+            text_fields: O::text_fields,
+        });
+        Ok(())
+    }
+
+    pub fn delete<O: StorageObject>(&self, key: &str) {
+        self.operations().push(TransactionOperation::Delete {
+            type_name: O::type_name(),
+            schema: O::schema,
+            key: key.to_string(),
+            text_fields: O::text_fields,
+        });
+    }
+
+    /// The staged operations in the order they were staged
+    pub(crate) fn into_operations(self) -> Vec<TransactionOperation> {
+        std::mem::take(&mut *self.operations())
+    }
+}
+
+/// Writes across object types for `StorageClient::put_all`, applied together like a transaction
+/// - `batch.put("order-1", &order)?.put("order-1", &event)?;`
+/// - Objects are formatted as they are added, a later write to the same key replaces an earlier one
+pub struct WriteBatch<F: StorageFormat> {
+    operations: Vec<TransactionOperation>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> Default for WriteBatch<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: StorageFormat> WriteBatch<F> {
+
+    pub fn new() -> Self {
+        Self { operations: Vec::new(), _formatter: PhantomData::<F> }
+    }
+
+    pub fn put<O: StorageObject + Serialize>(&mut self, key: &str, value: &O) -> anyhow::Result<&mut Self> {
+        let data = F::serialize(value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+        let fields = serde_json::to_value(value).with_context(|| {
+            format!("Failed to extract fields of {} for key: {}", O::type_name(), key)
+        })?;
+        self.operations.push(TransactionOperation::Put {
+            type_name: O::type_name(),
+            schema: O::schema,
+            key: key.to_string(),
+            data,
+            fields,
+            indexed_fields: O::indexed_fields,
+            text_fields: O::text_fields,
+        });
+        Ok(self)
+    }
+
+    pub fn delete<O: StorageObject>(&mut self, key: &str) -> &mut Self {
+        self.operations.push(TransactionOperation::Delete {
+            type_name: O::type_name(),
+            schema: O::schema,
+            key: key.to_string(),
+            text_fields: O::text_fields,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub(crate) fn into_operations(self) -> Vec<TransactionOperation> {
+        self.operations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[test]
+    fn test_transaction_stages_operations_in_order() {
+        let txn = Transaction::<JsonStorageFormat>::new();
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        txn.put("test_key", &obj).unwrap();
+        // clones passed into the closure stage into the same transaction
+        txn.clone().delete::<TestObject>("stale_key");
+
+        let operations = txn.into_operations();
+        assert_eq!(operations.len(), 2);
+        match &operations[0] {
+            TransactionOperation::Put { type_name, key, data, fields, .. } => {
+                assert_eq!(*type_name, "TestObject");
+                assert_eq!(key, "test_key");
+                assert_eq!(JsonStorageFormat::deserialize::<TestObject>(data).unwrap(), obj);
+                assert_eq!(fields["value"], "test_value");
+            }
+            TransactionOperation::Delete { .. } => panic!("expected a put"),
+        }
+        assert!(matches!(&operations[1], TransactionOperation::Delete { .. }));
+        assert_eq!(operations[1].type_name(), "TestObject");
+        assert_eq!(operations[1].key(), "stale_key");
+    }
+
+    #[test]
+    fn test_write_batch_chains_operations() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let mut batch = WriteBatch::<JsonStorageFormat>::default();
+        assert!(batch.is_empty());
+        batch.put("test_key", &obj).unwrap().delete::<TestObject>("stale_key").put("other_key", &obj).unwrap();
+        assert_eq!(batch.len(), 3);
+
+        let keys: Vec<String> = batch.into_operations().iter().map(|operation| operation.key().to_string()).collect();
+        assert_eq!(keys, vec!["test_key", "stale_key", "other_key"]);
+    }
+}
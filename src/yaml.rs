@@ -0,0 +1,44 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{envelope::FormatInfo, StorageFormat, StorageObject};
+
+
+/// Human readable and hand editable, for configuration-style objects in file backed stores
+#[derive(Debug, Clone)]
+pub struct YamlStorageFormat;
+
+impl FormatInfo for YamlStorageFormat {
+    const ID: &'static str = "yaml";
+    const VERSION: u16 = 1;
+}
+
+impl StorageFormat for YamlStorageFormat {
+    fn serialize<T: StorageObject + Serialize>(obj: &T) -> anyhow::Result<Vec<u8>> {
+        serde_yaml::to_string(obj).map(|yaml| yaml.into_bytes()).map_err(|e| e.into())
+    }
+
+    fn deserialize<T: StorageObject + DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+        serde_yaml::from_slice(data).map_err(|e| e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_object::TestObject;
+
+    use super::*;
+
+    #[test]
+    fn test_yaml_storage_format_round_trip() {
+        let obj = TestObject { key: "test_key".to_string(), value: "test_value".to_string() };
+        let data = YamlStorageFormat::serialize(&obj).unwrap();
+        assert_eq!(std::str::from_utf8(&data).unwrap(), "key: test_key\nvalue: test_value\n");
+        assert_eq!(YamlStorageFormat::deserialize::<TestObject>(&data).unwrap(), obj);
+
+        // hand edits with comments and quoting read back
+        let edited = b"# changed by hand\nkey: test_key\nvalue: 'edited'\n";
+        let retrieved: TestObject = YamlStorageFormat::deserialize(edited).unwrap();
+        assert_eq!(retrieved.value, "edited");
+    }
+}
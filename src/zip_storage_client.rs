@@ -0,0 +1,265 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{StorageClient, StorageFormat, StorageObject};
+
+/// Contents of the archive, kept in memory and written back on every change
+#[derive(Default)]
+struct Archive {
+    directories: BTreeSet<String>,
+    entries: BTreeMap<String, Vec<u8>>,
+}
+
+impl Archive {
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let mut archive = Archive::default();
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(archive),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut zip = ZipArchive::new(file)?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let name = entry.name().to_string();
+            if entry.is_dir() {
+                archive.directories.insert(name.trim_end_matches('/').to_string());
+            } else {
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data).with_context(|| {
+                    format!("Failed to read archive entry: {}", name)
+                })?;
+                archive.entries.insert(name, data);
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Writes the archive next to `path` and renames it over, so a crash never leaves a truncated archive
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let file = std::fs::File::create(&tmp).with_context(|| {
+            format!("Failed to create archive at path: {}", tmp.display())
+        })?;
+        let mut zip = ZipWriter::new(file);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for directory in &self.directories {
+            zip.add_directory(format!("{}/", directory), options)?;
+        }
+        for (name, data) in &self.entries {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(data)?;
+        }
+        zip.finish()?;
+
+        std::fs::rename(&tmp, path).with_context(|| {
+            format!("Failed to replace archive at path: {}", path.display())
+        })?;
+        Ok(())
+    }
+}
+
+/// Stores every object inside a single zip archive with entries named `{type_name}/{key}`.
+/// - `file:///path/to/store.zip`, the archive is created on the first write
+/// - The archive is loaded into memory on init and rewritten on every change, which suits
+///   bundled datasets that are mostly read
+pub struct ZipStorageClient<F: StorageFormat> {
+    storage_url: Url,
+    archive: Arc<Mutex<Archive>>,
+    _formatter: PhantomData<F>,
+}
+
+impl<F: StorageFormat> ZipStorageClient<F> {
+
+    /// Applies `f` to the archive and writes it back to disk if `f` reports a change
+    async fn update<T, R>(&self, f: T) -> anyhow::Result<R>
+    where
+        T: FnOnce(&mut Archive) -> (bool, R) + Send + 'static,
+        R: Send + 'static,
+    {
+        let archive = self.archive.clone();
+        let path = PathBuf::from(self.storage_url.path());
+        tokio::task::spawn_blocking(move || {
+            let mut archive = archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Archive lock is poisoned"))?;
+            let (changed, result) = f(&mut archive);
+            if changed {
+                archive.write(&path)?;
+            }
+            Ok(result)
+        }).await?
+    }
+}
+
+#[async_trait]
+impl<F> StorageClient<F> for ZipStorageClient<F>
+where
+    F: StorageFormat + Send + Sync,
+{
+
+    async fn init(storage_url: Url) -> anyhow::Result<Self> {
+        let path = PathBuf::from(storage_url.path());
+        if path.as_os_str().is_empty() {
+            return Err(anyhow::anyhow!("Storage URL does not have a valid path"));
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create directory at path: {}", parent.display())
+            })?;
+        }
+
+        let archive = tokio::task::spawn_blocking(move || {
+            Archive::read(&path).with_context(|| {
+                format!("Failed to open archive at path: {}", path.display())
+            })
+        }).await??;
+
+        Ok(Self { storage_url, archive: Arc::new(Mutex::new(archive)), _formatter: PhantomData::<F> })
+    }
+
+    fn directory(&self) -> &str {
+        self.storage_url.path()
+    }
+
+    /// Entry name inside the archive
+    fn object_path<O: StorageObject>(&self, key: &str) -> String {
+        format!("{}/{}", self.object_directory::<O>(), key)
+    }
+
+    async fn create_object_directory<O: StorageObject>(&self) -> anyhow::Result<()> {
+        let directory = self.object_directory::<O>().to_string();
+        self.update(move |archive| (archive.directories.insert(directory), ())).await
+    }
+
+    async fn get<O: StorageObject + DeserializeOwned + Send + Sync>(&self, key: &str) -> anyhow::Result<Option<O>> {
+        let name = self.object_path::<O>(key);
+        let data = {
+            let archive = self.archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Archive lock is poisoned"))?;
+            archive.entries.get(&name).cloned()
+        };
+
+        match data {
+            Some(data) => {
+                let obj = F::deserialize(&data).with_context(|| {
+                    format!("Failed to deserialize {} for key: {}", O::type_name(), key)
+                })?;
+                Ok(Some(obj))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put<O: StorageObject + Serialize + Send + Sync>(&self, key: &str, value: O) -> anyhow::Result<()> {
+        let data = F::serialize(&value).with_context(|| {
+            format!("Failed to serialize object for key: {}", key)
+        })?;
+
+        let directory = self.object_directory::<O>().to_string();
+        let name = self.object_path::<O>(key);
+        self.update(move |archive| {
+            archive.directories.insert(directory);
+            archive.entries.insert(name, data);
+            (true, ())
+        }).await.with_context(|| {
+            format!("Failed to put {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete<O: StorageObject>(&self, key: &str) -> anyhow::Result<bool> {
+        let name = self.object_path::<O>(key);
+        self.update(move |archive| {
+            let removed = archive.entries.remove(&name).is_some();
+            (removed, removed)
+        }).await.with_context(|| {
+            format!("Failed to delete {} for key: {}", O::type_name(), key)
+        })
+    }
+
+    async fn delete_object_directory<O: StorageObject>(&self) -> anyhow::Result<bool> {
+        let directory = self.object_directory::<O>().to_string();
+        self.update(move |archive| {
+            let prefix = format!("{}/", directory);
+            let before = archive.entries.len();
+            archive.entries.retain(|name, _| !name.starts_with(&prefix));
+            let removed = archive.directories.remove(&directory) || archive.entries.len() != before;
+            (removed, removed)
+        }).await
+    }
+
+    async fn delete_all(&self) -> anyhow::Result<()> {
+        {
+            let mut archive = self.archive
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Archive lock is poisoned"))?;
+            *archive = Archive::default();
+        }
+        let path = self.storage_url.path();
+        tokio::fs::remove_file(path).await
+            .or_else(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    Ok(())
+                } else {
+                    Err(e)
+                }
+            })
+            .with_context(|| format!("Failed to remove archive at path: {}", path))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{json::JsonStorageFormat, test_object::TestObject};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_zip_storage_client_json() {
+        let path = std::env::temp_dir().join("zip_storage_client_test").join("store.zip");
+        let _ = tokio::fs::remove_file(&path).await;
+        let url = Url::from_file_path(&path).expect("Failed to create URL from file path");
+        let client = ZipStorageClient::<JsonStorageFormat>::init(url.clone()).await.expect("Failed to open archive");
+
+        assert!(client.create_object_directory::<TestObject>().await.is_ok());
+
+        let obj = TestObject {
+            key: "test_key".to_string(),
+            value: "test_value".to_string(),
+        };
+        client.put("test_key", obj.clone()).await.expect("Failed to put object");
+
+        // reopening the archive sees the same entries
+        let reopened = ZipStorageClient::<JsonStorageFormat>::init(url).await.expect("Failed to reopen archive");
+        let retrieved: Option<TestObject> = reopened.get("test_key").await.unwrap();
+        assert_eq!(retrieved, Some(obj));
+
+        assert!(client.delete::<TestObject>("test_key").await.unwrap());
+        assert!(!client.delete::<TestObject>("test_key").await.unwrap());
+
+        assert!(client.delete_object_directory::<TestObject>().await.unwrap());
+        assert!(!client.delete_object_directory::<TestObject>().await.unwrap());
+
+        assert!(client.delete_all().await.is_ok());
+        assert!(tokio::fs::metadata(&path).await.is_err());
+    }
+}